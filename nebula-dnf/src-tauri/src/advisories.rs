@@ -0,0 +1,155 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::error::NebulaError;
+use crate::process::run_command;
+use crate::validate_package_name;
+
+static CVE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"CVE-\d{4}-\d{4,}").unwrap());
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AdvisoryType {
+    Security,
+    Bugfix,
+    Enhancement,
+    Newpackage,
+    Unknown,
+}
+
+impl AdvisoryType {
+    fn parse(value: &str) -> Self {
+        match value.to_ascii_lowercase().as_str() {
+            "security" => AdvisoryType::Security,
+            "bugfix" => AdvisoryType::Bugfix,
+            "enhancement" => AdvisoryType::Enhancement,
+            "newpackage" => AdvisoryType::Newpackage,
+            _ => AdvisoryType::Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct UpdateAdvisory {
+    id: String, // e.g. FEDORA-2024-1a2b3c4d5e
+    kind: AdvisoryType,
+    severity: Option<String>, // "Critical", "Important", ... as published; absent for most bugfixes
+    cves: Vec<String>,
+    description: String,
+}
+
+impl UpdateAdvisory {
+    fn new(id: &str) -> Self {
+        UpdateAdvisory {
+            id: id.to_string(),
+            kind: AdvisoryType::Unknown,
+            severity: None,
+            cves: Vec::new(),
+            description: String::new(),
+        }
+    }
+}
+
+// Parses `dnf updateinfo info` (dnf4, "Update ID:") and `dnf advisory info` (dnf5, "Name :").
+// Both print "Key : value" lines with multi-line values continued as ": more". dnf5 nests the
+// references (with their own "Type") after the advisory fields, so only the first Type counts.
+fn parse_advisory_info(output: &str) -> Vec<UpdateAdvisory> {
+    let mut advisories: Vec<UpdateAdvisory> = Vec::new();
+    let mut last_key = String::new();
+    let mut has_type = false;
+
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once(':') else { continue };
+        let (key, value) = (key.trim(), value.trim());
+        if key == "Update ID" || key == "Name" {
+            advisories.push(UpdateAdvisory::new(value));
+            has_type = false;
+            last_key = key.to_string();
+            continue;
+        }
+        let Some(advisory) = advisories.last_mut() else { continue };
+        for cve in CVE_RE.find_iter(value) {
+            if !advisory.cves.iter().any(|known| known == cve.as_str()) {
+                advisory.cves.push(cve.as_str().to_string());
+            }
+        }
+        let key = if key.is_empty() { last_key.as_str() } else { key };
+        match key {
+            "Type" if !has_type => {
+                advisory.kind = AdvisoryType::parse(value);
+                has_type = true;
+            }
+            "Severity" if !value.is_empty() && value != "None" => advisory.severity = Some(value.to_string()),
+            "Description" => {
+                if !advisory.description.is_empty() {
+                    advisory.description.push('\n');
+                }
+                advisory.description.push_str(value);
+            }
+            _ => {}
+        }
+        last_key = key.to_string();
+    }
+    advisories
+}
+
+// Advisories for the available updates of `package`, so the UI can badge security fixes.
+// Tries the dnf5 spelling first and falls back to dnf4's `updateinfo info <package>`.
+#[tauri::command]
+pub async fn get_update_advisories(app: tauri::AppHandle, package: String) -> Result<Vec<UpdateAdvisory>, NebulaError> {
+    validate_package_name(&package)?;
+    let contains = format!("--contains-pkgs={}", package);
+    let out = run_command(&app, "dnf", &["advisory", "info", "--updates", "--quiet", &contains]).await?;
+    if out.success {
+        return Ok(parse_advisory_info(&out.stdout));
+    }
+    let out = run_command(&app, "dnf", &["updateinfo", "info", "--available", "--quiet", "--", &package]).await?;
+    if !out.success {
+        return Err(NebulaError::command_failed(out.code, &out.stderr, format!("Failed to read advisories for {}: {}", package, out.error_text().trim())));
+    }
+    Ok(parse_advisory_info(&out.stdout))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_advisory_info() {
+        let dnf4 = "===============================================================================\n\
+            \x20 firefox-125.0.3-1.fc40\n\
+            ===============================================================================\n\
+            \x20 Update ID: FEDORA-2024-1a2b3c4d5e\n\
+            \x20      Type: security\n\
+            \x20   Updated: 2024-05-01 00:00:00\n\
+            \x20      Bugs: 2278123 - CVE-2024-4367 firefox: arbitrary JavaScript execution\n\
+            \x20      CVEs: CVE-2024-4367\n\
+            \x20          : CVE-2024-4767\n\
+            Description: Update to 125.0.3.\n\
+            \x20          : Fixes several security issues.\n\
+            \x20  Severity: Important\n";
+        let dnf5 = "Name        : FEDORA-2024-1a2b3c4d5e\n\
+            Title       : firefox-125.0.3-1.fc40\n\
+            Severity    : Important\n\
+            Type        : security\n\
+            Issued      : 2024-05-01 00:00:00\n\
+            Description : Update to 125.0.3.\n\
+            \x20           : Fixes several security issues.\n\
+            References  : \n\
+            \x20 Reference ID : CVE-2024-4367\n\
+            \x20 Type         : cve\n\
+            \x20 Reference ID : CVE-2024-4767\n\
+            \x20 Type         : cve\n";
+        for output in [dnf4, dnf5] {
+            let advisories = parse_advisory_info(output);
+            assert_eq!(advisories.len(), 1, "{}", output);
+            let advisory = &advisories[0];
+            assert_eq!(advisory.id, "FEDORA-2024-1a2b3c4d5e");
+            assert_eq!(advisory.kind, AdvisoryType::Security);
+            assert_eq!(advisory.severity.as_deref(), Some("Important"));
+            assert_eq!(advisory.cves, vec!["CVE-2024-4367", "CVE-2024-4767"]);
+            assert_eq!(advisory.description, "Update to 125.0.3.\nFixes several security issues.");
+        }
+    }
+}
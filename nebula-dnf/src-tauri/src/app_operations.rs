@@ -0,0 +1,264 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::error::NebulaError;
+use crate::packagekit::PackageAction;
+use crate::plan::CommandPlan;
+use crate::process::CommandCapture;
+use crate::settings::PackageBackend;
+use crate::{append_json_line, app_data_file, load_json_lines, PackageOperationResult};
+
+const OPERATIONS_FILE_NAME: &str = "app_operations.jsonl";
+// The mode of transactions staged for the next boot, which only change packages then.
+const STAGED_MODE: &str = "Staged for the next boot";
+
+// An install, update or removal started from the app. Unlike dnf history this also covers the
+// other backends and failed attempts, and unlike the command audit trail it is one entry per
+// user action rather than per command.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AppOperation {
+    timestamp: String, // RFC 3339
+    backend: PackageBackend,
+    action: PackageAction,
+    packages: Vec<String>, // empty when everything was updated
+    mode: Option<String>,  // e.g. the uninstall mode
+    success: bool,
+    message: String,
+    transaction_id: Option<u32>, // the dnf history id, when dnf ran it
+}
+
+// One action of a package transaction plan.
+#[derive(Debug, PartialEq)]
+struct PlannedOperation {
+    backend: PackageBackend,
+    action: PackageAction,
+    packages: Vec<String>,
+    mode: Option<String>,
+}
+
+fn planned(backend: PackageBackend, action: PackageAction, packages: Vec<String>, mode: Option<&str>) -> Vec<PlannedOperation> {
+    vec![PlannedOperation { backend, action, packages, mode: mode.map(str::to_string) }]
+}
+
+// `dnf do`'s actions, each followed by its packages: `--action=install a b --action=remove c`.
+fn dnf_do_operations(args: &[String], mode: Option<&str>) -> Vec<PlannedOperation> {
+    let mut operations: Vec<PlannedOperation> = Vec::new();
+    for arg in args {
+        let action = match arg.strip_prefix("--action=") {
+            Some("install") => Some(PackageAction::Install),
+            Some("remove") => Some(PackageAction::Remove),
+            Some("upgrade") => Some(PackageAction::Update),
+            Some(_) => return Vec::new(),
+            None => None,
+        };
+        match (action, operations.last_mut()) {
+            (Some(action), _) => operations.extend(planned(PackageBackend::Dnf, action, Vec::new(), mode.or(Some("Queued")))),
+            (None, Some(operation)) if !arg.starts_with('-') => operation.packages.push(arg.clone()),
+            _ => {}
+        }
+    }
+    operations
+}
+
+// A `dnf shell` script from plan::queue_shell_script: "install a b", "remove c", "run".
+fn dnf_shell_operations(script: &str) -> Vec<PlannedOperation> {
+    let mut operations = Vec::new();
+    for line in script.lines() {
+        let mut words = line.split_whitespace();
+        let action = match words.next() {
+            Some("install") => PackageAction::Install,
+            Some("remove") => PackageAction::Remove,
+            _ => continue,
+        };
+        operations.extend(planned(PackageBackend::Dnf, action, words.map(String::from).collect(), Some("Queued")));
+    }
+    operations
+}
+
+// What a package transaction plan changes, one entry per action (`dnf do` has several). Plans that
+// only download packages, key imports and `dnf history`/`module` changes give none; dnf history
+// has the latter itself.
+fn plan_operations(plan: &CommandPlan) -> Vec<PlannedOperation> {
+    if !plan.is_package_transaction() {
+        return Vec::new();
+    }
+    let Some((tool, rest)) = plan.root_command().split_first() else { return Vec::new() };
+    let has = |flag: &str| rest.iter().any(|arg| arg == flag);
+    let words: Vec<&String> = rest.iter().filter(|arg| !arg.starts_with('-')).collect();
+    let names = |skip: usize| words.iter().skip(skip).map(|word| word.to_string()).collect::<Vec<_>>();
+    let subcommand = words.first().map(|word| word.as_str());
+    use PackageAction::{Install, Remove, Update};
+    match tool.as_str() {
+        "dnf" if has("--downloadonly") => Vec::new(),
+        "dnf" => {
+            let staged = has("--offline").then_some(STAGED_MODE);
+            match subcommand {
+                Some("install" | "reinstall" | "downgrade" | "swap") => planned(PackageBackend::Dnf, Install, names(1), staged),
+                Some("remove" | "erase") => planned(PackageBackend::Dnf, Remove, names(1), staged),
+                Some("autoremove") => planned(PackageBackend::Dnf, Remove, Vec::new(), Some("Unneeded dependencies")),
+                Some("update" | "upgrade" | "upgrade-minimal" | "distro-sync") => planned(PackageBackend::Dnf, Update, names(1), staged),
+                Some("group") => match words.get(1).map(|word| word.as_str()) {
+                    Some("install") => planned(PackageBackend::Dnf, Install, names(2), Some("Group")),
+                    Some("remove") => planned(PackageBackend::Dnf, Remove, names(2), Some("Group")),
+                    Some("upgrade") => planned(PackageBackend::Dnf, Update, names(2), Some("Group")),
+                    _ => Vec::new(),
+                },
+                Some("do") => dnf_do_operations(rest, staged),
+                // The script is still staged while its run is recorded.
+                Some("shell") => words.get(1).and_then(|script| std::fs::read_to_string(script.as_str()).ok()).map_or_else(Vec::new, |script| dnf_shell_operations(&script)),
+                Some("offline-upgrade") => planned(PackageBackend::Dnf, Update, Vec::new(), Some(STAGED_MODE)),
+                Some("system-upgrade") => {
+                    let release = rest.iter().find_map(|arg| arg.strip_prefix("--releasever="));
+                    let mode = format!("Release upgrade to {}, applied on the next boot", release.unwrap_or("the next release"));
+                    planned(PackageBackend::Dnf, Update, Vec::new(), Some(&mode))
+                }
+                _ => Vec::new(),
+            }
+        }
+        "rpm" if has("-e") || has("--erase") => planned(PackageBackend::Dnf, Remove, names(0), has("--nodeps").then_some("Force")),
+        "zypper" if has("--dry-run") => Vec::new(),
+        "zypper" => match subcommand {
+            Some("install") => planned(PackageBackend::Zypper, Install, names(1), None),
+            Some("remove") => planned(PackageBackend::Zypper, Remove, names(1), None),
+            Some("update") => planned(PackageBackend::Zypper, Update, names(1), None),
+            _ => Vec::new(),
+        },
+        "apt-get" => match subcommand {
+            Some("install") if has("--only-upgrade") => planned(PackageBackend::Apt, Update, names(1), None),
+            Some("install") => planned(PackageBackend::Apt, Install, names(1), None),
+            Some("remove") => planned(PackageBackend::Apt, Remove, names(1), None),
+            Some("upgrade") => planned(PackageBackend::Apt, Update, Vec::new(), None),
+            _ => Vec::new(),
+        },
+        "pacman" => match rest.first().map(String::as_str) {
+            Some("-S") => planned(PackageBackend::Pacman, Install, names(0), None),
+            Some("-Rs") => planned(PackageBackend::Pacman, Remove, names(0), None),
+            Some("-Syu") => planned(PackageBackend::Pacman, Update, Vec::new(), None),
+            _ => Vec::new(),
+        },
+        _ => Vec::new(),
+    }
+}
+
+async fn append(app: &tauri::AppHandle, operation: PlannedOperation, success: bool, message: String) {
+    let transaction_id = if success && operation.backend == PackageBackend::Dnf {
+        // boxed: the history query goes through the process runner, which records through here
+        Box::pin(crate::history::latest_transaction_id(app)).await.unwrap_or_else(|e| {
+            warn!("Could not look up the dnf transaction of an operation: {}", e);
+            None
+        })
+    } else {
+        None
+    };
+    let entry = AppOperation {
+        timestamp: Local::now().to_rfc3339(),
+        backend: operation.backend,
+        action: operation.action,
+        packages: operation.packages,
+        mode: operation.mode,
+        success,
+        message,
+        transaction_id,
+    };
+    let result = app_data_file(app, OPERATIONS_FILE_NAME).and_then(|path| append_json_line(&path, &entry));
+    if let Err(e) = result {
+        warn!("Failed to record app operation: {}", e);
+    }
+}
+
+// Records what a plan the process runner ran changed, so every package transaction run through
+// it is recorded whichever command started it. Best effort, like the audit trail; dry runs and
+// simulation-mode stand-ins changed nothing and are not recorded.
+pub async fn record_plan(app: &tauri::AppHandle, plan: &CommandPlan, capture: &CommandCapture) {
+    if capture.simulated {
+        return;
+    }
+    let command = plan.root_command().join(" ");
+    for operation in plan_operations(plan) {
+        let message = if capture.success { format!("`{}` finished.", command) } else { format!("`{}` failed. Exit code: {}.", command, capture.code) };
+        append(app, operation, capture.success, message).await;
+    }
+}
+
+// For the backends that change packages without a command plan (PackageKit and dnf5daemon over
+// D-Bus); everything else is recorded by record_plan. Previews and simulated runs are not recorded.
+pub async fn record(app: &tauri::AppHandle, backend: PackageBackend, action: PackageAction, packages: &[String], result: &PackageOperationResult) {
+    if result.confirmation_required || result.simulated {
+        return;
+    }
+    let operation = PlannedOperation { backend, action, packages: packages.to_vec(), mode: None };
+    append(app, operation, result.success, result.message.clone()).await;
+}
+
+fn newest_first(mut operations: Vec<AppOperation>, limit: Option<usize>) -> Vec<AppOperation> {
+    operations.reverse();
+    operations.truncate(limit.unwrap_or(operations.len()));
+    operations
+}
+
+// What the app installed, updated and removed, newest first; the last `limit` if given.
+#[tauri::command]
+pub async fn list_app_operations(app: tauri::AppHandle, limit: Option<usize>) -> Result<Vec<AppOperation>, NebulaError> {
+    let operations: Vec<AppOperation> = load_json_lines(&app_data_file(&app, OPERATIONS_FILE_NAME)?)?;
+    Ok(newest_first(operations, limit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_newest_first() {
+        let operation = |package: &str, transaction_id: Option<u32>| AppOperation {
+            timestamp: "2026-10-17T09:30:00+02:00".to_string(),
+            backend: PackageBackend::Dnf,
+            action: PackageAction::Install,
+            packages: vec![package.to_string()],
+            mode: None,
+            success: transaction_id.is_some(),
+            message: String::new(),
+            transaction_id,
+        };
+        let operations = vec![operation("htop", Some(41)), operation("gimp", None), operation("vim", Some(42))];
+        let newest = newest_first(operations.clone(), Some(2));
+        assert_eq!(newest, [operations[2].clone(), operations[1].clone()]);
+        assert_eq!(newest_first(operations, None).len(), 3);
+
+        let line = serde_json::to_string(&newest[0]).unwrap();
+        assert!(line.contains("\"action\":\"Install\""));
+        assert_eq!(serde_json::from_str::<AppOperation>(&line).unwrap(), newest[0]);
+    }
+
+    #[test]
+    fn test_plan_operations() {
+        use crate::plan::{self, strings};
+        let operation = |action, packages: &[&str], mode: Option<&str>| PlannedOperation {
+            backend: PackageBackend::Dnf,
+            action,
+            packages: strings(packages),
+            mode: mode.map(str::to_string),
+        };
+        let install = plan::install_packages(&strings(&["htop"]), false).unwrap();
+        assert_eq!(plan_operations(&install), [operation(PackageAction::Install, &["htop"], None)]);
+        assert_eq!(plan_operations(&plan::offline(&install, true).unwrap()), [operation(PackageAction::Install, &["htop"], Some(STAGED_MODE))]);
+        assert!(plan_operations(&plan::install_packages(&strings(&["htop"]), true).unwrap()).is_empty());
+        assert!(plan_operations(&plan::download_only(&install).unwrap()).is_empty());
+        assert!(plan_operations(&plan::refresh_metadata()).is_empty());
+
+        let queued = [operation(PackageAction::Install, &["gimp", "inkscape"], Some("Queued")), operation(PackageAction::Remove, &["krita"], Some("Queued"))];
+        let queue = plan::apply_queue(&strings(&["gimp", "inkscape"]), &strings(&["krita"]), plan::QueueTransaction::Do, false).unwrap();
+        assert_eq!(plan_operations(&queue[0]), queued);
+        assert_eq!(dnf_shell_operations(&plan::queue_shell_script(&strings(&["gimp", "inkscape"]), &strings(&["krita"])).unwrap()), queued);
+        let forced = CommandPlan::privileged("rpm", strings(&["-e", "--nodeps", "htop"]));
+        assert_eq!(plan_operations(&forced), [operation(PackageAction::Remove, &["htop"], Some("Force"))]);
+        assert_eq!(plan_operations(&plan::autoremove()), [operation(PackageAction::Remove, &[], Some("Unneeded dependencies"))]);
+        assert_eq!(plan_operations(&plan::upgrade_all(false, &strings(&["mesa-dri-drivers"]))), [operation(PackageAction::Update, &[], None)]);
+
+        let zypper = crate::zypper::action_plan(PackageAction::Remove, &strings(&["htop"]), false);
+        assert_eq!(plan_operations(&zypper)[0].backend, PackageBackend::Zypper);
+        assert!(plan_operations(&crate::zypper::action_plan(PackageAction::Remove, &strings(&["htop"]), true)).is_empty());
+        let pacman = crate::pacman::action_plan(PackageAction::Update, &[], false);
+        assert_eq!(plan_operations(&pacman)[0].action, PackageAction::Update);
+    }
+}
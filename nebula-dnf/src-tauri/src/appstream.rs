@@ -0,0 +1,417 @@
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tracing::warn;
+
+use crate::error::NebulaError;
+use crate::process::run_command;
+use crate::UserPackageWithDependencies;
+
+// Where AppStream catalogs (the "collection" XML distributions generate from their packages'
+// metainfo files) are installed; cached icons sit in an "icons" directory next to the XML one.
+const CATALOG_DIRS: &[&str] = &["/usr/share/swcatalog/xml", "/var/cache/swcatalog/xml", "/usr/share/app-info/xmls", "/var/cache/app-info/xmls"];
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum AppIcon {
+    File(String),  // a cached icon from the catalog's icon directory
+    Stock(String), // a name to look up in the icon theme
+    Url(String),
+}
+
+// What the software catalog says about the application a package ships.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AppStreamInfo {
+    id: String, // the component id, e.g. "org.gnome.TextEditor"
+    name: String,
+    summary: Option<String>,
+    description: Option<String>, // paragraphs separated by blank lines, list items on lines of their own starting with "• "
+    icon: Option<AppIcon>,
+    screenshots: Vec<String>, // image URLs, the default screenshot first
+}
+
+// What the catalog says a component is, for finding packages by what they ship rather than by
+// their names.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentKind {
+    pub(crate) package: String,
+    pub(crate) component_type: String, // e.g. "addon", "icon-theme"; "generic" when not given
+    pub(crate) extends: Vec<String>,   // for addons, the ids of the components they extend
+    pub(crate) categories: Vec<String>,
+    pub(crate) keywords: Vec<String>, // untranslated
+}
+
+#[derive(Debug, Default)]
+struct Index {
+    by_package: HashMap<String, AppStreamInfo>,
+    components: Vec<ComponentKind>,
+}
+
+// An index while its catalogs are read; `ranked` keeps the component type rank of each entry.
+#[derive(Debug, Default)]
+struct Parsed {
+    ranked: HashMap<String, (u8, AppStreamInfo)>,
+    components: Vec<ComponentKind>,
+}
+
+struct Catalog {
+    files: Vec<(PathBuf, SystemTime)>,
+    index: Arc<Index>,
+}
+
+// Parsed once and kept until a catalog file changes. LOADING lets one caller parse while the
+// others wait for its result.
+static CATALOG: Mutex<Option<Catalog>> = Mutex::new(None);
+static LOADING: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+
+// An element of a catalog. Catalogs are read with quick-xml and kept as a tree one <component>
+// at a time, so the lookups below only ever see the component they are about.
+#[derive(Debug)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+#[derive(Debug)]
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+impl Element {
+    fn from_start(start: &BytesStart) -> Self {
+        let attributes = start
+            .attributes()
+            .filter_map(Result::ok)
+            .map(|attribute| {
+                let value = attribute.unescape_value().map(|value| value.into_owned());
+                (String::from_utf8_lossy(attribute.key.as_ref()).into_owned(), value.unwrap_or_else(|_| String::from_utf8_lossy(&attribute.value).into_owned()))
+            })
+            .collect();
+        Element { name: String::from_utf8_lossy(start.name().as_ref()).into_owned(), attributes, children: Vec::new() }
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    // Translations carry an xml:lang attribute.
+    fn translated(&self) -> bool {
+        self.attribute("xml:lang").is_some()
+    }
+
+    // The direct children named `name`.
+    fn children<'a>(&'a self, name: &'static str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children.iter().filter_map(move |node| match node {
+            Node::Element(element) if element.name == name => Some(element),
+            _ => None,
+        })
+    }
+
+    fn child(&self, name: &'static str) -> Option<&Element> {
+        self.children(name).find(|element| !element.translated())
+    }
+
+    // All the text inside, markup dropped and whitespace collapsed.
+    fn text(&self) -> String {
+        fn collect(element: &Element, text: &mut String) {
+            for node in &element.children {
+                match node {
+                    Node::Element(child) => collect(child, text),
+                    Node::Text(content) => text.push_str(content),
+                }
+            }
+        }
+        let mut text = String::new();
+        collect(self, &mut text);
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    // The untranslated text of the direct child `name`.
+    fn child_text(&self, name: &'static str) -> Option<String> {
+        self.child(name).map(Element::text).filter(|text| !text.is_empty())
+    }
+}
+
+fn description(component: &Element) -> Option<String> {
+    let mut paragraphs = Vec::new();
+    for node in &component.child("description")?.children {
+        let Node::Element(block) = node else { continue };
+        match block.name.as_str() {
+            "p" if !block.translated() => paragraphs.push(block.text()),
+            "ul" | "ol" => paragraphs.extend(block.children("li").filter(|item| !item.translated()).map(|item| format!("• {}", item.text()))),
+            _ => {}
+        }
+    }
+    (!paragraphs.is_empty()).then(|| paragraphs.join("\n\n").replace("\n\n• ", "\n• "))
+}
+
+// The largest cached icon, then a stock or remote one.
+fn icon(component: &Element, icons_dir: &Path, origin: &str) -> Option<AppIcon> {
+    let width = |icon: &Element| icon.attribute("width").and_then(|width| width.parse::<u32>().ok()).unwrap_or(0);
+    let of_type = |kind: &'static str| component.children("icon").filter(move |icon| icon.attribute("type") == Some(kind));
+    if let Some(cached) = of_type("cached").max_by_key(|icon| width(icon)) {
+        let size = match (cached.attribute("width"), cached.attribute("height")) {
+            (Some(width), Some(height)) => format!("{}x{}", width, height),
+            _ => "64x64".to_string(),
+        };
+        return Some(AppIcon::File(icons_dir.join(origin).join(size).join(cached.text()).to_string_lossy().into_owned()));
+    }
+    let first = |kind: &'static str| of_type(kind).next().map(Element::text);
+    first("stock").map(AppIcon::Stock).or_else(|| first("remote").map(AppIcon::Url))
+}
+
+fn screenshots(component: &Element) -> Vec<String> {
+    let mut shots: Vec<&Element> = component.children("screenshots").flat_map(|screenshots| screenshots.children("screenshot")).collect();
+    shots.sort_by_key(|screenshot| screenshot.attribute("type") != Some("default"));
+    shots
+        .into_iter()
+        .filter_map(|screenshot| {
+            let mut images = screenshot.children("image");
+            let first = images.next()?;
+            let source = std::iter::once(first).chain(images).find(|image| image.attribute("type") == Some("source"));
+            Some(source.unwrap_or(first).text())
+        })
+        .filter(|url| !url.is_empty())
+        .collect()
+}
+
+// Applications describe a package better than the add-ons, fonts or codecs it may also ship.
+fn rank(component_type: Option<&str>) -> u8 {
+    match component_type {
+        Some("desktop-application" | "desktop") => 0,
+        Some("console-application") => 1,
+        _ => 2,
+    }
+}
+
+// The untranslated texts of the `item` children of `list`, e.g. <categories><category>.
+fn list(component: &Element, list: &'static str, item: &'static str) -> Vec<String> {
+    component.children(list).flat_map(|list| list.children(item)).filter(|item| !item.translated()).map(Element::text).collect()
+}
+
+fn add_component(component: &Element, icons_dir: &Path, origin: &str, parsed: &mut Parsed) {
+    let (Some(package), Some(id)) = (component.child_text("pkgname"), component.child_text("id")) else { return };
+    parsed.components.push(ComponentKind {
+        package: package.clone(),
+        component_type: component.attribute("type").unwrap_or("generic").to_string(),
+        extends: component.children("extends").map(Element::text).collect(),
+        categories: list(component, "categories", "category"),
+        keywords: list(component, "keywords", "keyword"),
+    });
+    let preference = rank(component.attribute("type"));
+    if parsed.ranked.get(&package).is_some_and(|(existing, _)| *existing <= preference) {
+        return;
+    }
+    let info = AppStreamInfo {
+        name: component.child_text("name").unwrap_or_else(|| id.clone()),
+        id,
+        summary: component.child_text("summary"),
+        description: description(component),
+        icon: icon(component, icons_dir, origin),
+        screenshots: screenshots(component),
+    };
+    parsed.ranked.insert(package, (preference, info));
+}
+
+// Merges the components of one catalog into `parsed`, keyed by package name. A malformed
+// catalog keeps the components read before the error.
+fn parse_catalog(xml: &str, icons_dir: &Path, parsed: &mut Parsed) {
+    let mut reader = Reader::from_str(xml);
+    let mut origin = String::new();
+    let mut open: Vec<Element> = Vec::new(); // the component being read, then its open descendants
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(start)) => {
+                let element = Element::from_start(&start);
+                match element.name.as_str() {
+                    _ if !open.is_empty() => open.push(element),
+                    "component" => open.push(element),
+                    "components" => origin = element.attribute("origin").unwrap_or_default().to_string(),
+                    _ => {}
+                }
+            }
+            Ok(Event::Empty(start)) => {
+                if let Some(parent) = open.last_mut() {
+                    parent.children.push(Node::Element(Element::from_start(&start)));
+                }
+            }
+            Ok(Event::Text(text)) => {
+                if let Some(parent) = open.last_mut() {
+                    let content = text.unescape().map(|content| content.into_owned());
+                    parent.children.push(Node::Text(content.unwrap_or_else(|_| String::from_utf8_lossy(&text).into_owned())));
+                }
+            }
+            Ok(Event::CData(data)) => {
+                if let Some(parent) = open.last_mut() {
+                    parent.children.push(Node::Text(String::from_utf8_lossy(&data).into_owned()));
+                }
+            }
+            Ok(Event::End(_)) => match (open.pop(), open.last_mut()) {
+                (Some(element), Some(parent)) => parent.children.push(Node::Element(element)),
+                (Some(component), None) => add_component(&component, icons_dir, &origin, parsed),
+                (None, _) => {}
+            },
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(e) => {
+                warn!("Malformed AppStream catalog at byte {}: {}", reader.buffer_position(), e);
+                break;
+            }
+        }
+    }
+}
+
+fn catalog_files() -> Vec<(PathBuf, SystemTime)> {
+    let mut files: Vec<(PathBuf, SystemTime)> = CATALOG_DIRS
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.filter_map(Result::ok))
+        .map(|entry| entry.path())
+        .filter(|path| path.to_string_lossy().ends_with(".xml") || path.to_string_lossy().ends_with(".xml.gz"))
+        .filter_map(|path| {
+            let modified = path.metadata().and_then(|metadata| metadata.modified()).ok()?;
+            Some((path, modified))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+fn cached(files: &[(PathBuf, SystemTime)]) -> Option<Arc<Index>> {
+    CATALOG.lock().unwrap().as_ref().filter(|catalog| catalog.files == files).map(|catalog| catalog.index.clone())
+}
+
+// Package name to catalog entry, and every component, for every installed catalog. Compressed
+// catalogs go through `zcat`; one that cannot be read is skipped. The parsing runs on the
+// blocking pool.
+async fn catalog(app: &tauri::AppHandle) -> Arc<Index> {
+    let files = catalog_files();
+    if let Some(index) = cached(&files) {
+        return index;
+    }
+    let _loading = LOADING.lock().await;
+    if let Some(index) = cached(&files) {
+        return index;
+    }
+    let mut parsed = Parsed::default();
+    for (path, _) in &files {
+        let xml = if path.extension().is_some_and(|extension| extension == "gz") {
+            match run_command(app, "zcat", &[&path.to_string_lossy()]).await {
+                Ok(out) if out.success => out.stdout,
+                Ok(out) => {
+                    warn!("Could not decompress AppStream catalog {}: {}", path.display(), out.error_text().trim());
+                    continue;
+                }
+                Err(e) => {
+                    warn!("Could not decompress AppStream catalog {}: {}", path.display(), e);
+                    continue;
+                }
+            }
+        } else {
+            match fs::read_to_string(path) {
+                Ok(xml) => xml,
+                Err(e) => {
+                    warn!("Could not read AppStream catalog {}: {}", path.display(), e);
+                    continue;
+                }
+            }
+        };
+        let icons_dir = path.parent().and_then(Path::parent).map(|root| root.join("icons")).unwrap_or_default();
+        let task = tauri::async_runtime::spawn_blocking(move || {
+            parse_catalog(&xml, &icons_dir, &mut parsed);
+            parsed
+        });
+        parsed = match task.await {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                warn!("AppStream catalog parse task failed: {}", e);
+                Parsed::default()
+            }
+        };
+    }
+    let index = Arc::new(Index {
+        by_package: parsed.ranked.into_iter().map(|(package, (_, info))| (package, info)).collect(),
+        components: parsed.components,
+    });
+    *CATALOG.lock().unwrap() = Some(Catalog { files, index: index.clone() });
+    index
+}
+
+// Started with the app, so the first package listing finds the catalog parsed.
+pub async fn preload(app: tauri::AppHandle) {
+    catalog(&app).await;
+}
+
+// Fills in `appstream` for the packages the catalog knows. Without a catalog the list is unchanged.
+pub async fn attach(app: &tauri::AppHandle, packages: &mut [UserPackageWithDependencies]) {
+    let catalog = catalog(app).await;
+    for package in packages {
+        package.appstream = catalog.by_package.get(&package.name).cloned();
+    }
+}
+
+// The components of every installed catalog that `matches` selects.
+pub async fn find_components(app: &tauri::AppHandle, matches: impl Fn(&ComponentKind) -> bool) -> Vec<ComponentKind> {
+    catalog(app).await.components.iter().filter(|component| matches(component)).cloned().collect()
+}
+
+#[tauri::command]
+pub async fn get_appstream_info(app: tauri::AppHandle, package: String) -> Result<Option<AppStreamInfo>, NebulaError> {
+    Ok(catalog(&app).await.by_package.get(&package).cloned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_catalog() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<components version="0.14" origin="fedora">
+  <component type="addon">
+    <id>org.gnome.TextEditor.spell</id>
+    <pkgname>gnome-text-editor</pkgname>
+    <extends>org.gnome.TextEditor</extends>
+    <name>Spell checking</name>
+  </component>
+  <component type="desktop-application">
+    <id>org.gnome.TextEditor</id>
+    <pkgname>gnome-text-editor</pkgname>
+    <developer><name>The GNOME Project</name></developer>
+    <name>Text Editor</name>
+    <name xml:lang="de">Texteditor</name>
+    <summary><![CDATA[Edit text files]]></summary>
+    <description>
+      <p>A simple <em>text</em> editor &amp; more&#8230;</p>
+      <ul><li>Tabs</li><li>Sessions</li></ul>
+    </description>
+    <description xml:lang="de"><p>Ein Texteditor.</p></description>
+    <icon type="stock">org.gnome.TextEditor</icon>
+    <icon type="cached" width="64" height="64">gnome-text-editor_org.gnome.TextEditor.png</icon>
+    <icon type="cached" width="128" height="128">gnome-text-editor_org.gnome.TextEditor.png</icon>
+    <screenshots>
+      <screenshot><image type="source">https://example.org/second.png</image></screenshot>
+      <screenshot type="default"><image type="thumbnail" width="224">https://example.org/thumb.png</image><image type="source">https://example.org/first.png</image></screenshot>
+    </screenshots>
+  </component>
+  <component type="font"><id>org.example.NoPackage</id><name>Orphan</name></component>
+</components>"#;
+        let mut parsed = Parsed::default();
+        parse_catalog(xml, Path::new("/usr/share/swcatalog/icons"), &mut parsed);
+        assert_eq!(parsed.ranked.len(), 1);
+        assert_eq!(parsed.components.len(), 2);
+        assert_eq!((parsed.components[0].component_type.as_str(), parsed.components[0].extends.as_slice()), ("addon", &["org.gnome.TextEditor".to_string()][..]));
+        let (_, info) = &parsed.ranked["gnome-text-editor"];
+        assert_eq!((info.id.as_str(), info.name.as_str(), info.summary.as_deref()), ("org.gnome.TextEditor", "Text Editor", Some("Edit text files")));
+        assert_eq!(info.description.as_deref(), Some("A simple text editor & more…\n• Tabs\n• Sessions"));
+        assert_eq!(info.icon, Some(AppIcon::File("/usr/share/swcatalog/icons/fedora/128x128/gnome-text-editor_org.gnome.TextEditor.png".to_string())));
+        assert_eq!(info.screenshots, ["https://example.org/first.png", "https://example.org/second.png"]);
+    }
+}
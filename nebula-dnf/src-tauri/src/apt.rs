@@ -0,0 +1,207 @@
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::backends::{self, PackageBackend};
+use crate::error::NebulaError;
+use crate::packagekit::PackageAction;
+use crate::plan::{strings, CommandPlan};
+use crate::process::run_command;
+use crate::{DependencyKind, DisplayablePackage, PackageCategory, PackageOperationResult, UserPackageWithDependencies};
+
+// Experimental: the core operations on Debian and Ubuntu, through dpkg-query for what is
+// installed and apt-get for transactions. Dry runs are apt-get simulations, which need no root.
+
+// Tab-separated; db:Status-Abbrev is "ii " for an installed package and Installed-Size is in KiB.
+const DPKG_FORMAT: &str = "${Package}\t${Version}\t${Architecture}\t${db:Status-Abbrev}\t${Installed-Size}\t${Section}\t${Depends}\n";
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct AptPackage {
+    name: String,
+    version: String,
+    architecture: String,
+    section: String, // e.g. "utils" or "universe/games"
+    installed_size: Option<u64>, // bytes
+    depends: Vec<String>,
+}
+
+// "libc6 (>= 2.34), libtinfo6 | libncurses6, python3:any" -> ["libc6", "libtinfo6", "python3"];
+// of alternatives, the first is what apt picks.
+fn dependency_names(depends: &str) -> Vec<String> {
+    depends
+        .split(',')
+        .filter_map(|group| group.split('|').next())
+        .map(|alternative| alternative.split(['(', '[', '<']).next().unwrap_or_default().trim())
+        .map(|name| name.split(':').next().unwrap_or_default().to_string())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+fn parse_dpkg_query(output: &str) -> Vec<AptPackage> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [name, version, architecture, status, size, section, depends] = fields[..] else { return None };
+            status.starts_with("ii").then(|| AptPackage {
+                name: name.to_string(),
+                version: version.to_string(),
+                architecture: architecture.to_string(),
+                section: section.to_string(),
+                installed_size: size.trim().parse::<u64>().ok().map(|kib| kib * 1024),
+                depends: dependency_names(depends),
+            })
+        })
+        .collect()
+}
+
+// Debian archive sections, with the "universe/" style component prefix dropped.
+fn category_from_section(section: &str) -> PackageCategory {
+    match section.rsplit('/').next().unwrap_or_default() {
+        "gnome" | "kde" | "xfce" | "x11" => PackageCategory::DesktopEnvironment,
+        "admin" | "kernel" | "shells" | "otherosfs" => PackageCategory::System,
+        "games" => PackageCategory::Games,
+        "sound" | "video" | "graphics" => PackageCategory::Multimedia,
+        "text" | "tex" => PackageCategory::Office,
+        "net" | "web" | "mail" | "comm" | "news" | "httpd" => PackageCategory::Network,
+        "libs" | "oldlibs" => PackageCategory::Library,
+        "devel" | "libdevel" | "debug" | "vcs" | "interpreters" | "python" | "perl" | "ruby" | "rust" | "java" | "javascript"
+        | "haskell" | "ocaml" | "lisp" | "php" | "gnu-r" => PackageCategory::Development,
+        "utils" | "editors" | "misc" => PackageCategory::Utility,
+        "" => PackageCategory::Unknown,
+        _ => PackageCategory::Manual,
+    }
+}
+
+// "Remv libfoo1 [1.2-3]" lines of an autoremove simulation.
+fn parse_autoremove_simulation(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.strip_prefix("Remv "))
+        .filter_map(|rest| rest.split_whitespace().next())
+        .map(String::from)
+        .collect()
+}
+
+async fn installed(app: &tauri::AppHandle) -> Result<Vec<AptPackage>, String> {
+    let out = run_command(app, "dpkg-query", &["-W", "-f", DPKG_FORMAT]).await?;
+    if !out.success {
+        return Err(format!("dpkg-query failed: {}", out.error_text().trim()));
+    }
+    Ok(parse_dpkg_query(&out.stdout))
+}
+
+// The installed packages apt-mark has as manually installed, in the shape the package list
+// and its cache use.
+async fn user_installed_packages(app: &tauri::AppHandle) -> Result<Vec<UserPackageWithDependencies>, String> {
+    let out = run_command(app, "apt-mark", &["showmanual"]).await?;
+    if !out.success {
+        return Err(format!("apt-mark showmanual failed: {}", out.error_text().trim()));
+    }
+    let manual: HashSet<&str> = out.stdout.lines().map(str::trim).collect();
+    let mut packages: Vec<UserPackageWithDependencies> = installed(app)
+        .await?
+        .into_iter()
+        .filter(|package| manual.contains(package.name.as_str()))
+        .map(|package| UserPackageWithDependencies {
+            category: category_from_section(&package.section),
+            dependencies: package
+                .depends
+                .into_iter()
+                .map(|name| DisplayablePackage { name, kind: DependencyKind::Package, module_provider: None })
+                .collect(),
+            incomplete: false,
+            installed_size: package.installed_size,
+            appstream: None,
+            name: package.name,
+        })
+        .collect();
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    packages.dedup_by(|a, b| a.name == b.name); // one entry per name across architectures
+    Ok(packages)
+}
+
+// Dry runs are simulations (-s) and run unprivileged.
+pub fn action_plan(action: PackageAction, packages: &[String], dry_run: bool) -> CommandPlan {
+    let mut args = strings(&[if dry_run { "-s" } else { "-y" }]);
+    match (action, packages.is_empty()) {
+        (PackageAction::Install, _) => args.push("install".to_string()),
+        // like dnf's clean_requirements_on_remove
+        (PackageAction::Remove, _) => args.extend(strings(&["remove", "--autoremove"])),
+        (PackageAction::Update, true) => args.push("upgrade".to_string()),
+        (PackageAction::Update, false) => args.extend(strings(&["install", "--only-upgrade"])),
+    }
+    args.extend(packages.iter().cloned());
+    if dry_run {
+        CommandPlan::new("apt-get", args)
+    } else {
+        CommandPlan::privileged("apt-get", args)
+    }
+}
+
+// Installs, removes or updates packages with apt-get, and lists what apt-mark has as manual.
+pub struct Apt;
+
+impl PackageBackend for Apt {
+    async fn list(&self, app: &tauri::AppHandle) -> Result<Option<Vec<UserPackageWithDependencies>>, String> {
+        Ok(Some(user_installed_packages(app).await?))
+    }
+
+    async fn run(&self, app: &tauri::AppHandle, action: PackageAction, packages: &[String], dry_run: bool) -> Result<PackageOperationResult, String> {
+        backends::run_cli(app, "apt-get", action_plan(action, packages, dry_run), action, packages, dry_run).await
+    }
+}
+
+#[tauri::command]
+pub async fn apt_package_action(
+    app: tauri::AppHandle,
+    action: PackageAction,
+    packages: Vec<String>,
+    dry_run: bool,
+    confirmed: Option<bool>,
+) -> Result<PackageOperationResult, NebulaError> {
+    Ok(backends::action_command(&Apt, &app, action, &packages, dry_run, confirmed).await?)
+}
+
+// Every installed package, with what the package list shows for RPMs.
+#[tauri::command]
+pub async fn apt_list_installed(app: tauri::AppHandle) -> Result<Vec<AptPackage>, NebulaError> {
+    Ok(installed(&app).await?)
+}
+
+// What `apt-get autoremove` would remove, from a simulation.
+#[tauri::command]
+pub async fn apt_list_orphans(app: tauri::AppHandle) -> Result<Vec<DisplayablePackage>, NebulaError> {
+    let out = run_command(&app, "apt-get", &["-s", "autoremove"]).await?;
+    if !out.success {
+        return Err(NebulaError::command_failed(out.code, &out.stderr, format!("apt-get -s autoremove failed: {}", out.error_text().trim())));
+    }
+    Ok(parse_autoremove_simulation(&out.stdout)
+        .into_iter()
+        .map(|name| DisplayablePackage { name, kind: DependencyKind::Package, module_provider: None })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apt_parsing() {
+        let output = "htop\t3.3.0-4\tamd64\tii \t412\tutils\tlibc6 (>= 2.34), libncursesw6 (>= 6) | libncurses6, python3:any\n\
+                      oldpkg\t1.0\tamd64\trc \t\tmisc\t\n";
+        let packages = parse_dpkg_query(output);
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].installed_size, Some(412 * 1024));
+        assert_eq!(packages[0].depends, ["libc6", "libncursesw6", "python3"]);
+
+        assert_eq!(category_from_section("universe/games"), PackageCategory::Games);
+        assert_eq!(category_from_section("libs"), PackageCategory::Library);
+        assert_eq!(category_from_section("fonts"), PackageCategory::Manual);
+
+        let simulation = "Reading package lists...\nRemv libfoo1 [1.2-3]\nRemv libbar2 [0.9]\n";
+        assert_eq!(parse_autoremove_simulation(simulation), ["libfoo1", "libbar2"]);
+
+        assert_eq!(action_plan(PackageAction::Remove, &strings(&["htop"]), true).command_line(), "apt-get -s remove --autoremove htop");
+        assert_eq!(action_plan(PackageAction::Update, &[], false).command_line(), "pkexec apt-get -y upgrade");
+    }
+}
@@ -0,0 +1,100 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::error::NebulaError;
+use crate::plan::{shell_quote, CommandPlan};
+use crate::{append_json_line, app_data_file, load_json_lines};
+
+const AUDIT_FILE_NAME: &str = "command_audit.jsonl";
+
+// One executed CommandPlan. Written after the command finished.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CommandAuditEntry {
+    timestamp: String, // RFC 3339
+    program: String,
+    args: Vec<String>,
+    privileged: bool,
+    success: bool,
+    exit_code: i32,
+}
+
+// Best effort: a failed write only logs a warning, it never fails the operation itself.
+pub fn record_command(app: &tauri::AppHandle, plan: &CommandPlan, success: bool, exit_code: i32) {
+    let entry = CommandAuditEntry {
+        timestamp: Local::now().to_rfc3339(),
+        program: plan.program.clone(),
+        args: plan.args.clone(),
+        privileged: plan.privileged,
+        success,
+        exit_code,
+    };
+    let result = app_data_file(app, AUDIT_FILE_NAME).and_then(|path| append_json_line(&path, &entry));
+    if let Err(e) = result {
+        warn!("Failed to record command in audit trail: {}", e);
+    }
+}
+
+// pkexec needs a graphical session agent, so the script uses sudo instead.
+fn script_line(entry: &CommandAuditEntry) -> String {
+    let words: Vec<String> = entry.args.iter().map(|arg| shell_quote(arg)).collect();
+    if entry.privileged {
+        format!("sudo {}", words.join(" "))
+    } else {
+        format!("{} {}", shell_quote(&entry.program), words.join(" "))
+    }
+}
+
+// Only successful, state-changing commands are reproduced; dry runs are unprivileged and skipped.
+fn build_reproduction_script(entries: &[CommandAuditEntry]) -> String {
+    let mut script = String::from(
+        "#!/bin/sh\n\
+         # Package operations performed with NebulaSys, in the order they ran.\n\
+         # Review before running on another machine.\n\
+         set -e\n",
+    );
+    for entry in entries.iter().filter(|e| e.success && e.privileged) {
+        script.push_str(&format!("\n# {}\n{}\n", entry.timestamp, script_line(entry)));
+    }
+    script
+}
+
+// Shell script repeating the last `last` successful operations (all of them if omitted).
+#[tauri::command]
+pub async fn export_reproduction_script(app: tauri::AppHandle, last: Option<usize>) -> Result<String, NebulaError> {
+    let entries: Vec<CommandAuditEntry> = load_json_lines(&app_data_file(&app, AUDIT_FILE_NAME)?)?;
+    let mut reproducible: Vec<CommandAuditEntry> = entries.into_iter().filter(|e| e.success && e.privileged).collect();
+    if let Some(last) = last {
+        let skip = reproducible.len().saturating_sub(last);
+        reproducible.drain(..skip);
+    }
+    Ok(build_reproduction_script(&reproducible))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_reproduction_script() {
+        let entry = |args: &[&str], privileged: bool, success: bool| CommandAuditEntry {
+            timestamp: "2024-05-01T10:22:00+02:00".to_string(),
+            program: if privileged { "pkexec" } else { "dnf" }.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+            privileged,
+            success,
+            exit_code: if success { 0 } else { 1 },
+        };
+        let script = build_reproduction_script(&[
+            entry(&["dnf", "update", "htop", "--assumeyes"], true, true),
+            entry(&["remove", "htop", "--assumeno"], false, true),
+            entry(&["rpm", "-e", "--nodeps", "gimp"], true, false),
+            entry(&["snapper", "create", "--description", "before updates"], true, true),
+        ]);
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains("\nsudo dnf update htop --assumeyes\n"));
+        assert!(script.contains("\nsudo snapper create --description 'before updates'\n"));
+        assert!(!script.contains("--assumeno"));
+        assert!(!script.contains("gimp"));
+    }
+}
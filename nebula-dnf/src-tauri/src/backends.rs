@@ -0,0 +1,140 @@
+use tauri::Manager;
+
+use crate::packagekit::PackageAction;
+use crate::plan::CommandPlan;
+use crate::process::run_plan;
+use crate::services::OperationManager;
+use crate::settings::GuardedAction;
+use crate::{PackageOperationResult, UserPackageWithDependencies};
+
+// A backend carrying out package operations instead of the dnf CLI. Implementors provide `run`,
+// and `list` unless the rpmdb listing covers them; the operations check their names first.
+pub trait PackageBackend {
+    // The user-installed packages, or None where they come from the rpmdb like dnf's.
+    async fn list(&self, _app: &tauri::AppHandle) -> Result<Option<Vec<UserPackageWithDependencies>>, String> {
+        Ok(None)
+    }
+
+    // Carries out an action on checked names; an update without names updates everything.
+    async fn run(&self, app: &tauri::AppHandle, action: PackageAction, packages: &[String], dry_run: bool) -> Result<PackageOperationResult, String>;
+
+    async fn install(&self, app: &tauri::AppHandle, packages: &[String], dry_run: bool) -> Result<PackageOperationResult, String> {
+        self.run(app, PackageAction::Install, checked(packages)?, dry_run).await
+    }
+
+    async fn remove(&self, app: &tauri::AppHandle, packages: &[String], dry_run: bool) -> Result<PackageOperationResult, String> {
+        self.run(app, PackageAction::Remove, checked(packages)?, dry_run).await
+    }
+
+    async fn update(&self, app: &tauri::AppHandle, packages: &[String], dry_run: bool) -> Result<PackageOperationResult, String> {
+        self.run(app, PackageAction::Update, checked(packages)?, dry_run).await
+    }
+
+    async fn update_all(&self, app: &tauri::AppHandle, dry_run: bool) -> Result<PackageOperationResult, String> {
+        self.run(app, PackageAction::Update, &[], dry_run).await
+    }
+}
+
+fn checked(packages: &[String]) -> Result<&[String], String> {
+    if packages.is_empty() {
+        return Err("No packages given.".to_string());
+    }
+    for package in packages {
+        crate::validate_package_name(package)?;
+    }
+    Ok(packages)
+}
+
+fn target(packages: &[String]) -> String {
+    if packages.is_empty() { "all packages".to_string() } else { packages.join(", ") }
+}
+
+// `action` as the package action commands take it: an update without names updates everything.
+// None of these backends can exclude packages, so updates that would move a pin are refused.
+pub async fn package_action(backend: &impl PackageBackend, app: &tauri::AppHandle, action: PackageAction, packages: &[String], dry_run: bool) -> Result<PackageOperationResult, String> {
+    if action == PackageAction::Update {
+        crate::pins::refuse_unexcluded_update(&crate::pins::pinned_packages(app)?, packages)?;
+    }
+    match (action, packages.is_empty()) {
+        (PackageAction::Install, _) => backend.install(app, packages, dry_run).await,
+        (PackageAction::Remove, _) => backend.remove(app, packages, dry_run).await,
+        (PackageAction::Update, false) => backend.update(app, packages, dry_run).await,
+        (PackageAction::Update, true) => backend.update_all(app, dry_run).await,
+    }
+}
+
+// What the package commands run: the action, or with `preview` only its dry run, returned for
+// review. After a change the package list is rebuilt.
+pub async fn guarded_action(
+    backend: &impl PackageBackend,
+    app: &tauri::AppHandle,
+    action: PackageAction,
+    packages: &[String],
+    dry_run: bool,
+    preview: bool,
+) -> Result<PackageOperationResult, String> {
+    let mut result = package_action(backend, app, action, packages, dry_run || preview).await?;
+    if preview && result.success {
+        result.confirmation_required = true;
+        result.message.push_str(" Review the dry run, then confirm.");
+    } else if !dry_run && result.success {
+        crate::refresh_user_packages_in_background(app);
+    }
+    Ok(result)
+}
+
+// The per-backend action commands: a removal or update the settings guard is previewed until it
+// is confirmed, as through the generic package commands.
+pub async fn action_command(
+    backend: &impl PackageBackend,
+    app: &tauri::AppHandle,
+    action: PackageAction,
+    packages: &[String],
+    dry_run: bool,
+    confirmed: Option<bool>,
+) -> Result<PackageOperationResult, String> {
+    let guarded = match action {
+        PackageAction::Install => None,
+        PackageAction::Remove => Some(GuardedAction::Uninstall),
+        PackageAction::Update => Some(GuardedAction::Update),
+    };
+    let preview = match guarded {
+        Some(guarded) if !dry_run => crate::settings::requires_preview(app, guarded, confirmed.unwrap_or(false))?,
+        _ => false,
+    };
+    guarded_action(backend, app, action, packages, dry_run, preview).await
+}
+
+// `run` for the command-line backends: runs `program`'s plan for the action, with the app marked
+// busy unless it is a dry run.
+pub async fn run_cli(app: &tauri::AppHandle, program: &str, plan: CommandPlan, action: PackageAction, packages: &[String], dry_run: bool) -> Result<PackageOperationResult, String> {
+    let target = target(packages);
+    let _busy = (!dry_run).then(|| app.state::<OperationManager>().begin(format!("{:?} {} through {}", action, target, program)));
+    let out = run_plan(app, &plan).await?;
+    Ok(PackageOperationResult {
+        success: out.success,
+        message: match (out.success, dry_run || out.simulated) {
+            (true, true) => format!("Dry run finished for {}.", target),
+            (true, false) => format!("{:?} finished for {}.", action, target),
+            (false, _) => format!("{} failed for {}. Exit code: {}.", program, target, out.code),
+        },
+        details: Some(format!("STDOUT:\n{}\nSTDERR:\n{}", out.stdout, out.stderr)),
+        confirmation_required: false,
+        simulated: out.simulated,
+        reboot_required: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan::strings;
+
+    #[test]
+    fn test_checked_packages() {
+        assert_eq!(checked(&[]), Err("No packages given.".to_string()));
+        assert!(checked(&strings(&["htop", "htop; reboot"])).is_err());
+        assert_eq!(checked(&strings(&["htop"])).unwrap(), ["htop"]);
+        assert_eq!(target(&[]), "all packages");
+    }
+}
@@ -0,0 +1,5 @@
+// The privileged helper behind the com.nebula-dnf.manage polkit action; see helper.rs.
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    std::process::exit(nebula_dnf_lib::helper::run(&args));
+}
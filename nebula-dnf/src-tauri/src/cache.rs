@@ -0,0 +1,429 @@
+use rusqlite::{params, Connection, ErrorCode, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tauri::Manager;
+use tracing::warn;
+
+use crate::error::NebulaError;
+use crate::process::run_command;
+use crate::{app_data_file, DependencyKind, DisplayablePackage, UserPackageWithDependencies};
+
+const CACHE_DB_FILE_NAME: &str = "package_cache.sqlite3";
+// The JSON blob the cache used to be; imported once, then removed.
+const LEGACY_CACHE_FILE_NAME: &str = "package_cache.json";
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+// How long a reader waits for a background refresh to commit before giving up.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+const METADATA_SCHEMA: &str = "
+    PRAGMA foreign_keys = ON;
+    CREATE TABLE IF NOT EXISTS metadata (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+";
+
+const SCHEMA_V1: &str = "
+    CREATE TABLE IF NOT EXISTS packages (
+        name TEXT PRIMARY KEY,
+        category TEXT NOT NULL,
+        incomplete INTEGER NOT NULL DEFAULT 0,
+        installed_size INTEGER,
+        updated_at INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS dependencies (
+        package TEXT NOT NULL REFERENCES packages(name) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        module_provider TEXT,
+        PRIMARY KEY (package, position)
+    );
+    CREATE INDEX IF NOT EXISTS dependencies_by_name ON dependencies(name);
+";
+
+// Each step brings the database from the version before it to its own. New package fields get
+// a new step (an ALTER TABLE with a default) appended here; released steps are never edited.
+const MIGRATIONS: &[(u32, &str)] = &[(1, SCHEMA_V1)];
+
+fn sql_error(e: rusqlite::Error) -> NebulaError {
+    NebulaError::CacheCorrupt { message: format!("Package cache database error: {}", e) }
+}
+
+// Why the cache could not be opened, and whether deleting the file and starting over would help.
+#[derive(Debug)]
+struct OpenError {
+    error: rusqlite::Error,
+    rebuild: bool,
+}
+
+impl OpenError {
+    // Only a file that is not a usable database is worth deleting; anything else, a lock held
+    // by a refresh in particular, is reported instead.
+    fn new(error: rusqlite::Error) -> Self {
+        let rebuild = matches!(error.sqlite_error_code(), Some(ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase));
+        OpenError { error, rebuild }
+    }
+
+    // A migration step that fails for any reason but contention leaves a schema nothing can use.
+    fn migration(error: rusqlite::Error) -> Self {
+        let rebuild = !matches!(error.sqlite_error_code(), Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked));
+        OpenError { error, rebuild }
+    }
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0)
+}
+
+// Unit enums are stored by variant name, e.g. "Library".
+fn enum_text<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(text)) => text,
+        _ => String::new(),
+    }
+}
+
+fn enum_from_text<T: DeserializeOwned + Default>(text: &str) -> T {
+    serde_json::from_value(serde_json::Value::String(text.to_string())).unwrap_or_default()
+}
+
+fn schema_version(conn: &Connection) -> rusqlite::Result<u32> {
+    let value: Option<String> = conn
+        .query_row("SELECT value FROM metadata WHERE key = 'schema_version'", [], |row| row.get(0))
+        .optional()?;
+    Ok(value.and_then(|value| value.parse().ok()).unwrap_or(0))
+}
+
+// Brings the schema up to the latest version. A database from a newer build of the app is
+// emptied instead: it is only a cache, and the next refresh fills it again.
+fn migrate(conn: &mut Connection) -> Result<(), OpenError> {
+    conn.execute_batch(METADATA_SCHEMA).map_err(OpenError::new)?;
+    let mut current = schema_version(conn).map_err(OpenError::new)?;
+    let latest = MIGRATIONS.last().map(|(version, _)| *version).unwrap_or(0);
+    if current > latest {
+        warn!("Package cache schema {} is newer than {}, starting over.", current, latest);
+        conn.execute_batch("DROP TABLE IF EXISTS dependencies; DROP TABLE IF EXISTS packages; DELETE FROM metadata;")
+            .map_err(OpenError::migration)?;
+        current = 0;
+    }
+    for (version, sql) in MIGRATIONS.iter().filter(|(version, _)| *version > current) {
+        let tx = conn.transaction().map_err(OpenError::migration)?;
+        tx.execute_batch(sql).map_err(OpenError::migration)?;
+        tx.execute("INSERT OR REPLACE INTO metadata (key, value) VALUES ('schema_version', ?1)", params![version.to_string()])
+            .map_err(OpenError::migration)?;
+        tx.commit().map_err(OpenError::migration)?;
+    }
+    Ok(())
+}
+
+fn insert_package(conn: &Connection, package: &UserPackageWithDependencies) -> Result<(), NebulaError> {
+    conn.execute(
+        "INSERT OR REPLACE INTO packages (name, category, incomplete, installed_size, updated_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![package.name, enum_text(&package.category), package.incomplete, package.installed_size.map(|size| size as i64), now_secs()],
+    )
+    .map_err(sql_error)?;
+    conn.execute("DELETE FROM dependencies WHERE package = ?1", params![package.name]).map_err(sql_error)?;
+    let mut insert = conn
+        .prepare_cached("INSERT INTO dependencies (package, position, name, kind, module_provider) VALUES (?1, ?2, ?3, ?4, ?5)")
+        .map_err(sql_error)?;
+    for (position, dep) in package.dependencies.iter().enumerate() {
+        let provider = dep.module_provider.as_ref().map(serde_json::to_string).transpose().map_err(|e| e.to_string())?;
+        insert.execute(params![package.name, position as i64, dep.name, enum_text(&dep.kind), provider]).map_err(sql_error)?;
+    }
+    Ok(())
+}
+
+// Replaces every cached package, as a full refresh does, and stamps the refresh time.
+fn write_packages(conn: &mut Connection, packages: &[UserPackageWithDependencies]) -> Result<(), NebulaError> {
+    let tx = conn.transaction().map_err(sql_error)?;
+    tx.execute("DELETE FROM packages", []).map_err(sql_error)?;
+    for package in packages {
+        insert_package(&tx, package)?;
+    }
+    tx.execute("INSERT OR REPLACE INTO metadata (key, value) VALUES ('refreshed_at', ?1)", params![now_secs().to_string()])
+        .map_err(sql_error)?;
+    tx.commit().map_err(sql_error)
+}
+
+fn read_dependencies(conn: &Connection, package: &str) -> Result<Vec<DisplayablePackage>, NebulaError> {
+    let mut query = conn
+        .prepare_cached("SELECT name, kind, module_provider FROM dependencies WHERE package = ?1 ORDER BY position")
+        .map_err(sql_error)?;
+    let rows = query
+        .query_map(params![package], |row| {
+            let provider: Option<String> = row.get(2)?;
+            Ok(DisplayablePackage {
+                name: row.get(0)?,
+                kind: enum_from_text(&row.get::<_, String>(1)?),
+                module_provider: provider.and_then(|json| serde_json::from_str(&json).ok()),
+            })
+        })
+        .map_err(sql_error)?;
+    rows.collect::<Result<_, _>>().map_err(sql_error)
+}
+
+// Packages from a `SELECT name, category, incomplete, installed_size` query, with their dependencies.
+fn read_rows(conn: &Connection, sql: &str, params: impl rusqlite::Params) -> Result<Vec<UserPackageWithDependencies>, NebulaError> {
+    let mut query = conn.prepare(sql).map_err(sql_error)?;
+    let rows: Vec<(String, String, bool, Option<i64>)> = query
+        .query_map(params, |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+        .map_err(sql_error)?
+        .collect::<Result<_, _>>()
+        .map_err(sql_error)?;
+    rows.into_iter()
+        .map(|(name, category, incomplete, installed_size)| {
+            Ok(UserPackageWithDependencies {
+                dependencies: read_dependencies(conn, &name)?,
+                name,
+                category: enum_from_text(&category),
+                incomplete,
+                installed_size: installed_size.map(|size| size as u64),
+                appstream: None,
+            })
+        })
+        .collect()
+}
+
+fn refreshed_at_secs(conn: &Connection) -> Result<Option<u64>, NebulaError> {
+    let value: Option<String> = conn
+        .query_row("SELECT value FROM metadata WHERE key = 'refreshed_at'", [], |row| row.get(0))
+        .optional()
+        .map_err(sql_error)?;
+    Ok(value.and_then(|value| value.parse().ok()))
+}
+
+// None until the first full refresh has been stored.
+fn read_packages(conn: &Connection) -> Result<Option<Vec<UserPackageWithDependencies>>, NebulaError> {
+    if refreshed_at_secs(conn)?.is_none() {
+        return Ok(None);
+    }
+    read_rows(conn, "SELECT name, category, incomplete, installed_size FROM packages ORDER BY name", []).map(Some)
+}
+
+// Packages whose name, or the name of one of their dependencies, starts with `query`.
+fn search(conn: &Connection, query: &str, limit: usize) -> Result<Vec<UserPackageWithDependencies>, NebulaError> {
+    let pattern = format!("{}%", query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+    read_rows(
+        conn,
+        "SELECT name, category, incomplete, installed_size FROM packages
+         WHERE name LIKE ?1 ESCAPE '\\'
+            OR name IN (SELECT package FROM dependencies WHERE name LIKE ?1 ESCAPE '\\')
+         ORDER BY name LIMIT ?2",
+        params![pattern, limit as i64],
+    )
+}
+
+// Best effort: a JSON cache that no longer parses is just dropped; the next refresh rebuilds it.
+fn import_legacy_cache(legacy_path: &Path, conn: &mut Connection) {
+    if !legacy_path.exists() {
+        return;
+    }
+    match crate::load_json_file::<Vec<UserPackageWithDependencies>>(legacy_path) {
+        Ok(Some(packages)) => {
+            if let Err(e) = write_packages(conn, &packages) {
+                warn!("Failed to import the old package cache: {}", e);
+                return;
+            }
+        }
+        Ok(None) => {}
+        Err(e) => warn!("Dropping unreadable old package cache: {}", e),
+    }
+    if let Err(e) = std::fs::remove_file(legacy_path) {
+        warn!("Failed to remove {:?}: {}", legacy_path, e);
+    }
+}
+
+// Deletes the cached packages that are no longer installed and returns them.
+fn remove_missing(conn: &mut Connection, installed: &HashSet<String>) -> Result<Vec<UserPackageWithDependencies>, NebulaError> {
+    let Some(cached) = read_packages(conn)? else { return Ok(Vec::new()) };
+    let gone: Vec<UserPackageWithDependencies> = cached.into_iter().filter(|package| !installed.contains(&package.name)).collect();
+    let tx = conn.transaction().map_err(sql_error)?;
+    for package in &gone {
+        tx.execute("DELETE FROM packages WHERE name = ?1", params![package.name]).map_err(sql_error)?;
+    }
+    tx.commit().map_err(sql_error)?;
+    Ok(gone)
+}
+
+// The package cache as a managed service; its paths are resolved once at startup.
+pub struct CacheService {
+    db_path: PathBuf,
+    legacy_path: PathBuf,
+    refreshing: AtomicBool, // a background refresh is under way
+}
+
+impl CacheService {
+    pub fn new(app: &tauri::AppHandle) -> Result<Self, NebulaError> {
+        Ok(CacheService {
+            db_path: app_data_file(app, CACHE_DB_FILE_NAME)?,
+            legacy_path: app_data_file(app, LEGACY_CACHE_FILE_NAME)?,
+            refreshing: AtomicBool::new(false),
+        })
+    }
+
+    // Readers wait out a refresh's write transaction instead of failing on the lock.
+    fn open_and_migrate(&self) -> Result<Connection, OpenError> {
+        let mut conn = Connection::open(&self.db_path).map_err(OpenError::new)?;
+        conn.busy_timeout(BUSY_TIMEOUT).map_err(OpenError::new)?;
+        migrate(&mut conn)?;
+        Ok(conn)
+    }
+
+    // A database that is not usable (truncated, not SQLite at all) or whose migration failed is
+    // deleted and rebuilt rather than reported: users only ever see an empty cache being refilled.
+    fn open(&self) -> Result<Connection, NebulaError> {
+        if let Some(parent_dir) = self.db_path.parent() {
+            std::fs::create_dir_all(parent_dir).map_err(|e| format!("Failed to create cache directory: {}", e))?;
+        }
+        let mut conn = match self.open_and_migrate() {
+            Ok(conn) => conn,
+            Err(e) if e.rebuild => {
+                warn!("Rebuilding unusable package cache {:?}: {}", self.db_path, e.error);
+                std::fs::remove_file(&self.db_path).map_err(|e| format!("Failed to remove {:?}: {}", self.db_path, e))?;
+                self.open_and_migrate().map_err(|e| sql_error(e.error))?
+            }
+            Err(e) => return Err(sql_error(e.error)),
+        };
+        import_legacy_cache(&self.legacy_path, &mut conn);
+        Ok(conn)
+    }
+
+    pub fn load_packages(&self) -> Result<Option<Vec<UserPackageWithDependencies>>, NebulaError> {
+        read_packages(&self.open()?)
+    }
+
+    pub fn save_packages(&self, packages: &[UserPackageWithDependencies]) -> Result<(), NebulaError> {
+        write_packages(&mut self.open()?, packages)
+    }
+
+    // Brings the cache up to date after packages were removed, instead of a full refresh: drops
+    // the entries of everything no longer installed (dnf may have taken dependents along) and
+    // checks which of their dependencies nothing needs anymore. Returns a note for the result.
+    pub async fn update_after_removal(&self, app: &tauri::AppHandle) -> Result<String, NebulaError> {
+        let installed = crate::rpmdb::installed_names(app).await?;
+        let gone = remove_missing(&mut self.open()?, &installed)?;
+        if gone.is_empty() {
+            return Ok(String::new());
+        }
+        let dependencies: HashSet<&str> = gone
+            .iter()
+            .flat_map(|package| package.dependencies.iter())
+            .filter(|dep| dep.kind == DependencyKind::Package)
+            .map(|dep| dep.name.as_str())
+            .collect();
+        let out = run_command(app, "dnf", &["repoquery", "--unneeded", "--quiet", "--queryformat", "%{name}\n"]).await?;
+        let mut unneeded: Vec<&str> = if out.success {
+            out.stdout.lines().map(str::trim).filter(|name| dependencies.contains(name)).collect()
+        } else {
+            warn!("dnf repoquery --unneeded failed: {}", out.error_text().trim());
+            Vec::new()
+        };
+        unneeded.sort();
+        unneeded.dedup();
+        let removed: Vec<&str> = gone.iter().map(|package| package.name.as_str()).collect();
+        let mut note = format!("Package cache updated: removed {}.", removed.join(", "));
+        if !unneeded.is_empty() {
+            note.push_str(&format!(" No longer needed by anything: {}.", unneeded.join(", ")));
+        }
+        Ok(note)
+    }
+
+    // Unix time of the last full refresh.
+    pub fn refreshed_at(&self) -> Result<Option<u64>, NebulaError> {
+        refreshed_at_secs(&self.open()?)
+    }
+
+    // Whether the last full refresh is older than `max_age`; a cache never filled is not stale,
+    // it is missing.
+    pub fn is_stale(&self, max_age: Duration) -> Result<bool, NebulaError> {
+        Ok(self.refreshed_at()?.is_some_and(|refreshed| (now_secs() as u64).saturating_sub(refreshed) > max_age.as_secs()))
+    }
+
+    // Claims the background refresh; false when one is already running.
+    pub fn start_refresh(&self) -> bool {
+        !self.refreshing.swap(true, Ordering::SeqCst)
+    }
+
+    pub fn finish_refresh(&self) {
+        self.refreshing.store(false, Ordering::SeqCst);
+    }
+}
+
+// Searches the cached package list without loading all of it; empty until the first refresh.
+#[tauri::command]
+pub async fn search_package_cache(app: tauri::AppHandle, query: String, limit: Option<usize>) -> Result<Vec<UserPackageWithDependencies>, NebulaError> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    search(&app.state::<CacheService>().open()?, query, limit.unwrap_or(DEFAULT_SEARCH_LIMIT))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PackageCategory;
+
+    #[test]
+    fn test_cache_round_trip() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), MIGRATIONS.last().unwrap().0);
+        assert_eq!(read_packages(&conn).unwrap(), None);
+
+        let dep = |name: &str, kind| DisplayablePackage { name: name.to_string(), kind, module_provider: None };
+        let packages = vec![
+            UserPackageWithDependencies {
+                name: "htop".to_string(),
+                category: PackageCategory::Utility,
+                dependencies: vec![dep("libc.so.6", DependencyKind::Library), dep("ncurses-libs", DependencyKind::Package)],
+                incomplete: false,
+                installed_size: Some(512_000),
+                appstream: None,
+            },
+            UserPackageWithDependencies {
+                name: "perl-Foo".to_string(),
+                category: PackageCategory::Development,
+                dependencies: vec![dep("perl(strict)", DependencyKind::LanguageModule)],
+                incomplete: true,
+                installed_size: None,
+                appstream: None,
+            },
+        ];
+        write_packages(&mut conn, &packages).unwrap();
+        assert_eq!(read_packages(&conn).unwrap(), Some(packages.clone()));
+
+        assert_eq!(search(&conn, "ncurses", 10).unwrap()[0].name, "htop");
+        assert_eq!(search(&conn, "perl", 10).unwrap().len(), 1);
+        assert!(search(&conn, "%", 10).unwrap().is_empty());
+
+        let gone = remove_missing(&mut conn, &HashSet::from(["htop".to_string()])).unwrap();
+        assert_eq!(gone, vec![packages[1].clone()]);
+        assert_eq!(read_packages(&conn).unwrap(), Some(vec![packages[0].clone()]));
+        let dependencies: i64 = conn.query_row("SELECT COUNT(*) FROM dependencies", [], |row| row.get(0)).unwrap();
+        assert_eq!(dependencies, 2);
+
+        // Migrating again is a no-op; a schema from a newer build is dropped, not an error.
+        migrate(&mut conn).unwrap();
+        assert_eq!(read_packages(&conn).unwrap(), Some(vec![packages[0].clone()]));
+        conn.execute("UPDATE metadata SET value = '999' WHERE key = 'schema_version'", []).unwrap();
+        migrate(&mut conn).unwrap();
+        assert_eq!(read_packages(&conn).unwrap(), None);
+        assert_eq!(schema_version(&conn).unwrap(), MIGRATIONS.last().unwrap().0);
+    }
+
+    #[test]
+    fn test_open_error_rebuild() {
+        let failure = |code| rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(code), None);
+        assert!(OpenError::new(failure(rusqlite::ffi::SQLITE_NOTADB)).rebuild);
+        assert!(OpenError::new(failure(rusqlite::ffi::SQLITE_CORRUPT)).rebuild);
+        assert!(!OpenError::new(failure(rusqlite::ffi::SQLITE_BUSY)).rebuild);
+        assert!(!OpenError::new(failure(rusqlite::ffi::SQLITE_CANTOPEN)).rebuild);
+        assert!(OpenError::migration(failure(rusqlite::ffi::SQLITE_ERROR)).rebuild);
+        assert!(!OpenError::migration(failure(rusqlite::ffi::SQLITE_LOCKED)).rebuild);
+    }
+}
@@ -0,0 +1,267 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tauri::Manager;
+
+use crate::error::NebulaError;
+use crate::plan;
+use crate::process::{run_command, run_plan};
+use crate::services::OperationManager;
+use crate::transaction::{parse_transaction_table, TransactionItem};
+use crate::{extract_base_package_name, DependencyKind, DisplayablePackage, PackageOperationResult};
+
+const PACKAGE_QUERY_FORMAT: &str = "%{name}\t%{version}-%{release}\t%{arch}\t%{summary}\n";
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum OrphanReason {
+    Unneeded, // installed as a dependency, and nothing requires it anymore
+    Extra,    // not available from any enabled repository
+    WeakOnly, // pulled in by a Recommends/Supplements, and nothing installed hard-requires it
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct OrphanCleanupResult {
+    dry_run: bool,
+    transaction: Vec<TransactionItem>, // what was (or would be) removed
+    incomplete: bool,                  // some rows of dnf's table could not be parsed
+    result: PackageOperationResult,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct OrphanPackage {
+    pub name: String,
+    version: String,
+    pub arch: String,
+    summary: String,
+    reasons: Vec<OrphanReason>,
+}
+
+// Parses repoquery lines in PACKAGE_QUERY_FORMAT into (name, version, arch, summary).
+fn parse_package_rows(output: &str) -> Vec<(String, String, String, String)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(4, '\t').map(str::trim);
+            let name = fields.next().filter(|n| !n.is_empty())?;
+            Some((
+                name.to_string(),
+                fields.next()?.to_string(),
+                fields.next()?.to_string(),
+                fields.next().unwrap_or("").to_string(),
+            ))
+        })
+        .collect()
+}
+
+async fn query_installed(app: &tauri::AppHandle, flag: &str) -> Result<String, String> {
+    let out = run_command(app, "dnf", &["repoquery", "--installed", flag, "--quiet", "--queryformat", PACKAGE_QUERY_FORMAT]).await?;
+    if out.success {
+        Ok(out.stdout)
+    } else {
+        Err(format!("dnf repoquery {} failed: {}", flag, out.error_text().trim()))
+    }
+}
+
+// Merges both queries; a package can be unneeded and extra at the same time.
+fn merge_orphans(unneeded: &str, extras: &str) -> Vec<OrphanPackage> {
+    let mut by_key: BTreeMap<(String, String), OrphanPackage> = BTreeMap::new();
+    for (output, reason) in [(unneeded, OrphanReason::Unneeded), (extras, OrphanReason::Extra)] {
+        for (name, version, arch, summary) in parse_package_rows(output) {
+            let entry = by_key.entry((name.clone(), arch.clone())).or_insert_with(|| OrphanPackage {
+                name,
+                version,
+                arch,
+                summary,
+                reasons: Vec::new(),
+            });
+            if !entry.reasons.contains(&reason) {
+                entry.reasons.push(reason);
+            }
+        }
+    }
+    by_key.into_values().collect()
+}
+
+// Removal candidates, without removing anything. Sorted by name.
+pub async fn orphan_packages(app: &tauri::AppHandle) -> Result<Vec<OrphanPackage>, String> {
+    let unneeded = query_installed(app, "--unneeded").await?;
+    let extras = query_installed(app, "--extras").await?;
+    Ok(merge_orphans(&unneeded, &extras))
+}
+
+#[tauri::command]
+pub async fn list_orphan_packages(app: tauri::AppHandle) -> Result<Vec<OrphanPackage>, NebulaError> {
+    Ok(orphan_packages(&app).await?)
+}
+
+// Installed packages that nothing else requires. Prefers `repoquery --leaves` (dnf5) and falls
+// back to the `dnf leaves` plugin command, whose lines look like "- name-1.0-1.fc40.x86_64".
+pub async fn fetch_leaf_names(app: &tauri::AppHandle) -> Result<HashSet<String>, String> {
+    let out = run_command(app, "dnf", &["repoquery", "--installed", "--leaves", "--quiet", "--queryformat", "%{name}\n"]).await?;
+    if out.success {
+        return Ok(out.stdout.lines().map(str::trim).filter(|l| !l.is_empty()).map(String::from).collect());
+    }
+    let out = run_command(app, "dnf", &["leaves", "--quiet"]).await?;
+    if !out.success {
+        return Err(format!("Could not determine leaf packages: {}", out.error_text().trim()));
+    }
+    Ok(out
+        .stdout
+        .lines()
+        .filter_map(|line| line.strip_prefix("- "))
+        .map(extract_base_package_name)
+        .collect())
+}
+
+// Packages nothing else requires: candidates for removal, though many are apps the user wants.
+#[tauri::command]
+pub async fn list_leaf_packages(app: tauri::AppHandle) -> Result<Vec<DisplayablePackage>, NebulaError> {
+    let mut leaves: Vec<DisplayablePackage> = fetch_leaf_names(&app)
+        .await?
+        .into_iter()
+        .map(|name| DisplayablePackage { name, kind: DependencyKind::Package, module_provider: None })
+        .collect();
+    leaves.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(leaves)
+}
+
+// Packages installed as weak dependencies ("reason" weak-dependency) that no other installed
+// package requires: every requirement, including file and rich-dependency operands, is matched
+// against what the candidates provide.
+fn find_weak_only(candidates: &HashSet<String>, requires: &str, provides: &str) -> HashSet<String> {
+    let mut required_by: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for (package, requirement) in requires.lines().filter_map(|line| line.split_once('\t')) {
+        // "(foo >= 1.0 if bar)" is resolved conservatively: every operand counts as required
+        for capability in requirement.split(|c: char| c.is_whitespace() || c == '(' || c == ')').filter(|c| !c.is_empty()) {
+            required_by.entry(capability).or_default().insert(package);
+        }
+    }
+    let mut hard_required = HashSet::new();
+    for (package, capability) in provides.lines().filter_map(|line| line.split_once('\t')) {
+        if required_by.get(capability).is_some_and(|requirers| requirers.iter().any(|r| *r != package)) {
+            hard_required.insert(package);
+        }
+    }
+    candidates.iter().filter(|c| !hard_required.contains(c.as_str())).cloned().collect()
+}
+
+// Candidates for a lean system: what only Recommends keeps installed. Sorted by name.
+#[tauri::command]
+pub async fn list_weak_only_packages(app: tauri::AppHandle) -> Result<Vec<OrphanPackage>, NebulaError> {
+    let format = "%{name}\t%{version}-%{release}\t%{arch}\t%{reason}\t%{summary}\n";
+    let out = run_command(&app, "dnf", &["repoquery", "--installed", "--quiet", "--queryformat", format]).await?;
+    if !out.success {
+        return Err(NebulaError::command_failed(out.code, &out.stderr, format!("dnf repoquery failed: {}", out.error_text().trim())));
+    }
+    let mut weak_rows = BTreeMap::new();
+    for (name, version, arch, rest) in parse_package_rows(&out.stdout) {
+        // dnf4 reports "weak-dependency", dnf5 "Weak Dependency"
+        let Some((reason, summary)) = rest.split_once('\t') else { continue };
+        if reason.to_lowercase().replace(' ', "-") == "weak-dependency" {
+            weak_rows.insert((name.clone(), arch.clone()), OrphanPackage {
+                name,
+                version,
+                arch,
+                summary: summary.to_string(),
+                reasons: vec![OrphanReason::WeakOnly],
+            });
+        }
+    }
+    if weak_rows.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let candidates: HashSet<String> = weak_rows.keys().map(|(name, _)| name.clone()).collect();
+    let requires = run_command(&app, "rpm", &["-qa", "--queryformat", "[%{NAME}\t%{REQUIRENAME}\n]"]).await?;
+    let mut provides_args = vec!["-q", "--queryformat", "[%{NAME}\t%{PROVIDENAME}\n][%{NAME}\t%{FILENAMES}\n]", "--"];
+    provides_args.extend(candidates.iter().map(String::as_str));
+    let provides = run_command(&app, "rpm", &provides_args).await?;
+    if !requires.success || !provides.success {
+        return Err(format!("rpm dependency query failed: {}", requires.error_text().trim()).into());
+    }
+    let weak_only = find_weak_only(&candidates, &requires.stdout, &provides.stdout);
+    Ok(weak_rows.into_values().filter(|package| weak_only.contains(&package.name)).collect())
+}
+
+// Resolves (dry run) or runs the removal of `packages` along with dependencies only they needed.
+#[tauri::command]
+pub async fn remove_weak_only_packages(
+    app: tauri::AppHandle,
+    packages: Vec<String>,
+    dry_run: bool,
+    confirmed: Option<bool>,
+) -> Result<OrphanCleanupResult, NebulaError> {
+    let preview = !dry_run && crate::settings::requires_preview(&app, crate::settings::GuardedAction::Autoremove, confirmed.unwrap_or(false))?;
+    let dry_run = dry_run || preview;
+    let command = plan::remove_with_unneeded(&packages, dry_run)?;
+    Ok(run_removal(&app, &command, dry_run, preview, "weak-dependency-only").await?)
+}
+
+// With `dry_run` the autoremove transaction is only resolved and returned for confirmation;
+// otherwise it is executed through pkexec. If the settings ask for a preview, an unconfirmed
+// removal is turned into a dry run.
+#[tauri::command]
+pub async fn cleanup_orphans(app: tauri::AppHandle, dry_run: bool, confirmed: Option<bool>) -> Result<OrphanCleanupResult, NebulaError> {
+    let preview = !dry_run && crate::settings::requires_preview(&app, crate::settings::GuardedAction::Autoremove, confirmed.unwrap_or(false))?;
+    let dry_run = dry_run || preview;
+    let command = if dry_run { plan::autoremove_preview() } else { plan::autoremove() };
+    Ok(run_removal(&app, &command, dry_run, preview, "orphaned").await?)
+}
+
+// `kind` describes the packages in messages, e.g. "orphaned".
+async fn run_removal(app: &tauri::AppHandle, command: &plan::CommandPlan, dry_run: bool, preview: bool, kind: &str) -> Result<OrphanCleanupResult, String> {
+    let _busy = (!dry_run).then(|| app.state::<OperationManager>().begin(format!("Removing {} packages", kind)));
+    let out = run_plan(app, command).await?;
+    let (transaction, unparsed) = parse_transaction_table(&out.stdout);
+    let incomplete = crate::parse_failures::record(app, "dnf transaction table", &unparsed).await;
+    let nothing_to_do = out.stdout.contains("Nothing to do");
+    let transaction_is_empty = transaction.is_empty();
+
+    // --assumeno makes dnf exit non-zero after printing the transaction; that is the expected outcome.
+    let success = out.success || (dry_run && (!transaction.is_empty() || nothing_to_do));
+    let message = match (success, dry_run) {
+        (true, _) if transaction.is_empty() => format!("No {} packages to remove.", kind),
+        (true, true) => format!("{} package(s) would be removed.", transaction.len()),
+        (true, false) => format!("Removed {} {} package(s).", transaction.len(), kind),
+        (false, _) => format!("{} failed with exit code {}.", command.command_line(), out.code),
+    };
+    Ok(OrphanCleanupResult {
+        dry_run,
+        transaction,
+        incomplete,
+        result: PackageOperationResult {
+            success,
+            message,
+            details: Some(format!("STDOUT:\n{}\nSTDERR:\n{}", out.stdout, out.stderr)),
+            confirmation_required: preview && !transaction_is_empty,
+            simulated: out.simulated,
+            reboot_required: false,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_orphans() {
+        let unneeded = "libfoo\t1.0-1.fc40\tx86_64\tFoo library\n\nold-tool\t2.1-3.fc39\tnoarch\tOld tool\n";
+        let extras = "old-tool\t2.1-3.fc39\tnoarch\tOld tool\nvendor-app\t5.0-1\tx86_64\tVendor app\n";
+        let orphans = merge_orphans(unneeded, extras);
+        assert_eq!(orphans.len(), 3);
+        assert_eq!(orphans[0].name, "libfoo");
+        assert_eq!(orphans[0].reasons, vec![OrphanReason::Unneeded]);
+        assert_eq!(orphans[1].name, "old-tool");
+        assert_eq!(orphans[1].reasons, vec![OrphanReason::Unneeded, OrphanReason::Extra]);
+        assert_eq!(orphans[2].version, "5.0-1");
+    }
+
+    #[test]
+    fn test_find_weak_only() {
+        let candidates: HashSet<String> = ["flac-libs", "ibus-anthy", "jq"].iter().map(|s| s.to_string()).collect();
+        let requires = "sox\tlibFLAC.so.12()(64bit)\nsox\t/usr/bin/sh\nibus-anthy\tibus\nvim\t(jq if python3)\n";
+        let provides = "flac-libs\tlibFLAC.so.12()(64bit)\nflac-libs\t/usr/lib64/libFLAC.so.12\nibus-anthy\tibus-anthy\njq\tjq\n";
+        let weak_only = find_weak_only(&candidates, requires, provides);
+        assert_eq!(weak_only, HashSet::from(["ibus-anthy".to_string()]));
+    }
+}
@@ -0,0 +1,422 @@
+use serde::Serialize;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::cleanup::OrphanPackage;
+use crate::error::NebulaError;
+use crate::process::run_command;
+use crate::settings::PackageBackend;
+
+const INSTALLED_QUERY_FORMAT: &str = "%{NAME}\t%{VERSION}-%{RELEASE}\t%{ARCH}\t%{SIZE}\t%{EPOCHNUM}\n";
+const LEFTOVER_SUFFIXES: &[&str] = &[".rpmnew", ".rpmsave", ".rpmorig"];
+const LOCALE_CONF_PATH: &str = "/etc/locale.conf";
+
+// Steps of the cleanup wizard, each an analysis of its own.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum CleanupCategory {
+    DuplicatePackages,
+    DnfCache,
+    Orphans,
+    OldKernels,
+    UnusedFlatpaks,
+    Debuginfo,
+    Langpacks,
+    LeftoverConfigs,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CleanupPriority {
+    High,   // safe and worth doing, or a problem that gets in the way of updates
+    Medium, // safe, but check the list first
+    Low,    // takes a decision only the user can make
+}
+
+// How the UI carries a step out.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum CleanupAction {
+    Command(&'static str), // the app command to call, with the step's items where it takes packages
+    Manual(&'static str),  // a command line to show; the app has no command for it
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct CleanupItem {
+    name: String, // a package, Flatpak ref, kernel release or file path
+    bytes: Option<u64>,
+    detail: Option<String>, // e.g. the Flatpak installation or the package version
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct CleanupStep {
+    category: CleanupCategory,
+    priority: CleanupPriority,
+    explanation: &'static str,
+    action: CleanupAction,
+    items: Vec<CleanupItem>,
+    bytes: u64, // what the step frees, as far as the items' sizes are known
+    error: Option<String>, // the analysis failed; the other steps are still there
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct CleanupAnalysis {
+    steps: Vec<CleanupStep>, // by priority, then by size; steps with nothing to do are left out
+    total_bytes: u64,
+}
+
+fn about(category: CleanupCategory) -> (CleanupPriority, &'static str, CleanupAction) {
+    use CleanupAction::*;
+    use CleanupPriority::*;
+    match category {
+        CleanupCategory::DuplicatePackages => (High, "Two versions of these packages are installed, usually after an interrupted update. They can make later updates fail.", Manual("dnf remove --duplicates")),
+        CleanupCategory::DnfCache => (High, "Downloaded packages and repository metadata. dnf downloads them again when it needs them.", Command("clean_dnf_cache")),
+        CleanupCategory::Orphans => (Medium, "Dependencies nothing needs anymore, and packages no enabled repository provides.", Command("cleanup_orphans")),
+        CleanupCategory::OldKernels => (Medium, "Older kernels of each kind, other than the running and default ones.", Manual("dnf remove --oldinstallonly")),
+        CleanupCategory::UnusedFlatpaks => (Medium, "Flatpak runtimes and extensions no installed app uses.", Manual("flatpak uninstall --unused")),
+        CleanupCategory::Debuginfo => (Medium, "Debugging symbols, only needed to debug crashes.", Command("execute_package_uninstall")),
+        CleanupCategory::Langpacks => (Low, "Translations, dictionaries and fonts for languages other than the system's.", Command("execute_package_uninstall")),
+        CleanupCategory::LeftoverConfigs => (Low, "Configuration files rpm set aside because they had been changed. Merge what you need, then delete them.", Manual("find /etc -name '*.rpmnew' -o -name '*.rpmsave' -o -name '*.rpmorig'")),
+    }
+}
+
+fn step(category: CleanupCategory, items: Result<Vec<CleanupItem>, String>) -> CleanupStep {
+    let (priority, explanation, action) = about(category);
+    let (items, error) = match items {
+        Ok(items) => (items, None),
+        Err(e) => (Vec::new(), Some(e)),
+    };
+    CleanupStep { category, priority, explanation, action, bytes: items.iter().filter_map(|item| item.bytes).sum(), items, error }
+}
+
+fn item(name: impl Into<String>, bytes: Option<u64>, detail: Option<String>) -> CleanupItem {
+    CleanupItem { name: name.into(), bytes, detail }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct InstalledRow {
+    name: String,
+    version: String, // version-release
+    arch: String,
+    size: u64,
+    epoch: u64,
+}
+
+fn parse_installed_rows(output: &str) -> Vec<InstalledRow> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t').map(str::trim);
+            Some(InstalledRow {
+                name: fields.next().filter(|name| !name.is_empty())?.to_string(),
+                version: fields.next()?.to_string(),
+                arch: fields.next()?.to_string(),
+                size: fields.next()?.parse().unwrap_or(0),
+                epoch: fields.next().and_then(|epoch| epoch.parse().ok()).unwrap_or(0),
+            })
+        })
+        .collect()
+}
+
+// rpmvercmp: compares alternating runs of digits and letters, numerically and alphabetically;
+// "~" sorts before anything (pre-releases), "^" after the version but before anything else.
+fn rpmvercmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.as_bytes(), b.as_bytes());
+    let is_separator = |c: &u8| !c.is_ascii_alphanumeric() && *c != b'~' && *c != b'^';
+    loop {
+        a = &a[a.iter().take_while(|c| is_separator(c)).count()..];
+        b = &b[b.iter().take_while(|c| is_separator(c)).count()..];
+        match (a.first(), b.first()) {
+            (Some(b'~'), Some(b'~')) | (Some(b'^'), Some(b'^')) => {
+                a = &a[1..];
+                b = &b[1..];
+                continue;
+            }
+            (Some(b'~'), _) => return Ordering::Less,
+            (_, Some(b'~')) => return Ordering::Greater,
+            (None, Some(b'^')) => return Ordering::Less,
+            (Some(b'^'), None) => return Ordering::Greater,
+            (Some(b'^'), _) => return Ordering::Less,
+            (_, Some(b'^')) => return Ordering::Greater,
+            (None, _) | (_, None) => break,
+            _ => {}
+        }
+        let numeric = a[0].is_ascii_digit();
+        let run = |s: &[u8]| s.iter().take_while(|c| if numeric { c.is_ascii_digit() } else { c.is_ascii_alphabetic() }).count();
+        let (len_a, len_b) = (run(a), run(b));
+        if len_b == 0 {
+            // A number is newer than letters in the same place
+            return if numeric { Ordering::Greater } else { Ordering::Less };
+        }
+        let (mut seg_a, mut seg_b) = (&a[..len_a], &b[..len_b]);
+        if numeric {
+            seg_a = &seg_a[seg_a.iter().take_while(|c| **c == b'0').count()..];
+            seg_b = &seg_b[seg_b.iter().take_while(|c| **c == b'0').count()..];
+        }
+        let order = if numeric { seg_a.len().cmp(&seg_b.len()).then_with(|| seg_a.cmp(seg_b)) } else { seg_a.cmp(seg_b) };
+        if order != Ordering::Equal {
+            return order;
+        }
+        a = &a[len_a..];
+        b = &b[len_b..];
+    }
+    a.len().cmp(&b.len())
+}
+
+fn version_release(row: &InstalledRow) -> (&str, &str) {
+    row.version.rsplit_once('-').unwrap_or((&row.version, ""))
+}
+
+// Epoch, then version and release, each with rpmvercmp.
+fn compare_evr(a: &InstalledRow, b: &InstalledRow) -> Ordering {
+    let ((version_a, release_a), (version_b, release_b)) = (version_release(a), version_release(b));
+    a.epoch.cmp(&b.epoch).then_with(|| rpmvercmp(version_a, version_b)).then_with(|| rpmvercmp(release_a, release_b))
+}
+
+fn package_item(row: &InstalledRow) -> CleanupItem {
+    item(row.name.clone(), Some(row.size), Some(format!("{}.{}", row.version, row.arch)))
+}
+
+// Installed more than once for the same architecture. Kernels are meant to be, and every
+// imported signing key is a gpg-pubkey "package". The older copies are the removal candidates.
+fn duplicate_items(rows: &[InstalledRow]) -> Vec<CleanupItem> {
+    let mut by_package: BTreeMap<(&str, &str), Vec<&InstalledRow>> = BTreeMap::new();
+    for row in rows.iter().filter(|row| !row.name.starts_with("kernel") && row.name != "gpg-pubkey") {
+        by_package.entry((&row.name, &row.arch)).or_default().push(row);
+    }
+    by_package
+        .into_values()
+        .filter(|copies| copies.len() > 1)
+        .flat_map(|mut copies| {
+            copies.sort_by(|a, b| compare_evr(a, b));
+            copies.pop();
+            copies.into_iter().map(package_item)
+        })
+        .collect()
+}
+
+fn orphan_items(rows: &[InstalledRow], orphans: &[OrphanPackage]) -> Vec<CleanupItem> {
+    orphans
+        .iter()
+        .map(|orphan| {
+            let row = rows.iter().find(|row| row.name == orphan.name && row.arch == orphan.arch);
+            item(orphan.name.clone(), row.map(|row| row.size), row.map(|row| format!("{}.{}", row.version, row.arch)))
+        })
+        .collect()
+}
+
+// Every kernel package of a release counts towards it: kernel-core, kernel-modules and so on.
+fn kernel_items(rows: &[InstalledRow], releases: &[String]) -> Vec<CleanupItem> {
+    releases
+        .iter()
+        .map(|release| {
+            let bytes = rows
+                .iter()
+                .filter(|row| row.name.starts_with("kernel") && format!("{}.{}", row.version, row.arch) == *release)
+                .map(|row| row.size)
+                .sum();
+            item(release.clone(), Some(bytes), None)
+        })
+        .collect()
+}
+
+fn debuginfo_items(rows: &[InstalledRow]) -> Vec<CleanupItem> {
+    rows.iter().filter(|row| row.name.contains("-debuginfo") || row.name.ends_with("-debugsource")).map(package_item).collect()
+}
+
+// The language of "glibc-langpack-de", "langpacks-core-pt_BR" or "libreoffice-langpack-fr".
+fn langpack_language(name: &str) -> Option<&str> {
+    let code = match name.split_once("-langpack-") {
+        Some((_, code)) => code,
+        None => ["langpacks-core-", "langpacks-fonts-", "langpacks-"].iter().find_map(|prefix| name.strip_prefix(prefix))?,
+    };
+    code.split(['_', '-']).next().filter(|language| !language.is_empty())
+}
+
+fn langpack_items(rows: &[InstalledRow], languages: &HashSet<String>) -> Vec<CleanupItem> {
+    rows.iter()
+        .filter(|row| langpack_language(&row.name).is_some_and(|language| !languages.contains(language)))
+        .map(package_item)
+        .collect()
+}
+
+// English plus the languages of the user's and the system's locale settings.
+fn system_languages() -> HashSet<String> {
+    let locale_conf = fs::read_to_string(LOCALE_CONF_PATH).unwrap_or_default();
+    let configured = locale_conf.lines().filter_map(|line| line.strip_prefix("LANG=")).map(|value| value.trim_matches('"').to_string());
+    let from_env = ["LANGUAGE", "LC_ALL", "LC_MESSAGES", "LANG"].into_iter().filter_map(|key| std::env::var(key).ok());
+    let mut languages: HashSet<String> = configured
+        .chain(from_env)
+        .flat_map(|value| value.split(':').map(String::from).collect::<Vec<_>>())
+        .filter_map(|locale| locale.split(['_', '.', '@']).next().map(str::to_lowercase))
+        .filter(|language| !language.is_empty() && language != "c" && language != "posix")
+        .collect();
+    languages.insert("en".to_string());
+    languages
+}
+
+// "262.1 MB" as flatpak prints sizes, in decimal units.
+fn parse_human_size(text: &str) -> Option<u64> {
+    let (number, unit) = text.trim().split_once(char::is_whitespace)?;
+    let number: f64 = number.replace(',', ".").parse().ok()?;
+    let factor = match unit.trim() {
+        "bytes" | "B" => 1.0,
+        "kB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "TB" => 1e12,
+        _ => return None,
+    };
+    Some((number * factor).round() as u64)
+}
+
+// Runtimes no app names as its runtime, and that do not extend a used runtime or an installed app
+// (extensions are named after what they extend, e.g. org.freedesktop.Platform.GL.default). A
+// guess on the safe side; `flatpak uninstall --unused` makes the final call.
+fn unused_runtime_items(apps: &str, runtimes: &str) -> Vec<CleanupItem> {
+    let mut used: HashSet<String> = HashSet::new();
+    let mut owners: Vec<String> = Vec::new();
+    for line in apps.lines() {
+        let mut fields = line.split('\t').map(str::trim);
+        let (Some(application), Some(runtime)) = (fields.next(), fields.next()) else { continue };
+        owners.push(format!("{}.", application));
+        if let Some((id, _)) = runtime.split_once('/') {
+            owners.push(format!("{}.", id));
+        }
+        used.insert(runtime.to_string());
+    }
+    runtimes
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t').map(str::trim);
+            let (flatpak_ref, installation, size) = (fields.next()?, fields.next(), fields.next());
+            let mut parts = flatpak_ref.trim_start_matches("runtime/").split('/');
+            let (id, arch, branch) = (parts.next()?, parts.next()?, parts.next()?);
+            let in_use = used.contains(&format!("{}/{}/{}", id, arch, branch)) || owners.iter().any(|owner| id.starts_with(owner.as_str()));
+            (!in_use).then(|| item(flatpak_ref, size.and_then(parse_human_size), installation.map(String::from)))
+        })
+        .collect()
+}
+
+async fn unused_flatpaks(app: &tauri::AppHandle) -> Result<Vec<CleanupItem>, String> {
+    // flatpak is optional; when it cannot be started there is nothing of it to clean up
+    let Ok(apps) = run_command(app, "flatpak", &["list", "--app", "--columns=application,runtime"]).await else {
+        return Ok(Vec::new());
+    };
+    let runtimes = run_command(app, "flatpak", &["list", "--runtime", "--columns=ref,installation,size"]).await?;
+    if !apps.success || !runtimes.success {
+        return Err(format!("flatpak list failed: {}", if apps.success { runtimes.error_text() } else { apps.error_text() }.trim()));
+    }
+    Ok(unused_runtime_items(&apps.stdout, &runtimes.stdout))
+}
+
+// Walks `dir` for rpm's leftovers, skipping what cannot be read; /etc is mostly readable.
+fn add_leftover_configs(dir: &Path, items: &mut Vec<CleanupItem>) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        let Ok(metadata) = path.symlink_metadata() else { continue };
+        if metadata.is_dir() {
+            add_leftover_configs(&path, items);
+        } else if metadata.is_file() && LEFTOVER_SUFFIXES.iter().any(|suffix| path.to_string_lossy().ends_with(suffix)) {
+            items.push(item(path.to_string_lossy(), Some(metadata.len()), None));
+        }
+    }
+}
+
+async fn installed_rows(app: &tauri::AppHandle) -> Result<Vec<InstalledRow>, String> {
+    let out = run_command(app, "rpm", &["-qa", "--queryformat", INSTALLED_QUERY_FORMAT]).await?;
+    if !out.success {
+        return Err(format!("rpm -qa failed: {}", out.error_text().trim()));
+    }
+    Ok(parse_installed_rows(&out.stdout))
+}
+
+fn sorted(steps: Vec<CleanupStep>) -> CleanupAnalysis {
+    let mut steps: Vec<CleanupStep> = steps.into_iter().filter(|step| step.error.is_some() || !step.items.is_empty()).collect();
+    steps.sort_by_key(|step| (step.priority, Reverse(step.bytes)));
+    CleanupAnalysis { total_bytes: steps.iter().map(|step| step.bytes).sum(), steps }
+}
+
+// Runs every cleanup analysis and returns them as one plan for the cleanup wizard. Nothing is
+// removed; each step names the command that carries it out. The rpm-based steps, whose commands
+// are dnf's, are skipped unless the system is managed through dnf.
+#[tauri::command]
+pub async fn run_cleanup_analysis(app: tauri::AppHandle) -> Result<CleanupAnalysis, NebulaError> {
+    let mut steps = Vec::new();
+    if matches!(crate::settings::package_backend(&app)?, PackageBackend::Dnf | PackageBackend::PackageKit | PackageBackend::Dnf5Daemon) {
+        let rows = installed_rows(&app).await;
+        let with_rows = |analysis: &dyn Fn(&[InstalledRow]) -> Vec<CleanupItem>| rows.as_deref().map(analysis).map_err(String::clone);
+        steps.push(step(CleanupCategory::DuplicatePackages, with_rows(&duplicate_items)));
+        let cache = crate::dnf_cache::cache_size(crate::repos::METADATA_CACHE_DIRS);
+        steps.push(step(
+            CleanupCategory::DnfCache,
+            Ok([("Repository metadata", cache.metadata_bytes), ("Downloaded packages", cache.package_bytes)]
+                .into_iter()
+                .filter(|(_, bytes)| *bytes > 0)
+                .map(|(name, bytes)| item(name, Some(bytes), None))
+                .collect()),
+        ));
+        let orphans = crate::cleanup::orphan_packages(&app).await;
+        steps.push(step(CleanupCategory::Orphans, orphans.and_then(|orphans| with_rows(&|rows| orphan_items(rows, &orphans)))));
+        let kernels = crate::kernels::removable_kernel_releases(&app).await;
+        steps.push(step(CleanupCategory::OldKernels, kernels.and_then(|releases| with_rows(&|rows| kernel_items(rows, &releases)))));
+        steps.push(step(CleanupCategory::Debuginfo, with_rows(&debuginfo_items)));
+        let languages = system_languages();
+        steps.push(step(CleanupCategory::Langpacks, with_rows(&|rows| langpack_items(rows, &languages))));
+        let mut leftovers = Vec::new();
+        add_leftover_configs(Path::new("/etc"), &mut leftovers);
+        steps.push(step(CleanupCategory::LeftoverConfigs, Ok(leftovers)));
+    }
+    steps.push(step(CleanupCategory::UnusedFlatpaks, unused_flatpaks(&app).await));
+    Ok(sorted(steps))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cleanup_analyses() {
+        let rows = parse_installed_rows(
+            "htop\t3.3.0-1.fc40\tx86_64\t400000\n\
+             htop\t3.2.2-1.fc40\tx86_64\t390000\n\
+             gpg-pubkey\ta15b79cc-63d04c2c\t(none)\t0\n\
+             gpg-pubkey\t18b8e74c-62f2920f\t(none)\t0\n\
+             glibc-langpack-de\t2.39-1.fc40\tx86_64\t900000\n\
+             glibc-langpack-en\t2.39-1.fc40\tx86_64\t800000\n\
+             langpacks-core-pt_BR\t4.0-1.fc40\tnoarch\t100\n\
+             gdb-debuginfo\t14.2-1.fc40\tx86_64\t5000000\n",
+        );
+        assert_eq!(duplicate_items(&rows), [item("htop", Some(390000), Some("3.2.2-1.fc40.x86_64".to_string()))]);
+        // 10.0 is newer than 9.1 although it sorts first as text; the epoch outweighs both
+        let rows = parse_installed_rows("vim-enhanced\t9.1.0-1.fc40\tx86_64\t100\nvim-enhanced\t10.0.0-1.fc40\tx86_64\t200\n");
+        assert_eq!(duplicate_items(&rows)[0].detail.as_deref(), Some("9.1.0-1.fc40.x86_64"));
+        let rows = parse_installed_rows("foo\t2.0-1\tnoarch\t1\t0\nfoo\t1.0-1\tnoarch\t2\t1\n");
+        assert_eq!(duplicate_items(&rows)[0].detail.as_deref(), Some("2.0-1.noarch"));
+        assert_eq!(rpmvercmp("1.0~rc1", "1.0"), Ordering::Less);
+        assert_eq!(rpmvercmp("1.0^git1", "1.0"), Ordering::Greater);
+        assert_eq!(rpmvercmp("1.0^git1", "1.0.1"), Ordering::Less);
+        assert_eq!(rpmvercmp("1.010", "1.9"), Ordering::Greater);
+        assert_eq!(rpmvercmp("1.0a", "1.0.1"), Ordering::Less);
+        assert_eq!(rpmvercmp("fc40", "fc40"), Ordering::Equal);
+        let languages = HashSet::from(["en".to_string(), "pt".to_string()]);
+        assert_eq!(langpack_items(&rows, &languages).iter().map(|item| item.name.as_str()).collect::<Vec<_>>(), ["glibc-langpack-de"]);
+        assert_eq!(debuginfo_items(&rows)[0].bytes, Some(5000000));
+
+        let apps = "org.gnome.Builder\torg.gnome.Sdk/x86_64/46\n";
+        let runtimes = "runtime/org.gnome.Sdk/x86_64/46\tsystem\t1.2 GB\n\
+                        runtime/org.gnome.Sdk.Locale/x86_64/46\tsystem\t20.5 kB\n\
+                        runtime/org.gnome.Platform/x86_64/45\tuser\t262.1 MB\n";
+        assert_eq!(unused_runtime_items(apps, runtimes), [item("runtime/org.gnome.Platform/x86_64/45", Some(262_100_000), Some("user".to_string()))]);
+
+        let analysis = sorted(vec![
+            step(CleanupCategory::Langpacks, Ok(langpack_items(&rows, &languages))),
+            step(CleanupCategory::Debuginfo, Ok(debuginfo_items(&rows))),
+            step(CleanupCategory::Orphans, Ok(Vec::new())),
+            step(CleanupCategory::DuplicatePackages, Err("rpm -qa failed".to_string())),
+        ]);
+        let order: Vec<CleanupCategory> = analysis.steps.iter().map(|step| step.category).collect();
+        assert_eq!(order, [CleanupCategory::DuplicatePackages, CleanupCategory::Debuginfo, CleanupCategory::Langpacks]);
+        assert_eq!(analysis.total_bytes, 5900000);
+    }
+}
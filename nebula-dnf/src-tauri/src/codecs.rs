@@ -0,0 +1,159 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::error::NebulaError;
+use crate::process::run_command;
+
+// Media types and GStreamer element/caps names, e.g. "video/x-h265" or "decoder-audio/x-ac3".
+static CAPS_NAME_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[a-zA-Z0-9._+-]+(/[a-zA-Z0-9._+-]+)?$").unwrap());
+// FFmpeg codec names as `ffmpeg -codecs` lists them, e.g. "h264", "hevc", "aac_latm".
+static FFMPEG_CODEC_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[a-z0-9_]+$").unwrap());
+
+// Codecs the Fedora ffmpeg-free build leaves out for patent reasons, by their `ffmpeg -codecs` names.
+const FFMPEG_ENCUMBERED_CODECS: &[&str] = &[
+    "h264", "hevc", "vc1", "wmv3", "mpeg1video", "mpeg2video", "mpeg4", "msmpeg4v2", "msmpeg4v3", "h263", "libx264", "libx265",
+];
+// Builds carrying the encumbered codecs, from RPM Fusion; the stock build has the rest.
+const FFMPEG_FULL_PACKAGES: &[&str] = &["libavcodec-freeworld", "ffmpeg-libs"];
+const FFMPEG_FREE_PACKAGES: &[&str] = &["libavcodec-free"];
+const CANDIDATE_QUERY_FORMAT: &str = "%{name}\t%{version}-%{release}\t%{repoid}\t%{summary}\n";
+
+#[derive(Debug, Clone, PartialEq)]
+enum CodecQuery {
+    Provides(String), // a gstreamer1(...) capability glob for --whatprovides
+    Ffmpeg(String),
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CodecCandidate {
+    name: String,
+    version: String,
+    repo: String,
+    summary: String,
+    installed: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CodecResolution {
+    capability: String,
+    query: String, // what the repositories were searched for
+    candidates: Vec<CodecCandidate>,
+    hint: Option<String>, // set when nothing was found and RPM Fusion could help
+}
+
+// Accepts what apps report when a codec is missing:
+//   "gstreamer1(decoder-video/x-h265)()(64bit)"  as in PackageKit codec requests
+//   "decoder-video/x-h265" or "element-x264enc"  GStreamer capability names
+//   "video/x-h265, stream-format=byte-stream"    raw caps; a decoder is assumed
+//   "ffmpeg:hevc"                                FFmpeg codec names
+fn parse_capability(input: &str) -> Result<CodecQuery, String> {
+    let input = input.trim();
+    if let Some(codec) = input.strip_prefix("ffmpeg:") {
+        let codec = codec.trim().to_lowercase();
+        return if FFMPEG_CODEC_RE.is_match(&codec) { Ok(CodecQuery::Ffmpeg(codec)) } else { Err(format!("Invalid FFmpeg codec name: '{}'", codec)) };
+    }
+    let name = match input.strip_prefix("gstreamer1(") {
+        Some(rest) => rest.split(')').next().unwrap_or_default(),
+        None => input.split(',').next().unwrap_or_default().trim(),
+    };
+    if !CAPS_NAME_RE.is_match(name) {
+        return Err(format!("Unrecognized codec capability: '{}'", input));
+    }
+    let is_typed = ["decoder-", "encoder-", "element-", "urisource-", "urisink-"].iter().any(|prefix| name.starts_with(prefix));
+    let name = if is_typed || !name.contains('/') { name.to_string() } else { format!("decoder-{}", name) };
+    Ok(CodecQuery::Provides(format!("gstreamer1({})*", name)))
+}
+
+// The packages whose libavcodec includes `codec`.
+fn ffmpeg_packages(codec: &str) -> &'static [&'static str] {
+    if FFMPEG_ENCUMBERED_CODECS.contains(&codec) {
+        FFMPEG_FULL_PACKAGES
+    } else {
+        FFMPEG_FREE_PACKAGES
+    }
+}
+
+fn parse_candidates(output: &str, installed: &HashSet<String>) -> Vec<CodecCandidate> {
+    let mut seen = HashSet::new();
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(4, '\t').map(str::trim);
+            let name = fields.next().filter(|name| !name.is_empty())?.to_string();
+            Some(CodecCandidate {
+                installed: installed.contains(&name),
+                version: fields.next()?.to_string(),
+                repo: fields.next()?.to_string(),
+                summary: fields.next().unwrap_or("").to_string(),
+                name,
+            })
+        })
+        .filter(|candidate| seen.insert(candidate.name.clone()))
+        .collect()
+}
+
+// Maps a missing GStreamer/FFmpeg capability to packages in the enabled repositories (RPM Fusion
+// included when it is set up) that provide it. Install a candidate with `install_packages`.
+#[tauri::command]
+pub async fn resolve_codec(app: tauri::AppHandle, capability: String) -> Result<CodecResolution, NebulaError> {
+    let query = parse_capability(&capability)?;
+    let mut args = vec!["repoquery", "--quiet", "--latest-limit=1", "--queryformat", CANDIDATE_QUERY_FORMAT];
+    let query_text = match &query {
+        CodecQuery::Provides(pattern) => {
+            args.extend(["--whatprovides", pattern.as_str()]);
+            pattern.clone()
+        }
+        CodecQuery::Ffmpeg(codec) => {
+            args.push("--");
+            args.extend(ffmpeg_packages(codec));
+            format!("FFmpeg with the {} codec", codec)
+        }
+    };
+    let out = run_command(&app, "dnf", &args).await?;
+    if !out.success {
+        return Err(NebulaError::command_failed(out.code, &out.stderr, format!("dnf repoquery failed: {}", out.error_text().trim())));
+    }
+
+    let names: Vec<String> = out.stdout.lines().filter_map(|line| line.split('\t').next()).map(String::from).collect();
+    let mut installed = HashSet::new();
+    if !names.is_empty() {
+        let mut rpm_args = vec!["-q", "--queryformat", "%{NAME}\n", "--"];
+        rpm_args.extend(names.iter().map(String::as_str));
+        let out = run_command(&app, "rpm", &rpm_args).await?;
+        installed.extend(out.stdout.lines().filter(|line| !line.contains(' ')).map(|line| line.trim().to_string()));
+    }
+    let candidates = parse_candidates(&out.stdout, &installed);
+
+    let mut hint = None;
+    if candidates.is_empty() && !crate::repos::fetch_repositories(&app).await?.iter().any(|repo| repo.is_rpmfusion()) {
+        hint = Some("Many codecs are only packaged in RPM Fusion. Enable it and search again.".to_string());
+    }
+    Ok(CodecResolution { capability, query: query_text, candidates, hint })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_capability() {
+        let provides = |p: &str| CodecQuery::Provides(p.to_string());
+        assert_eq!(parse_capability("gstreamer1(decoder-video/x-h265)()(64bit)"), Ok(provides("gstreamer1(decoder-video/x-h265)*")));
+        assert_eq!(parse_capability("video/x-h264, stream-format=(string)avc"), Ok(provides("gstreamer1(decoder-video/x-h264)*")));
+        assert_eq!(parse_capability("element-x264enc"), Ok(provides("gstreamer1(element-x264enc)*")));
+        assert_eq!(parse_capability("ffmpeg:HEVC"), Ok(CodecQuery::Ffmpeg("hevc".to_string())));
+        assert!(parse_capability("video/x-h264; rm -rf /").is_err());
+        assert_eq!(ffmpeg_packages("hevc"), FFMPEG_FULL_PACKAGES);
+        assert_eq!(ffmpeg_packages("vp9"), FFMPEG_FREE_PACKAGES);
+
+        let output = "gstreamer1-plugin-openh264\t1.22.1-1.fc40\tfedora-cisco-openh264\tGStreamer H.264 plugin\n\
+                      gstreamer1-plugins-ugly\t1.22.9-1.fc40\trpmfusion-free\tGStreamer streaming media framework \"ugly\" plugins\n";
+        let installed = HashSet::from(["gstreamer1-plugin-openh264".to_string()]);
+        let candidates = parse_candidates(output, &installed);
+        assert_eq!(candidates.len(), 2);
+        assert!(candidates[0].installed);
+        assert_eq!(candidates[1].repo, "rpmfusion-free");
+    }
+}
@@ -0,0 +1,250 @@
+use serde::Serialize;
+use std::path::Path;
+use tracing::warn;
+
+use crate::error::NebulaError;
+use crate::plan::{self, CommandPlan};
+use crate::process::{run_command, run_plan};
+
+// systemd boots into system-update.target when this symlink exists (PackageKit offline updates,
+// dnf system-upgrade and dnf5 offline transactions all use it).
+pub const SYSTEM_UPDATE_LINK: &str = "/system-update";
+const PACKAGEKIT_PREPARED_UPDATE: &str = "/var/lib/PackageKit/prepared-update";
+const DNF4_SYSTEM_UPGRADE_STATE: &str = "/var/lib/dnf/system-upgrade.json";
+const DNF5_OFFLINE_STATE: &str = "/usr/lib/sysimage/libdnf5/offline/offline-transaction-state.toml";
+// Written by `shutdown -r +N` and friends; contains MODE=reboot|poweroff|...
+const SCHEDULED_SHUTDOWN_FILE: &str = "/run/systemd/shutdown/scheduled";
+// A root file system staged for `systemctl soft-reboot`.
+const SOFT_REBOOT_ROOT: &str = "/run/nextroot";
+
+const PACKAGEKIT_BUS_NAME: &str = "org.freedesktop.PackageKit";
+const PACKAGEKIT_PATH: &str = "/org/freedesktop/PackageKit";
+// GNOME Software is what drives PackageKit's background refresh and offline update downloads.
+const GNOME_SOFTWARE_SCHEMA: &str = "org.gnome.software";
+const GNOME_SOFTWARE_AUTO_DOWNLOAD_KEY: &str = "download-updates";
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum PendingTransactionKind {
+    OfflineUpdate,
+    ScheduledShutdown,
+    SoftReboot,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct PendingSystemTransaction {
+    pub(crate) kind: PendingTransactionKind,
+    pub(crate) source: String, // the tool that scheduled it, as far as it can be told
+    pub(crate) detail: String,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct PackageKitState {
+    running: bool,
+    locked: bool,                  // packagekitd currently holds the package manager lock
+    offline_update_prepared: bool, // downloaded and waiting to be triggered from GNOME Software
+    offline_update_triggered: bool,
+    auto_download: Option<bool>, // None if GNOME Software is not installed
+}
+
+// Only talks to packagekitd when it is already running: it is D-Bus activated, and asking it
+// for properties would start it just to answer that it is idle.
+async fn query_packagekit_daemon(state: &mut PackageKitState) -> zbus::Result<()> {
+    let connection = zbus::Connection::system().await?;
+    let bus = zbus::fdo::DBusProxy::new(&connection).await?;
+    state.running = bus.name_has_owner(PACKAGEKIT_BUS_NAME.try_into()?).await?;
+    if !state.running {
+        return Ok(());
+    }
+    let daemon = zbus::Proxy::new(&connection, PACKAGEKIT_BUS_NAME, PACKAGEKIT_PATH, "org.freedesktop.PackageKit").await?;
+    state.locked = daemon.get_property("Locked").await?;
+    let offline = zbus::Proxy::new(&connection, PACKAGEKIT_BUS_NAME, PACKAGEKIT_PATH, "org.freedesktop.PackageKit.Offline").await?;
+    state.offline_update_prepared = offline.get_property("UpdatePrepared").await?;
+    state.offline_update_triggered = offline.get_property("UpdateTriggered").await?;
+    Ok(())
+}
+
+fn parse_gsettings_bool(output: &str) -> Option<bool> {
+    match output.trim() {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+pub async fn packagekit_state(app: &tauri::AppHandle) -> PackageKitState {
+    let mut state = PackageKitState {
+        offline_update_prepared: Path::new(PACKAGEKIT_PREPARED_UPDATE).exists(),
+        ..Default::default()
+    };
+    if let Err(e) = query_packagekit_daemon(&mut state).await {
+        warn!("Failed to query PackageKit over D-Bus: {}", e);
+    }
+    state.auto_download = match run_command(app, "gsettings", &["get", GNOME_SOFTWARE_SCHEMA, GNOME_SOFTWARE_AUTO_DOWNLOAD_KEY]).await {
+        Ok(out) if out.success => parse_gsettings_bool(&out.stdout),
+        _ => None,
+    };
+    state
+}
+
+// Identifies who prepared the offline update from the /system-update link target and state files.
+fn offline_update_source(link_target: Option<&Path>, exists: impl Fn(&str) -> bool) -> &'static str {
+    let target = link_target.map(|t| t.to_string_lossy().into_owned()).unwrap_or_default();
+    if target.contains("PackageKit") || exists(PACKAGEKIT_PREPARED_UPDATE) {
+        "PackageKit (e.g. GNOME Software)"
+    } else if target.contains("system-upgrade") || exists(DNF4_SYSTEM_UPGRADE_STATE) {
+        "dnf system-upgrade"
+    } else if target.contains("libdnf5") || exists(DNF5_OFFLINE_STATE) {
+        "dnf5 offline"
+    } else {
+        "unknown tool"
+    }
+}
+
+// The status line of dnf5's offline state file. "download-complete" is a transaction staged with
+// `--offline` that `dnf offline reboot` has not been asked to apply yet ("ready" once it has);
+// dnf5 removes the file after applying it.
+fn dnf5_transaction_staged(contents: &str) -> bool {
+    contents
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("status"))
+        .filter_map(|rest| rest.trim_start().strip_prefix('='))
+        .any(|status| matches!(status.trim().trim_matches('"'), "download-complete" | "ready"))
+}
+
+fn parse_scheduled_shutdown(contents: &str) -> String {
+    contents
+        .lines()
+        .find_map(|line| line.strip_prefix("MODE="))
+        .map(|mode| mode.trim().to_string())
+        .unwrap_or_else(|| "shutdown".to_string())
+}
+
+pub fn detect_pending_transactions() -> Vec<PendingSystemTransaction> {
+    let exists = |path: &str| Path::new(path).exists();
+    let mut pending = Vec::new();
+
+    let link = Path::new(SYSTEM_UPDATE_LINK);
+    let linked = link.symlink_metadata().is_ok();
+    if linked {
+        let target = std::fs::read_link(link).ok();
+        pending.push(PendingSystemTransaction {
+            kind: PendingTransactionKind::OfflineUpdate,
+            source: offline_update_source(target.as_deref(), exists).to_string(),
+            detail: format!(
+                "An offline update will be applied on the next reboot ({} -> {}).",
+                SYSTEM_UPDATE_LINK,
+                target.map(|t| t.display().to_string()).unwrap_or_else(|| "?".to_string())
+            ),
+        });
+    }
+    if !linked && std::fs::read_to_string(DNF5_OFFLINE_STATE).is_ok_and(|contents| dnf5_transaction_staged(&contents)) {
+        pending.push(PendingSystemTransaction {
+            kind: PendingTransactionKind::OfflineUpdate,
+            source: "dnf5 offline".to_string(),
+            detail: "A dnf5 offline transaction is staged and waits for `dnf offline reboot`.".to_string(),
+        });
+    }
+    if let Ok(contents) = std::fs::read_to_string(SCHEDULED_SHUTDOWN_FILE) {
+        let mode = parse_scheduled_shutdown(&contents);
+        pending.push(PendingSystemTransaction {
+            kind: PendingTransactionKind::ScheduledShutdown,
+            source: "systemd-logind".to_string(),
+            detail: format!("A {} is scheduled.", mode),
+        });
+    }
+    if exists(SOFT_REBOOT_ROOT) {
+        pending.push(PendingSystemTransaction {
+            kind: PendingTransactionKind::SoftReboot,
+            source: "systemd".to_string(),
+            detail: format!("A new root file system is staged in {} for a soft reboot.", SOFT_REBOOT_ROOT),
+        });
+    }
+    pending
+}
+
+fn refuse_staged_update(pending: &[PendingSystemTransaction]) -> Result<(), NebulaError> {
+    match pending.iter().find(|p| p.kind == PendingTransactionKind::OfflineUpdate) {
+        Some(pending) => Err(NebulaError::LockHeld {
+            message: format!(
+                "{} Prepared by {}. Reboot to apply it, or cancel it in that tool, before changing packages here.",
+                pending.detail, pending.source
+            ),
+        }),
+        None => Ok(()),
+    }
+}
+
+// Refuses `plans` when one of them changes packages while an offline update is staged. Cache
+// maintenance and the reboot or clean that applies or cancels the staged update go ahead.
+pub fn check_staged_update(plans: &[CommandPlan], pending: &[PendingSystemTransaction]) -> Result<(), NebulaError> {
+    if plans.iter().any(CommandPlan::is_package_transaction) { refuse_staged_update(pending) } else { Ok(()) }
+}
+
+// Called before running plans; only the package transactions among them are checked.
+pub async fn ensure_plans_can_run(plans: &[CommandPlan]) -> Result<(), NebulaError> {
+    check_staged_update(plans, &detect_pending_transactions())?;
+    if plans.iter().any(CommandPlan::is_package_transaction) {
+        ensure_packagekit_idle().await?;
+    }
+    Ok(())
+}
+
+async fn ensure_packagekit_idle() -> Result<(), NebulaError> {
+    let mut state = PackageKitState::default();
+    if query_packagekit_daemon(&mut state).await.is_ok() && state.locked {
+        let message = "PackageKit (e.g. GNOME Software refreshing in the background) is using the package manager. Try again once it has finished.";
+        return Err(NebulaError::LockHeld { message: message.to_string() });
+    }
+    Ok(())
+}
+
+// Called before package transactions. A staged offline update would be applied on top of
+// whatever we change now, and a PackageKit transaction holding the lock would make dnf wait
+// or fail half way, so we refuse instead of racing either.
+pub async fn ensure_no_pending_transaction() -> Result<(), NebulaError> {
+    refuse_staged_update(&detect_pending_transactions())?;
+    ensure_packagekit_idle().await
+}
+
+#[tauri::command]
+pub async fn get_pending_system_transactions() -> Result<Vec<PendingSystemTransaction>, NebulaError> {
+    Ok(detect_pending_transactions())
+}
+
+#[tauri::command]
+pub async fn get_packagekit_state(app: tauri::AppHandle) -> Result<PackageKitState, NebulaError> {
+    Ok(packagekit_state(&app).await)
+}
+
+// For users who want NebulaSys to be the only updater: stops GNOME Software from refreshing
+// metadata and downloading updates through PackageKit in the background.
+#[tauri::command]
+pub async fn set_packagekit_auto_download(app: tauri::AppHandle, enabled: bool) -> Result<PackageKitState, NebulaError> {
+    let value = if enabled { "true" } else { "false" };
+    let command = CommandPlan::new("gsettings", plan::strings(&["set", GNOME_SOFTWARE_SCHEMA, GNOME_SOFTWARE_AUTO_DOWNLOAD_KEY, value]));
+    let out = run_plan(&app, &command).await?;
+    if !out.success {
+        return Err(NebulaError::command_failed(out.code, &out.stderr, format!("Failed to change GNOME Software's automatic updates: {}", out.error_text().trim())));
+    }
+    Ok(packagekit_state(&app).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offline_update_source() {
+        let none = |_: &str| false;
+        let packagekit = Path::new("/var/lib/PackageKit/prepared-update");
+        assert_eq!(offline_update_source(Some(packagekit), none), "PackageKit (e.g. GNOME Software)");
+        let upgrade = Path::new("/var/lib/dnf/system-upgrade");
+        assert_eq!(offline_update_source(Some(upgrade), none), "dnf system-upgrade");
+        assert_eq!(offline_update_source(None, |p: &str| p == DNF5_OFFLINE_STATE), "dnf5 offline");
+        assert!(dnf5_transaction_staged("[offline-transaction-state]\nstatus = \"download-complete\"\nverb = \"install\"\n"));
+        assert!(!dnf5_transaction_staged("[offline-transaction-state]\nstatus = \"transaction-incomplete\"\n"));
+        assert_eq!(parse_scheduled_shutdown("USEC=1714550400000000\nWARN_WALL=1\nMODE=reboot\n"), "reboot");
+        assert_eq!(parse_gsettings_bool("false\n"), Some(false));
+        assert_eq!(parse_gsettings_bool("No such schema “org.gnome.software”\n"), None);
+    }
+}
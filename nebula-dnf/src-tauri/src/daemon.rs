@@ -0,0 +1,119 @@
+use serde::Serialize;
+use std::io::{BufRead, BufReader, Write};
+use std::os::fd::{FromRawFd, RawFd};
+use std::time::Duration;
+use tauri::Manager;
+use tracing::info;
+
+use crate::error::NebulaError;
+use crate::plan::{strings, CommandPlan};
+use crate::process::{run_command, run_plan};
+
+// `nebula-dnf --daemon` runs the backend without a window, as the user service does.
+pub const DAEMON_FLAG: &str = "--daemon";
+const MAIN_WINDOW: &str = "main";
+const SOCKET_UNIT: &str = "nebula-dnf.socket";
+const SERVICE_UNIT: &str = "nebula-dnf.service";
+// First file descriptor systemd passes to a socket-activated service (SD_LISTEN_FDS_START).
+const LISTEN_FDS_START: RawFd = 3;
+const ATTACH_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DaemonStatus {
+    enabled: bool,  // the user units start with the session
+    running: bool,  // a daemon currently serves this user
+    is_daemon: bool, // this process is that daemon
+}
+
+pub fn is_daemon() -> bool {
+    std::env::args().any(|arg| arg == DAEMON_FLAG)
+}
+
+// The sd_listen_fds(3) protocol: LISTEN_PID names the process the sockets are meant for.
+fn listen_fd(listen_pid: Option<&str>, listen_fds: Option<&str>, own_pid: u32) -> Option<RawFd> {
+    let for_us = listen_pid?.trim().parse::<u32>().ok()? == own_pid;
+    let count = listen_fds?.trim().parse::<u32>().ok()?;
+    (for_us && count >= 1).then_some(LISTEN_FDS_START)
+}
+
+// The listening socket systemd handed over when the service was socket-activated. Clears the
+// LISTEN_* variables, so it has to run in `run` before any other thread exists.
+pub fn activated_listener() -> Option<std::os::unix::net::UnixListener> {
+    let fd = listen_fd(
+        std::env::var("LISTEN_PID").ok().as_deref(),
+        std::env::var("LISTEN_FDS").ok().as_deref(),
+        std::process::id(),
+    )?;
+    // not inherited by the commands this process runs
+    for name in ["LISTEN_PID", "LISTEN_FDS", "LISTEN_FDNAMES"] {
+        std::env::remove_var(name);
+    }
+    // SAFETY: systemd passes fd 3 open and owned by this process; it is taken exactly once.
+    Some(unsafe { std::os::unix::net::UnixListener::from_raw_fd(fd) })
+}
+
+// Shows the main window, creating it from the config when the app started without one.
+pub fn show_main_window(app: &tauri::AppHandle) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+        window.show().and_then(|_| window.set_focus()).map_err(|e| format!("Failed to show the window: {}", e))?;
+        return Ok(());
+    }
+    let config = app.config().app.windows.iter().find(|w| w.label == MAIN_WINDOW).ok_or("No main window configured.")?;
+    tauri::WebviewWindowBuilder::from_config(app, config)
+        .and_then(|builder| builder.build())
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open the window: {}", e))
+}
+
+// When a daemon already runs for this user, asks it to open its window, so the GUI works on the
+// daemon's caches and schedules instead of starting a second backend. True if it did.
+pub fn attach_to_daemon() -> bool {
+    let Ok(path) = crate::rpc::socket_path() else { return false };
+    let Ok(mut stream) = std::os::unix::net::UnixStream::connect(&path) else { return false };
+    let _ = stream.set_read_timeout(Some(ATTACH_TIMEOUT));
+    if stream.write_all(b"{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"show_window\"}\n").is_err() {
+        return false;
+    }
+    let mut reply = String::new();
+    if BufReader::new(&stream).read_line(&mut reply).is_err() {
+        return false;
+    }
+    let attached = serde_json::from_str::<serde_json::Value>(&reply).is_ok_and(|reply| reply.get("result").is_some());
+    if attached {
+        info!("Attached to the running nebula-dnf daemon.");
+    }
+    attached
+}
+
+#[tauri::command]
+pub async fn get_daemon_status(app: tauri::AppHandle) -> Result<DaemonStatus, NebulaError> {
+    let enabled = run_command(&app, "systemctl", &["--user", "is-enabled", "--quiet", SOCKET_UNIT]).await?;
+    let running = run_command(&app, "systemctl", &["--user", "is-active", "--quiet", SERVICE_UNIT]).await?;
+    Ok(DaemonStatus { enabled: enabled.success, running: running.success, is_daemon: is_daemon() })
+}
+
+// Enables the user units so the backend starts with the session and keeps running with no window
+// open, or disables them again.
+#[tauri::command]
+pub async fn set_daemon_enabled(app: tauri::AppHandle, enabled: bool) -> Result<DaemonStatus, NebulaError> {
+    let action = if enabled { "enable" } else { "disable" };
+    let plan = CommandPlan::new("systemctl", strings(&["--user", action, "--now", SOCKET_UNIT, SERVICE_UNIT]));
+    let out = run_plan(&app, &plan).await?;
+    if !out.success {
+        return Err(NebulaError::command_failed(out.code, &out.stderr, format!("Failed to {} daemon mode: {}", action, out.error_text().trim())));
+    }
+    get_daemon_status(app).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listen_fd() {
+        assert_eq!(listen_fd(Some("4242"), Some("1"), 4242), Some(3));
+        assert_eq!(listen_fd(Some("4242"), Some("1"), 1000), None);
+        assert_eq!(listen_fd(Some("4242"), Some("0"), 4242), None);
+        assert_eq!(listen_fd(None, Some("1"), 4242), None);
+    }
+}
@@ -0,0 +1,134 @@
+use std::sync::{Arc, Mutex};
+use tauri::Manager;
+use zbus::object_server::SignalEmitter;
+use tracing::{info, warn};
+
+// Session bus name and object that shell extensions / bar modules can watch.
+// e.g. `busctl --user get-property org.nebulasys.Status /org/nebulasys/Status org.nebulasys.Status1 PendingUpdates`
+const BUS_NAME: &str = "org.nebulasys.Status";
+const OBJECT_PATH: &str = "/org/nebulasys/Status";
+
+#[derive(Debug, Default)]
+struct StatusValues {
+    pending_updates: u32,
+    active_operations: u32,
+    current_operation: String,
+}
+
+// The exported object only reads from the shared values, so status can be updated
+// (and is kept) even before the bus connection is up or if there is no session bus at all.
+struct StatusInterface {
+    values: Arc<Mutex<StatusValues>>,
+}
+
+#[zbus::interface(name = "org.nebulasys.Status1")]
+impl StatusInterface {
+    #[zbus(property)]
+    async fn pending_updates(&self) -> u32 {
+        self.values.lock().unwrap().pending_updates
+    }
+
+    #[zbus(property)]
+    async fn operation_in_progress(&self) -> bool {
+        self.values.lock().unwrap().active_operations > 0
+    }
+
+    // Human readable description of the most recently started operation, empty when idle.
+    #[zbus(property)]
+    async fn current_operation(&self) -> String {
+        self.values.lock().unwrap().current_operation.clone()
+    }
+}
+
+#[derive(Default)]
+pub struct DbusStatusState {
+    values: Arc<Mutex<StatusValues>>,
+    connection: Mutex<Option<zbus::Connection>>,
+}
+
+// Connects to the session bus and exports the status object. Failure is not fatal,
+// the app simply runs without the D-Bus interface (e.g. no session bus under some setups).
+pub async fn start(app: tauri::AppHandle) {
+    let state = app.state::<DbusStatusState>();
+    let iface = StatusInterface { values: state.values.clone() };
+    let connection = async {
+        zbus::connection::Builder::session()?
+            .name(BUS_NAME)?
+            .serve_at(OBJECT_PATH, iface)?
+            .build()
+            .await
+    }
+    .await;
+
+    match connection {
+        Ok(connection) => {
+            info!("Exported status interface on the session bus as {}.", BUS_NAME);
+            *state.connection.lock().unwrap() = Some(connection);
+        }
+        Err(e) => warn!("D-Bus status interface unavailable: {}", e),
+    }
+}
+
+async fn emit_changes(app: tauri::AppHandle) {
+    let Some(state) = app.try_state::<DbusStatusState>() else { return };
+    let connection = state.connection.lock().unwrap().clone();
+    let Some(connection) = connection else { return };
+
+    let iface_ref = match connection.object_server().interface::<_, StatusInterface>(OBJECT_PATH).await {
+        Ok(iface_ref) => iface_ref,
+        Err(e) => {
+            warn!("D-Bus status object not found: {}", e);
+            return;
+        }
+    };
+    let iface = iface_ref.get().await;
+    let emitter: &SignalEmitter<'static> = iface_ref.signal_emitter();
+    let results = [
+        iface.pending_updates_changed(emitter).await,
+        iface.operation_in_progress_changed(emitter).await,
+        iface.current_operation_changed(emitter).await,
+    ];
+    for result in results {
+        if let Err(e) = result {
+            warn!("Failed to emit D-Bus PropertiesChanged: {}", e);
+        }
+    }
+}
+
+fn update_values(app: &tauri::AppHandle, apply: impl FnOnce(&mut StatusValues)) {
+    let Some(state) = app.try_state::<DbusStatusState>() else { return };
+    apply(&mut state.values.lock().unwrap());
+    tauri::async_runtime::spawn(emit_changes(app.clone()));
+}
+
+pub fn set_pending_updates(app: &tauri::AppHandle, count: u32) {
+    update_values(app, |values| values.pending_updates = count);
+}
+
+pub fn operation_in_progress(app: &tauri::AppHandle) -> bool {
+    app.try_state::<DbusStatusState>().is_some_and(|state| state.values.lock().unwrap().active_operations > 0)
+}
+
+// Marks an operation as running until the returned guard is dropped.
+pub fn begin_operation(app: &tauri::AppHandle, description: String) -> OperationGuard {
+    update_values(app, |values| {
+        values.active_operations += 1;
+        values.current_operation = description;
+    });
+    OperationGuard { app: app.clone() }
+}
+
+pub struct OperationGuard {
+    app: tauri::AppHandle,
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        update_values(&self.app, |values| {
+            values.active_operations = values.active_operations.saturating_sub(1);
+            if values.active_operations == 0 {
+                values.current_operation.clear();
+            }
+        });
+    }
+}
@@ -0,0 +1,317 @@
+use futures_util::StreamExt;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use tauri::{Emitter, Manager};
+use zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
+use tracing::{info, warn};
+
+use crate::backends::{self, PackageBackend};
+use crate::error::NebulaError;
+use crate::packagekit::PackageAction;
+use crate::plan::CommandPlan;
+use crate::process::OperationOutputLine;
+use crate::services::OperationManager;
+use crate::settings;
+use crate::PackageOperationResult;
+
+// dnf5daemon-server: libdnf5 behind D-Bus, with polkit deciding who may change the system.
+const DNF5DAEMON_BUS_NAME: &str = "org.rpm.dnf.v0";
+const SESSION_MANAGER_PATH: &str = "/org/rpm/dnf/v0";
+const SESSION_MANAGER_INTERFACE: &str = "org.rpm.dnf.v0.SessionManager";
+const RPM_INTERFACE: &str = "org.rpm.dnf.v0.rpm.Rpm";
+const GOAL_INTERFACE: &str = "org.rpm.dnf.v0.Goal";
+const PACKAGE_ATTRS: &[&str] = &["name", "evr", "arch", "repo_id", "summary", "is_installed"];
+const QUERY_SCOPES: &[&str] = &["installed", "available", "upgrades", "all"];
+// libdnf5 GoalProblem result of resolve(): 0 no problem, 1 warnings only, 2 errors.
+const RESOLVE_ERROR: u32 = 2;
+
+static QUERY_PATTERN_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[a-zA-Z0-9_*][a-zA-Z0-9._+:~^*-]*$").unwrap());
+
+type Options<'a> = HashMap<&'a str, Value<'a>>;
+// (object type, action, reason, item attributes, object attributes) from Goal.resolve
+type TransactionItem = (String, String, String, HashMap<String, OwnedValue>, HashMap<String, OwnedValue>);
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct NativePackage {
+    name: String,
+    evr: String,
+    arch: String,
+    repo_id: String,
+    summary: String,
+    installed: bool,
+}
+
+fn text(attrs: &HashMap<String, OwnedValue>, key: &str) -> String {
+    match attrs.get(key).map(|value| &**value) {
+        Some(Value::Str(s)) => s.as_str().to_string(),
+        _ => String::new(),
+    }
+}
+
+fn flag(attrs: &HashMap<String, OwnedValue>, key: &str) -> bool {
+    matches!(attrs.get(key).map(|value| &**value), Some(Value::Bool(true)))
+}
+
+fn native_package(attrs: &HashMap<String, OwnedValue>) -> NativePackage {
+    NativePackage {
+        name: text(attrs, "name"),
+        evr: text(attrs, "evr"),
+        arch: text(attrs, "arch"),
+        repo_id: text(attrs, "repo_id"),
+        summary: text(attrs, "summary"),
+        installed: flag(attrs, "is_installed"),
+    }
+}
+
+// "Install htop-3.3.0-3.fc40.x86_64", as a line of the transaction summary.
+fn describe_item(item: &TransactionItem) -> String {
+    let (object_type, action, _reason, _item_attrs, object) = item;
+    let nevra = text(object, "full_nevra");
+    let subject = if nevra.is_empty() { text(object, "name") } else { nevra };
+    if object_type == "Package" {
+        format!("{} {}", action, subject)
+    } else {
+        format!("{} {} {}", action, object_type.to_lowercase(), subject)
+    }
+}
+
+// libdnf5 TransactionItemAction, as sent with transaction_action_start.
+fn action_label(action: u32) -> &'static str {
+    match action {
+        1 => "Installing",
+        2 => "Upgrading",
+        3 => "Downgrading",
+        4 => "Reinstalling",
+        5 => "Removing",
+        6 => "Cleaning up",
+        _ => "Processing",
+    }
+}
+
+fn dbus_error(e: zbus::Error) -> String {
+    format!("dnf5daemon request failed: {}", e)
+}
+
+// One dnf5daemon session: its own libdnf5 Base, closed again when the work is done.
+struct Session {
+    connection: zbus::Connection,
+    path: OwnedObjectPath,
+}
+
+impl Session {
+    async fn open(load_available_repos: bool) -> Result<Session, String> {
+        let connection = zbus::Connection::system().await.map_err(dbus_error)?;
+        let manager = zbus::Proxy::new(&connection, DNF5DAEMON_BUS_NAME, SESSION_MANAGER_PATH, SESSION_MANAGER_INTERFACE)
+            .await
+            .map_err(dbus_error)?;
+        let options: Options = HashMap::from([("load_available_repos", Value::from(load_available_repos))]);
+        let path: OwnedObjectPath = manager.call("open_session", &(options,)).await.map_err(dbus_error)?;
+        Ok(Session { connection, path })
+    }
+
+    async fn proxy(&self, interface: &'static str) -> Result<zbus::Proxy<'static>, String> {
+        zbus::Proxy::new(&self.connection, DNF5DAEMON_BUS_NAME, self.path.clone(), interface).await.map_err(dbus_error)
+    }
+
+    async fn close(self) {
+        let manager = zbus::Proxy::new(&self.connection, DNF5DAEMON_BUS_NAME, SESSION_MANAGER_PATH, SESSION_MANAGER_INTERFACE).await;
+        if let Ok(manager) = manager {
+            if let Err(e) = manager.call_method("close_session", &(&self.path,)).await {
+                warn!("Failed to close dnf5daemon session: {}", e);
+            }
+        }
+    }
+}
+
+async fn list_packages(session: &Session, patterns: &[String], scope: &str) -> Result<Vec<NativePackage>, String> {
+    let rpm = session.proxy(RPM_INTERFACE).await?;
+    let options: Options = HashMap::from([
+        ("package_attrs", Value::from(PACKAGE_ATTRS.to_vec())),
+        ("patterns", Value::from(patterns.iter().map(String::as_str).collect::<Vec<&str>>())),
+        ("scope", Value::from(scope)),
+    ]);
+    let found: Vec<HashMap<String, OwnedValue>> = rpm.call("list", &(options,)).await.map_err(dbus_error)?;
+    Ok(found.iter().map(native_package).collect())
+}
+
+// Package query through libdnf5: no CLI output to parse, and the repositories are loaded once
+// per session rather than once per dnf invocation. Scope is "installed", "available",
+// "upgrades" or "all".
+async fn query_packages(patterns: &[String], scope: &str) -> Result<Vec<NativePackage>, String> {
+    if !QUERY_SCOPES.contains(&scope) {
+        return Err(format!("Unknown query scope: '{}'", scope));
+    }
+    if let Some(pattern) = patterns.iter().find(|pattern| !QUERY_PATTERN_RE.is_match(pattern)) {
+        return Err(format!("Invalid package pattern: '{}'", pattern));
+    }
+    let session = Session::open(scope != "installed").await?;
+    let result = list_packages(&session, patterns, scope).await;
+    session.close().await;
+    let mut packages = result?;
+    packages.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.arch.cmp(&b.arch)));
+    Ok(packages)
+}
+
+#[tauri::command]
+pub async fn query_packages_native(patterns: Vec<String>, scope: String) -> Result<Vec<NativePackage>, NebulaError> {
+    Ok(query_packages(&patterns, &scope).await?)
+}
+
+// Names of the installed packages, or None when dnf5daemon is not available (dnf4 systems, or
+// the daemon is not installed) or the query failed, in which case callers fall back to `rpm`.
+pub async fn installed_package_names() -> Option<Vec<String>> {
+    match query_packages(&[], "installed").await {
+        Ok(packages) => Some(packages.into_iter().map(|package| package.name).collect()),
+        Err(e) => {
+            info!("Listing installed packages through dnf5daemon failed, using rpm instead: {}", e);
+            None
+        }
+    }
+}
+
+// Adds the request to the session's goal and resolves it. Err carries the resolver's problems.
+async fn resolve(session: &Session, action: PackageAction, packages: &[String]) -> Result<Vec<TransactionItem>, String> {
+    let rpm = session.proxy(RPM_INTERFACE).await?;
+    let method = match action {
+        PackageAction::Install => "install",
+        PackageAction::Remove => "remove",
+        PackageAction::Update => "upgrade",
+    };
+    rpm.call_method(method, &(packages, Options::new())).await.map_err(dbus_error)?;
+    let goal = session.proxy(GOAL_INTERFACE).await?;
+    let (items, result): (Vec<TransactionItem>, u32) = goal.call("resolve", &(Options::new(),)).await.map_err(dbus_error)?;
+    if result == RESOLVE_ERROR {
+        let problems: Vec<String> = goal.call("get_transaction_problems_string", &()).await.map_err(dbus_error)?;
+        return Err(problems.join("\n"));
+    }
+    Ok(items)
+}
+
+// Runs the resolved transaction, forwarding each package libdnf5 starts on as an
+// "operation-output" line.
+async fn do_transaction(app: &tauri::AppHandle, session: &Session, operation: &str) -> Result<(), String> {
+    let rpm = session.proxy(RPM_INTERFACE).await?;
+    let mut signals = rpm.receive_all_signals().await.map_err(dbus_error)?;
+    let (forward_app, forward_operation) = (app.clone(), operation.to_string());
+    let forwarder = tauri::async_runtime::spawn(async move {
+        while let Some(message) = signals.next().await {
+            let header = message.header();
+            if header.member().map(|member| member.as_str()) != Some("transaction_action_start") {
+                continue;
+            }
+            let Ok((_session, nevra, action, _total)) = message.body().deserialize::<(OwnedObjectPath, String, u32, u64)>() else { continue };
+            let line = format!("{} {}", action_label(action), nevra);
+            if let Err(e) = forward_app.emit("operation-output", OperationOutputLine::new(&forward_operation, &line, false)) {
+                warn!("Failed to emit operation-output event: {}", e);
+            }
+        }
+    });
+    let goal = session.proxy(GOAL_INTERFACE).await?;
+    let result = goal.call_method("do_transaction", &(Options::new(),)).await;
+    forwarder.abort();
+    result.map(|_| ()).map_err(|e| match e {
+        zbus::Error::MethodError(_, Some(detail), _) => detail,
+        e => dbus_error(e),
+    })
+}
+
+// Installs, removes or updates packages through dnf5daemon: libdnf5 resolves the request and
+// reports the transaction as structured items.
+pub struct Dnf5Daemon;
+
+// Its transactions run over D-Bus rather than through the process runner, so they are recorded here.
+impl PackageBackend for Dnf5Daemon {
+    async fn run(&self, app: &tauri::AppHandle, action: PackageAction, packages: &[String], dry_run: bool) -> Result<PackageOperationResult, String> {
+        let result = run_package_action(app, action, packages, dry_run).await?;
+        if !dry_run {
+            crate::app_operations::record(app, settings::PackageBackend::Dnf5Daemon, action, packages, &result).await;
+        }
+        Ok(result)
+    }
+}
+
+async fn run_package_action(app: &tauri::AppHandle, action: PackageAction, packages: &[String], dry_run: bool) -> Result<PackageOperationResult, String> {
+    let simulated = !dry_run && crate::settings::simulation_mode(app)?;
+    let session = Session::open(action != PackageAction::Remove).await?;
+    let outcome: Result<(bool, String, Vec<String>), String> = async {
+        let items = match resolve(&session, action, packages).await {
+            Ok(items) => items,
+            Err(problems) => return Ok((false, format!("The transaction could not be resolved:\n{}", problems), Vec::new())),
+        };
+        let details: Vec<String> = items.iter().map(describe_item).collect();
+        if items.is_empty() {
+            return Ok((true, "Nothing to do.".to_string(), details));
+        }
+        if dry_run || simulated {
+            return Ok((true, format!("Dry run finished: {} change(s) would be made.", details.len()), details));
+        }
+        crate::coordination::ensure_no_pending_transaction().await?;
+        let _busy = app.state::<OperationManager>().begin(format!("{:?} {} through dnf5daemon", action, packages.join(", ")));
+        let operation = format!("dnf5daemon_{:?}", action).to_lowercase();
+        let result = do_transaction(app, &session, &operation).await;
+        let mut method = vec![format!("{:?}", action).to_lowercase()];
+        method.extend(packages.iter().cloned());
+        crate::audit::record_command(app, &CommandPlan::new("dnf5daemon", method), result.is_ok(), if result.is_ok() { 0 } else { 1 });
+        Ok(match result {
+            Ok(()) => (true, format!("{:?} finished for {}.", action, if packages.is_empty() { "all packages".to_string() } else { packages.join(", ") }), details),
+            Err(e) => (false, format!("dnf5daemon failed: {}", e), details),
+        })
+    }
+    .await;
+    session.close().await;
+    let (success, message, details) = outcome?;
+    Ok(PackageOperationResult {
+        success,
+        message,
+        details: Some(details.join("\n")),
+        confirmation_required: false,
+        simulated,
+        reboot_required: false,
+    })
+}
+
+#[tauri::command]
+pub async fn dnf5daemon_package_action(
+    app: tauri::AppHandle,
+    action: PackageAction,
+    packages: Vec<String>,
+    dry_run: bool,
+    confirmed: Option<bool>,
+) -> Result<PackageOperationResult, NebulaError> {
+    Ok(backends::action_command(&Dnf5Daemon, &app, action, &packages, dry_run, confirmed).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attrs(pairs: &[(&str, &str)]) -> HashMap<String, OwnedValue> {
+        pairs.iter().map(|(k, v)| (k.to_string(), OwnedValue::from(zbus::zvariant::Str::from(*v)))).collect()
+    }
+
+    #[test]
+    fn test_describe_item() {
+        let package = attrs(&[("name", "htop"), ("full_nevra", "htop-0:3.3.0-3.fc40.x86_64")]);
+        let item: TransactionItem = ("Package".to_string(), "Install".to_string(), "User".to_string(), HashMap::new(), package);
+        assert_eq!(describe_item(&item), "Install htop-0:3.3.0-3.fc40.x86_64");
+        let group: TransactionItem = ("Group".to_string(), "Install".to_string(), "User".to_string(), HashMap::new(), attrs(&[("name", "c-development")]));
+        assert_eq!(describe_item(&group), "Install group c-development");
+    }
+
+    #[test]
+    fn test_native_package() {
+        let mut listed = attrs(&[("name", "bash"), ("evr", "5.2.26-3.fc40"), ("arch", "x86_64"), ("repo_id", "@System")]);
+        listed.insert("is_installed".to_string(), OwnedValue::from(true));
+        let package = native_package(&listed);
+        assert!(package.installed);
+        assert_eq!(package.summary, "");
+    }
+
+    #[test]
+    fn test_query_pattern() {
+        assert!(QUERY_PATTERN_RE.is_match("python3-*"));
+        assert!(!QUERY_PATTERN_RE.is_match("-x"));
+    }
+}
@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::error::NebulaError;
+use crate::plan;
+use crate::process::run_plan;
+use crate::repos::METADATA_CACHE_DIRS;
+use crate::PackageOperationResult;
+
+// What `dnf clean` removes.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum CacheKind {
+    All,
+    Metadata,
+    Packages,
+}
+
+impl CacheKind {
+    pub fn as_arg(self) -> &'static str {
+        match self {
+            CacheKind::All => "all",
+            CacheKind::Metadata => "metadata",
+            CacheKind::Packages => "packages",
+        }
+    }
+}
+
+// Bytes in dnf's caches. Downloaded packages sit in a "packages" directory inside each
+// repository's cache directory; everything else counts as metadata.
+#[derive(Debug, Serialize, Clone, Copy, Default, PartialEq)]
+pub struct DnfCacheSize {
+    pub metadata_bytes: u64,
+    pub package_bytes: u64,
+    total_bytes: u64,
+}
+
+// Sums the files under `dir` without following symlinks; unreadable entries are skipped.
+fn add_dir(dir: &Path, in_packages: bool, size: &mut DnfCacheSize) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    for entry in entries.filter_map(Result::ok) {
+        let Ok(metadata) = entry.path().symlink_metadata() else { continue };
+        if metadata.is_dir() {
+            add_dir(&entry.path(), in_packages || entry.file_name() == "packages", size);
+        } else if metadata.is_file() {
+            if in_packages {
+                size.package_bytes += metadata.len();
+            } else {
+                size.metadata_bytes += metadata.len();
+            }
+            size.total_bytes += metadata.len();
+        }
+    }
+}
+
+pub fn cache_size(dirs: &[&str]) -> DnfCacheSize {
+    let mut size = DnfCacheSize::default();
+    for dir in dirs {
+        add_dir(Path::new(dir), false, &mut size);
+    }
+    size
+}
+
+// How much `clean_dnf_cache` could reclaim, for dnf4's and dnf5's cache directories together.
+#[tauri::command]
+pub async fn get_dnf_cache_size() -> Result<DnfCacheSize, NebulaError> {
+    Ok(cache_size(METADATA_CACHE_DIRS))
+}
+
+// Runs `dnf clean <kind>`. Metadata is downloaded again on the next dnf run that needs it.
+#[tauri::command]
+pub async fn clean_dnf_cache(app: tauri::AppHandle, kind: CacheKind) -> Result<PackageOperationResult, NebulaError> {
+    let before = cache_size(METADATA_CACHE_DIRS);
+    let out = run_plan(&app, &plan::clean_cache(kind)).await?;
+    let freed = before.total_bytes.saturating_sub(cache_size(METADATA_CACHE_DIRS).total_bytes);
+    Ok(PackageOperationResult {
+        success: out.success,
+        message: if out.success {
+            format!("Cleaned the dnf cache ({}); {} MiB freed.", kind.as_arg(), freed / (1024 * 1024))
+        } else {
+            format!("Failed to clean the dnf cache: {}", out.error_text().trim())
+        },
+        details: Some(format!("STDOUT:\n{}\nSTDERR:\n{}", out.stdout, out.stderr)),
+        confirmation_required: false,
+        simulated: out.simulated,
+        reboot_required: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_size() {
+        let dir = std::env::temp_dir().join(format!("nebula-dnf-cache-{}", std::process::id()));
+        let repo = dir.join("fedora-0123abcd");
+        fs::create_dir_all(repo.join("repodata")).unwrap();
+        fs::create_dir_all(repo.join("packages")).unwrap();
+        fs::write(repo.join("repodata").join("repomd.xml"), [0u8; 100]).unwrap();
+        fs::write(repo.join("packages").join("htop-3.3.0-1.fc40.x86_64.rpm"), [0u8; 1000]).unwrap();
+        fs::write(dir.join("expired_repos.json"), [0u8; 10]).unwrap();
+
+        let size = cache_size(&[dir.to_str().unwrap(), "/nonexistent/nebula-dnf-cache"]);
+        assert_eq!(size, DnfCacheSize { metadata_bytes: 110, package_bytes: 1000, total_bytes: 1110 });
+        assert_eq!(plan::clean_cache(CacheKind::Packages).command_line(), "pkexec dnf clean packages");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,122 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::error::NebulaError;
+use crate::scope::write_root_file;
+
+const DNF_CONF_PATH: &str = "/etc/dnf/dnf.conf";
+// Exclude entries are package names or globs such as "kernel*".
+static EXCLUDE_PATTERN_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[a-zA-Z0-9_.+*?\[\]-]+$").unwrap());
+
+// `exclude` is the older spelling of `excludepkgs`; dnf reads both.
+fn is_exclude_key(key: &str) -> bool {
+    matches!(key.trim(), "excludepkgs" | "exclude")
+}
+
+fn split_key_value(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.split_once('=')?;
+    Some((key.trim(), value.trim()))
+}
+
+// Entries of every exclude option in [main], which dnf splits on commas and whitespace.
+fn parse_excludes(conf: &str) -> Vec<String> {
+    let mut in_main = false;
+    let mut excludes = Vec::new();
+    for line in conf.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_main = line == "[main]";
+            continue;
+        }
+        let Some((key, value)) = split_key_value(line).filter(|_| in_main && !line.starts_with('#')) else { continue };
+        if is_exclude_key(key) {
+            excludes.extend(value.split([',', ' ', '\t']).filter(|p| !p.is_empty()).map(String::from));
+        }
+    }
+    excludes
+}
+
+// Rewrites [main] with a single `excludepkgs` line (none if `excludes` is empty), leaving every
+// other line and comment as it was.
+fn with_excludes(conf: &str, excludes: &[String]) -> String {
+    let new_line = (!excludes.is_empty()).then(|| format!("excludepkgs={}", excludes.join(",")));
+    let mut out = Vec::new();
+    let mut in_main = false;
+    let mut seen_main = false;
+    let mut written = false;
+    for line in conf.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            if in_main && !written {
+                // keep the new option inside [main], before the blank lines that end it
+                let at = out.iter().rposition(|l: &String| !l.trim().is_empty()).map_or(out.len(), |i| i + 1);
+                out.splice(at..at, new_line.clone());
+                written = true;
+            }
+            in_main = trimmed == "[main]";
+            seen_main |= in_main;
+        } else if in_main && !trimmed.starts_with('#') && split_key_value(trimmed).is_some_and(|(key, _)| is_exclude_key(key)) {
+            if !written {
+                out.extend(new_line.clone());
+                written = true;
+            }
+            continue;
+        }
+        out.push(line.to_string());
+    }
+    if !written {
+        if !seen_main {
+            out.push("[main]".to_string());
+        }
+        out.extend(new_line);
+    }
+    let mut text = out.join("\n");
+    text.push('\n');
+    text
+}
+
+fn read_dnf_conf() -> Result<String, String> {
+    match std::fs::read_to_string(DNF_CONF_PATH) {
+        Ok(conf) => Ok(conf),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(format!("Failed to read {}: {}", DNF_CONF_PATH, e)),
+    }
+}
+
+// Packages dnf never installs or updates, from [main] in /etc/dnf/dnf.conf.
+#[tauri::command]
+pub async fn get_dnf_excludes() -> Result<Vec<String>, NebulaError> {
+    Ok(parse_excludes(&read_dnf_conf()?))
+}
+
+// Replaces the exclude list; an empty list removes the option. Writes the file as root.
+#[tauri::command]
+pub async fn set_dnf_excludes(app: tauri::AppHandle, excludes: Vec<String>) -> Result<Vec<String>, NebulaError> {
+    if let Some(bad) = excludes.iter().find(|p| !EXCLUDE_PATTERN_RE.is_match(p)) {
+        return Err(format!("Invalid exclude pattern: '{}'", bad).into());
+    }
+    let mut seen = HashSet::new();
+    let excludes: Vec<String> = excludes.into_iter().filter(|p| seen.insert(p.clone())).collect();
+    let updated = with_excludes(&read_dnf_conf()?, &excludes);
+    write_root_file(&app, Path::new(DNF_CONF_PATH), &updated).await?;
+    Ok(parse_excludes(&read_dnf_conf()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_excludes_round_trip() {
+        let conf = "# see man dnf.conf\n[main]\ngpgcheck=True\nexclude=kernel* mesa-libGL\ninstallonly_limit=3\n\n[other]\nexcludepkgs=ignored\n";
+        assert_eq!(parse_excludes(conf), vec!["kernel*", "mesa-libGL"]);
+
+        let updated = with_excludes(conf, &["kernel*".to_string(), "nvidia*".to_string()]);
+        assert_eq!(updated, "# see man dnf.conf\n[main]\ngpgcheck=True\nexcludepkgs=kernel*,nvidia*\ninstallonly_limit=3\n\n[other]\nexcludepkgs=ignored\n");
+        assert_eq!(parse_excludes(&with_excludes(conf, &[])), Vec::<String>::new());
+
+        let added = with_excludes("[main]\ngpgcheck=True\n\n[other]\nfoo=1\n", &["kernel*".to_string()]);
+        assert_eq!(added, "[main]\ngpgcheck=True\nexcludepkgs=kernel*\n\n[other]\nfoo=1\n");
+    }
+}
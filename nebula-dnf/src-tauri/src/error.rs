@@ -0,0 +1,118 @@
+use serde::Serialize;
+use std::fmt;
+
+// What a command returns when it fails. Serialized with its kind as a tag, e.g.
+// `{ "kind": "LockHeld", "message": "..." }`, so the frontend can branch on `kind` and still show
+// `message`. Errors are built with their kind where it is known (coordination, the process
+// runner, the validators); the String errors the rest of the internals return are classified
+// from their message on the way out.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(tag = "kind")]
+pub enum NebulaError {
+    CommandFailed { code: i32, stderr: String, message: String },
+    NotFound { message: String },         // a package, file or program that is not there
+    PermissionDenied { message: String }, // polkit refused or the authentication was dismissed
+    LockHeld { message: String },         // another package manager or a prepared offline update
+    CacheCorrupt { message: String },
+    InvalidInput { message: String },
+    Other { message: String },
+}
+
+impl NebulaError {
+    pub fn command_failed(code: i32, stderr: &str, message: String) -> Self {
+        NebulaError::CommandFailed { code, stderr: stderr.to_string(), message }
+    }
+
+    // A program that could not be started.
+    pub fn spawn_failed(program: &str, error: tauri_plugin_shell::Error) -> Self {
+        let message = format!("Failed to execute {}: {}", program, error);
+        match error {
+            tauri_plugin_shell::Error::Io(e) if e.kind() == std::io::ErrorKind::NotFound => NebulaError::NotFound { message },
+            tauri_plugin_shell::Error::Io(e) if e.kind() == std::io::ErrorKind::PermissionDenied => NebulaError::PermissionDenied { message },
+            _ => NebulaError::Other { message },
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            NebulaError::CommandFailed { message, .. }
+            | NebulaError::NotFound { message }
+            | NebulaError::PermissionDenied { message }
+            | NebulaError::LockHeld { message }
+            | NebulaError::CacheCorrupt { message }
+            | NebulaError::InvalidInput { message }
+            | NebulaError::Other { message } => message,
+        }
+    }
+}
+
+impl fmt::Display for NebulaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+// The last resort for String errors: the messages come from this crate (cache, settings, the
+// plans) and from the programs it runs, so the markers below are the ones those produce.
+fn classify(message: String) -> NebulaError {
+    let lower = message.to_lowercase();
+    let has = |markers: &[&str]| markers.iter().any(|marker| lower.contains(marker));
+    if has(&["is using the package manager", "reboot to apply it", "waiting for process with pid", "database is locked"]) {
+        NebulaError::LockHeld { message }
+    } else if has(&["not authorized", "request dismissed", "permission denied", "authentication"]) {
+        NebulaError::PermissionDenied { message }
+    } else if has(&["package cache database error"]) {
+        NebulaError::CacheCorrupt { message }
+    } else if lower.starts_with("invalid") || has(&["no packages given"]) {
+        NebulaError::InvalidInput { message }
+    } else if has(&["not installed", "not found", "no such file", "failed to execute"]) {
+        NebulaError::NotFound { message }
+    } else {
+        NebulaError::Other { message }
+    }
+}
+
+impl From<String> for NebulaError {
+    fn from(message: String) -> Self {
+        classify(message)
+    }
+}
+
+impl From<&str> for NebulaError {
+    fn from(message: &str) -> Self {
+        classify(message.to_string())
+    }
+}
+
+// Lets commands that other code calls keep working where a String error is expected.
+impl From<NebulaError> for String {
+    fn from(error: NebulaError) -> Self {
+        error.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kinds_where_errors_start() {
+        assert!(matches!(crate::validate_package_name("--all"), Err(NebulaError::InvalidInput { .. })));
+        let missing = std::io::Error::from(std::io::ErrorKind::NotFound);
+        assert!(matches!(NebulaError::spawn_failed("fwupdmgr", missing.into()), NebulaError::NotFound { .. }));
+    }
+
+    #[test]
+    fn test_classify() {
+        let lock = NebulaError::from("PackageKit (e.g. GNOME Software refreshing in the background) is using the package manager. Try again once it has finished.");
+        assert!(matches!(lock, NebulaError::LockHeld { .. }));
+        assert!(matches!(NebulaError::from("Invalid package name: '--all'"), NebulaError::InvalidInput { .. }));
+        assert!(matches!(NebulaError::from("Error executing command as another user: Not authorized"), NebulaError::PermissionDenied { .. }));
+        assert!(matches!(NebulaError::from("Failed to execute fwupdmgr: No such file or directory"), NebulaError::NotFound { .. }));
+        assert_eq!(
+            serde_json::to_value(NebulaError::from("Package cache database error: file is not a database")).unwrap(),
+            serde_json::json!({ "kind": "CacheCorrupt", "message": "Package cache database error: file is not a database" })
+        );
+        assert_eq!(String::from(NebulaError::command_failed(1, "boom", "dnf failed".to_string())), "dnf failed");
+    }
+}
@@ -0,0 +1,130 @@
+use serde::Serialize;
+use std::path::Path;
+
+use crate::error::NebulaError;
+use crate::probes::{ensure_supported, ProbedFeature};
+use crate::settings::PackageBackend;
+use crate::usage::resolve_binary;
+
+// D-Bus activation files; the daemons only run on demand, so these say whether they can.
+const PACKAGEKIT_SERVICE_FILE: &str = "/usr/share/dbus-1/system-services/org.freedesktop.PackageKit.service";
+const DNF5DAEMON_SERVICE_FILE: &str = "/usr/share/dbus-1/system-services/org.rpm.dnf.v0.service";
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum FeatureStatus {
+    Available,
+    Degraded,    // works, but slower or with less than it could do
+    Unavailable, // would fail; `reason` says why
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct FeatureState {
+    feature: String,
+    status: FeatureStatus,
+    reason: Option<String>,
+}
+
+fn state(feature: &str, status: FeatureStatus, reason: Option<String>) -> FeatureState {
+    FeatureState { feature: feature.to_string(), status, reason }
+}
+
+// `status` when `found` is false, with `reason` to explain it.
+fn requirement_state(feature: &str, found: bool, status: FeatureStatus, reason: &str) -> FeatureState {
+    if found {
+        state(feature, FeatureStatus::Available, None)
+    } else {
+        state(feature, status, Some(reason.to_string()))
+    }
+}
+
+// Unavailable as soon as one of the syntax probes the feature relies on failed.
+fn probed_state(app: &tauri::AppHandle, feature: &str, probes: &[ProbedFeature]) -> FeatureState {
+    match probes.iter().try_for_each(|probe| ensure_supported(app, *probe)) {
+        Ok(()) => state(feature, FeatureStatus::Available, None),
+        Err(e) => state(feature, FeatureStatus::Unavailable, Some(e)),
+    }
+}
+
+// The zypper, apt and pacman backends: degraded at best, since dnf-only features fail.
+fn experimental_backend_state(name: &str, binary: &str) -> FeatureState {
+    if resolve_binary(binary).is_none() {
+        return state("package_backend", FeatureStatus::Unavailable, Some(format!("The {} backend is selected but {} is not installed.", name, binary)));
+    }
+    let reason = format!(
+        "The experimental {} backend lists packages and installs, removes and updates them. The action queue, forced removal, advanced options, download-only and offline updates, and the other dnf-only features fail.",
+        name
+    );
+    state("package_backend", FeatureStatus::Degraded, Some(reason))
+}
+
+fn backend_state(backend: PackageBackend) -> FeatureState {
+    let service_file = match backend {
+        PackageBackend::Dnf => return state("package_backend", FeatureStatus::Available, None),
+        PackageBackend::Zypper => return experimental_backend_state("zypper", "zypper"),
+        PackageBackend::Apt => return experimental_backend_state("apt", "apt-get"),
+        PackageBackend::Pacman => return experimental_backend_state("pacman", "pacman"),
+        PackageBackend::PackageKit => PACKAGEKIT_SERVICE_FILE,
+        PackageBackend::Dnf5Daemon => DNF5DAEMON_SERVICE_FILE,
+    };
+    let reason = format!("The {:?} backend is selected but its daemon is not installed; installs fail until dnf is selected again.", backend);
+    requirement_state("package_backend", Path::new(service_file).exists(), FeatureStatus::Unavailable, &reason)
+}
+
+// What works on this machine with the current settings, so the frontend can hide or explain a
+// feature up front instead of failing when it is clicked. Cheap checks only: files, the last
+// syntax probe report and one snapper call.
+#[tauri::command]
+pub async fn get_feature_matrix(app: tauri::AppHandle) -> Result<Vec<FeatureState>, NebulaError> {
+    let mut matrix = vec![
+        requirement_state("privileged_actions", resolve_binary("pkexec").is_some(), FeatureStatus::Unavailable, "pkexec (polkit) is not installed, so nothing can run as root."),
+        backend_state(crate::settings::package_backend(&app)?),
+        requirement_state(
+            "installed_package_list",
+            crate::rpmdb::librpm_available(),
+            FeatureStatus::Degraded,
+            "librpm could not be loaded; listings query rpm in batches and are slower.",
+        ),
+        probed_state(&app, "user_installed_packages", &[ProbedFeature::UserInstalledQuery]),
+        probed_state(&app, "package_details", &[ProbedFeature::RpmQueryFormat]),
+        probed_state(&app, "package_history", &[ProbedFeature::History]),
+        probed_state(&app, "kickstart_export", &[ProbedFeature::UserInstalledQuery, ProbedFeature::InstalledGroups]),
+        requirement_state("flatpak", resolve_binary("flatpak").is_some(), FeatureStatus::Unavailable, "Flatpak is not installed."),
+        requirement_state(
+            "userspace_packages",
+            !crate::userspace::installed_managers().is_empty(),
+            FeatureStatus::Unavailable,
+            "Neither Homebrew nor Nix is installed.",
+        ),
+        requirement_state("boot_menu", resolve_binary("grubby").is_some(), FeatureStatus::Degraded, "grubby is not installed; boot entries are read from /boot/loader/entries and the default kernel is unknown."),
+    ];
+    let snapshots = crate::snapshot::detect_snapshot_support(&app).await;
+    matrix.push(state(
+        "snapshot_updates",
+        if snapshots.available { FeatureStatus::Available } else { FeatureStatus::Unavailable },
+        snapshots.reason,
+    ));
+    let usage_insights = crate::settings::usage_insights(&app)?;
+    matrix.push(requirement_state("usage_insights", usage_insights, FeatureStatus::Unavailable, "Turned off in settings."));
+    let simulation_mode = crate::settings::simulation_mode(&app)?;
+    matrix.push(requirement_state(
+        "package_changes",
+        !simulation_mode,
+        FeatureStatus::Degraded,
+        "Simulation mode is on: changes run as dry runs and nothing is modified.",
+    ));
+    Ok(matrix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feature_states() {
+        assert_eq!(requirement_state("flatpak", true, FeatureStatus::Unavailable, "missing"), state("flatpak", FeatureStatus::Available, None));
+        let degraded = requirement_state("boot_menu", false, FeatureStatus::Degraded, "no grubby");
+        assert_eq!(degraded.status, FeatureStatus::Degraded);
+        assert_eq!(degraded.reason.as_deref(), Some("no grubby"));
+        assert_eq!(backend_state(PackageBackend::Dnf).status, FeatureStatus::Available);
+    }
+}
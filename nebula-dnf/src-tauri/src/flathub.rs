@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::error::NebulaError;
+
+const SEARCH_URL: &str = "https://flathub.org/api/v2/search";
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(15);
+const MAX_QUERY_LENGTH: usize = 100;
+
+// One hit of the Flathub search API; it returns many more fields than these.
+#[derive(Debug, Deserialize)]
+struct SearchHit {
+    app_id: String,
+    name: String,
+    #[serde(default)]
+    summary: Option<String>,
+    #[serde(default)]
+    icon: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    hits: Vec<SearchHit>,
+}
+
+// What the frontend needs to show a result and install it with `install_flatpak` from "flathub".
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct FlathubApp {
+    app_id: String,
+    name: String,
+    summary: String,
+    icon_url: Option<String>,
+}
+
+fn parse_search_response(body: &[u8]) -> Result<Vec<FlathubApp>, String> {
+    let response: SearchResponse = serde_json::from_slice(body).map_err(|e| format!("Unexpected Flathub response: {}", e))?;
+    Ok(response
+        .hits
+        .into_iter()
+        .map(|hit| FlathubApp {
+            app_id: hit.app_id,
+            name: hit.name,
+            summary: hit.summary.unwrap_or_default(),
+            icon_url: hit.icon.filter(|url| url.starts_with("https://")),
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub async fn search_flathub(query: String) -> Result<Vec<FlathubApp>, NebulaError> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    if query.len() > MAX_QUERY_LENGTH {
+        return Err("Search query is too long.".into());
+    }
+    let client = reqwest::Client::builder()
+        .timeout(SEARCH_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let response = client
+        .post(SEARCH_URL)
+        .json(&serde_json::json!({ "query": query }))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to search Flathub: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Flathub search returned HTTP {}", response.status()).into());
+    }
+    let body = response.bytes().await.map_err(|e| format!("Failed to read Flathub response: {}", e))?;
+    Ok(parse_search_response(&body)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_search_response() {
+        let body = br#"{"query":"builder","hits":[
+            {"app_id":"org.gnome.Builder","name":"Builder","summary":"An IDE for GNOME","icon":"https://dl.flathub.org/media/org/gnome/Builder/icon.png","installs_last_month":1200},
+            {"app_id":"com.example.Tool","name":"Tool","icon":null}
+        ],"totalHits":2}"#;
+        let apps = parse_search_response(body).unwrap();
+        assert_eq!(apps.len(), 2);
+        assert_eq!(apps[0].app_id, "org.gnome.Builder");
+        assert!(apps[0].icon_url.is_some());
+        assert_eq!(apps[1].summary, "");
+        assert_eq!(apps[1].icon_url, None);
+        assert!(parse_search_response(b"<html>").is_err());
+    }
+}
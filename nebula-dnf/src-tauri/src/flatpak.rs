@@ -0,0 +1,299 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use tauri::Manager;
+
+use crate::error::NebulaError;
+use crate::plan::{strings, CommandPlan};
+use crate::process::{run_command, run_plan, run_plan_streaming};
+use crate::scope::Scope;
+use crate::services::OperationManager;
+use crate::PackageOperationResult;
+
+// Application ids ("org.gnome.Builder") or full refs ("app/org.gnome.Builder/x86_64/stable").
+static FLATPAK_REF_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Za-z0-9_][A-Za-z0-9_./-]*$").unwrap());
+static REMOTE_NAME_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Za-z0-9_][A-Za-z0-9_.-]*$").unwrap());
+// .flatpakrepo files or repository URLs for remote-add.
+static REMOTE_URL_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"^(https://|file:///)[^\s'"]+$"#).unwrap());
+
+const FLATHUB_NAME: &str = "flathub";
+const FLATHUB_REPO_FILE: &str = "https://dl.flathub.org/repo/flathub.flatpakrepo";
+
+// Tab-separated, one ref per line; "ref" tells apps from runtimes.
+const LIST_COLUMNS: &str = "--columns=application,name,version,branch,arch,origin,installation,ref";
+// "options" holds the installation ("system", "user") and flags such as "disabled".
+const REMOTE_COLUMNS: &str = "--columns=name,title,url,priority,options";
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub enum FlatpakKind {
+    App,
+    Runtime,
+}
+
+// Mirrors DisplayablePackage so the frontend can show RPMs and Flatpaks in one list.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct FlatpakPackage {
+    pub name: String,         // application id, e.g. "org.gnome.Builder"
+    pub display_name: String,
+    pub version: Option<String>,
+    branch: String,
+    arch: String,
+    origin: String,           // remote it was installed from, e.g. "flathub"
+    pub installation: String, // "user", "system" or the name of a custom installation
+    pub kind: FlatpakKind,
+    pub flatpak_ref: String,  // full ref, e.g. "app/org.gnome.Builder/x86_64/stable"
+}
+
+fn parse_flatpak_list(output: &str) -> Vec<FlatpakPackage> {
+    let mut packages: Vec<FlatpakPackage> = output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').map(str::trim).collect();
+            let [name, display_name, version, branch, arch, origin, installation, flatpak_ref] = fields[..] else { return None };
+            Some(FlatpakPackage {
+                name: name.to_string(),
+                display_name: if display_name.is_empty() { name } else { display_name }.to_string(),
+                version: Some(version).filter(|v| !v.is_empty()).map(String::from),
+                branch: branch.to_string(),
+                arch: arch.to_string(),
+                origin: origin.to_string(),
+                installation: installation.to_string(),
+                kind: if flatpak_ref.starts_with("runtime/") { FlatpakKind::Runtime } else { FlatpakKind::App },
+                flatpak_ref: flatpak_ref.to_string(),
+            })
+        })
+        .collect();
+    packages.sort_by_key(|package| package.display_name.to_lowercase());
+    packages
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct FlatpakRemote {
+    name: String,
+    title: String,
+    url: String,
+    priority: Option<i32>,
+    installation: String,
+    disabled: bool,
+}
+
+fn parse_remotes(output: &str) -> Vec<FlatpakRemote> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').map(str::trim).collect();
+            let [name, title, url, priority, options] = fields[..] else { return None };
+            let options: Vec<&str> = options.split(',').map(str::trim).collect();
+            Some(FlatpakRemote {
+                name: name.to_string(),
+                title: if title.is_empty() { name } else { title }.to_string(),
+                url: url.to_string(),
+                priority: priority.parse().ok(),
+                installation: options.first().copied().unwrap_or_default().to_string(),
+                disabled: options.contains(&"disabled"),
+            })
+        })
+        .collect()
+}
+
+pub async fn fetch_flatpaks(app: &tauri::AppHandle) -> Result<Vec<FlatpakPackage>, String> {
+    let out = run_command(app, "flatpak", &["list", LIST_COLUMNS])
+        .await
+        .map_err(|_| "Flatpak is not installed.".to_string())?;
+    if !out.success {
+        return Err(format!("flatpak list failed: {}", out.error_text().trim()));
+    }
+    Ok(parse_flatpak_list(&out.stdout))
+}
+
+// Installed Flatpaks from both the user and system installations. Runtimes are left out unless
+// asked for, as they are dependencies rather than software the user picked. `remote` narrows the
+// list to what was installed from that remote.
+#[tauri::command]
+pub async fn list_flatpaks(app: tauri::AppHandle, include_runtimes: Option<bool>, remote: Option<String>) -> Result<Vec<FlatpakPackage>, NebulaError> {
+    let mut packages = fetch_flatpaks(&app).await?;
+    if !include_runtimes.unwrap_or(false) {
+        packages.retain(|package| package.kind == FlatpakKind::App);
+    }
+    if let Some(remote) = remote {
+        packages.retain(|package| package.origin == remote);
+    }
+    Ok(packages)
+}
+
+// Remotes of both installations, disabled ones included.
+#[tauri::command]
+pub async fn list_flatpak_remotes(app: tauri::AppHandle) -> Result<Vec<FlatpakRemote>, NebulaError> {
+    let out = run_command(&app, "flatpak", &["remotes", "--show-disabled", REMOTE_COLUMNS])
+        .await
+        .map_err(|_| "Flatpak is not installed.".to_string())?;
+    if !out.success {
+        return Err(NebulaError::command_failed(out.code, &out.stderr, format!("flatpak remotes failed: {}", out.error_text().trim())));
+    }
+    Ok(parse_remotes(&out.stdout))
+}
+
+fn validate_refs(refs: &[String]) -> Result<(), String> {
+    match refs.iter().find(|r| !FLATPAK_REF_RE.is_match(r)) {
+        Some(bad) => Err(format!("Invalid Flatpak ref: '{}'", bad)),
+        None => Ok(()),
+    }
+}
+
+fn validate_remote(remote: &str) -> Result<(), String> {
+    if REMOTE_NAME_RE.is_match(remote) {
+        Ok(())
+    } else {
+        Err(format!("Invalid Flatpak remote: '{}'", remote))
+    }
+}
+
+// `flatpak <command> --user|--system --noninteractive [args...]`
+fn change_plan(command: &str, scope: Scope, refs: &[String]) -> CommandPlan {
+    let mut args = strings(&[command, scope.flatpak_flag(), "--noninteractive"]);
+    args.extend(refs.iter().cloned());
+    CommandPlan::new("flatpak", args)
+}
+
+// Runs a Flatpak change with its output streamed as "operation-output" events.
+async fn run_change(app: &tauri::AppHandle, plan: CommandPlan, operation: &str, done: String) -> Result<PackageOperationResult, String> {
+    let _busy = app.state::<OperationManager>().begin(done.clone());
+    let out = run_plan_streaming(app, &plan, operation).await?;
+    Ok(PackageOperationResult {
+        success: out.success,
+        message: if out.success { done } else { format!("{} failed with exit code {}.", plan.command_line(), out.code) },
+        details: Some(format!("STDOUT:\n{}\nSTDERR:\n{}", out.stdout, out.stderr)),
+        confirmation_required: false,
+        simulated: out.simulated,
+        reboot_required: false,
+    })
+}
+
+// Flatpak has no dry-run mode, so dry runs answer from read-only queries instead.
+async fn run_dry_run(app: &tauri::AppHandle, plan: CommandPlan, done: String) -> Result<PackageOperationResult, String> {
+    let out = run_plan(app, &plan).await?;
+    Ok(PackageOperationResult {
+        success: out.success,
+        message: if out.success { done } else { format!("Dry run failed: {}", out.error_text().trim()) },
+        details: Some(format!("STDOUT:\n{}\nSTDERR:\n{}", out.stdout, out.stderr)),
+        confirmation_required: false,
+        simulated: out.simulated,
+        reboot_required: false,
+    })
+}
+
+// Adds a remote from a .flatpakrepo file or repository URL. Without `name` and `url`, adds Flathub.
+#[tauri::command]
+pub async fn add_flatpak_remote(app: tauri::AppHandle, name: Option<String>, url: Option<String>, scope: Scope) -> Result<PackageOperationResult, NebulaError> {
+    let name = name.unwrap_or_else(|| FLATHUB_NAME.to_string());
+    let url = url.unwrap_or_else(|| FLATHUB_REPO_FILE.to_string());
+    validate_remote(&name)?;
+    if !REMOTE_URL_RE.is_match(&url) {
+        return Err(format!("Remotes can be added from an https or file URL, not '{}'.", url).into());
+    }
+    let plan = CommandPlan::new("flatpak", strings(&["remote-add", scope.flatpak_flag(), "--if-not-exists", &name, &url]));
+    Ok(run_change(&app, plan, "flatpak_remote_add", format!("Added remote {}.", name)).await?)
+}
+
+// flatpak refuses while apps from the remote are still installed; the message says which.
+#[tauri::command]
+pub async fn remove_flatpak_remote(app: tauri::AppHandle, name: String, scope: Scope) -> Result<PackageOperationResult, NebulaError> {
+    validate_remote(&name)?;
+    let plan = CommandPlan::new("flatpak", strings(&["remote-delete", scope.flatpak_flag(), &name]));
+    let mut result = run_change(&app, plan, "flatpak_remote_delete", format!("Removed remote {}.", name)).await?;
+    if !result.success {
+        let installed: Vec<String> = fetch_flatpaks(&app).await?.into_iter().filter(|p| p.origin == name).map(|p| p.name).collect();
+        if !installed.is_empty() {
+            result.message = format!("Remote {} is still used by {}. Uninstall them first.", name, installed.join(", "));
+        }
+    }
+    Ok(result)
+}
+
+// Installs an app (and the runtimes it needs) from a configured remote. A dry run shows the
+// remote's metadata for the ref, including download size and runtime.
+#[tauri::command]
+pub async fn install_flatpak(app: tauri::AppHandle, remote: String, flatpak_ref: String, scope: Scope, dry_run: bool) -> Result<PackageOperationResult, NebulaError> {
+    validate_remote(&remote)?;
+    let refs = vec![flatpak_ref.clone()];
+    validate_refs(&refs)?;
+    if dry_run {
+        let plan = CommandPlan::new("flatpak", strings(&["remote-info", scope.flatpak_flag(), &remote, &flatpak_ref]));
+        return Ok(run_dry_run(&app, plan, format!("{} is available from {}. Review it before installing.", flatpak_ref, remote)).await?);
+    }
+    let plan = change_plan("install", scope, &[remote.clone(), flatpak_ref.clone()]);
+    Ok(run_change(&app, plan, "flatpak_install", format!("Installed {} from {}.", flatpak_ref, remote)).await?)
+}
+
+// A dry run only checks that every ref is installed in the given installation.
+#[tauri::command]
+pub async fn uninstall_flatpak(app: tauri::AppHandle, refs: Vec<String>, scope: Scope, dry_run: bool) -> Result<PackageOperationResult, NebulaError> {
+    if refs.is_empty() {
+        return Err("No Flatpaks given.".into());
+    }
+    validate_refs(&refs)?;
+    if dry_run {
+        let installation = if scope == Scope::User { "user" } else { "system" };
+        let installed = fetch_flatpaks(&app).await?;
+        let missing: Vec<&str> = refs
+            .iter()
+            .filter(|r| !installed.iter().any(|p| p.installation == installation && (p.name == **r || p.flatpak_ref == **r)))
+            .map(String::as_str)
+            .collect();
+        return Ok(PackageOperationResult {
+            success: missing.is_empty(),
+            message: if missing.is_empty() {
+                format!("Dry run: would uninstall {} from the {} installation.", refs.join(", "), installation)
+            } else {
+                format!("Not installed in the {} installation: {}.", installation, missing.join(", "))
+            },
+            details: None,
+            confirmation_required: false,
+            simulated: false,
+            reboot_required: false,
+        });
+    }
+    let plan = change_plan("uninstall", scope, &refs);
+    Ok(run_change(&app, plan, "flatpak_uninstall", format!("Uninstalled {}.", refs.join(", "))).await?)
+}
+
+// Updates the given refs, or everything in the installation when `refs` is empty. A dry run
+// lists the pending updates.
+#[tauri::command]
+pub async fn update_flatpaks(app: tauri::AppHandle, refs: Vec<String>, scope: Scope, dry_run: bool) -> Result<PackageOperationResult, NebulaError> {
+    validate_refs(&refs)?;
+    if dry_run {
+        let plan = CommandPlan::new("flatpak", strings(&["remote-ls", "--updates", scope.flatpak_flag(), "--columns=application,version,origin"]));
+        return Ok(run_dry_run(&app, plan, "Dry run finished. Review the pending Flatpak updates.".to_string()).await?);
+    }
+    let done = if refs.is_empty() { "Updated all Flatpaks.".to_string() } else { format!("Updated {}.", refs.join(", ")) };
+    Ok(run_change(&app, change_plan("update", scope, &refs), "flatpak_update", done).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_flatpak_list() {
+        let output = "org.gnome.Builder\tBuilder\t46.2\tstable\tx86_64\tflathub\tsystem\tapp/org.gnome.Builder/x86_64/stable\n\
+                      org.gnome.Platform\tGNOME Application Platform version 46\t\t46\tx86_64\tflathub\tsystem\truntime/org.gnome.Platform/x86_64/46\n\
+                      com.example.Tool\t\t1.0\tmaster\tx86_64\texample-repo\tuser\tapp/com.example.Tool/x86_64/master\n";
+        let packages = parse_flatpak_list(output);
+        assert_eq!(packages.len(), 3);
+        assert_eq!(packages[0].name, "org.gnome.Builder");
+        assert_eq!(packages[1].display_name, "com.example.Tool");
+        assert_eq!(packages[1].installation, "user");
+        assert_eq!(packages[2].kind, FlatpakKind::Runtime);
+        assert_eq!(packages[2].version, None);
+        assert!(validate_refs(&strings(&["app/org.gnome.Builder/x86_64/stable", "org.gnome.Builder"])).is_ok());
+        assert!(validate_refs(&strings(&["--from=evil"])).is_err());
+        assert_eq!(change_plan("update", Scope::User, &[]).command_line(), "flatpak update --user --noninteractive");
+
+        let remotes = parse_remotes("flathub\tFlathub\thttps://dl.flathub.org/repo/\t1\tsystem\nfedora\tFedora Flatpaks\toci+https://registry.fedoraproject.org\t1\tsystem,disabled,oci\n");
+        assert_eq!(remotes.len(), 2);
+        assert_eq!(remotes[0].installation, "system");
+        assert!(!remotes[0].disabled);
+        assert!(remotes[1].disabled);
+    }
+}
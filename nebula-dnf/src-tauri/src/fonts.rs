@@ -0,0 +1,145 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::error::NebulaError;
+use crate::process::run_command;
+
+// Font family names as users type them, e.g. "Noto Sans CJK" or "Fira Code".
+static FAMILY_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[a-zA-Z0-9 ._+-]+$").unwrap());
+
+// Fedora font packages provide "font(<family, lowercased without spaces>)" per family and
+// "font(:lang=<code>)" per covered language.
+const INSTALLED_FONTS_FORMAT: &str = "[%{NAME}\t%{VERSION}-%{RELEASE}\t%{PROVIDES}\t%{SUMMARY}\n]";
+const AVAILABLE_FONTS_FORMAT: &str = "%{name}\t%{version}-%{release}\t%{summary}\n";
+const FONT_FILE_EXTENSIONS: &[&str] = &[".ttf", ".otf", ".ttc", ".woff2"];
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct FontPackage {
+    name: String,
+    version: String,
+    summary: String,
+    families: Vec<String>,
+    languages: Vec<String>,
+    sample_file: Option<String>, // a font file the frontend can load to render a preview
+    installed: bool,
+}
+
+fn parse_installed_fonts(output: &str) -> Vec<FontPackage> {
+    let mut packages: BTreeMap<String, FontPackage> = BTreeMap::new();
+    for line in output.lines() {
+        let mut fields = line.splitn(4, '\t').map(str::trim);
+        let (Some(name), Some(version), Some(provide)) = (fields.next(), fields.next(), fields.next()) else { continue };
+        let Some(font) = provide.strip_prefix("font(").and_then(|rest| rest.strip_suffix(')')) else { continue };
+        let package = packages.entry(name.to_string()).or_insert_with(|| FontPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            summary: fields.next().unwrap_or("").to_string(),
+            families: Vec::new(),
+            languages: Vec::new(),
+            sample_file: None,
+            installed: true,
+        });
+        match font.strip_prefix(":lang=") {
+            Some(lang) => package.languages.push(lang.to_string()),
+            None if !font.is_empty() => package.families.push(font.to_string()),
+            None => {}
+        }
+    }
+    packages.into_values().collect()
+}
+
+// The first font file each package ships, keyed by package name.
+fn first_font_files(output: &str) -> BTreeMap<String, String> {
+    let mut files = BTreeMap::new();
+    for (package, file) in output.lines().filter_map(|line| line.split_once('\t')) {
+        if FONT_FILE_EXTENSIONS.iter().any(|ext| file.to_lowercase().ends_with(ext)) {
+            files.entry(package.to_string()).or_insert_with(|| file.to_string());
+        }
+    }
+    files
+}
+
+// Installed font packages with the families and languages they cover.
+#[tauri::command]
+pub async fn list_installed_fonts(app: tauri::AppHandle) -> Result<Vec<FontPackage>, NebulaError> {
+    let out = run_command(&app, "rpm", &["-qa", "--queryformat", INSTALLED_FONTS_FORMAT]).await?;
+    if !out.success {
+        return Err(NebulaError::command_failed(out.code, &out.stderr, format!("rpm -qa failed: {}", out.error_text().trim())));
+    }
+    let mut fonts = parse_installed_fonts(&out.stdout);
+    if fonts.is_empty() {
+        return Ok(fonts);
+    }
+    let mut file_args = vec!["-q", "--queryformat", "[%{NAME}\t%{FILENAMES}\n]", "--"];
+    file_args.extend(fonts.iter().map(|font| font.name.as_str()));
+    let files = first_font_files(&run_command(&app, "rpm", &file_args).await?.stdout);
+    for font in &mut fonts {
+        font.sample_file = files.get(&font.name).cloned();
+    }
+    Ok(fonts)
+}
+
+// Available font packages for a family name, matched both on the font() provides and on the
+// "<family>-fonts" naming convention. Install the chosen one with `install_packages`.
+#[tauri::command]
+pub async fn search_font_packages(app: tauri::AppHandle, family: String) -> Result<Vec<FontPackage>, NebulaError> {
+    let family = family.trim();
+    if !FAMILY_RE.is_match(family) {
+        return Err(format!("Invalid font family: '{}'", family).into());
+    }
+    let provide = format!("font({}*)", family.to_lowercase().replace(' ', ""));
+    let name_glob = format!("*{}*-fonts", family.to_lowercase().replace(' ', "-"));
+    let mut results: BTreeMap<String, FontPackage> = BTreeMap::new();
+    for query in [vec!["--whatprovides", &provide], vec!["--", &name_glob]] {
+        let mut args = vec!["repoquery", "--quiet", "--latest-limit=1", "--queryformat", AVAILABLE_FONTS_FORMAT];
+        args.extend(query);
+        let out = run_command(&app, "dnf", &args).await?;
+        if !out.success {
+            return Err(NebulaError::command_failed(out.code, &out.stderr, format!("dnf repoquery failed: {}", out.error_text().trim())));
+        }
+        for line in out.stdout.lines() {
+            let mut fields = line.splitn(3, '\t').map(str::trim);
+            let (Some(name), Some(version)) = (fields.next(), fields.next()) else { continue };
+            results.entry(name.to_string()).or_insert_with(|| FontPackage {
+                name: name.to_string(),
+                version: version.to_string(),
+                summary: fields.next().unwrap_or("").to_string(),
+                families: Vec::new(),
+                languages: Vec::new(),
+                sample_file: None,
+                installed: false,
+            });
+        }
+    }
+
+    let installed: HashSet<String> = list_installed_fonts(app).await?.into_iter().map(|font| font.name).collect();
+    Ok(results
+        .into_values()
+        .map(|mut font| {
+            font.installed = installed.contains(&font.name);
+            font
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_installed_fonts() {
+        let output = "google-noto-sans-fonts\t20240401-1.fc40\tfont(notosans)\tNoto Sans font\n\
+                      google-noto-sans-fonts\t20240401-1.fc40\tfont(:lang=en)\tNoto Sans font\n\
+                      google-noto-sans-fonts\t20240401-1.fc40\tgoogle-noto-sans-fonts\tNoto Sans font\n\
+                      bash\t5.2.26-3.fc40\tbash\tThe GNU Bourne Again shell\n";
+        let fonts = parse_installed_fonts(output);
+        assert_eq!(fonts.len(), 1);
+        assert_eq!(fonts[0].families, vec!["notosans"]);
+        assert_eq!(fonts[0].languages, vec!["en"]);
+
+        let files = first_font_files("google-noto-sans-fonts\t/usr/share/fonts/google-noto\ngoogle-noto-sans-fonts\t/usr/share/fonts/google-noto/NotoSans-Regular.ttf\n");
+        assert_eq!(files.get("google-noto-sans-fonts").map(String::as_str), Some("/usr/share/fonts/google-noto/NotoSans-Regular.ttf"));
+    }
+}
@@ -0,0 +1,135 @@
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::error::NebulaError;
+use crate::plan::{strings, CommandPlan};
+use crate::process::{run_command, run_plan};
+use crate::PackageOperationResult;
+
+// rpm stores each imported key as a pseudo-package "gpg-pubkey-<key id>-<creation time, hex>".
+static KEY_VERSION_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[0-9a-f]{8,16}-[0-9a-f]{8}$").unwrap());
+// Keys are imported from a local file or an https URL, as found in .repo files' gpgkey=.
+static KEY_SOURCE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"^(https://|file:///|/)[^\s'"]+$"#).unwrap());
+
+const KEY_QUERY_FORMAT: &str = "%{VERSION}-%{RELEASE}\t%{INSTALLTIME}\t%{SUMMARY}\n";
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct GpgKey {
+    key_id: String,
+    version: String, // "<key id>-<created>", what remove_gpg_key takes
+    summary: String, // usually "gpg(Owner <address>)"
+    created: Option<DateTime<Utc>>,
+    imported: Option<DateTime<Utc>>,
+}
+
+fn parse_keys(output: &str) -> Vec<GpgKey> {
+    let mut keys: Vec<GpgKey> = output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t').map(str::trim);
+            let version = fields.next().filter(|v| KEY_VERSION_RE.is_match(v))?;
+            let (key_id, created) = version.split_once('-')?;
+            let imported = fields.next()?.parse::<i64>().ok();
+            Some(GpgKey {
+                key_id: key_id.to_uppercase(),
+                version: version.to_string(),
+                summary: fields.next().unwrap_or("").to_string(),
+                created: i64::from_str_radix(created, 16).ok().and_then(|t| DateTime::from_timestamp(t, 0)),
+                imported: imported.and_then(|t| DateTime::from_timestamp(t, 0)),
+            })
+        })
+        .collect();
+    keys.sort_by(|a, b| a.summary.cmp(&b.summary).then_with(|| a.created.cmp(&b.created)));
+    keys
+}
+
+fn validate_key_version(version: &str) -> Result<(), String> {
+    if KEY_VERSION_RE.is_match(version) {
+        Ok(())
+    } else {
+        Err(format!("Invalid key: '{}'", version))
+    }
+}
+
+async fn fetch_keys(app: &tauri::AppHandle) -> Result<Vec<GpgKey>, String> {
+    let out = run_command(app, "rpm", &["-q", "gpg-pubkey", "--queryformat", KEY_QUERY_FORMAT]).await?;
+    // "package gpg-pubkey is not installed" when no key was imported yet
+    if !out.success && !out.stdout.contains("not installed") {
+        return Err(format!("Failed to list GPG keys: {}", out.error_text().trim()));
+    }
+    Ok(parse_keys(&out.stdout))
+}
+
+// Lowercase ids of the imported keys, for matching against package signatures.
+pub async fn imported_key_ids(app: &tauri::AppHandle) -> Result<HashSet<String>, String> {
+    Ok(fetch_keys(app).await?.into_iter().map(|key| key.key_id.to_lowercase()).collect())
+}
+
+// Keys rpm trusts for package signatures, i.e. every repository key ever imported.
+#[tauri::command]
+pub async fn list_gpg_keys(app: tauri::AppHandle) -> Result<Vec<GpgKey>, NebulaError> {
+    Ok(fetch_keys(&app).await?)
+}
+
+// `rpm -qi` output for one key, including the packager line and the armored key block.
+#[tauri::command]
+pub async fn get_gpg_key_details(app: tauri::AppHandle, version: String) -> Result<String, NebulaError> {
+    validate_key_version(&version)?;
+    let out = run_command(&app, "rpm", &["-qi", &format!("gpg-pubkey-{}", version)]).await?;
+    if !out.success {
+        return Err(NebulaError::command_failed(out.code, &out.stderr, format!("Failed to read key {}: {}", version, out.error_text().trim())));
+    }
+    Ok(out.stdout)
+}
+
+async fn run_key_plan(app: &tauri::AppHandle, plan: CommandPlan, done: String) -> Result<PackageOperationResult, String> {
+    let out = run_plan(app, &plan).await?;
+    Ok(PackageOperationResult {
+        success: out.success,
+        message: if out.success { done } else { format!("{} failed: {}", plan.command_line(), out.error_text().trim()) },
+        details: Some(format!("STDOUT:\n{}\nSTDERR:\n{}", out.stdout, out.stderr)),
+        confirmation_required: false,
+        simulated: out.simulated,
+        reboot_required: false,
+    })
+}
+
+// Packages signed only with this key will no longer pass signature checks.
+#[tauri::command]
+pub async fn remove_gpg_key(app: tauri::AppHandle, version: String) -> Result<PackageOperationResult, NebulaError> {
+    validate_key_version(&version)?;
+    let plan = CommandPlan::privileged("rpm", strings(&["-e", &format!("gpg-pubkey-{}", version)]));
+    Ok(run_key_plan(&app, plan, format!("Removed key {}.", version)).await?)
+}
+
+#[tauri::command]
+pub async fn import_gpg_key(app: tauri::AppHandle, source: String) -> Result<PackageOperationResult, NebulaError> {
+    let source = source.trim();
+    if !KEY_SOURCE_RE.is_match(source) {
+        return Err(format!("Keys can be imported from a local path or an https URL, not '{}'.", source).into());
+    }
+    let plan = CommandPlan::privileged("rpm", strings(&["--import", source]));
+    Ok(run_key_plan(&app, plan, format!("Imported key from {}.", source)).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_keys() {
+        let output = "a15b79cc-63d04c2c\t1714550400\tgpg(Fedora (40) <fedora-40-primary@fedoraproject.org>)\n\
+                      package gpg-pubkey-junk is not installed\n\
+                      94843c65-5e21f4d2\t1714550500\tgpg(RPM Fusion free repository for Fedora (2020) <rpmfusion-buildsys@lists.rpmfusion.org>)\n";
+        let keys = parse_keys(output);
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].key_id, "A15B79CC");
+        assert_eq!(keys[0].created.map(|t| t.timestamp()), Some(0x63d04c2c));
+        assert_eq!(keys[1].version, "94843c65-5e21f4d2");
+        assert!(validate_key_version("a15b79cc-63d04c2c").is_ok());
+        assert!(validate_key_version("a15b79cc-63d04c2c --nodeps").is_err());
+    }
+}
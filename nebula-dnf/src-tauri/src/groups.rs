@@ -0,0 +1,273 @@
+use serde::Serialize;
+use tauri::Manager;
+
+use crate::error::NebulaError;
+use crate::packagekit::PackageAction;
+use crate::plan;
+use crate::process::{dry_run_succeeded, run_command, run_plan};
+use crate::services::OperationManager;
+use crate::settings::{GuardedAction, PackageBackend};
+use crate::PackageOperationResult;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum GroupKind {
+    Environment, // a whole system type, e.g. "Fedora Workstation", made of groups
+    Group,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct PackageGroup {
+    id: String,
+    name: String,
+    kind: GroupKind,
+    description: String,
+    installed: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct GroupList {
+    installed: Vec<PackageGroup>,
+    available: Vec<PackageGroup>,
+}
+
+// What `group info` lists for a group: its packages, or for an environment its groups (by name).
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct GroupContents {
+    id: String,
+    name: String,
+    description: String,
+    mandatory: Vec<String>,
+    default: Vec<String>,
+    optional: Vec<String>,
+}
+
+fn group(id: &str, name: &str, kind: GroupKind, installed: bool) -> PackageGroup {
+    PackageGroup { id: id.to_string(), name: name.to_string(), kind, description: String::new(), installed }
+}
+
+// dnf4 `group list --hidden --ids`: "Name (id)" lines under "Installed Groups:",
+// "Available Environment Groups:" and similar headers.
+fn parse_dnf4_group_list(output: &str) -> Vec<PackageGroup> {
+    let mut groups = Vec::new();
+    let mut section: Option<(GroupKind, bool)> = None;
+    for line in output.lines() {
+        if !line.starts_with(' ') && line.trim_end().ends_with(':') {
+            let kind = if line.contains("Environment") { GroupKind::Environment } else { GroupKind::Group };
+            section = Some((kind, line.starts_with("Installed")));
+            continue;
+        }
+        let Some((kind, installed)) = section else { continue };
+        let Some((name, id)) = line.trim().strip_suffix(')').and_then(|rest| rest.rsplit_once(" (")) else { continue };
+        groups.push(group(id, name, kind, installed));
+    }
+    groups
+}
+
+// dnf5 `group list --hidden` and `environment list`: an "ID  Name  Installed" table.
+fn parse_dnf5_group_list(output: &str, kind: GroupKind) -> Vec<PackageGroup> {
+    output
+        .lines()
+        .skip_while(|line| !line.starts_with("ID"))
+        .skip(1)
+        .filter_map(|line| {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let (id, rest) = words.split_first()?;
+            let (installed, name) = rest.split_last()?;
+            Some(group(id, &name.join(" "), kind, *installed == "yes"))
+        })
+        .collect()
+}
+
+// dnf4 prints "Group: Name" blocks with indented " Group-Id: id" keys and package lists under
+// " Mandatory Packages:" headers; dnf5 prints "Id : id" blocks whose lists continue on lines
+// that start with ":". dnf4 may mark listed packages with =, + or -.
+fn parse_group_info(output: &str) -> Vec<GroupContents> {
+    let mut groups: Vec<GroupContents> = Vec::new();
+    let mut list: Option<usize> = None; // 0 mandatory, 1 default, 2 optional
+    for line in output.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let (key, value) = match trimmed.split_once(':') {
+            Some((key, value)) => (key.trim().to_lowercase(), value.trim()),
+            None => (String::new(), trimmed),
+        };
+        match key.as_str() {
+            "group" | "environment group" => groups.push(GroupContents { name: value.to_string(), ..Default::default() }),
+            "id" => groups.push(GroupContents { id: value.to_string(), ..Default::default() }),
+            _ => {}
+        }
+        let Some(current) = groups.last_mut() else { continue };
+        match key.as_str() {
+            "group-id" | "environment-id" => current.id = value.to_string(),
+            "name" => current.name = value.to_string(),
+            "description" => current.description = value.to_string(),
+            _ => {}
+        }
+        match key.as_str() {
+            "mandatory packages" | "mandatory groups" => list = Some(0),
+            "default packages" => list = Some(1),
+            "optional packages" | "optional groups" => list = Some(2),
+            "" => {}
+            _ => list = None,
+        }
+        let entry = value.trim_start_matches(['=', '+', '-']);
+        if entry.is_empty() {
+            continue;
+        }
+        match list {
+            Some(0) => current.mandatory.push(entry.to_string()),
+            Some(1) => current.default.push(entry.to_string()),
+            Some(2) => current.optional.push(entry.to_string()),
+            _ => {}
+        }
+    }
+    groups
+}
+
+// `group info` for the ids; dnf5 describes environments with `environment info` instead.
+async fn group_info(app: &tauri::AppHandle, ids: &[String]) -> Result<Vec<GroupContents>, String> {
+    let mut args = vec!["group", "info"];
+    args.extend(ids.iter().map(String::as_str));
+    let out = run_command(app, "dnf", &args).await?;
+    let mut info = parse_group_info(&out.stdout);
+    let missing: Vec<&str> = ids.iter().map(String::as_str).filter(|id| !info.iter().any(|group| group.id == *id)).collect();
+    if !missing.is_empty() {
+        let mut args = vec!["environment", "info"];
+        args.extend(missing);
+        if let Ok(environments) = run_command(app, "dnf", &args).await {
+            info.extend(parse_group_info(&environments.stdout));
+        }
+    }
+    if info.is_empty() && !out.success {
+        return Err(format!("dnf group info failed: {}", out.error_text().trim()));
+    }
+    Ok(info)
+}
+
+// Every comps group and environment, hidden ones included, split into installed and available.
+#[tauri::command]
+pub async fn list_groups(app: tauri::AppHandle) -> Result<GroupList, NebulaError> {
+    let out = run_command(&app, "dnf", &["group", "list", "--hidden", "--ids"]).await?;
+    let mut groups = if out.success {
+        parse_dnf4_group_list(&out.stdout)
+    } else {
+        // dnf5 has no --ids, and lists environments separately.
+        let groups = run_command(&app, "dnf", &["group", "list", "--hidden"]).await?;
+        if !groups.success {
+            return Err(NebulaError::command_failed(groups.code, &groups.stderr, format!("Failed to list groups: {}", groups.error_text().trim())));
+        }
+        let environments = run_command(&app, "dnf", &["environment", "list"]).await?;
+        let mut list = parse_dnf5_group_list(&environments.stdout, GroupKind::Environment);
+        list.extend(parse_dnf5_group_list(&groups.stdout, GroupKind::Group));
+        list
+    };
+    let ids: Vec<String> = groups.iter().map(|group| group.id.clone()).collect();
+    if !ids.is_empty() {
+        let info = group_info(&app, &ids).await?;
+        for group in &mut groups {
+            if let Some(details) = info.iter().find(|details| details.id == group.id) {
+                group.description = details.description.clone();
+            }
+        }
+    }
+    let (installed, available) = groups.into_iter().partition(|group| group.installed);
+    Ok(GroupList { installed, available })
+}
+
+#[tauri::command]
+pub async fn get_group_contents(app: tauri::AppHandle, group_id: String) -> Result<GroupContents, NebulaError> {
+    plan::validate_group_id(&group_id)?;
+    let info = group_info(&app, std::slice::from_ref(&group_id)).await?;
+    Ok(info.into_iter().find(|group| group.id == group_id).ok_or_else(|| format!("Unknown group: '{}'", group_id))?)
+}
+
+// Installs or removes a group or environment as `@id`. With `dry_run` the transaction is only
+// resolved; removals are previewed first when the settings ask for it, as uninstalls are.
+async fn change_group(app: &tauri::AppHandle, group_id: &str, action: PackageAction, dry_run: bool, confirmed: bool) -> Result<PackageOperationResult, String> {
+    if app.state::<crate::services::BackendRegistry>().selected()? != PackageBackend::Dnf {
+        return Err("Groups need the dnf backend.".to_string());
+    }
+    let preview = !dry_run && action == PackageAction::Remove && crate::settings::requires_preview(app, GuardedAction::Uninstall, confirmed)?;
+    let resolve_only = dry_run || preview;
+    let groups = [group_id.to_string()];
+    let plan = match action {
+        PackageAction::Remove => plan::remove_groups(&groups, resolve_only)?,
+        _ => plan::install_groups_and_packages(&groups, &[], resolve_only)?,
+    };
+    let verb = if action == PackageAction::Remove { "remove" } else { "install" };
+    let _busy = (!resolve_only).then(|| app.state::<OperationManager>().begin(format!("Changing group {}", group_id)));
+    let out = run_plan(app, &plan).await?;
+    let success = if resolve_only { dry_run_succeeded(out.code, &out.stdout, &out.stderr) } else { out.success };
+    let mut result = PackageOperationResult {
+        success,
+        message: match (success, resolve_only) {
+            (true, true) if preview => format!("Review what removing the group {} takes with it, then confirm.", group_id),
+            (true, true) => format!("Dry run finished. Review the transaction before you {} the group {}.", verb, group_id),
+            (true, false) => format!("The group {} was {}.", group_id, if action == PackageAction::Remove { "removed" } else { "installed" }),
+            (false, _) => format!("Failed to {} the group {}. Exit code: {}.", verb, group_id, out.code),
+        },
+        details: Some(format!("STDOUT:\n{}\nSTDERR:\n{}", out.stdout, out.stderr)),
+        confirmation_required: preview,
+        simulated: out.simulated,
+        reboot_required: false,
+    };
+    if !resolve_only {
+        crate::restarts::check_reboot(app, &mut result, &[]).await;
+    }
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn install_group(app: tauri::AppHandle, group_id: String, dry_run: bool) -> Result<PackageOperationResult, NebulaError> {
+    Ok(change_group(&app, &group_id, PackageAction::Install, dry_run, false).await?)
+}
+
+#[tauri::command]
+pub async fn remove_group(app: tauri::AppHandle, group_id: String, dry_run: bool, confirmed: Option<bool>) -> Result<PackageOperationResult, NebulaError> {
+    Ok(change_group(&app, &group_id, PackageAction::Remove, dry_run, confirmed.unwrap_or(false)).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_groups() {
+        let dnf4 = "Last metadata expiration check: 0:12:01 ago on Mon 01 Jan 2024.\n\
+            Available Environment Groups:\n   Minimal Install (minimal-environment)\n\
+            Installed Environment Groups:\n   Fedora Workstation (workstation-product-environment)\n\
+            Installed Groups:\n   C Development Tools and Libraries (c-development)\n\
+            Available Groups:\n   3D Printing (3d-printing)\n";
+        let groups = parse_dnf4_group_list(dnf4);
+        assert_eq!(groups.len(), 4);
+        assert_eq!(groups[1], group("workstation-product-environment", "Fedora Workstation", GroupKind::Environment, true));
+        assert_eq!(groups[3], group("3d-printing", "3D Printing", GroupKind::Group, false));
+
+        let dnf5 = "ID                   Name                                Installed\n\
+            c-development        C Development Tools and Libraries         yes\n\
+            3d-printing          3D Printing                                no\n";
+        let groups = parse_dnf5_group_list(dnf5, GroupKind::Group);
+        assert_eq!(groups[0], group("c-development", "C Development Tools and Libraries", GroupKind::Group, true));
+        assert!(!groups[1].installed);
+
+        let dnf4_info = "Group: C Development Tools and Libraries\n Group-Id: c-development\n\
+            Description: Core development tools: gcc and debuggers.\n Mandatory Packages:\n   =autoconf\n   automake\n\
+            Default Packages:\n   ccache\n Optional Packages:\n   ElectricFence\n";
+        let dnf5_info = "Id                   : c-development\nName                 : C Development Tools and Libraries\n\
+            Description          : Core development tools: gcc and debuggers.\nInstalled            : yes\n\
+            Mandatory packages   : autoconf\n                     : automake\nDefault packages     : ccache\n\
+            Optional packages    : ElectricFence\nConditional packages : \n";
+        for output in [dnf4_info, dnf5_info] {
+            let info = parse_group_info(output);
+            assert_eq!(info.len(), 1, "{}", output);
+            assert_eq!(info[0].id, "c-development");
+            assert_eq!(info[0].description, "Core development tools: gcc and debuggers.");
+            assert_eq!(info[0].mandatory, ["autoconf", "automake"]);
+            assert_eq!(info[0].default, ["ccache"]);
+            assert_eq!(info[0].optional, ["ElectricFence"]);
+        }
+        assert_eq!(plan::remove_groups(&plan::strings(&["c-development"]), false).unwrap().command_line(), "pkexec dnf remove --assumeyes @c-development");
+    }
+}
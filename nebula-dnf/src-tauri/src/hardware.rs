@@ -0,0 +1,184 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::error::NebulaError;
+use crate::process::run_command;
+
+// "01:00.0 VGA compatible controller [0300]: NVIDIA Corporation GA106 [GeForce RTX 3060] [10de:2503] (rev a1)"
+static LSPCI_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\S+ .*?\[([0-9a-f]{4})\]: (.*) \[([0-9a-f]{4}):([0-9a-f]{4})\]").unwrap());
+// "Bus 001 Device 003: ID 03f0:c311 HP, Inc ENVY 5000"
+static LSUSB_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"ID ([0-9a-f]{4}):([0-9a-f]{4})\s*(.*)$").unwrap());
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+pub enum Bus {
+    Pci,
+    Usb,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Device {
+    bus: Bus,
+    vendor: String,
+    product: String,
+    class: Option<String>, // PCI class code, e.g. "0300" for VGA
+    description: String,
+}
+
+struct DriverRule {
+    bus: Bus,
+    vendor: &'static str,
+    products: &'static [&'static str], // empty matches every product of the vendor
+    class_prefix: Option<&'static str>,
+    package: &'static str,
+    repo_hint: &'static str, // where the package normally comes from
+    reason: &'static str,
+}
+
+// Broadcom chips driven by the proprietary wl module rather than b43/brcmfmac.
+const BROADCOM_WL_DEVICES: &[&str] = &["4311", "4312", "4313", "4315", "4328", "4329", "432a", "432b", "432c", "432d", "4353", "4357", "4358", "4359", "4365", "43a0", "43b1", "4727"];
+
+const DRIVER_RULES: &[DriverRule] = &[
+    DriverRule { bus: Bus::Usb, vendor: "03f0", products: &[], class_prefix: None, package: "hplip", repo_hint: "fedora", reason: "HP printer or scanner" },
+    DriverRule { bus: Bus::Usb, vendor: "04f9", products: &[], class_prefix: None, package: "printer-driver-brlaser", repo_hint: "fedora", reason: "Brother printer" },
+    DriverRule { bus: Bus::Usb, vendor: "04b8", products: &[], class_prefix: None, package: "epson-inkjet-printer-escpr2", repo_hint: "fedora", reason: "Epson printer" },
+    DriverRule { bus: Bus::Usb, vendor: "04b8", products: &[], class_prefix: None, package: "sane-backends-drivers-scanners", repo_hint: "fedora", reason: "Epson scanner" },
+    DriverRule { bus: Bus::Usb, vendor: "04a9", products: &[], class_prefix: None, package: "sane-backends-drivers-scanners", repo_hint: "fedora", reason: "Canon scanner" },
+    DriverRule { bus: Bus::Pci, vendor: "14e4", products: BROADCOM_WL_DEVICES, class_prefix: Some("02"), package: "broadcom-wl", repo_hint: "rpmfusion-nonfree", reason: "Broadcom wireless chip that needs the wl driver" },
+    DriverRule { bus: Bus::Pci, vendor: "10de", products: &[], class_prefix: Some("03"), package: "akmod-nvidia", repo_hint: "rpmfusion-nonfree", reason: "NVIDIA graphics (proprietary driver)" },
+    DriverRule { bus: Bus::Pci, vendor: "8086", products: &[], class_prefix: Some("0280"), package: "iwlwifi-mvm-firmware", repo_hint: "fedora", reason: "Intel wireless firmware" },
+];
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DriverSuggestion {
+    package: String,
+    reason: String,
+    device: String,    // the matched device as lspci/lsusb describe it
+    device_id: String, // "vendor:product"
+    bus: Bus,
+    repo_hint: String,
+    available_from: Option<String>, // enabled repository that has it; None if none does
+    installed: bool,
+}
+
+fn parse_lspci(output: &str) -> Vec<Device> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let caps = LSPCI_RE.captures(line)?;
+            Some(Device {
+                bus: Bus::Pci,
+                class: Some(caps[1].to_string()),
+                description: caps[2].trim().to_string(),
+                vendor: caps[3].to_string(),
+                product: caps[4].to_string(),
+            })
+        })
+        .collect()
+}
+
+fn parse_lsusb(output: &str) -> Vec<Device> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let caps = LSUSB_RE.captures(line)?;
+            Some(Device {
+                bus: Bus::Usb,
+                vendor: caps[1].to_string(),
+                product: caps[2].to_string(),
+                class: None,
+                description: caps[3].trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+fn rule_matches(rule: &DriverRule, device: &Device) -> bool {
+    rule.bus == device.bus
+        && rule.vendor == device.vendor
+        && (rule.products.is_empty() || rule.products.contains(&device.product.as_str()))
+        && rule.class_prefix.is_none_or(|prefix| device.class.as_deref().is_some_and(|class| class.starts_with(prefix)))
+}
+
+// One suggestion per package, for the first device that called for it.
+fn match_devices(devices: &[Device]) -> Vec<DriverSuggestion> {
+    let mut suggestions: BTreeMap<&str, DriverSuggestion> = BTreeMap::new();
+    for device in devices {
+        for rule in DRIVER_RULES.iter().filter(|rule| rule_matches(rule, device)) {
+            suggestions.entry(rule.package).or_insert_with(|| DriverSuggestion {
+                package: rule.package.to_string(),
+                reason: rule.reason.to_string(),
+                device: device.description.clone(),
+                device_id: format!("{}:{}", device.vendor, device.product),
+                bus: device.bus,
+                repo_hint: rule.repo_hint.to_string(),
+                available_from: None,
+                installed: false,
+            });
+        }
+    }
+    suggestions.into_values().collect()
+}
+
+// Driver and firmware packages for connected printers, scanners and PCI devices, with the device
+// that triggered each suggestion and where the package can be installed from.
+#[tauri::command]
+pub async fn suggest_hardware_drivers(app: tauri::AppHandle) -> Result<Vec<DriverSuggestion>, NebulaError> {
+    let mut devices = Vec::new();
+    // either tool may be missing (pciutils/usbutils); the other still gives useful results
+    if let Ok(out) = run_command(&app, "lspci", &["-nn"]).await {
+        devices.extend(parse_lspci(&out.stdout));
+    }
+    if let Ok(out) = run_command(&app, "lsusb", &[]).await {
+        devices.extend(parse_lsusb(&out.stdout));
+    }
+    let mut suggestions = match_devices(&devices);
+    if suggestions.is_empty() {
+        return Ok(suggestions);
+    }
+
+    let packages: Vec<&str> = suggestions.iter().map(|s| s.package.as_str()).collect();
+    let mut query_args = vec!["repoquery", "--quiet", "--available", "--queryformat", "%{name}\t%{repoid}\n", "--"];
+    query_args.extend(&packages);
+    let available = run_command(&app, "dnf", &query_args).await?;
+    let mut installed_args = vec!["-q", "--queryformat", "%{NAME}\n", "--"];
+    installed_args.extend(&packages);
+    let installed = run_command(&app, "rpm", &installed_args).await?;
+    let installed: HashSet<&str> = installed.stdout.lines().map(str::trim).collect();
+
+    for suggestion in &mut suggestions {
+        suggestion.installed = installed.contains(suggestion.package.as_str());
+        suggestion.available_from = available
+            .stdout
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .find(|(name, _)| *name == suggestion.package)
+            .map(|(_, repo)| repo.trim().to_string());
+    }
+    Ok(suggestions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_devices() {
+        let lspci = "00:02.0 VGA compatible controller [0300]: Intel Corporation UHD Graphics 620 [8086:5917] (rev 07)\n\
+                     02:00.0 Network controller [0280]: Broadcom Inc. and subsidiaries BCM4360 802.11ac Wireless Network Adapter [14e4:43a0] (rev 03)\n\
+                     03:00.0 Ethernet controller [0200]: Broadcom Inc. and subsidiaries NetXtreme BCM5720 [14e4:165f]\n";
+        let lsusb = "Bus 001 Device 003: ID 03f0:c311 HP, Inc ENVY 5000\nBus 001 Device 001: ID 1d6b:0002 Linux Foundation 2.0 root hub\n";
+        let mut devices = parse_lspci(lspci);
+        devices.extend(parse_lsusb(lsusb));
+        assert_eq!(devices.len(), 5);
+        assert_eq!(devices[1].class.as_deref(), Some("0280"));
+
+        let suggestions = match_devices(&devices);
+        let packages: Vec<&str> = suggestions.iter().map(|s| s.package.as_str()).collect();
+        assert_eq!(packages, vec!["broadcom-wl", "hplip"]);
+        assert_eq!(suggestions[0].device_id, "14e4:43a0");
+        assert_eq!(suggestions[0].repo_hint, "rpmfusion-nonfree");
+    }
+}
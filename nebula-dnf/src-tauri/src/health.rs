@@ -0,0 +1,157 @@
+use serde::Serialize;
+
+use crate::error::NebulaError;
+use crate::process::run_command;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HealthIssueKind {
+    MissingRequires,
+    Conflict,
+    Duplicate,
+    Obsoleted,
+    Other,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HealthIssue {
+    kind: HealthIssueKind,
+    package: String, // NEVRA of the affected package
+    detail: String,  // the missing capability, conflicting or duplicate package, ...
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct HealthReport {
+    healthy: bool,
+    issues: Vec<HealthIssue>,
+    warnings: Vec<String>, // checks that could not run
+}
+
+fn issue(kind: HealthIssueKind, package: &str, detail: &str) -> HealthIssue {
+    HealthIssue {
+        kind,
+        package: package.trim().to_string(),
+        detail: detail.trim().trim_matches('"').to_string(),
+    }
+}
+
+// dnf4 prints one problem per line ("foo-1.0-1.x86_64 has missing requires of libbar.so.1"),
+// dnf5 prints the package followed by indented problems (` missing require "libbar.so.1"`).
+fn parse_dnf_check_output(output: &str) -> Vec<HealthIssue> {
+    let mut issues = Vec::new();
+    let mut current_package: Option<&str> = None;
+    for line in output.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if !line.starts_with(char::is_whitespace) {
+            if let Some((package, detail)) = line.split_once(" has missing requires of ") {
+                issues.push(issue(HealthIssueKind::MissingRequires, package, detail));
+            } else if let Some((package, detail)) = line.split_once(" has installed conflict ") {
+                issues.push(issue(HealthIssueKind::Conflict, package, detail));
+            } else if let Some((package, detail)) = line.split_once(" is a duplicate with ") {
+                issues.push(issue(HealthIssueKind::Duplicate, package, detail));
+            } else if let Some((package, detail)) = line.split_once(" is obsoleted by ") {
+                issues.push(issue(HealthIssueKind::Obsoleted, package, detail));
+            } else if !line.contains(' ') {
+                current_package = Some(line.trim()); // dnf5 package header
+                continue;
+            } else {
+                current_package = None;
+            }
+            continue;
+        }
+        let Some(package) = current_package else { continue };
+        let problem = line.trim();
+        let (kind, detail) = if let Some(detail) = problem.strip_prefix("missing require ") {
+            (HealthIssueKind::MissingRequires, detail)
+        } else if let Some(detail) = problem.strip_prefix("installed conflict ") {
+            (HealthIssueKind::Conflict, detail)
+        } else if let Some(detail) = problem.strip_prefix("duplicate with ") {
+            (HealthIssueKind::Duplicate, detail)
+        } else if let Some(detail) = problem.strip_prefix("obsoleted by ") {
+            (HealthIssueKind::Obsoleted, detail)
+        } else {
+            (HealthIssueKind::Other, problem)
+        };
+        issues.push(issue(kind, package, detail));
+    }
+    issues
+}
+
+// `rpm -Va --nofiles --nodigest` only reports dependency problems:
+// "Unsatisfied dependencies for foo-1.0-1.x86_64:" then "\tlibbar.so.1 is needed by foo-1.0-1.x86_64".
+fn parse_rpm_verify_output(output: &str) -> Vec<HealthIssue> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (capability, package) = line.trim().split_once(" is needed by ")?;
+            let package = package.trim_start_matches("(installed) ").split_whitespace().next()?;
+            Some(issue(HealthIssueKind::MissingRequires, package, capability))
+        })
+        .collect()
+}
+
+// Both tools exit non-zero when they find problems, so only a run with no parseable
+// problems and a non-zero exit counts as a failed check.
+#[tauri::command]
+pub async fn check_system_health(app: tauri::AppHandle) -> Result<HealthReport, NebulaError> {
+    let mut issues = Vec::new();
+    let mut warnings = Vec::new();
+
+    match run_command(&app, "dnf", &["check"]).await {
+        Ok(out) => {
+            let found = parse_dnf_check_output(&format!("{}\n{}", out.stdout, out.stderr));
+            if !out.success && found.is_empty() {
+                warnings.push(format!("dnf check failed: {}", out.error_text().trim()));
+            }
+            issues.extend(found);
+        }
+        Err(e) => warnings.push(e.to_string()),
+    }
+    match run_command(&app, "rpm", &["-Va", "--nofiles", "--nodigest"]).await {
+        Ok(out) => {
+            let found = parse_rpm_verify_output(&out.stdout);
+            if !out.success && found.is_empty() {
+                warnings.push(format!("rpm -Va failed: {}", out.error_text().trim()));
+            }
+            issues.extend(found);
+        }
+        Err(e) => warnings.push(e.to_string()),
+    }
+    if issues.is_empty() && warnings.len() == 2 {
+        return Err(warnings.join("\n").into());
+    }
+
+    // dnf and rpm report the same missing requirements
+    issues.sort();
+    issues.dedup();
+    Ok(HealthReport {
+        healthy: issues.is_empty(),
+        issues,
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dnf_check_output() {
+        let dnf4 = "foo-1.0-1.fc40.x86_64 has missing requires of libbar.so.1()(64bit)\n\
+                    foo-1.0-1.fc40.x86_64 is a duplicate with foo-0.9-1.fc40.x86_64\n";
+        let dnf5 = "foo-1.0-1.fc40.x86_64\n \
+                    missing require \"libbar.so.1()(64bit)\"\n \
+                    duplicate with \"foo-0.9-1.fc40.x86_64\"\n";
+        for output in [dnf4, dnf5] {
+            let issues = parse_dnf_check_output(output);
+            assert_eq!(issues.len(), 2, "{}", output);
+            assert_eq!(issues[0], issue(HealthIssueKind::MissingRequires, "foo-1.0-1.fc40.x86_64", "libbar.so.1()(64bit)"));
+            assert_eq!(issues[1].kind, HealthIssueKind::Duplicate);
+            assert_eq!(issues[1].detail, "foo-0.9-1.fc40.x86_64");
+        }
+
+        let rpm = "Unsatisfied dependencies for foo-1.0-1.fc40.x86_64:\n\tlibbar.so.1()(64bit) is needed by foo-1.0-1.fc40.x86_64\n";
+        assert_eq!(parse_rpm_verify_output(rpm), vec![issue(HealthIssueKind::MissingRequires, "foo-1.0-1.fc40.x86_64", "libbar.so.1()(64bit)")]);
+    }
+}
@@ -0,0 +1,203 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+
+use crate::plan::{CommandPlan, BATCH_STEP_MARKER};
+use crate::validate_package_name;
+
+// The root helper (src/bin/nebula-dnf-helper.rs), installed as the exec.path of the
+// com.nebula-dnf.manage polkit action. It runs dnf for a narrow JSON protocol only, so
+// administrators can authorize it, even persistently, without authorizing `pkexec dnf` with
+// arbitrary arguments. The plans keep their `pkexec dnf ...` form; when the helper is installed,
+// the ones it covers are routed through it at execution time.
+pub const HELPER_PATH: &str = "/usr/libexec/nebula-dnf/nebula-dnf-helper";
+const HELPER_ACTION: &str = "com.nebula-dnf.manage";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum HelperAction {
+    Install,
+    Remove,
+    Update, // everything when `packages` is empty
+    Autoremove,
+}
+
+// One step of a helper request. The request is a JSON array of these, run in order until one fails.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct HelperOperation {
+    pub action: HelperAction,
+    #[serde(default)]
+    pub packages: Vec<String>, // exact package names
+    #[serde(default)]
+    pub exclude: Vec<String>, // Update only: pinned packages
+    #[serde(default)]
+    pub security_only: bool, // Update only
+}
+
+impl HelperOperation {
+    // The operation a `dnf ...` argv from plan.rs performs, or None if it uses anything beyond
+    // the protocol (advanced options, other subcommands or flags).
+    fn from_dnf_args(args: &[String]) -> Option<Self> {
+        let (tool, rest) = args.split_first()?;
+        let (command, rest) = rest.split_first()?;
+        if tool != "dnf" {
+            return None;
+        }
+        let action = match command.as_str() {
+            "install" => HelperAction::Install,
+            "remove" | "erase" => HelperAction::Remove,
+            "update" | "upgrade" => HelperAction::Update,
+            "autoremove" => HelperAction::Autoremove,
+            _ => return None,
+        };
+        let mut operation = HelperOperation { action, packages: Vec::new(), exclude: Vec::new(), security_only: false };
+        let mut confirmed = false;
+        for arg in rest {
+            match arg.as_str() {
+                "--assumeyes" | "-y" => confirmed = true,
+                "--security" if action == HelperAction::Update => operation.security_only = true,
+                _ if action == HelperAction::Update && arg.starts_with("--exclude=") => operation.exclude.push(arg["--exclude=".len()..].to_string()),
+                _ if arg.starts_with('-') => return None,
+                _ => operation.packages.push(arg.clone()),
+            }
+        }
+        (confirmed && operation.validate().is_ok()).then_some(operation)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for name in self.packages.iter().chain(&self.exclude) {
+            validate_package_name(name)?;
+        }
+        match self.action {
+            HelperAction::Install | HelperAction::Remove if self.packages.is_empty() => Err("No packages given.".to_string()),
+            HelperAction::Update => Ok(()),
+            _ if !self.exclude.is_empty() || self.security_only => Err(format!("{:?} takes no update options.", self.action)),
+            HelperAction::Autoremove if !self.packages.is_empty() => Err("Autoremove takes no packages.".to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    fn dnf_args(&self) -> Vec<String> {
+        let command = match self.action {
+            HelperAction::Install => "install",
+            HelperAction::Remove => "remove",
+            HelperAction::Update => "upgrade",
+            HelperAction::Autoremove => "autoremove",
+        };
+        let mut args = vec![command.to_string(), "--assumeyes".to_string()];
+        if self.security_only {
+            args.push("--security".to_string());
+        }
+        args.extend(self.exclude.iter().map(|package| format!("--exclude={}", package)));
+        args.extend(self.packages.iter().cloned());
+        args
+    }
+}
+
+pub fn installed() -> bool {
+    Path::new(HELPER_PATH).exists()
+}
+
+// Whether polkit lets us run the helper without an authentication prompt, i.e. a rule authorizes
+// the action for this user. pkcheck without --allow-user-interaction never prompts.
+pub async fn authorized_without_prompt(app: &tauri::AppHandle) -> bool {
+    let pid = std::process::id().to_string();
+    matches!(crate::process::run_command(app, "pkcheck", &["--action-id", HELPER_ACTION, "--process", &pid]).await, Ok(out) if out.success)
+}
+
+// The helper call doing all of `plans`, if each is a root dnf run the protocol covers.
+pub fn helper_plan(plans: &[CommandPlan]) -> Option<CommandPlan> {
+    let operations = plans
+        .iter()
+        .map(|plan| if plan.program == "pkexec" { HelperOperation::from_dnf_args(&plan.args) } else { None })
+        .collect::<Option<Vec<_>>>()?;
+    if operations.is_empty() {
+        return None;
+    }
+    let request = serde_json::to_string(&operations).ok()?;
+    Some(CommandPlan::privileged(HELPER_PATH, vec![request]))
+}
+
+// `plan` through the helper when it is installed and covers it; `plan` itself otherwise.
+pub fn route(plan: CommandPlan) -> CommandPlan {
+    if !installed() {
+        return plan;
+    }
+    helper_plan(std::slice::from_ref(&plan)).unwrap_or(plan)
+}
+
+fn parse_request(args: &[String]) -> Result<Vec<HelperOperation>, String> {
+    let [request] = args else {
+        return Err("usage: nebula-dnf-helper '[{\"action\": \"Install\", \"packages\": [\"htop\"]}]'".to_string());
+    };
+    let operations: Vec<HelperOperation> = serde_json::from_str(request).map_err(|e| format!("Invalid request: {}", e))?;
+    if operations.is_empty() {
+        return Err("Invalid request: no operations".to_string());
+    }
+    for operation in &operations {
+        operation.validate()?;
+    }
+    Ok(operations)
+}
+
+// The helper's main, run as root: validates the whole request before running anything, then
+// runs dnf once per operation, printing BATCH_STEP_MARKER between them like batched plans do.
+// Returns the exit code: the failed step's, or 2 for a rejected request.
+pub fn run(args: &[String]) -> i32 {
+    let operations = match parse_request(args) {
+        Ok(operations) => operations,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+    for (index, operation) in operations.iter().enumerate() {
+        if index > 0 {
+            println!("{}", BATCH_STEP_MARKER);
+            eprintln!("{}", BATCH_STEP_MARKER);
+        }
+        let status = match Command::new("dnf").args(operation.dnf_args()).env_clear().env("PATH", "/usr/sbin:/usr/bin").status() {
+            Ok(status) => status,
+            Err(e) => {
+                eprintln!("Failed to execute dnf: {}", e);
+                return 1;
+            }
+        };
+        if !status.success() {
+            return status.code().unwrap_or(1);
+        }
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan::strings;
+
+    #[test]
+    fn test_helper_protocol() {
+        let plans = [
+            CommandPlan::privileged("dnf", strings(&["remove", "htop", "--assumeyes"])),
+            crate::plan::autoremove(),
+        ];
+        let helper = helper_plan(&plans).unwrap();
+        assert_eq!(helper.args[0], HELPER_PATH);
+        let operations = parse_request(&helper.args[1..]).unwrap();
+        assert_eq!(operations[0].dnf_args(), ["remove", "--assumeyes", "htop"]);
+        assert_eq!(operations[1].action, HelperAction::Autoremove);
+
+        let upgrade = crate::plan::upgrade_all(true, &strings(&["mesa-dri-drivers"]));
+        assert_eq!(helper_plan(std::slice::from_ref(&upgrade)).map(|plan| parse_request(&plan.args[1..]).unwrap()[0].dnf_args()), Some(upgrade.args[1..].to_vec()));
+
+        // Anything beyond the protocol keeps its pkexec form.
+        assert!(helper_plan(&[CommandPlan::privileged("rpm", strings(&["-e", "--nodeps", "htop"]))]).is_none());
+        assert!(helper_plan(&[CommandPlan::privileged("dnf", strings(&["install", "--assumeyes", "--nogpgcheck", "htop"]))]).is_none());
+        assert!(helper_plan(&[CommandPlan::new("dnf", strings(&["install", "--assumeno", "htop"]))]).is_none());
+
+        assert!(parse_request(&strings(&[r#"[{"action":"Install","packages":["--allowerasing"]}]"#])).is_err());
+        assert!(parse_request(&strings(&[r#"[{"action":"Remove","packages":[]}]"#])).is_err());
+        assert!(parse_request(&strings(&[r#"[{"action":"Install","packages":["htop"],"args":["-x"]}]"#])).is_err());
+        assert!(parse_request(&strings(&[r#"[{"action":"Autoremove","exclude":["bash"]}]"#])).is_err());
+    }
+}
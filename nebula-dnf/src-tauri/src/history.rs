@@ -0,0 +1,323 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::error::NebulaError;
+use crate::plan::CommandPlan;
+use crate::process::{run_command, run_plan, CommandCapture};
+
+// dnf5 prints the table without separators, so rows are split around the date column.
+static HISTORY_ROW_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\s*(\d+)\s+(.*?)\s+(\d{4}-\d{2}-\d{2} \d{2}:\d{2}(?::\d{2})?)\s+(.*?)\s*(\d+)\s*\S*\s*$").unwrap()
+});
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct HistoryTransaction {
+    id: u32,
+    command: String,
+    date: String,         // as printed by dnf, local time
+    actions: Vec<String>, // e.g. ["Install"] or ["I", "U"] for mixed transactions
+    altered: u32,
+}
+
+fn parse_actions(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|a| !a.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// Leading count only; dnf4 appends flags like "EE" or "<" for transactions with problems.
+fn parse_altered(value: &str) -> Option<u32> {
+    let digits: String = value.trim().chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
+
+fn parse_history_row(line: &str) -> Option<HistoryTransaction> {
+    let columns: Vec<&str> = line.split('|').collect();
+    if columns.len() == 5 {
+        return Some(HistoryTransaction {
+            id: columns[0].trim().parse().ok()?,
+            command: columns[1].trim().to_string(),
+            date: columns[2].trim().to_string(),
+            actions: parse_actions(columns[3]),
+            altered: parse_altered(columns[4])?,
+        });
+    }
+    let caps = HISTORY_ROW_RE.captures(line)?;
+    Some(HistoryTransaction {
+        id: caps[1].parse().ok()?,
+        command: caps[2].trim().to_string(),
+        date: caps[3].to_string(),
+        actions: parse_actions(&caps[4]),
+        altered: caps[5].parse().ok()?,
+    })
+}
+
+// Accepts both the dnf4 `|`-separated table and the dnf5 whitespace-aligned one. Also returns
+// the lines that are neither a row nor the header or separator.
+fn parse_history_output(output: &str) -> (Vec<HistoryTransaction>, Vec<String>) {
+    let mut transactions = Vec::new();
+    let mut unparsed = Vec::new();
+    for line in output.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with("ID ") || trimmed.chars().all(|c| c == '-') {
+            continue;
+        }
+        match parse_history_row(line) {
+            Some(transaction) => transactions.push(transaction),
+            None => unparsed.push(line.to_string()),
+        }
+    }
+    (transactions, unparsed)
+}
+
+// `incomplete` is set when some lines of the list could not be parsed; those are logged.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct HistoryList {
+    transactions: Vec<HistoryTransaction>,
+    incomplete: bool,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ChangedPackage {
+    name: String,
+    arch: String,
+    version: String, // [epoch:]version-release
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct VersionChange {
+    name: String,
+    arch: String,
+    from_version: Option<String>, // None if dnf didn't list the replaced package
+    to_version: String,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct TransactionInfo {
+    id: u32,
+    begin_time: Option<String>,
+    end_time: Option<String>,
+    user: Option<String>,
+    return_code: Option<String>,
+    command_line: Option<String>,
+    installed: Vec<ChangedPackage>,
+    removed: Vec<ChangedPackage>,
+    upgraded: Vec<VersionChange>,
+    downgraded: Vec<VersionChange>,
+    incomplete: bool, // some package lines could not be parsed
+}
+
+// "bash-5.2.26-3.fc40.x86_64" -> name, arch, version-release.
+fn parse_nevra(nevra: &str) -> Option<ChangedPackage> {
+    let (rest, arch) = nevra.rsplit_once('.')?;
+    let (rest, release) = rest.rsplit_once('-')?;
+    let (name, version) = rest.rsplit_once('-')?;
+    if name.is_empty() || version.is_empty() || arch.is_empty() {
+        return None;
+    }
+    Some(ChangedPackage {
+        name: name.to_string(),
+        arch: arch.to_string(),
+        version: format!("{}-{}", version, release),
+    })
+}
+
+// Handles both dnf4 ("Upgraded") and dnf5 ("Replaced") spellings for the package an update replaced.
+// Also returns the package lines without a recognizable NEVRA.
+fn parse_transaction_info(id: u32, output: &str) -> (TransactionInfo, Vec<String>) {
+    let mut info = TransactionInfo { id, ..Default::default() };
+    let mut unparsed = Vec::new();
+    let mut replaced: HashMap<(String, String), String> = HashMap::new();
+    let mut in_packages = false;
+
+    for line in output.lines() {
+        let indented = line.starts_with(' ') || line.starts_with('\t');
+        if !in_packages || !indented {
+            if in_packages && !line.trim().is_empty() {
+                in_packages = false; // e.g. "Scriptlet output:" follows the package list
+            }
+            let Some((key, value)) = line.split_once(':') else { continue };
+            let value = Some(value.trim().to_string()).filter(|v| !v.is_empty());
+            match key.trim().to_lowercase().as_str() {
+                "begin time" => info.begin_time = value,
+                "end time" => info.end_time = value,
+                "user" => info.user = value,
+                "return-code" | "status" => info.return_code = value,
+                "command line" => info.command_line = value,
+                "packages altered" => in_packages = true,
+                _ => {}
+            }
+            continue;
+        }
+
+        // The action can be two words, e.g. "Reason Change"
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((position, package)) = words.iter().enumerate().find_map(|(i, word)| parse_nevra(word).map(|package| (i, package))) else {
+            unparsed.push(line.to_string());
+            continue;
+        };
+        let action = words[..position].join(" ");
+        match action.as_str() {
+            "Install" | "Dep-Install" => info.installed.push(package),
+            "Removed" | "Remove" | "Erase" | "Obsoleted" => info.removed.push(package),
+            "Upgraded" | "Downgraded" | "Replaced" => {
+                replaced.insert((package.name, package.arch), package.version);
+            }
+            "Upgrade" | "Downgrade" => {
+                let change = VersionChange {
+                    name: package.name,
+                    arch: package.arch,
+                    from_version: None,
+                    to_version: package.version,
+                };
+                if action == "Upgrade" { info.upgraded.push(change) } else { info.downgraded.push(change) }
+            }
+            _ => {} // Reinstall, Reason Change, ...
+        }
+    }
+
+    for change in info.upgraded.iter_mut().chain(info.downgraded.iter_mut()) {
+        change.from_version = replaced.remove(&(change.name.clone(), change.arch.clone()));
+    }
+    (info, unparsed)
+}
+
+const DNF_LOG_PATHS: &[&str] = &["/var/log/dnf5.log", "/var/log/dnf.log"];
+const DEFAULT_LOG_LINES: u32 = 200;
+
+// Some setups keep the history database or logs readable by root only.
+fn needs_elevation(stderr: &str) -> bool {
+    let stderr = stderr.to_lowercase();
+    ["permission denied", "unable to open database", "cannot be accessed"].iter().any(|s| stderr.contains(s))
+}
+
+// Runs the query unprivileged first and only asks for the read-only polkit action if that was refused.
+async fn run_history_query(app: &tauri::AppHandle, dnf_args: &[&str], query_args: &[&str]) -> Result<CommandCapture, String> {
+    let out = run_command(app, "dnf", dnf_args).await?;
+    if out.success || !needs_elevation(&out.stderr) {
+        return Ok(out);
+    }
+    Ok(run_plan(app, &CommandPlan::query(query_args)).await?)
+}
+
+// Newest transactions first, as dnf lists them.
+#[tauri::command]
+pub async fn list_dnf_history(app: tauri::AppHandle) -> Result<HistoryList, NebulaError> {
+    crate::probes::ensure_supported(&app, crate::probes::ProbedFeature::History)?;
+    let out = run_history_query(&app, &["history", "list"], &["history-list"]).await?;
+    if !out.success {
+        return Err(NebulaError::command_failed(out.code, &out.stderr, format!("dnf history list failed: {}", out.error_text().trim())));
+    }
+    let (transactions, unparsed) = parse_history_output(&out.stdout);
+    let incomplete = crate::parse_failures::record(&app, "dnf history list", &unparsed).await;
+    Ok(HistoryList { transactions, incomplete })
+}
+
+#[tauri::command]
+pub async fn get_transaction_info(app: tauri::AppHandle, id: u32) -> Result<TransactionInfo, NebulaError> {
+    let id_arg = id.to_string();
+    let out = run_history_query(&app, &["history", "info", &id_arg], &["history-info", &id_arg]).await?;
+    if !out.success {
+        return Err(NebulaError::command_failed(out.code, &out.stderr, format!("dnf history info {} failed: {}", id, out.error_text().trim())));
+    }
+    let (mut info, unparsed) = parse_transaction_info(id, &out.stdout);
+    info.incomplete = crate::parse_failures::record(&app, "dnf history info", &unparsed).await;
+    Ok(info)
+}
+
+// The id of the newest dnf transaction, to link an operation the app just ran to dnf history.
+pub async fn latest_transaction_id(app: &tauri::AppHandle) -> Result<Option<u32>, String> {
+    let out = run_history_query(app, &["history", "list"], &["history-list"]).await?;
+    if !out.success {
+        return Err(format!("dnf history list failed: {}", out.error_text().trim()));
+    }
+    Ok(parse_history_output(&out.stdout).0.iter().map(|transaction| transaction.id).max())
+}
+
+// The last `lines` lines (default 200) of the dnf log; read through the query helper when it is
+// root-only.
+#[tauri::command]
+pub async fn get_dnf_log(app: tauri::AppHandle, lines: Option<u32>) -> Result<String, NebulaError> {
+    let lines = lines.unwrap_or(DEFAULT_LOG_LINES);
+    let Some(path) = DNF_LOG_PATHS.iter().find(|path| std::path::Path::new(path).exists()) else {
+        return Err("No dnf log found.".into());
+    };
+    match std::fs::read_to_string(path) {
+        Ok(contents) => {
+            let all: Vec<&str> = contents.lines().collect();
+            Ok(all[all.len().saturating_sub(lines as usize)..].join("\n"))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+            let out = run_plan(&app, &CommandPlan::query(&["dnf-log", &lines.to_string()])).await?;
+            if !out.success {
+                return Err(NebulaError::command_failed(out.code, &out.stderr, format!("Failed to read {}: {}", path, out.error_text().trim())));
+            }
+            Ok(out.stdout.trim_end().to_string())
+        }
+        Err(e) => Err(format!("Failed to read {}: {}", path, e).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_history_output() {
+        let dnf4 = "ID     | Command line             | Date and time    | Action(s)      | Altered\n\
+                    -------------------------------------------------------------------------------\n\
+                    \x20   12 | install htop             | 2024-05-01 10:22 | Install        |    1   \n\
+                    \x20   11 | upgrade                  | 2024-04-30 09:00 | I, U           |  134 EE\n";
+        let (parsed, unparsed) = parse_history_output(dnf4);
+        assert_eq!(parsed.len(), 2);
+        assert!(unparsed.is_empty());
+        assert_eq!(parsed[0].command, "install htop");
+        assert_eq!(parsed[1].actions, vec!["I".to_string(), "U".to_string()]);
+        assert_eq!(parsed[1].altered, 134);
+
+        let dnf5 = "ID Command line       Date and time       Action(s) Altered\n\
+                    \x203 dnf5 install htop 2024-05-01 10:22:31                 1\n";
+        let (parsed, unparsed) = parse_history_output(dnf5);
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].id, 3);
+        assert_eq!(parsed[0].command, "dnf5 install htop");
+        assert!(parsed[0].actions.is_empty());
+        assert!(unparsed.is_empty());
+
+        let (parsed, unparsed) = parse_history_output("\x203 dnf5 install htop 01/05/2024 10:22                 1\n");
+        assert!(parsed.is_empty());
+        assert_eq!(unparsed.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_transaction_info() {
+        let output = "Transaction ID : 12\n\
+                      Begin time     : Wed 01 May 2024 10:22:31 AM CEST\n\
+                      User           : Jane <jane>\n\
+                      Return-Code    : Success\n\
+                      Command Line   : upgrade\n\
+                      Packages Altered:\n\
+                      \x20   Install  kernel-core-6.8.8-300.fc40.x86_64 @updates\n\
+                      \x20   Upgrade  bash-5.2.26-3.fc40.x86_64         @updates\n\
+                      \x20   Upgraded bash-5.2.26-1.fc40.x86_64         @@System\n\
+                      \x20   Removed  kernel-core-6.7.5-200.fc39.x86_64 @@System\n\
+                      \x20   Reason Change  htop-3.3.0-3.fc40.x86_64   @@System\n\
+                      \x20   Install  (package name unavailable)\n\
+                      Scriptlet output:\n\
+                      \x20  1 warning: foo-1.0-1.noarch\n";
+        let (info, unparsed) = parse_transaction_info(12, output);
+        assert_eq!(info.command_line.as_deref(), Some("upgrade"));
+        assert_eq!(info.installed[0].version, "6.8.8-300.fc40");
+        assert_eq!(info.removed[0].name, "kernel-core");
+        assert_eq!(info.upgraded.len(), 1);
+        assert_eq!(info.upgraded[0].from_version.as_deref(), Some("5.2.26-1.fc40"));
+        assert_eq!(info.upgraded[0].to_version, "5.2.26-3.fc40");
+        assert_eq!(info.installed.len(), 1);
+        assert_eq!(unparsed, vec!["    Install  (package name unavailable)"]);
+    }
+}
@@ -0,0 +1,66 @@
+use tauri::Manager;
+
+use crate::error::NebulaError;
+use crate::options::{apply_options, AdvancedOptions};
+use crate::packagekit::PackageAction;
+use crate::plan;
+use crate::process::{dry_run_succeeded, run_plan_streaming};
+use crate::services::{BackendRegistry, OperationManager};
+use crate::settings::PackageBackend;
+use crate::PackageOperationResult;
+
+// Installs packages picked from suggestions elsewhere in the app (codecs, drivers, themes, ...).
+// With `dry_run` the transaction is only resolved so it can be reviewed first; with
+// `download_only` the packages are only fetched, to be installed later, and with `offline` the
+// install is staged for the next boot.
+#[tauri::command]
+pub async fn install_packages(
+    app: tauri::AppHandle,
+    packages: Vec<String>,
+    dry_run: bool,
+    options: Option<AdvancedOptions>,
+    download_only: Option<bool>,
+    offline: Option<bool>,
+) -> Result<PackageOperationResult, NebulaError> {
+    let download_only = download_only.unwrap_or(false) && !dry_run;
+    let offline = offline.unwrap_or(false) && !dry_run;
+    let backends = app.state::<BackendRegistry>();
+    if backends.selected()? != PackageBackend::Dnf && options.is_some() {
+        return Err("Advanced options need the dnf backend.".into());
+    }
+    if backends.selected()? != PackageBackend::Dnf && (download_only || offline) {
+        return Err("Download-only mode and offline installs need the dnf backend.".into());
+    }
+    if download_only && offline {
+        return Err("A download-only install cannot also be staged for the next boot.".into());
+    }
+    if let Some(result) = backends.package_action(PackageAction::Install, &packages, dry_run).await? {
+        return Ok(result);
+    }
+    let mut plan = plan::install_packages(&packages, dry_run)?;
+    if download_only {
+        plan = plan::download_only(&plan)?;
+    }
+    if offline {
+        plan = plan::offline(&plan, crate::probes::is_dnf5(&app).await)?;
+    }
+    let plan = apply_options(&plan, options.as_ref())?;
+    let verb = if download_only { "Downloading" } else { "Installing" };
+    let _busy = (!dry_run).then(|| app.state::<OperationManager>().begin(format!("{} {}", verb, packages.join(", "))));
+    let out = run_plan_streaming(&app, &plan, "install").await?;
+    let success = if dry_run { dry_run_succeeded(out.code, &out.stdout, &out.stderr) } else { out.success };
+    Ok(PackageOperationResult {
+        success,
+        message: match (success, dry_run) {
+            (true, true) => format!("Dry run finished for {}. Review the transaction before installing.", packages.join(", ")),
+            (true, false) if download_only => format!("Downloaded {}. Install them later to apply the transaction from the cache.", packages.join(", ")),
+            (true, false) if offline => format!("The install of {} is staged and will be applied on the next reboot.", packages.join(", ")),
+            (true, false) => format!("Installed {}.", packages.join(", ")),
+            (false, _) => format!("Failed to {} {}. Exit code: {}.", if download_only { "download" } else { "install" }, packages.join(", "), out.code),
+        },
+        details: Some(format!("STDOUT:\n{}\nSTDERR:\n{}", out.stdout, out.stderr)),
+        confirmation_required: false,
+        simulated: out.simulated,
+        reboot_required: success && offline && !out.simulated,
+    })
+}
@@ -0,0 +1,194 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use tauri::Manager;
+
+use crate::error::NebulaError;
+use crate::options::AdvancedOptions;
+use crate::plan::{self, CommandPlan};
+use crate::process::{dry_run_succeeded, run_command, run_plan};
+use crate::services::OperationManager;
+use crate::PackageOperationResult;
+
+// Where an rpm database may live inside a root, newest layout first.
+const RPMDB_DIRS: &[&str] = &["usr/lib/sysimage/rpm", "var/lib/rpm"];
+const ROOT_PACKAGES_FORMAT: &str = "%{NAME}\t%{EPOCHNUM}:%{VERSION}-%{RELEASE}\t%{ARCH}\n";
+
+// The root an operation targets, shown with every result so it is never mistaken for the
+// running system.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct InstallRoot {
+    path: String,
+    os_name: Option<String>, // PRETTY_NAME from the root's os-release
+    has_rpmdb: bool,         // false for an empty root that is being bootstrapped
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct RootPackage {
+    name: String,
+    version: String,
+    arch: String,
+}
+
+fn os_release_name(contents: &str) -> Option<String> {
+    contents
+        .lines()
+        .find_map(|line| line.strip_prefix("PRETTY_NAME="))
+        .map(|value| value.trim().trim_matches('"').to_string())
+        .filter(|name| !name.is_empty())
+}
+
+// Resolves symlinks so the label shows the directory actually modified. The running system's
+// own root is refused: the regular operations are for that.
+fn resolve_root(path: &str) -> Result<InstallRoot, String> {
+    if !path.starts_with('/') {
+        return Err(format!("The install root must be an absolute path, not '{}'.", path));
+    }
+    let resolved = std::fs::canonicalize(path).map_err(|e| format!("Cannot use {} as install root: {}", path, e))?;
+    if resolved == Path::new("/") {
+        return Err("The install root is the running system. Use the regular package operations instead.".to_string());
+    }
+    if !resolved.is_dir() {
+        return Err(format!("{} is not a directory.", resolved.display()));
+    }
+    let os_name = ["etc/os-release", "usr/lib/os-release"]
+        .iter()
+        .find_map(|file| std::fs::read_to_string(resolved.join(file)).ok())
+        .and_then(|contents| os_release_name(&contents));
+    Ok(InstallRoot {
+        path: resolved.to_string_lossy().into_owned(),
+        os_name,
+        has_rpmdb: RPMDB_DIRS.iter().any(|dir| resolved.join(dir).is_dir()),
+    })
+}
+
+fn label(root: &InstallRoot) -> String {
+    match &root.os_name {
+        Some(name) => format!("{} ({})", root.path, name),
+        None => root.path.clone(),
+    }
+}
+
+// `plan` run against the root through --setopt=installroot, validated like any other advanced option.
+fn root_plan(plan: &CommandPlan, root: &InstallRoot, releasever: Option<&str>) -> Result<CommandPlan, String> {
+    let mut setopt = BTreeMap::from([("installroot".to_string(), root.path.clone())]);
+    if let Some(releasever) = releasever {
+        setopt.insert("releasever".to_string(), releasever.to_string());
+    }
+    AdvancedOptions { setopt, ..Default::default() }.apply(plan)
+}
+
+#[tauri::command]
+pub async fn describe_install_root(path: String) -> Result<InstallRoot, NebulaError> {
+    Ok(resolve_root(&path)?)
+}
+
+#[tauri::command]
+pub async fn list_root_packages(app: tauri::AppHandle, path: String) -> Result<Vec<RootPackage>, NebulaError> {
+    let root = resolve_root(&path)?;
+    if !root.has_rpmdb {
+        return Ok(Vec::new());
+    }
+    let out = run_command(&app, "rpm", &["--root", &root.path, "-qa", "--queryformat", ROOT_PACKAGES_FORMAT]).await?;
+    if !out.success {
+        return Err(NebulaError::command_failed(out.code, &out.stderr, format!("Failed to list packages in {}: {}", label(&root), out.error_text().trim())));
+    }
+    let mut packages: Vec<RootPackage> = out
+        .stdout
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t').map(str::trim);
+            Some(RootPackage {
+                name: fields.next().filter(|name| !name.is_empty())?.to_string(),
+                version: fields.next()?.trim_start_matches("0:").to_string(),
+                arch: fields.next()?.to_string(),
+            })
+        })
+        .collect();
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(packages)
+}
+
+// Changing another root only goes ahead once the frontend passes the root's path back as
+// `confirm_root`, after showing the user which system it is.
+async fn run_in_root(
+    app: &tauri::AppHandle,
+    root: InstallRoot,
+    plan: CommandPlan,
+    action: &str,
+    packages: &[String],
+    dry_run: bool,
+    confirm_root: Option<String>,
+) -> Result<PackageOperationResult, String> {
+    let target = label(&root);
+    if !dry_run && confirm_root.as_deref() != Some(root.path.as_str()) {
+        return Ok(crate::settings::preview_result(
+            format!("This will {} {} in {}, not on the running system. Confirm the install root to continue.", action, packages.join(", "), target),
+            plan.command_line().to_string(),
+        ));
+    }
+    let _busy = (!dry_run).then(|| app.state::<OperationManager>().begin(format!("{} {} in {}", action, packages.join(", "), root.path)));
+    let out = run_plan(app, &plan).await?;
+    let success = if dry_run { dry_run_succeeded(out.code, &out.stdout, &out.stderr) } else { out.success };
+    Ok(PackageOperationResult {
+        success,
+        message: match (success, dry_run) {
+            (true, true) => format!("[{}] Dry run finished: {} {}.", target, action, packages.join(", ")),
+            (true, false) => format!("[{}] Finished: {} {}.", target, action, packages.join(", ")),
+            (false, _) => format!("[{}] Failed to {} {}. Exit code: {}.", target, action, packages.join(", "), out.code),
+        },
+        details: Some(format!("STDOUT:\n{}\nSTDERR:\n{}", out.stdout, out.stderr)),
+        confirmation_required: false,
+        simulated: out.simulated,
+        reboot_required: false,
+    })
+}
+
+// `releasever` is needed when bootstrapping an empty root; otherwise dnf reads it from the root.
+#[tauri::command]
+pub async fn install_into_root(
+    app: tauri::AppHandle,
+    path: String,
+    packages: Vec<String>,
+    releasever: Option<String>,
+    dry_run: bool,
+    confirm_root: Option<String>,
+) -> Result<PackageOperationResult, NebulaError> {
+    let root = resolve_root(&path)?;
+    let plan = root_plan(&plan::install_packages(&packages, dry_run)?, &root, releasever.as_deref())?;
+    Ok(run_in_root(&app, root, plan, "install", &packages, dry_run, confirm_root).await?)
+}
+
+#[tauri::command]
+pub async fn remove_from_root(
+    app: tauri::AppHandle,
+    path: String,
+    packages: Vec<String>,
+    dry_run: bool,
+    confirm_root: Option<String>,
+) -> Result<PackageOperationResult, NebulaError> {
+    let root = resolve_root(&path)?;
+    if !root.has_rpmdb {
+        return Err(format!("{} has no rpm database, so there is nothing to remove.", root.path).into());
+    }
+    let plan = root_plan(&plan::remove_packages(&packages, dry_run)?, &root, None)?;
+    Ok(run_in_root(&app, root, plan, "remove", &packages, dry_run, confirm_root).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_root_plan() {
+        assert!(resolve_root("/").is_err());
+        assert!(resolve_root("relative/root").is_err());
+        assert_eq!(os_release_name("NAME=Fedora\nPRETTY_NAME=\"Fedora Linux 40 (Workstation Edition)\"\n").as_deref(), Some("Fedora Linux 40 (Workstation Edition)"));
+
+        let root = InstallRoot { path: "/mnt/sysimage".to_string(), os_name: None, has_rpmdb: true };
+        let install = plan::install_packages(&plan::strings(&["htop"]), false).unwrap();
+        let plan = root_plan(&install, &root, Some("40")).unwrap();
+        assert_eq!(plan.command_line(), "pkexec dnf install --assumeyes htop --setopt=installroot=/mnt/sysimage --setopt=releasever=40");
+        assert!(root_plan(&install, &root, Some("40; reboot")).is_err());
+    }
+}
@@ -0,0 +1,318 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::path::Path;
+
+use crate::error::NebulaError;
+use crate::plan::{strings, CommandPlan};
+use crate::process::{run_command, run_plan};
+use crate::PackageOperationResult;
+
+const BLS_ENTRIES_DIR: &str = "/boot/loader/entries";
+
+// uname -r style release, e.g. "6.8.8-300.fc40.x86_64" or "6.6.30-200.lts.fc40.x86_64".
+static KERNEL_RELEASE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[a-zA-Z0-9_][a-zA-Z0-9._+~-]*$").unwrap());
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct InstalledKernel {
+    variant: String, // "kernel", "kernel-longterm", ... (package name without "-core")
+    package: String,
+    release: String,
+    running: bool,
+    default: bool,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Default)]
+pub struct BootEntry {
+    title: String,
+    kernel: String, // image path, e.g. /boot/vmlinuz-6.8.8-300.fc40.x86_64
+    release: Option<String>,
+    initrd: Option<String>,
+    kernel_present: bool, // the image actually exists on disk
+    is_default: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BootMenuState {
+    default_release: Option<String>,
+    entries: Vec<BootEntry>,
+    warnings: Vec<String>, // e.g. the default entry points at a kernel that is gone
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct KernelOverview {
+    running_release: String,
+    default_release: Option<String>,
+    kernels: Vec<InstalledKernel>,
+    variants: Vec<String>,
+    boot_menu: BootMenuState,
+}
+
+// Every kernel build provides kernel-uname-r, including COPR streams built from the Fedora spec,
+// which makes it a reliable way to find them regardless of the package name.
+const KERNEL_QUERY_FORMAT: &str = "%{NAME}\t%{VERSION}-%{RELEASE}.%{ARCH}\n";
+
+fn variant_of(package: &str) -> String {
+    package.strip_suffix("-core").unwrap_or(package).to_string()
+}
+
+fn parse_installed_kernels(output: &str, running_release: &str, default_release: Option<&str>) -> Vec<InstalledKernel> {
+    let mut kernels: Vec<InstalledKernel> = output
+        .lines()
+        .filter_map(|line| {
+            let (package, release) = line.split_once('\t')?;
+            let release = release.trim();
+            Some(InstalledKernel {
+                variant: variant_of(package.trim()),
+                package: package.trim().to_string(),
+                release: release.to_string(),
+                running: release == running_release,
+                default: Some(release) == default_release,
+            })
+        })
+        .collect();
+    kernels.sort_by(|a, b| a.variant.cmp(&b.variant).then_with(|| a.release.cmp(&b.release)));
+    kernels
+}
+
+// grubby prints the default kernel image path, e.g. "/boot/vmlinuz-6.8.8-300.fc40.x86_64".
+fn release_from_image_path(path: &str) -> Option<String> {
+    path.trim().rsplit('/').next()?.strip_prefix("vmlinuz-").map(str::to_string)
+}
+
+async fn default_kernel_release(app: &tauri::AppHandle) -> Option<String> {
+    match run_command(app, "grubby", &["--default-kernel"]).await {
+        Ok(out) if out.success => release_from_image_path(&out.stdout),
+        _ => None,
+    }
+}
+
+// `grubby --info=ALL` prints key="value" lines, starting a new entry at each index=N.
+fn parse_grubby_info(output: &str) -> Vec<BootEntry> {
+    let mut entries: Vec<BootEntry> = Vec::new();
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once('=') else { continue };
+        let value = value.trim().trim_matches('"');
+        if key == "index" {
+            entries.push(BootEntry::default());
+            continue;
+        }
+        let Some(entry) = entries.last_mut() else { continue };
+        match key {
+            "title" => entry.title = value.to_string(),
+            "kernel" => entry.kernel = value.to_string(),
+            "initrd" => entry.initrd = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    entries.retain(|entry| !entry.kernel.is_empty());
+    entries
+}
+
+// Boot Loader Spec entry: "title ...", "linux /vmlinuz-...", "initrd /initramfs-...". Paths are
+// relative to the boot partition, which is mounted at /boot.
+fn parse_bls_entry(contents: &str) -> Option<BootEntry> {
+    let mut entry = BootEntry::default();
+    for line in contents.lines() {
+        let Some((key, value)) = line.trim().split_once(char::is_whitespace) else { continue };
+        let value = value.trim();
+        match key {
+            "title" => entry.title = value.to_string(),
+            "linux" => entry.kernel = format!("/boot{}", value),
+            "initrd" => entry.initrd = Some(format!("/boot{}", value)),
+            _ => {}
+        }
+    }
+    (!entry.kernel.is_empty()).then_some(entry)
+}
+
+async fn load_boot_entries(app: &tauri::AppHandle) -> Result<Vec<BootEntry>, String> {
+    if let Ok(out) = run_command(app, "grubby", &["--info=ALL"]).await {
+        let entries = parse_grubby_info(&out.stdout);
+        if out.success && !entries.is_empty() {
+            return Ok(entries);
+        }
+    }
+    let dir = std::fs::read_dir(BLS_ENTRIES_DIR).map_err(|e| format!("Failed to read {}: {}", BLS_ENTRIES_DIR, e))?;
+    let mut entries: Vec<BootEntry> = dir
+        .filter_map(|item| item.ok())
+        .filter(|item| item.path().extension().is_some_and(|ext| ext == "conf"))
+        .filter_map(|item| std::fs::read_to_string(item.path()).ok())
+        .filter_map(|contents| parse_bls_entry(&contents))
+        .collect();
+    entries.sort_by(|a, b| b.kernel.cmp(&a.kernel));
+    Ok(entries)
+}
+
+// What the boot menu will offer on the next reboot, with a warning for anything that would not boot.
+pub async fn load_boot_menu(app: &tauri::AppHandle) -> BootMenuState {
+    let default_release = default_kernel_release(app).await;
+    let mut warnings = Vec::new();
+    let mut entries = load_boot_entries(app).await.unwrap_or_else(|e| {
+        warnings.push(e);
+        Vec::new()
+    });
+    for entry in &mut entries {
+        entry.release = release_from_image_path(&entry.kernel);
+        entry.kernel_present = Path::new(&entry.kernel).exists();
+        entry.is_default = entry.release.is_some() && entry.release == default_release;
+        if !entry.kernel_present {
+            warnings.push(format!("Boot entry '{}' points to a missing kernel image ({}).", entry.title, entry.kernel));
+        }
+        if let Some(initrd) = entry.initrd.as_deref().filter(|initrd| !Path::new(initrd).exists()) {
+            warnings.push(format!("Boot entry '{}' is missing its initramfs ({}).", entry.title, initrd));
+        }
+    }
+    match &default_release {
+        None => warnings.push("Could not determine the default boot entry.".to_string()),
+        Some(release) if !entries.iter().any(|e| e.is_default && e.kernel_present) => {
+            warnings.push(format!("The default boot entry ({}) has no bootable kernel.", release))
+        }
+        Some(_) => {}
+    }
+    BootMenuState {
+        default_release,
+        entries,
+        warnings,
+    }
+}
+
+pub fn is_kernel_package(name: &str) -> bool {
+    name == "kernel" || (name.starts_with("kernel") && name.ends_with("-core"))
+}
+
+// Appended to the result of operations touching kernel packages so users can see whether
+// the next reboot is safe.
+pub async fn append_boot_menu_report(app: &tauri::AppHandle, result: &mut PackageOperationResult) {
+    let menu = load_boot_menu(app).await;
+    let mut report = format!(
+        "\n\n--- Boot menu ---\nDefault kernel: {}\n",
+        menu.default_release.as_deref().unwrap_or("unknown")
+    );
+    for entry in &menu.entries {
+        report.push_str(&format!("{} {}{}\n", if entry.is_default { "*" } else { " " }, entry.title, if entry.kernel_present { "" } else { " (missing kernel)" }));
+    }
+    for warning in &menu.warnings {
+        report.push_str(&format!("Warning: {}\n", warning));
+    }
+    if !menu.warnings.is_empty() {
+        result.message.push_str("\nCheck the boot menu before rebooting.");
+    }
+    result.details.get_or_insert_with(String::new).push_str(&report);
+}
+
+async fn load_overview(app: &tauri::AppHandle) -> Result<KernelOverview, String> {
+    let uname = run_command(app, "uname", &["-r"]).await?;
+    let running_release = uname.stdout.trim().to_string();
+    let default_release = default_kernel_release(app).await;
+
+    let out = run_command(app, "rpm", &["-q", "--whatprovides", "kernel-uname-r", "--queryformat", KERNEL_QUERY_FORMAT]).await?;
+    if !out.success {
+        return Err(format!("Failed to list installed kernels: {}", out.error_text().trim()));
+    }
+    let kernels = parse_installed_kernels(&out.stdout, &running_release, default_release.as_deref());
+    let mut variants: Vec<String> = kernels.iter().map(|k| k.variant.clone()).collect();
+    variants.dedup(); // kernels are sorted by variant
+    Ok(KernelOverview {
+        running_release,
+        default_release,
+        kernels,
+        variants,
+        boot_menu: load_boot_menu(app).await,
+    })
+}
+
+// Releases `dnf remove --oldinstallonly` could take: all but the newest of each variant, never the
+// running or default one.
+fn removable_releases(kernels: &[InstalledKernel]) -> Vec<String> {
+    let mut releases: Vec<String> = kernels
+        .iter()
+        .enumerate()
+        .filter(|(index, kernel)| kernels.get(index + 1).is_some_and(|next| next.variant == kernel.variant)) // sorted by variant, then release
+        .filter(|(_, kernel)| !kernel.running && !kernel.default)
+        .map(|(_, kernel)| kernel.release.clone())
+        .collect();
+    releases.dedup();
+    releases
+}
+
+pub async fn removable_kernel_releases(app: &tauri::AppHandle) -> Result<Vec<String>, String> {
+    Ok(removable_releases(&load_overview(app).await?.kernels))
+}
+
+pub fn set_default_kernel_plan(release: &str) -> Result<CommandPlan, String> {
+    if !KERNEL_RELEASE_RE.is_match(release) {
+        return Err(format!("Invalid kernel release: '{}'", release));
+    }
+    Ok(CommandPlan::privileged("grubby", strings(&["--set-default", &format!("/boot/vmlinuz-{}", release)])))
+}
+
+// Installed kernel streams (stock, longterm, COPR builds), which one is running and which boots by default.
+#[tauri::command]
+pub async fn get_kernel_overview(app: tauri::AppHandle) -> Result<KernelOverview, NebulaError> {
+    Ok(load_overview(&app).await?)
+}
+
+// Makes an installed kernel the default boot entry, e.g. after installing a different variant.
+#[tauri::command]
+pub async fn set_default_kernel(app: tauri::AppHandle, release: String) -> Result<PackageOperationResult, NebulaError> {
+    let overview = load_overview(&app).await?;
+    if !overview.kernels.iter().any(|k| k.release == release) {
+        return Err(format!("Kernel {} is not installed.", release).into());
+    }
+    let out = run_plan(&app, &set_default_kernel_plan(&release)?).await?;
+    let mut result = PackageOperationResult {
+        success: out.success,
+        message: if out.success {
+            format!("Kernel {} will be used on the next boot.", release)
+        } else {
+            format!("Failed to set the default kernel: {}", out.error_text().trim())
+        },
+        details: Some(format!("STDOUT:\n{}\nSTDERR:\n{}", out.stdout, out.stderr)),
+        confirmation_required: false,
+        simulated: out.simulated,
+        reboot_required: false,
+    };
+    append_boot_menu_report(&app, &mut result).await;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn get_boot_menu(app: tauri::AppHandle) -> Result<BootMenuState, NebulaError> {
+    Ok(load_boot_menu(&app).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_installed_kernels() {
+        let output = "kernel-core\t6.8.8-300.fc40.x86_64\n\
+                      kernel-longterm-core\t6.6.30-200.fc40.x86_64\n\
+                      kernel-core\t6.8.9-300.fc40.x86_64\n";
+        let kernels = parse_installed_kernels(output, "6.8.9-300.fc40.x86_64", Some("6.6.30-200.fc40.x86_64"));
+        assert_eq!(kernels.len(), 3);
+        assert_eq!(kernels[0].variant, "kernel");
+        assert!(kernels[1].running);
+        assert_eq!(kernels[2].variant, "kernel-longterm");
+        assert!(kernels[2].default);
+        assert_eq!(removable_releases(&kernels), ["6.8.8-300.fc40.x86_64"]);
+        assert_eq!(release_from_image_path("/boot/vmlinuz-6.8.8-300.fc40.x86_64\n").as_deref(), Some("6.8.8-300.fc40.x86_64"));
+    }
+
+    #[test]
+    fn test_parse_boot_entries() {
+        let grubby = "index=0\nkernel=\"/boot/vmlinuz-6.8.9-300.fc40.x86_64\"\nargs=\"ro quiet\"\n\
+                      initrd=\"/boot/initramfs-6.8.9-300.fc40.x86_64.img\"\ntitle=\"Fedora Linux (6.8.9-300.fc40.x86_64) 40\"\n\
+                      index=1\nkernel=\"/boot/vmlinuz-0-rescue-abc\"\ntitle=\"Fedora Linux (0-rescue-abc) 40\"\n";
+        let entries = parse_grubby_info(grubby);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].title, "Fedora Linux (6.8.9-300.fc40.x86_64) 40");
+        assert_eq!(entries[0].initrd.as_deref(), Some("/boot/initramfs-6.8.9-300.fc40.x86_64.img"));
+        assert_eq!(entries[1].initrd, None);
+
+        let bls = "title Fedora Linux (6.8.9-300.fc40.x86_64) 40\nversion 6.8.9-300.fc40.x86_64\nlinux /vmlinuz-6.8.9-300.fc40.x86_64\n";
+        assert_eq!(parse_bls_entry(bls).unwrap().kernel, "/boot/vmlinuz-6.8.9-300.fc40.x86_64");
+    }
+}
@@ -0,0 +1,163 @@
+use serde::Serialize;
+
+use crate::error::NebulaError;
+use crate::process::run_command;
+
+// Repositories the installer sets up on its own; a `repo` line for them would be redundant.
+const INSTALLER_REPOS: &[&str] = &["fedora", "updates"];
+// Packages tied to this machine's hardware. They are excluded so a kickstart made on one machine
+// does not drag drivers and firmware onto another.
+const HARDWARE_PATTERNS: &[&str] = &["akmod-*", "kmod-*", "*-firmware", "xorg-x11-drv-*", "nvidia-*", "broadcom-wl", "*-kmodsrc"];
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct KickstartFragment {
+    text: String, // repo lines and the %packages section, ready to paste into a kickstart
+    environments: Vec<String>,
+    groups: Vec<String>,
+    packages: Vec<String>,
+    excluded: Vec<String>, // hardware-specific packages turned into "-name" lines
+}
+
+// Patterns only use '*' at the start and/or end.
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    match (pattern.strip_prefix('*'), pattern.strip_suffix('*')) {
+        (Some(rest), _) if rest.ends_with('*') => name.contains(rest.trim_end_matches('*')),
+        (Some(suffix), _) => name.ends_with(suffix),
+        (None, Some(prefix)) => name.starts_with(prefix),
+        (None, None) => name == pattern,
+    }
+}
+
+fn is_hardware_specific(name: &str) -> bool {
+    HARDWARE_PATTERNS.iter().any(|pattern| matches_pattern(pattern, name))
+}
+
+// dnf4 `group list --installed --ids`: ids in parentheses under "Installed Environment Groups:"
+// and "Installed Groups:" headers. Returns (environments, groups).
+fn parse_dnf4_groups(output: &str) -> (Vec<String>, Vec<String>) {
+    let (mut environments, mut groups) = (Vec::new(), Vec::new());
+    let mut in_environments = false;
+    for line in output.lines() {
+        if !line.starts_with(' ') && line.trim_end().ends_with(':') {
+            in_environments = line.contains("Environment");
+            continue;
+        }
+        let Some(id) = line.trim().strip_suffix(')').and_then(|rest| rest.rsplit_once('(')).map(|(_, id)| id) else { continue };
+        if in_environments { &mut environments } else { &mut groups }.push(id.to_string());
+    }
+    (environments, groups)
+}
+
+// dnf5 `group list --installed` / `environment list --installed`: a table whose first column is the id.
+fn parse_dnf5_ids(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .skip_while(|line| !line.starts_with("ID"))
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().next())
+        .map(String::from)
+        .collect()
+}
+
+// Installed comps ids as (environments, groups), from either dnf4 or dnf5.
+pub async fn installed_groups(app: &tauri::AppHandle) -> Result<(Vec<String>, Vec<String>), String> {
+    crate::probes::ensure_supported(app, crate::probes::ProbedFeature::InstalledGroups)?;
+    let out = run_command(app, "dnf", &["group", "list", "--installed", "--ids"]).await?;
+    if out.success {
+        return Ok(parse_dnf4_groups(&out.stdout));
+    }
+    // dnf5 has no --ids; it lists groups and environments separately, ids first.
+    let groups = run_command(app, "dnf", &["group", "list", "--installed"]).await?;
+    let environments = run_command(app, "dnf", &["environment", "list", "--installed"]).await?;
+    if !groups.success {
+        return Err(format!("Failed to list installed groups: {}", groups.error_text().trim()));
+    }
+    Ok((parse_dnf5_ids(&environments.stdout), parse_dnf5_ids(&groups.stdout)))
+}
+
+fn render(repo_lines: &[String], fragment: &KickstartFragment) -> String {
+    let mut text = String::new();
+    for line in repo_lines {
+        text.push_str(line);
+        text.push('\n');
+    }
+    if !repo_lines.is_empty() {
+        text.push('\n');
+    }
+    text.push_str("%packages\n");
+    text.extend(fragment.environments.iter().map(|id| format!("@^{}\n", id)));
+    text.extend(fragment.groups.iter().map(|id| format!("@{}\n", id)));
+    text.extend(fragment.packages.iter().map(|name| format!("{}\n", name)));
+    if !fragment.excluded.is_empty() {
+        text.push_str("# Hardware-specific on the machine this was generated on\n");
+        text.extend(fragment.excluded.iter().map(|name| format!("-{}\n", name)));
+    }
+    text.push_str("%end\n");
+    text
+}
+
+// Names of the packages installed on request rather than as dependencies, sorted.
+pub async fn user_installed_names(app: &tauri::AppHandle) -> Result<Vec<String>, String> {
+    crate::probes::ensure_supported(app, crate::probes::ProbedFeature::UserInstalledQuery)?;
+    let out = run_command(app, "dnf", &["repoquery", "--userinstalled", "--quiet", "--queryformat", "%{name}\n"]).await?;
+    if !out.success {
+        return Err(format!("Failed to list user-installed packages: {}", out.error_text().trim()));
+    }
+    let mut names: Vec<String> = out.stdout.lines().map(str::trim).filter(|name| !name.is_empty()).map(String::from).collect();
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+// The user-installed packages, installed groups and extra enabled repositories of this system
+// as a kickstart fragment.
+#[tauri::command]
+pub async fn generate_kickstart(app: tauri::AppHandle) -> Result<KickstartFragment, NebulaError> {
+    let names = user_installed_names(&app).await?;
+    let (excluded, packages): (Vec<String>, Vec<String>) = names.into_iter().partition(|name| is_hardware_specific(name));
+    let (environments, groups) = installed_groups(&app).await?;
+
+    let repo_lines: Vec<String> = crate::repos::fetch_repositories(&app)
+        .await?
+        .iter()
+        .filter(|repo| repo.enabled() && !INSTALLER_REPOS.contains(&repo.id()))
+        .filter_map(|repo| repo.source().map(|(kind, url)| format!("repo --name={} --{}={}", repo.id(), kind, url)))
+        .collect();
+
+    let mut fragment = KickstartFragment { text: String::new(), environments, groups, packages, excluded };
+    fragment.text = render(&repo_lines, &fragment);
+    Ok(fragment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kickstart_fragment() {
+        let dnf4 = "Installed Environment Groups:\n   Fedora Workstation (workstation-product-environment)\n\
+                    Installed Groups:\n   C Development Tools and Libraries (c-development)\n   Container Management (container-management)\n";
+        let (environments, groups) = parse_dnf4_groups(dnf4);
+        assert_eq!(environments, vec!["workstation-product-environment"]);
+        assert_eq!(groups, vec!["c-development", "container-management"]);
+        assert_eq!(parse_dnf5_ids("ID                   Name                 Installed\ncontainer-management Container Management       yes\n"), vec!["container-management"]);
+
+        assert!(is_hardware_specific("akmod-nvidia"));
+        assert!(is_hardware_specific("iwlwifi-mvm-firmware"));
+        assert!(!is_hardware_specific("htop"));
+
+        let fragment = KickstartFragment {
+            text: String::new(),
+            environments,
+            groups: vec!["c-development".to_string()],
+            packages: vec!["htop".to_string()],
+            excluded: vec!["akmod-nvidia".to_string()],
+        };
+        let text = render(&["repo --name=copr-foo --baseurl=https://example.org/repo".to_string()], &fragment);
+        assert_eq!(
+            text,
+            "repo --name=copr-foo --baseurl=https://example.org/repo\n\n%packages\n@^workstation-product-environment\n@c-development\nhtop\n\
+             # Hardware-specific on the machine this was generated on\n-akmod-nvidia\n%end\n"
+        );
+    }
+}
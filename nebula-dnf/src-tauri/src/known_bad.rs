@@ -0,0 +1,218 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::{error, info};
+
+use crate::error::NebulaError;
+use crate::updates::{fetch_pending_updates, PendingUpdate};
+use crate::{app_data_file, load_json_file, save_json_file};
+
+const SUBSCRIPTIONS_FILE_NAME: &str = "known_bad_subscriptions.json";
+const PERSONAL_LIST_FILE_NAME: &str = "known_bad_personal.json";
+const FEED_TIMEOUT: Duration = Duration::from_secs(20);
+
+// A community feed: `url` serves the JSON feed, `url` + ".sig" a base64 Ed25519 signature
+// of the exact feed bytes, made with the key whose base64 public half is `public_key`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KnownBadSubscription {
+    name: String,
+    url: String,
+    public_key: String,
+    enabled: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KnownBadEntry {
+    package: String,
+    // Affected candidate versions ("1.2-3.fc40"); a version ending in '*' matches as a prefix.
+    // Empty means every pending update of the package.
+    #[serde(default)]
+    versions: Vec<String>,
+    issue: String, // link to the bug report / discussion
+    #[serde(default)]
+    reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+struct KnownBadFeed {
+    entries: Vec<KnownBadEntry>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct KnownBadMatch {
+    package: String,
+    candidate_version: String,
+    issue: String,
+    reason: String,
+    source: String,           // subscription name, or "personal"
+    suggested_exclude: String, // value for `dnf --exclude=` until a fixed build lands
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct KnownBadReport {
+    matches: Vec<KnownBadMatch>,
+    feed_errors: Vec<String>,
+}
+
+fn verify_feed(body: &[u8], signature_b64: &str, public_key_b64: &str) -> Result<(), String> {
+    let key_bytes: [u8; 32] = BASE64
+        .decode(public_key_b64.trim())
+        .map_err(|e| format!("Invalid public key encoding: {}", e))?
+        .try_into()
+        .map_err(|_| "Public key must be 32 bytes.".to_string())?;
+    let key = VerifyingKey::from_bytes(&key_bytes).map_err(|e| format!("Invalid public key: {}", e))?;
+    let signature_bytes = BASE64
+        .decode(signature_b64.trim())
+        .map_err(|e| format!("Invalid signature encoding: {}", e))?;
+    let signature = Signature::from_slice(&signature_bytes).map_err(|e| format!("Invalid signature: {}", e))?;
+    key.verify_strict(body, &signature)
+        .map_err(|_| "Signature verification failed.".to_string())
+}
+
+async fn fetch_feed(client: &reqwest::Client, subscription: &KnownBadSubscription) -> Result<KnownBadFeed, String> {
+    let fetch = |url: String| async move {
+        let response = client.get(&url).send().await.map_err(|e| format!("Failed to fetch {}: {}", url, e))?;
+        if !response.status().is_success() {
+            return Err(format!("Fetching {} returned HTTP {}", url, response.status()));
+        }
+        response.bytes().await.map_err(|e| format!("Failed to read {}: {}", url, e))
+    };
+    let body = fetch(subscription.url.clone()).await?;
+    let signature = fetch(format!("{}.sig", subscription.url)).await?;
+    verify_feed(&body, &String::from_utf8_lossy(&signature), &subscription.public_key)
+        .map_err(|e| format!("Feed '{}' rejected: {}", subscription.name, e))?;
+    serde_json::from_slice(&body).map_err(|e| format!("Feed '{}' is not valid JSON: {}", subscription.name, e))
+}
+
+fn entry_matches(entry: &KnownBadEntry, update: &PendingUpdate) -> bool {
+    entry.package == update.name
+        && (entry.versions.is_empty()
+            || entry.versions.iter().any(|v| match v.strip_suffix('*') {
+                Some(prefix) => update.version.starts_with(prefix),
+                None => *v == update.version,
+            }))
+}
+
+fn cross_reference(updates: &[PendingUpdate], entries: &[KnownBadEntry], source: &str) -> Vec<KnownBadMatch> {
+    let mut matches = Vec::new();
+    for update in updates {
+        for entry in entries.iter().filter(|e| entry_matches(e, update)) {
+            matches.push(KnownBadMatch {
+                package: update.name.clone(),
+                candidate_version: update.version.clone(),
+                issue: entry.issue.clone(),
+                reason: entry.reason.clone(),
+                source: source.to_string(),
+                suggested_exclude: format!("{}-{}", update.name, update.version),
+            });
+        }
+    }
+    matches
+}
+
+#[tauri::command]
+pub async fn get_known_bad_subscriptions(app: tauri::AppHandle) -> Result<Vec<KnownBadSubscription>, NebulaError> {
+    Ok(load_json_file(&app_data_file(&app, SUBSCRIPTIONS_FILE_NAME)?)?.unwrap_or_default())
+}
+
+#[tauri::command]
+pub async fn set_known_bad_subscriptions(app: tauri::AppHandle, subscriptions: Vec<KnownBadSubscription>) -> Result<(), NebulaError> {
+    for subscription in &subscriptions {
+        if !subscription.url.starts_with("https://") {
+            return Err(format!("Feed URL must use https: {}", subscription.url).into());
+        }
+        if BASE64.decode(subscription.public_key.trim()).map(|k| k.len()) != Ok(32) {
+            return Err(format!("Feed '{}' needs a base64 Ed25519 public key.", subscription.name).into());
+        }
+    }
+    Ok(save_json_file(&app_data_file(&app, SUBSCRIPTIONS_FILE_NAME)?, &subscriptions)?)
+}
+
+#[tauri::command]
+pub async fn get_personal_known_bad(app: tauri::AppHandle) -> Result<Vec<KnownBadEntry>, NebulaError> {
+    let feed: KnownBadFeed = load_json_file(&app_data_file(&app, PERSONAL_LIST_FILE_NAME)?)?.unwrap_or_default();
+    Ok(feed.entries)
+}
+
+#[tauri::command]
+pub async fn set_personal_known_bad(app: tauri::AppHandle, entries: Vec<KnownBadEntry>) -> Result<(), NebulaError> {
+    Ok(save_json_file(&app_data_file(&app, PERSONAL_LIST_FILE_NAME)?, &KnownBadFeed { entries })?)
+}
+
+// Cross-references pending updates against the personal list and every enabled feed.
+// A feed that fails to download or verify is reported and skipped, never trusted.
+#[tauri::command]
+pub async fn check_known_bad_updates(app: tauri::AppHandle) -> Result<KnownBadReport, NebulaError> {
+    let updates = fetch_pending_updates(&app).await?;
+    let personal: KnownBadFeed = load_json_file(&app_data_file(&app, PERSONAL_LIST_FILE_NAME)?)?.unwrap_or_default();
+    let subscriptions: Vec<KnownBadSubscription> =
+        load_json_file(&app_data_file(&app, SUBSCRIPTIONS_FILE_NAME)?)?.unwrap_or_default();
+
+    let mut report = KnownBadReport {
+        matches: cross_reference(&updates, &personal.entries, "personal"),
+        feed_errors: Vec::new(),
+    };
+    let client = reqwest::Client::builder()
+        .timeout(FEED_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    for subscription in subscriptions.iter().filter(|s| s.enabled) {
+        match fetch_feed(&client, subscription).await {
+            Ok(feed) => report.matches.extend(cross_reference(&updates, &feed.entries, &subscription.name)),
+            Err(e) => {
+                error!("Known-bad feed error: {}", e);
+                report.feed_errors.push(e);
+            }
+        }
+    }
+    info!("Known-bad check found {} affected pending update(s).", report.matches.len());
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_matches_versions() {
+        let update = PendingUpdate {
+            name: "mesa-dri-drivers".to_string(),
+            arch: "x86_64".to_string(),
+            version: "24.1.2-1.fc40".to_string(),
+            repo: "updates".to_string(),
+        };
+        let entry = |versions: &[&str]| KnownBadEntry {
+            package: "mesa-dri-drivers".to_string(),
+            versions: versions.iter().map(|v| v.to_string()).collect(),
+            issue: "https://bugzilla.redhat.com/show_bug.cgi?id=1".to_string(),
+            reason: String::new(),
+        };
+        assert!(entry_matches(&entry(&[]), &update));
+        assert!(entry_matches(&entry(&["24.1.2-1.fc40"]), &update));
+        assert!(entry_matches(&entry(&["24.1.*"]), &update));
+        assert!(!entry_matches(&entry(&["24.1.3-1.fc40"]), &update));
+    }
+
+    #[test]
+    fn test_verify_feed() {
+        use ed25519_dalek::{Signer, SigningKey};
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let other_key = SigningKey::from_bytes(&[8; 32]);
+        let public_key = BASE64.encode(key.verifying_key().to_bytes());
+        let body = br#"{"entries":[{"package":"mesa-dri-drivers","issue":"https://example.org/1"}]}"#;
+        let signature = BASE64.encode(key.sign(body).to_bytes());
+
+        assert_eq!(verify_feed(body, &format!("{}\n", signature), &public_key), Ok(()));
+        let tampered = br#"{"entries":[]}"#;
+        assert_eq!(verify_feed(tampered, &signature, &public_key), Err("Signature verification failed.".to_string()));
+        let wrong_key = BASE64.encode(other_key.verifying_key().to_bytes());
+        assert_eq!(verify_feed(body, &signature, &wrong_key), Err("Signature verification failed.".to_string()));
+
+        assert!(verify_feed(body, &signature, "not base64!").unwrap_err().starts_with("Invalid public key encoding"));
+        assert_eq!(verify_feed(body, &signature, &BASE64.encode([1; 16])), Err("Public key must be 32 bytes.".to_string()));
+        assert!(verify_feed(body, "not base64!", &public_key).unwrap_err().starts_with("Invalid signature encoding"));
+        assert!(verify_feed(body, &BASE64.encode([1; 10]), &public_key).unwrap_err().starts_with("Invalid signature"));
+    }
+}
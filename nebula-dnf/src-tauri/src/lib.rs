@@ -1,19 +1,98 @@
 use regex::Regex;
 use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
 use tauri_plugin_shell::ShellExt;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use once_cell::sync::Lazy;
-use std::fs::{self, File};
-use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::Semaphore;
-use tauri::Manager; // Required for app.path()
+use tauri::{Emitter, Manager}; // Manager is required for app.path()
+use tracing::{error, info, warn};
+
+use error::NebulaError;
+
+mod advisories;
+mod app_operations;
+mod appstream;
+mod apt;
+mod audit;
+mod backends;
+mod cache;
+mod cleanup;
+mod cleanup_wizard;
+mod codecs;
+mod coordination;
+mod daemon;
+mod dbus_status;
+mod dnf5daemon;
+mod dnf_cache;
+mod dnf_conf;
+mod error;
+mod features;
+mod flathub;
+mod flatpak;
+mod fonts;
+mod gpg_keys;
+mod groups;
+mod hardware;
+mod health;
+pub mod helper;
+mod history;
+mod install;
+mod installroot;
+mod kernels;
+mod kickstart;
+mod known_bad;
+mod logging;
+mod maintenance;
+mod metrics;
+mod offline;
+mod options;
+mod orchestrator;
+mod package_info;
+mod packagekit;
+mod pacman;
+mod parse_failures;
+mod pins;
+mod plan;
+mod probes;
+mod process;
+mod progress;
+mod provides;
+mod queue;
+mod repoquery;
+mod repos;
+mod restarts;
+mod roles;
+mod rollout;
+mod rpc;
+mod rpmdb;
+mod scope;
+mod session;
+mod services;
+mod settings;
+mod snapshot;
+mod software;
+mod system_upgrade;
+mod themes;
+mod transaction;
+mod updates;
+mod usage;
+mod userspace;
+mod versionlock;
+mod watcher;
+mod zypper;
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 
-const CACHE_FILE_NAME: &str = "package_cache.json";
-const MAX_CONCURRENT_RPM_QUERIES: usize = 5; // Limit concurrent rpm processes
+const RPM_QUERY_BATCH_SIZE: usize = 100; // Packages per rpm invocation when librpm is unavailable
+
+// One block per package for a batched `rpm -q`: a header line, then the requirements the way
+// `rpm -qR` prints them. "package foo is not installed" lines fall outside any block.
+const RPM_BATCH_QUERY_FORMAT: &str = "@@package\t%{NAME}\t%{SIZE}\n[%{REQUIRENEVRS}\n]@@end\n";
 
 // --- Regex Definitions ---
 // Regex for extracting base package name: captures name part before potential version string.
@@ -25,17 +104,34 @@ static NAME_EXTRACTOR_RE: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r"^([a-zA-Z0-9][a-zA-Z0-9._+-]*?)(?:-([0-9].*))?$").unwrap()
 });
 
-// Regexes for parsing deplist output
-static PACKAGE_LINE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^package:\s*(.+)").unwrap());
-static PROVIDER_LINE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s+provider:\s*(.+)").unwrap());
+// Package names/specs accepted from the frontend before they are passed to rpm/dnf.
+// Must not start with '-' so a name can never be interpreted as an option.
+static PACKAGE_NAME_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^[a-zA-Z0-9_][a-zA-Z0-9._+:~^-]*$").unwrap()
+});
 
 // --- Struct Definitions ---
+// What a requirement refers to, assigned while parsing `rpm -qR`.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DependencyKind {
+    #[default]
+    Package,
+    Library,        // soname, e.g. libc.so.6()(64bit)
+    BinaryPath,     // file dependency, e.g. /usr/bin/perl
+    LanguageModule, // perl(...), python3dist(...), rubygem(...) and friends
+    Internal,       // rpmlib(...) and config(...) entries only rpm itself cares about
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub struct DisplayablePackage {
     name: String,
+    #[serde(default)] // Caches written before kinds existed
+    kind: DependencyKind,
+    #[serde(default)]
+    module_provider: Option<provides::ModuleProvider>, // set for LanguageModule dependencies
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)] // Added PartialEq, Eq, Hash for potential future use
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Default)] // Added PartialEq, Eq, Hash for potential future use
 pub enum PackageCategory {
     Manual,
     DesktopEnvironment,
@@ -49,20 +145,21 @@ pub enum PackageCategory {
     Network,
     Security,
     OtherApplication, // For other apps not fitting above
+    #[default]
     Unknown,
 }
 
-impl Default for PackageCategory {
-    fn default() -> Self {
-        PackageCategory::Unknown
-    }
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 struct UserPackageWithDependencies {
     name: String,
     category: PackageCategory, // New field
     dependencies: Vec<DisplayablePackage>,
+    #[serde(default)] // Caches written before parse failures were tracked
+    incomplete: bool, // some lines of the rpm -qR output could not be parsed
+    #[serde(default)]
+    installed_size: Option<u64>, // bytes; known when the rpmdb was read directly
+    #[serde(default)]
+    appstream: Option<appstream::AppStreamInfo>, // attached on the way out, never cached
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -70,10 +167,16 @@ pub struct PackageOperationResult {
     success: bool,
     message: String,      // User-facing summary. For dry run, this could be a preamble.
     details: Option<String>, // For verbose output like dry run text or full dnf output.
+    #[serde(default)]
+    confirmation_required: bool, // Preview only; repeat the call with `confirmed` to run it.
+    #[serde(default)]
+    simulated: bool, // Simulation mode was on: the dry-run equivalent ran instead.
+    #[serde(default)]
+    reboot_required: bool, // The transaction changed something only a reboot applies (see restarts::check_reboot).
 }
 
 // Enum for different uninstall modes
-#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 pub enum UninstallMode {
     Safe,      // Actual removal: dnf remove <pkg> -y
     Force,     // Actual removal: rpm -e --nodeps <pkg>
@@ -84,9 +187,64 @@ pub enum UninstallMode {
 // Struct for uninstall arguments
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct UninstallArgs {
-    package_name: String,
-    mode: UninstallMode,
+    // removed together in one transaction; the single-name "package_name" form still works
+    #[serde(alias = "package_name", deserialize_with = "one_or_many")]
+    packages: Vec<String>,
+    #[serde(default)]
+    mode: Option<UninstallMode>, // None for the default mode from settings
     cleanup_orphans: bool, // Only relevant for Safe/DryRunSafe modes
+    #[serde(default)]
+    confirmed: bool, // The user has seen the dry run the settings ask for
+    #[serde(default)]
+    offline: bool, // stage the removal for the next boot instead (dnf5)
+}
+
+// A single string or a list of them.
+fn one_or_many<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(name) => vec![name],
+        OneOrMany::Many(names) => names,
+    })
+}
+
+impl UninstallArgs {
+    // For messages: "htop" or "htop, btop".
+    pub fn target(&self) -> String {
+        self.packages.join(", ")
+    }
+
+    pub fn mode(&self) -> UninstallMode {
+        self.mode.unwrap_or(UninstallMode::Safe)
+    }
+
+    // Whether the removal is staged for the next boot; a dry run only resolves it as usual.
+    fn staged(&self) -> bool {
+        self.offline && matches!(self.mode(), UninstallMode::Safe | UninstallMode::Force)
+    }
+
+    // Whether the removal is a dry run on a backend other than dnf. Their removals take unneeded
+    // dependencies along already; rpm's forced removal has no counterpart there.
+    fn backend_dry_run(&self) -> Result<bool, String> {
+        match self.mode() {
+            UninstallMode::Safe => Ok(false),
+            UninstallMode::DryRunSafe => Ok(true),
+            UninstallMode::Force | UninstallMode::DryRunForce => Err("Forced removal needs the dnf backend.".to_string()),
+        }
+    }
+
+    // Fills in the settings' default when the request named no mode.
+    pub fn resolve_mode(&mut self, app: &tauri::AppHandle) -> Result<(), String> {
+        if self.mode.is_none() {
+            self.mode = Some(settings::default_uninstall_mode(app)?);
+        }
+        Ok(())
+    }
 }
 
 // --- Helper Functions ---
@@ -113,149 +271,311 @@ fn extract_base_package_name(full_spec: &str) -> String {
     trimmed_spec.to_string()
 }
 
-// Renamed function from parse_requires_output to parse_rpm_requires_output
-fn parse_rpm_requires_output(output: &str, main_pkg_base_name_for_context: &str) -> Vec<DisplayablePackage> {
-    println!(
+fn validate_package_name(name: &str) -> Result<(), NebulaError> {
+    if PACKAGE_NAME_RE.is_match(name) {
+        Ok(())
+    } else {
+        Err(NebulaError::InvalidInput { message: format!("Invalid package name: '{}'", name) })
+    }
+}
+
+fn classify_dependency(dep_spec: &str) -> DependencyKind {
+    let spec = dep_spec.split_whitespace().next().unwrap_or("");
+    let namespace = spec.split_once('(').map(|(ns, _)| ns);
+    match namespace {
+        Some("rpmlib") | Some("config") => DependencyKind::Internal,
+        _ if spec.starts_with('/') => DependencyKind::BinaryPath,
+        _ if spec.contains(".so") => DependencyKind::Library,
+        Some(_) if provides::module_ecosystem(spec).is_some() => DependencyKind::LanguageModule,
+        _ => DependencyKind::Package,
+    }
+}
+
+// A requirement as rpm -qR prints it: a capability, optionally followed by an operator and a
+// version. Rich dependencies are handled by rich_requirement_names.
+fn is_simple_requirement(dep_spec: &str) -> bool {
+    let words: Vec<&str> = dep_spec.split_whitespace().collect();
+    let shape_ok = match words.as_slice() {
+        [_] => true,
+        [_, op, _] => matches!(*op, "<" | "<=" | "=" | ">=" | ">"),
+        _ => false,
+    };
+    shape_ok && !dep_spec.starts_with('(')
+}
+
+// The capabilities a rich dependency such as "(python3-foo if python3)" or
+// "((a >= 1.0 or b) with c)" can pull in, without versions. Conditions after "if", "unless" or
+// "without" are not requirements and are skipped; the "else" branch is kept. None when the
+// expression does not parse.
+fn rich_requirement_names(dep_spec: &str) -> Option<Vec<String>> {
+    let mut names = Vec::new();
+    let mut depth = 0usize;
+    let mut condition_depth: Option<usize> = None; // depth of the group whose condition is being read
+    let mut expect_version = false;
+    for word in dep_spec.split_whitespace() {
+        let mut term = word.trim_start_matches('(');
+        depth += word.len() - term.len();
+        let mut closed = 0;
+        while term.ends_with(')') && term.matches(')').count() > term.matches('(').count() {
+            term = &term[..term.len() - 1];
+            closed += 1;
+        }
+        if expect_version {
+            expect_version = false;
+        } else {
+            match term {
+                "and" | "or" | "with" => {}
+                "if" | "unless" | "without" => condition_depth = Some(depth),
+                "else" if condition_depth == Some(depth) => condition_depth = None,
+                "<" | "<=" | "=" | ">=" | ">" => expect_version = true,
+                "" | "else" => return None,
+                _ if condition_depth.is_none() => names.push(term.to_string()),
+                _ => {}
+            }
+        }
+        depth = depth.checked_sub(closed)?;
+        if condition_depth.is_some_and(|condition| depth < condition) {
+            condition_depth = None;
+        }
+    }
+    (depth == 0 && !expect_version && !names.is_empty()).then_some(names)
+}
+
+// Renamed function from parse_requires_output to parse_rpm_requires_output. Also returns the
+// lines that were not a simple requirement.
+fn parse_rpm_requires_output(output: &str, main_pkg_base_name_for_context: &str) -> (Vec<DisplayablePackage>, Vec<String>) {
+    info!(
         "--- Parsing `rpm -qR` output for [{}] ---\n{}\n--- End `rpm -qR` output for [{}] ---",
         main_pkg_base_name_for_context, output, main_pkg_base_name_for_context
     );
 
     let mut deps = HashSet::new(); // Use HashSet to avoid duplicate deps
+    let mut unparsed = Vec::new();
 
     for line in output.lines() {
         let dep_spec = line.trim();
         if !dep_spec.is_empty() && !dep_spec.starts_with("Last metadata expiration check:") {
-            let dep_base_name = extract_base_package_name(dep_spec);
-            println!(
-                "  Found requirement spec: '{}', Extracted base name: '{}'",
-                dep_spec,
-                dep_base_name
-            );
-            // Avoid adding the package itself as its own dependency
-            if dep_base_name != main_pkg_base_name_for_context {
-                deps.insert(DisplayablePackage { name: dep_base_name });
+            let capabilities = if dep_spec.starts_with('(') {
+                rich_requirement_names(dep_spec)
+            } else {
+                is_simple_requirement(dep_spec).then(|| vec![dep_spec.to_string()])
+            };
+            let Some(capabilities) = capabilities else {
+                unparsed.push(dep_spec.to_string());
+                continue;
+            };
+            for dep_spec in capabilities.iter().map(String::as_str) {
+                let kind = classify_dependency(dep_spec);
+                let dep_base_name = match kind {
+                    // Keep the whole capability (minus any version constraint) so modules stay distinguishable
+                    DependencyKind::LanguageModule => dep_spec.split_whitespace().next().unwrap_or(dep_spec).to_string(),
+                    _ => extract_base_package_name(dep_spec),
+                };
+                info!(
+                    "  Found requirement spec: '{}', Extracted base name: '{}'",
+                    dep_spec,
+                    dep_base_name
+                );
+                // Avoid adding the package itself as its own dependency
+                if dep_base_name != main_pkg_base_name_for_context {
+                    deps.insert(DisplayablePackage { name: dep_base_name, kind, module_provider: None });
+                }
             }
         }
     }
     let mut deps_vec: Vec<DisplayablePackage> = deps.into_iter().collect();
     deps_vec.sort_by(|a, b| a.name.cmp(&b.name));
-    deps_vec
+    (deps_vec, unparsed)
 }
 
-fn get_cache_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+// Path of a file in the app's local data directory (where the cache and other state live).
+fn app_data_file(app: &tauri::AppHandle, file_name: &str) -> Result<PathBuf, String> {
     app.path().app_local_data_dir()
-        .map(|p| p.join(CACHE_FILE_NAME))
+        .map(|p| p.join(file_name))
         .map_err(|e| format!("Failed to get app local data directory path: {}", e))
 }
 
-fn load_cache(app: &tauri::AppHandle) -> Result<Option<Vec<UserPackageWithDependencies>>, String> {
-    let cache_path = get_cache_path(app)?;
-    if cache_path.exists() {
-        let mut file = File::open(cache_path).map_err(|e| format!("Failed to open cache file: {}", e))?;
-        let mut contents = String::new();
-        file.read_to_string(&mut contents).map_err(|e| format!("Failed to read cache file: {}", e))?;
-        if contents.is_empty() {
-             return Ok(None); // Cache file is empty
-        }
-        serde_json::from_str(&contents)
-            .map_err(|e| format!("Failed to deserialize cache: {}. Cache file might be corrupted.", e))
-            .map(Some)
-    } else {
-        Ok(None)
+// Reads a JSON state/config file. A missing or empty file is Ok(None).
+fn load_json_file<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents = fs::read_to_string(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    if contents.trim().is_empty() {
+        return Ok(None);
     }
+    serde_json::from_str(&contents)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse {:?}: {}", path, e))
 }
 
-fn save_cache(app: &tauri::AppHandle, data: &Vec<UserPackageWithDependencies>) -> Result<(), String> {
-    let cache_path = get_cache_path(app)?;
-    if let Some(parent_dir) = cache_path.parent() {
-        fs::create_dir_all(parent_dir).map_err(|e| format!("Failed to create cache directory: {}", e))?;
+fn save_json_file<T: Serialize>(path: &Path, data: &T) -> Result<(), String> {
+    if let Some(parent_dir) = path.parent() {
+        fs::create_dir_all(parent_dir).map_err(|e| format!("Failed to create directory {:?}: {}", parent_dir, e))?;
     }
-    let mut file = File::create(cache_path).map_err(|e| format!("Failed to create cache file: {}", e))?;
     let json_data = serde_json::to_string_pretty(data).map_err(|e| format!("Failed to serialize data: {}", e))?;
-    file.write_all(json_data.as_bytes()).map_err(|e| format!("Failed to write to cache file: {}", e))
+    fs::write(path, json_data).map_err(|e| format!("Failed to write {:?}: {}", path, e))
 }
 
-// Helper function to get package category based on RPM group
-async fn get_package_category(shell: &tauri_plugin_shell::Shell<tauri::Wry>, package_name: &str) -> PackageCategory {
-    let output_result = shell
-        .command("rpm")
-        .args(["-q", "--qf", "%{GROUP}\n", package_name])
-        .output()
-        .await;
+// Appends one record to a JSON Lines log, creating the file if needed.
+fn append_json_line<T: Serialize>(path: &Path, record: &T) -> Result<(), String> {
+    if let Some(parent_dir) = path.parent() {
+        fs::create_dir_all(parent_dir).map_err(|e| format!("Failed to create directory {:?}: {}", parent_dir, e))?;
+    }
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open {:?}: {}", path, e))?;
+    let line = serde_json::to_string(record).map_err(|e| format!("Failed to serialize record: {}", e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("Failed to write {:?}: {}", path, e))
+}
 
-    match output_result {
-        Ok(output_val) => {
-            if output_val.status.success() {
-                let group_str = String::from_utf8_lossy(&output_val.stdout).trim().to_lowercase();
-                // println!("Package: {}, RPM Group: '{}'", package_name, group_str);
+// Reads a JSON Lines log oldest first, skipping lines that no longer parse.
+fn load_json_lines<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let contents = fs::read_to_string(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    Ok(contents.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+}
 
-                if group_str.is_empty() || group_str.contains("not installed") || group_str.contains("no such file") {
-                    return PackageCategory::Unknown; // Package might have been removed or is a virtual package
-                }
+// What a batched `rpm -q` printed for one package.
+#[derive(Debug, Default, PartialEq)]
+struct RpmQueryEntry {
+    size: Option<u64>,
+    requires: String, // newline-separated, as `rpm -qR` prints it
+}
 
-                // More specific checks first
-                if group_str.contains("desktop environment") || group_str.contains("desktops") || group_str.contains("xfce") || group_str.contains("kde") || group_str.contains("gnome") {
-                    return PackageCategory::DesktopEnvironment;
-                }
-                if group_str.starts_with("system environment/base") || group_str.starts_with("system environment/kernel") || group_str == "system environment" {
-                    return PackageCategory::System;
-                }
-                if group_str.contains("games") {
-                    return PackageCategory::Games;
-                }
-                if group_str.contains("multimedia") || group_str.contains("sound") || group_str.contains("video") {
-                    return PackageCategory::Multimedia;
-                }
-                if group_str.contains("office") || group_str.contains("productivity") {
-                    return PackageCategory::Office;
-                }
-                 if group_str.contains("network") || group_str.contains("web") || group_str.contains("mail") {
-                    return PackageCategory::Network;
-                }
-                if group_str.contains("security") || group_str.contains("firewall") {
-                    return PackageCategory::Security;
-                }
-                 // General application categories
-                if group_str.starts_with("applications/") {
-                    if group_str.contains("development") || group_str.contains("debugging") {
-                        return PackageCategory::Development;
-                    }
-                    if group_str.contains("utilities") {
-                        return PackageCategory::Utility;
-                    }
-                     // Catch-all for other things under "applications/"
-                    return PackageCategory::OtherApplication; 
-                }
-                if group_str.starts_with("development/") {
-                    return PackageCategory::Development;
-                }
-                // Libraries are often harder to distinguish from system components if not explicitly categorized
-                if group_str.contains("libraries") || group_str.ends_with("lib") || group_str.contains("shared libraries") {
-                    return PackageCategory::Library;
-                }
-                // If it's user installed but doesn't fit above, lean towards Manual or OtherApplication
-                // For now, let's assume if it's in dnf userinstalled and not clearly system/DE/library, it was somewhat manual.
-                // This is a heuristic and might need refinement.
-                if !group_str.starts_with("system environment/") { // Avoid re-classifying things already potentially System
-                    return PackageCategory::Manual; 
-                }
-                
-                PackageCategory::Unknown
-            } else {
-                // e.g. package not found by rpm, or rpm command error
-                // eprintln!("RPM query for group failed for {}: Status {}, Stderr: {}", 
-                //     package_name, 
-                //     output_val.status.code().unwrap_or(-1),
-                //     String::from_utf8_lossy(&output_val.stderr).trim()
-                // );
-                PackageCategory::Unknown
+// Splits RPM_BATCH_QUERY_FORMAT output by package. A name installed for several architectures
+// gets one entry with the requirements of all of them.
+fn parse_rpm_batch_output(output: &str) -> HashMap<String, RpmQueryEntry> {
+    let mut entries: HashMap<String, RpmQueryEntry> = HashMap::new();
+    let mut current: Option<String> = None;
+    for line in output.lines() {
+        if let Some(header) = line.strip_prefix("@@package\t") {
+            let (name, size) = header.split_once('\t').unwrap_or((header, ""));
+            entries.entry(name.to_string()).or_insert_with(|| RpmQueryEntry {
+                size: size.trim().parse().ok(),
+                requires: String::new(),
+            });
+            current = Some(name.to_string());
+        } else if line == "@@end" {
+            current = None;
+        } else if let Some(entry) = current.as_ref().and_then(|name| entries.get_mut(name)) {
+            entry.requires.push_str(line);
+            entry.requires.push('\n');
+        }
+    }
+    entries
+}
+
+// Unparsed requirement lines as the parse-failure log records them, with the package they
+// belong to, e.g. "foo: (a or b".
+fn unparsed_for(package: &str, unparsed: Vec<String>) -> impl Iterator<Item = String> + '_ {
+    unparsed.into_iter().map(move |line| format!("{}: {}", package, line))
+}
+
+// Requirements and size of `names` from a single rpm invocation; categories come from the
+// `rpm -qa` pass. Packages rpm did not report (removed meanwhile, or rpm failed) come back
+// without dependencies. Also returns the requirement lines that did not parse.
+async fn query_rpm_batch(app: &tauri::AppHandle, names: &[String], categories: &HashMap<String, PackageCategory>) -> (Vec<UserPackageWithDependencies>, Vec<String>) {
+    let mut args = vec!["-q", "--queryformat", RPM_BATCH_QUERY_FORMAT, "--"];
+    args.extend(names.iter().map(String::as_str));
+    // rpm exits non-zero when any name is not installed, but still prints the others
+    let mut entries = match process::run_command(app, "rpm", &args).await {
+        Ok(out) => parse_rpm_batch_output(&out.stdout),
+        Err(e) => {
+            warn!("batched rpm query failed: {}", e);
+            HashMap::new()
+        }
+    };
+    let mut all_unparsed = Vec::new();
+    let packages = names
+        .iter()
+        .map(|name| {
+            let entry = entries.remove(name).unwrap_or_default();
+            let (dependencies, unparsed) = parse_rpm_requires_output(&entry.requires, name);
+            let incomplete = !unparsed.is_empty();
+            all_unparsed.extend(unparsed_for(name, unparsed));
+            UserPackageWithDependencies {
+                incomplete,
+                category: categories.get(name).cloned().unwrap_or_default(),
+                installed_size: entry.size,
+                appstream: None,
+                name: name.clone(),
+                dependencies,
             }
+        })
+        .collect();
+    (packages, all_unparsed)
+}
+
+// `rpm -qa --queryformat '%{NAME}|%{GROUP}\n'` output as a name -> category map.
+fn parse_rpm_categories(output: &str) -> HashMap<String, PackageCategory> {
+    output
+        .lines()
+        .filter_map(|line| line.trim().split_once('|'))
+        .filter(|(name, _)| !name.is_empty())
+        .map(|(name, group)| (name.to_string(), category_from_group(group)))
+        .collect()
+}
+
+// Maps an RPM Group tag, from rpm or read from the rpmdb directly, to a category.
+fn category_from_group(group: &str) -> PackageCategory {
+    let group_str = group.trim().to_lowercase();
+    if group_str.is_empty() || group_str.contains("not installed") || group_str.contains("no such file") {
+        return PackageCategory::Unknown; // Package might have been removed or is a virtual package
+    }
+
+    // More specific checks first
+    if group_str.contains("desktop environment") || group_str.contains("desktops") || group_str.contains("xfce") || group_str.contains("kde") || group_str.contains("gnome") {
+        return PackageCategory::DesktopEnvironment;
+    }
+    if group_str.starts_with("system environment/base") || group_str.starts_with("system environment/kernel") || group_str == "system environment" {
+        return PackageCategory::System;
+    }
+    if group_str.contains("games") {
+        return PackageCategory::Games;
+    }
+    if group_str.contains("multimedia") || group_str.contains("sound") || group_str.contains("video") {
+        return PackageCategory::Multimedia;
+    }
+    if group_str.contains("office") || group_str.contains("productivity") {
+        return PackageCategory::Office;
+    }
+     if group_str.contains("network") || group_str.contains("web") || group_str.contains("mail") {
+        return PackageCategory::Network;
+    }
+    if group_str.contains("security") || group_str.contains("firewall") {
+        return PackageCategory::Security;
+    }
+     // General application categories
+    if group_str.starts_with("applications/") {
+        if group_str.contains("development") || group_str.contains("debugging") {
+            return PackageCategory::Development;
         }
-        Err(_e) => {
-            // eprintln!("Failed to execute rpm query for group of {}: {}", package_name, e);
-            PackageCategory::Unknown
+        if group_str.contains("utilities") {
+            return PackageCategory::Utility;
         }
+         // Catch-all for other things under "applications/"
+        return PackageCategory::OtherApplication; 
+    }
+    if group_str.starts_with("development/") {
+        return PackageCategory::Development;
+    }
+    // Libraries are often harder to distinguish from system components if not explicitly categorized
+    if group_str.contains("libraries") || group_str.ends_with("lib") || group_str.contains("shared libraries") {
+        return PackageCategory::Library;
     }
+    // If it's user installed but doesn't fit above, lean towards Manual or OtherApplication
+    // For now, let's assume if it's in dnf userinstalled and not clearly system/DE/library, it was somewhat manual.
+    // This is a heuristic and might need refinement.
+    if !group_str.starts_with("system environment/") { // Avoid re-classifying things already potentially System
+        return PackageCategory::Manual; 
+    }
+    
+    PackageCategory::Unknown
 }
 
 // --- Tauri Commands ---
@@ -265,8 +585,21 @@ fn greet(name: &str) -> String {
 }
 
 #[tauri::command]
-async fn list_installed_packages(app: tauri::AppHandle) -> Result<Vec<DisplayablePackage>, String> {
-    println!("Attempting to list all installed packages using 'rpm -qa'.");
+async fn list_installed_packages(app: tauri::AppHandle) -> Result<Vec<DisplayablePackage>, NebulaError> {
+    let names = match rpmdb::installed_packages().await {
+        Some(headers) => Some(headers.into_iter().map(|header| header.name).collect::<Vec<String>>()),
+        None => dnf5daemon::installed_package_names().await,
+    };
+    if let Some(names) = names {
+        let unique_base_names: HashSet<String> = names.into_iter().collect();
+        let mut packages: Vec<DisplayablePackage> = unique_base_names
+            .into_iter()
+            .map(|name| DisplayablePackage { name, kind: DependencyKind::Package, module_provider: None })
+            .collect();
+        packages.sort_by(|a, b| a.name.cmp(&b.name));
+        return Ok(packages);
+    }
+    info!("Attempting to list all installed packages using 'rpm -qa'.");
     let shell = app.shell();
     let output_result = shell
         .command("rpm")
@@ -277,7 +610,7 @@ async fn list_installed_packages(app: tauri::AppHandle) -> Result<Vec<Displayabl
     match output_result {
         Ok(output_val) => {
             if output_val.status.success() {
-                let stdout_str = String::from_utf8_lossy(&output_val.stdout);
+                let stdout_str = process::decode_output(&output_val.stdout);
                 let unique_base_names: HashSet<String> = stdout_str
                     .lines()
                     // .skip(1) // Removed skip(1) as rpm -qa has no header
@@ -293,113 +626,188 @@ async fn list_installed_packages(app: tauri::AppHandle) -> Result<Vec<Displayabl
                 
                 let mut packages: Vec<DisplayablePackage> = unique_base_names
                     .into_iter()
-                    .map(|name| DisplayablePackage { name })
+                    .map(|name| DisplayablePackage { name, kind: DependencyKind::Package, module_provider: None })
                     .collect();
                 
                 packages.sort_by(|a, b| a.name.cmp(&b.name));
                 Ok(packages)
             } else {
-                let stderr_str = String::from_utf8_lossy(&output_val.stderr);
+                let stderr_str = process::decode_output(&output_val.stderr);
                 Err(format!(
                     "rpm -qa command failed with status {}: {}", // Correctly blames rpm -qa
                     output_val.status.code().unwrap_or(-1),
                     stderr_str
-                ))
+                ).into())
             }
         }
-        Err(e) => Err(format!("Failed to execute rpm -qa command: {}", e)), // Correctly blames rpm -qa
+        Err(e) => Err(format!("Failed to execute rpm -qa command: {}", e).into()), // Correctly blames rpm -qa
     }
 }
 
 #[tauri::command]
-async fn list_user_installed_packages(app: tauri::AppHandle, force_refresh: bool) -> Result<Vec<UserPackageWithDependencies>, String> {
-    println!(
+async fn list_user_installed_packages(
+    app: tauri::AppHandle,
+    force_refresh: bool,
+    include_internal: Option<bool>, // rpmlib()/config() entries are hidden unless this is true
+) -> Result<Vec<UserPackageWithDependencies>, NebulaError> {
+    let cache = app.state::<cache::CacheService>();
+    // A stale cache is still answered right away; the refresh runs behind it.
+    let stale = !force_refresh && cache.is_stale(settings::cache_max_age(&app)?)?;
+    let packages = fetch_user_installed_packages(&app, &cache, force_refresh).await?;
+    if stale {
+        refresh_user_packages_in_background(&app);
+    }
+    let mut packages = if include_internal.unwrap_or(false) { packages } else { without_internal_dependencies(packages) };
+    appstream::attach(&app, &mut packages).await;
+    Ok(packages)
+}
+
+fn without_internal_dependencies(packages: Vec<UserPackageWithDependencies>) -> Vec<UserPackageWithDependencies> {
+    packages
+        .into_iter()
+        .map(|mut pkg| {
+            pkg.dependencies.retain(|dep| dep.kind != DependencyKind::Internal);
+            pkg
+        })
+        .collect()
+}
+
+// Rebuilds the package cache off the request path, so a listing never waits on the `rpm -qR`
+// calls, and emits `cache-refreshed` with the new list as the listing returns it by default.
+// A refresh already under way is not started twice.
+fn refresh_user_packages_in_background(app: &tauri::AppHandle) {
+    if !app.state::<cache::CacheService>().start_refresh() {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let cache = app.state::<cache::CacheService>();
+        match fetch_user_installed_packages(&app, &cache, true).await {
+            Ok(packages) => {
+                let mut packages = without_internal_dependencies(packages);
+                appstream::attach(&app, &mut packages).await;
+                if let Err(e) = app.emit("cache-refreshed", packages) {
+                    warn!("Failed to emit cache-refreshed event: {}", e);
+                }
+            }
+            Err(e) => warn!("Background package cache refresh failed: {}", e),
+        }
+        cache.finish_refresh();
+    });
+}
+
+// The cache always holds the full dependency lists; filtering happens on the way out.
+async fn fetch_user_installed_packages(
+    app: &tauri::AppHandle,
+    cache: &cache::CacheService,
+    force_refresh: bool,
+) -> Result<Vec<UserPackageWithDependencies>, String> {
+    info!(
         "Attempting to list user-installed packages. Force refresh: {}",
         force_refresh
     );
-    let cache_path = get_cache_path(&app)?;
-    println!("Cache path: {:?}", cache_path);
-
     if !force_refresh {
-        if let Some(cached_data) = load_cache(&app)? {
-            println!("Returning cached user package data.");
+        if let Some(cached_data) = cache.load_packages()? {
+            info!("Returning cached user package data.");
             return Ok(cached_data);
         }
     }
-    println!("Cache not used or refresh forced. Fetching fresh data...");
+    info!("Cache not used or refresh forced. Fetching fresh data...");
+
+    // Debian/Ubuntu and Arch: dpkg and apt-mark, or pacman and expac, answer everything below
+    // in two calls.
+    let native_packages = app.state::<services::BackendRegistry>().list().await?;
+    if let Some(packages) = native_packages {
+        if let Err(e) = cache.save_packages(&packages) {
+            warn!("Failed to save updated cache: {}", e);
+        }
+        return Ok(packages);
+    }
 
     let shell = app.shell();
 
-    // Step 1: Get all actually installed packages (our source of truth for "is it installed?")
-    let rpm_qa_output_result = shell
-        .command("rpm")
-        .args(["-qa", "--queryformat", "%{NAME}\n"]) // Get only base names
-        .output()
-        .await;
+    // Read the rpmdb in-process when librpm can be loaded: one pass instead of batched `rpm -q`
+    // calls below.
+    let headers: Option<HashMap<String, rpmdb::InstalledHeader>> = rpmdb::installed_packages()
+        .await
+        .map(|packages| packages.into_iter().map(|header| (header.name.clone(), header)).collect());
 
-    let actually_installed_set: HashSet<String> = match rpm_qa_output_result {
-        Ok(output) => {
-            if output.status.success() {
-                String::from_utf8_lossy(&output.stdout)
-                    .lines()
-                    .map(str::trim)
-                    .filter(|s| !s.is_empty())
-                    .map(String::from)
-                    .collect()
-            } else {
-                return Err(format!(
-                    "Failed to get `rpm -qa` list: {}",
-                    String::from_utf8_lossy(&output.stderr)
-                ));
+    // Step 1: Get all actually installed packages (our source of truth for "is it installed?"),
+    // along with the category of each, in one pass
+    let categories: Arc<HashMap<String, PackageCategory>> = Arc::new(if let Some(headers) = &headers {
+        headers.iter().map(|(name, header)| (name.clone(), category_from_group(&header.group))).collect()
+    } else {
+        let rpm_qa_output_result = shell
+            .command("rpm")
+            .args(["-qa", "--queryformat", "%{NAME}|%{GROUP}\n"]) // Base names and their groups
+            .output()
+            .await;
+
+        match rpm_qa_output_result {
+            Ok(output) => {
+                if output.status.success() {
+                    parse_rpm_categories(&process::decode_output(&output.stdout))
+                } else {
+                    return Err(format!(
+                        "Failed to get `rpm -qa` list: {}",
+                        process::decode_output(&output.stderr)
+                    ));
+                }
             }
+            Err(e) => return Err(format!("Shell command error for `rpm -qa`: {}", e)),
         }
-        Err(e) => return Err(format!("Shell command error for `rpm -qa`: {}", e)),
-    };
+    });
+    let actually_installed_set: HashSet<String> = categories.keys().cloned().collect();
     if actually_installed_set.is_empty() {
-        println!("`rpm -qa` returned no packages. Assuming no user packages can be listed.");
+        info!("`rpm -qa` returned no packages. Assuming no user packages can be listed.");
          let empty_list = Vec::new();
-        if let Err(e) = save_cache(&app, &empty_list) {
-            eprintln!("Warning: Failed to save empty cache (rpm -qa was empty): {}", e);
+        if let Err(e) = cache.save_packages(&empty_list) {
+            warn!("Failed to save empty cache (rpm -qa was empty): {}", e);
         }
         return Ok(empty_list);
     }
 
 
     // Step 2: Get packages marked as user-installed by DNF
-    let dnf_history_output_result = shell
-        .command("dnf")
-        .args([
-            "repoquery",         // Changed from "history"
-            "--userinstalled",   // Argument for repoquery
-            "--quiet",
-        ])
-        .output()
-        .await;
+    // (zypper's AutoInstalled list on openSUSE)
+    let dnf_user_packages_list: Vec<String> = if settings::package_backend(app)? == settings::PackageBackend::Zypper {
+        zypper::user_installed_names(&actually_installed_set)?
+    } else {
+        let dnf_history_output_result = shell
+            .command("dnf")
+            .args([
+                "repoquery",         // Changed from "history"
+                "--userinstalled",   // Argument for repoquery
+                "--quiet",
+            ])
+            .output()
+            .await;
 
-    let dnf_user_packages_list: Vec<String> = match dnf_history_output_result {
-        Ok(output_val) => {
-            if output_val.status.success() {
-                String::from_utf8_lossy(&output_val.stdout)
-                    .lines()
-                    .map(str::trim)
-                    .filter(|line| !line.is_empty() && !line.starts_with("Last metadata expiration check:"))
-                    .map(String::from)
-                    .collect()
-            } else {
-                return Err(format!(
-                    "Failed to get user-installed packages list (dnf): {}",
-                    String::from_utf8_lossy(&output_val.stderr)
-                ));
+        match dnf_history_output_result {
+            Ok(output_val) => {
+                if output_val.status.success() {
+                    process::decode_output(&output_val.stdout)
+                        .lines()
+                        .map(str::trim)
+                        .filter(|line| !line.is_empty() && !line.starts_with("Last metadata expiration check:"))
+                        .map(String::from)
+                        .collect()
+                } else {
+                    return Err(format!(
+                        "Failed to get user-installed packages list (dnf): {}",
+                        process::decode_output(&output_val.stderr)
+                    ));
+                }
             }
+            Err(e) => return Err(format!("Shell command error for user-installed packages list (dnf): {}", e)),
         }
-        Err(e) => return Err(format!("Shell command error for user-installed packages list (dnf): {}", e)),
     };
 
     if dnf_user_packages_list.is_empty() {
-        println!("`dnf repoquery userinstalled` returned no packages.");
+        info!("`dnf repoquery userinstalled` returned no packages.");
         let empty_list = Vec::new();
-        if let Err(e) = save_cache(&app, &empty_list) {
-            eprintln!("Warning: Failed to save empty cache (dnf repoquery was empty): {}", e);
+        if let Err(e) = cache.save_packages(&empty_list) {
+            warn!("Failed to save empty cache (dnf repoquery was empty): {}", e);
         }
         return Ok(empty_list);
     }
@@ -409,12 +817,17 @@ async fn list_user_installed_packages(app: tauri::AppHandle, force_refresh: bool
     for pkg_name_from_dnf in dnf_user_packages_list {
         // dnf history userinstalled might give package.arch or just name.
         // rpm -qa --queryformat %{NAME} gives just the name.
-        // We need to compare them. extract_base_package_name can help.
-        let base_name_from_dnf = extract_base_package_name(&pkg_name_from_dnf);
+        // We need to compare them. extract_base_package_name can help, but exact names (zypper's
+        // list) are kept as they are: "libxml2-2" is a name, not "libxml2" at version 2.
+        let base_name_from_dnf = if actually_installed_set.contains(&pkg_name_from_dnf) {
+            pkg_name_from_dnf.clone()
+        } else {
+            extract_base_package_name(&pkg_name_from_dnf)
+        };
         if actually_installed_set.contains(&base_name_from_dnf) {
             packages_to_process.push(base_name_from_dnf); // Add the base name for consistency
         } else {
-            println!("Package '{}' (base: '{}') from DNF's userinstalled list is not in 'rpm -qa' output. Skipping.", pkg_name_from_dnf, base_name_from_dnf);
+            info!("Package '{}' (base: '{}') from DNF's userinstalled list is not in 'rpm -qa' output. Skipping.", pkg_name_from_dnf, base_name_from_dnf);
         }
     }
      // Deduplicate after base name extraction, as different arch/versions might resolve to same base name
@@ -422,83 +835,85 @@ async fn list_user_installed_packages(app: tauri::AppHandle, force_refresh: bool
 
 
     if unique_packages_to_process.is_empty() {
-        println!("No user-installed packages remain after cross-referencing with rpm -qa.");
+        info!("No user-installed packages remain after cross-referencing with rpm -qa.");
         let empty_list = Vec::new();
-         if let Err(e) = save_cache(&app, &empty_list) {
-            eprintln!("Warning: Failed to save empty cache (no packages after filter): {}", e);
+         if let Err(e) = cache.save_packages(&empty_list) {
+            warn!("Failed to save empty cache (no packages after filter): {}", e);
         }
         return Ok(empty_list);
     }
 
 
-    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_RPM_QUERIES));
-    let mut tasks = Vec::new();
+    // Now process only the filtered and confirmed installed packages: straight from the rpmdb
+    // pass when there was one, otherwise through rpm, many packages per invocation
+    let mut user_packages_with_deps = Vec::new();
+    let mut packages_to_query = Vec::new();
+    // Unparsed requirements are logged once per refresh, not once per package
+    let mut rpmdb_unparsed = Vec::new();
+    for package_name_str in unique_packages_to_process {
+        match headers.as_ref().and_then(|headers| headers.get(&package_name_str)) {
+            Some(header) => {
+                let (dependencies, unparsed) = parse_rpm_requires_output(&header.requires.join("\n"), &package_name_str);
+                let incomplete = !unparsed.is_empty();
+                rpmdb_unparsed.extend(unparsed_for(&package_name_str, unparsed));
+                user_packages_with_deps.push(UserPackageWithDependencies {
+                    incomplete,
+                    category: categories.get(&package_name_str).cloned().unwrap_or_default(),
+                    installed_size: Some(header.size),
+                    appstream: None,
+                    name: package_name_str,
+                    dependencies,
+                });
+            }
+            None => packages_to_query.push(package_name_str),
+        }
+    }
 
-    // Now process only the filtered and confirmed installed packages
-    for package_name_str in unique_packages_to_process { // Iterate over the filtered list
+    let semaphore = Arc::new(Semaphore::new(settings::refresh_concurrency(app)?));
+    let mut tasks = Vec::new();
+    for batch in packages_to_query.chunks(RPM_QUERY_BATCH_SIZE) {
         let app_clone = app.clone();
         let sem_clone = semaphore.clone();
-        let task = tokio::spawn(async move {
+        let categories_clone = categories.clone();
+        let batch = batch.to_vec();
+        tasks.push(tokio::spawn(async move {
             let _permit = sem_clone.acquire().await.unwrap();
-            let shell_clone = app_clone.shell();
-
-            let deps_output_result = shell_clone
-                .command("rpm")
-                .args(["-qR", &package_name_str])
-                .output()
-                .await;
-
-            let dependencies = match deps_output_result {
-                Ok(dep_val) => {
-                    if dep_val.status.success() {
-                        let dep_stdout_str = String::from_utf8_lossy(&dep_val.stdout);
-                        parse_rpm_requires_output(&dep_stdout_str, &package_name_str)
-                    } else {
-                        // Log error but continue, package might not have deps or is virtual
-                        // eprintln!(
-                        //     "rpm -qR for {} failed: Status {}, Stderr: {}",
-                        //     package_name_str, 
-                        //     dep_val.status.code().unwrap_or(-1),
-                        //     String::from_utf8_lossy(&dep_val.stderr).trim()
-                        // );
-                        Vec::new()
-                    }
-                }
-                Err(_e) => {
-                    // eprintln!("Failed to execute rpm -qR for {}: {}", package_name_str, e);
-                    Vec::new()
-                }
-            };
-            
-            // Sort dependencies by name for consistent display
-            let mut sorted_deps = dependencies;
-            sorted_deps.sort_by(|a, b| a.name.cmp(&b.name));
-
-            // Get category
-            let category = get_package_category(&shell_clone, &package_name_str).await;
-
-            UserPackageWithDependencies {
-                name: package_name_str,
-                dependencies: sorted_deps,
-                category,
-            }
-        });
-        tasks.push(task);
+            query_rpm_batch(&app_clone, &batch, &categories_clone).await
+        }));
     }
-
-    let mut user_packages_with_deps = Vec::new();
+    let mut rpm_unparsed = Vec::new();
     for task in tasks {
         match task.await {
-            Ok(pkg_with_deps) => user_packages_with_deps.push(pkg_with_deps),
-            Err(e) => eprintln!("Task join error: {}", e), // Log error and continue
+            Ok((packages, unparsed)) => {
+                user_packages_with_deps.extend(packages);
+                rpm_unparsed.extend(unparsed);
+            }
+            Err(e) => error!("Task join error: {}", e), // Log error and continue
         }
     }
-    
+    for (parser, mut unparsed) in [("rpmdb requires", rpmdb_unparsed), ("rpm -qR", rpm_unparsed)] {
+        // batches finish in any order; sorted, the same failures make the same record
+        unparsed.sort();
+        parse_failures::record(app, parser, &unparsed).await;
+    }
+
     // Sort the final list of packages by name before caching and returning
     user_packages_with_deps.sort_by(|a, b| a.name.cmp(&b.name));
 
-    if let Err(e) = save_cache(&app, &user_packages_with_deps) {
-        eprintln!("Warning: Failed to save updated cache: {}", e);
+    // Map perl(...)/python3dist(...) style requirements to the packages providing them
+    match provides::fetch_module_providers(app).await {
+        Ok(providers) => {
+            for dep in user_packages_with_deps.iter_mut().flat_map(|pkg| pkg.dependencies.iter_mut()) {
+                if dep.kind == DependencyKind::LanguageModule {
+                    dep.module_provider = provides::resolve_module(&dep.name, &providers);
+                }
+            }
+        }
+        Err(e) => warn!("Could not resolve language module dependencies: {}", e),
+    }
+
+    if let Err(e) = cache.save_packages(&user_packages_with_deps) {
+        warn!("Failed to save updated cache: {}", e);
         // Depending on desired behavior, you might choose to return an error here
         // return Err(format!("Failed to save cache: {}", e));
     }
@@ -506,220 +921,264 @@ async fn list_user_installed_packages(app: tauri::AppHandle, force_refresh: bool
 }
 
 #[tauri::command]
-async fn manage_package_update(app: tauri::AppHandle, package_name: String) -> Result<PackageOperationResult, String> {
-    println!("Attempting to update package: {}", package_name);
-    let shell = app.shell();
-
-    // Command: pkexec dnf update <package_name> -y
-    let output_result = shell
-        .command("pkexec") // Use pkexec for privilege escalation
-        .args(["dnf", "update", &package_name, "--assumeyes"])
-        .output()
-        .await;
-
-    match output_result {
-        Ok(output) => {
-            let stdout_str = String::from_utf8_lossy(&output.stdout).into_owned();
-            let stderr_str = String::from_utf8_lossy(&output.stderr).into_owned();
-            let full_details = format!("STDOUT:\n{}\nSTDERR:\n{}", stdout_str, stderr_str);
-
-            if output.status.success() {
-                println!("Package '{}' updated successfully.", package_name);
-                Ok(PackageOperationResult {
-                    success: true,
-                    message: format!("Package '{}' updated successfully.", package_name),
-                    details: Some(full_details),
-                })
+async fn manage_package_update(
+    app: tauri::AppHandle,
+    package_name: String,
+    confirmed: Option<bool>,
+    options: Option<options::AdvancedOptions>,
+    offline: Option<bool>, // stage the update for the next boot instead
+) -> Result<PackageOperationResult, NebulaError> {
+    info!("Attempting to update package: {}", package_name);
+    let offline = offline.unwrap_or(false);
+    let backends = app.state::<services::BackendRegistry>();
+    if backends.selected()? != settings::PackageBackend::Dnf {
+        if options.is_some() || offline {
+            return Err("Advanced options and offline updates need the dnf backend.".into());
+        }
+        let preview = settings::requires_preview(&app, settings::GuardedAction::Update, confirmed.unwrap_or(false))?;
+        if let Some(result) = backends.package_command(packagekit::PackageAction::Update, std::slice::from_ref(&package_name), false, preview).await? {
+            return Ok(result);
+        }
+    }
+    let mut update_plan = plan::update_package(&package_name, &pins::pinned_packages(&app)?)?;
+    if offline {
+        update_plan = plan::offline(&update_plan, probes::is_dnf5(&app).await)?;
+    }
+    let update_plan = options::apply_options(&update_plan, options.as_ref())?;
+    if settings::requires_preview(&app, settings::GuardedAction::Update, confirmed.unwrap_or(false))? {
+        let changelog = updates::fetch_update_changelog(&app, std::slice::from_ref(&package_name)).await;
+        return Ok(settings::preview_result(format!("Review the changes in '{}', then confirm the update.", package_name), changelog));
+    }
+    let _busy = app.state::<services::OperationManager>().begin(format!("Updating {}", package_name));
+    info!("Executing command: {}", update_plan.command_line());
+    let out = process::run_plan_streaming(&app, &update_plan, "update_package").await?;
+    let full_details = format!("STDOUT:\n{}\nSTDERR:\n{}", out.stdout, out.stderr);
+    let mut result = if out.success {
+        info!("Package '{}' updated successfully.", package_name);
+        PackageOperationResult {
+            success: true,
+            message: if offline {
+                format!("The update of '{}' is staged and will be applied on the next reboot.", package_name)
             } else {
-                let err_msg = format!(
-                    "Failed to update package '{}'. Exit code: {}.\n{}",
-                    package_name,
-                    output.status.code().unwrap_or(-1),
-                    if stderr_str.is_empty() { &stdout_str } else { &stderr_str }
-                );
-                eprintln!("{}", err_msg);
-                Ok(PackageOperationResult {
-                    success: false,
-                    message: format!("Failed to update package '{}'.", package_name),
-                    details: Some(full_details),
-                })
-            }
+                format!("Package '{}' updated successfully.", package_name)
+            },
+            details: Some(full_details),
+            confirmation_required: false,
+            simulated: out.simulated,
+            reboot_required: offline && !out.simulated,
         }
-        Err(e) => {
-            let err_msg = format!("Error executing update command for '{}': {}", package_name, e);
-            eprintln!("{}", err_msg);
-            Err(err_msg)
+    } else {
+        error!("Failed to update package '{}'. Exit code: {}.\n{}", package_name, out.code, out.error_text());
+        PackageOperationResult {
+            success: false,
+            message: format!("Failed to update package '{}'.", package_name),
+            details: Some(full_details),
+            confirmation_required: false,
+            simulated: out.simulated,
+            reboot_required: false,
         }
+    };
+
+    // A staged update has not changed anything yet.
+    if offline {
+        return Ok(result);
+    }
+    restarts::check_reboot(&app, &mut result, std::slice::from_ref(&package_name)).await;
+    if result.success && kernels::is_kernel_package(&package_name) {
+        kernels::append_boot_menu_report(&app, &mut result).await;
     }
+    Ok(result)
 }
 
 #[tauri::command]
-async fn execute_package_uninstall(app: tauri::AppHandle, args: UninstallArgs) -> Result<PackageOperationResult, String> {
-    println!("Executing uninstall for package: {}, Mode: {:?}, Cleanup: {}", args.package_name, args.mode, args.cleanup_orphans);
-    let shell = app.shell();
+async fn execute_package_uninstall(
+    app: tauri::AppHandle,
+    mut args: UninstallArgs,
+    options: Option<options::AdvancedOptions>,
+) -> Result<PackageOperationResult, NebulaError> {
+    args.resolve_mode(&app)?;
+    info!("Executing uninstall for package: {}, Mode: {:?}, Cleanup: {}", args.target(), args.mode(), args.cleanup_orphans);
+    // Settings may require the matching dry run to be seen before anything is removed.
+    let preview = matches!(args.mode(), UninstallMode::Safe | UninstallMode::Force)
+        && settings::requires_preview(&app, settings::GuardedAction::Uninstall, args.confirmed)?;
+    if preview {
+        args.mode = Some(match args.mode() {
+            UninstallMode::Force => UninstallMode::DryRunForce,
+            _ => UninstallMode::DryRunSafe,
+        });
+    }
+    let backends = app.state::<services::BackendRegistry>();
+    if backends.selected()? != settings::PackageBackend::Dnf {
+        if options.is_some() || args.offline {
+            return Err("Advanced options and offline removals need the dnf backend.".into());
+        }
+        // a preview is the dry run, which package_command runs itself
+        let dry_run = args.backend_dry_run()? && !preview;
+        if let Some(result) = backends.package_command(packagekit::PackageAction::Remove, &args.packages, dry_run, preview).await? {
+            return Ok(result);
+        }
+    }
+    let _busy = app.state::<services::OperationManager>().begin(format!("Removing {}", args.target()));
     let mut final_message = String::new();
     let mut final_details = String::new();
     let mut overall_success = true;
 
-    let (cmd_name, cmd_args, _is_privileged) = match args.mode { // _is_privileged was unused
-        UninstallMode::Safe => ("pkexec", vec!["dnf".to_string(), "remove".to_string(), args.package_name.clone(), "--assumeyes".to_string()], true),
-        UninstallMode::Force => ("pkexec", vec!["rpm".to_string(), "-e".to_string(), "--nodeps".to_string(), args.package_name.clone()], true),
-        UninstallMode::DryRunSafe => ("dnf", vec!["remove".to_string(), args.package_name.clone(), "--assumeno".to_string()], false),
-        UninstallMode::DryRunForce => ("rpm", vec!["-e".to_string(), "--nodeps".to_string(), args.package_name.clone(), "--test".to_string()], false),
-    };
-
-    println!("Executing command: {} with args: {:?}", cmd_name, cmd_args);
-
-    let output_result = shell
-        .command(cmd_name)
-        .args(&cmd_args)
-        .output()
-        .await;
-
-    match output_result {
-        Ok(output) => {
-            let stdout_str = String::from_utf8_lossy(&output.stdout).into_owned();
-            let stderr_str = String::from_utf8_lossy(&output.stderr).into_owned();
-            let details_for_this_step = format!("STDOUT:\n{}\nSTDERR:\n{}", stdout_str, stderr_str);
-
-            if output.status.success() {
-                let success_msg = format!(
-                    "{} operation for '{}' completed successfully.",
-                    match args.mode {
-                        UninstallMode::DryRunSafe | UninstallMode::DryRunForce => "Dry run",
-                        _ => "Uninstall"
-                    },
-                    args.package_name
-                );
-                println!("{}", success_msg);
-                final_message.push_str(&success_msg);
-                final_details.push_str(&details_for_this_step);
-                if matches!(args.mode, UninstallMode::DryRunSafe | UninstallMode::DryRunForce) {
-                    final_details = stdout_str; // For dry run, stdout is usually the most relevant detail
-                }
-            } else {
-                overall_success = false;
-                let err_msg = format!(
-                    "Failed {} for package '{}'. Exit code: {}.\nDetails:\n{}",
-                    match args.mode {
-                        UninstallMode::DryRunSafe | UninstallMode::DryRunForce => "dry run",
-                        _ => "uninstall"
-                    },
-                    args.package_name,
-                    output.status.code().unwrap_or(-1),
-                    if stderr_str.is_empty() { &stdout_str } else { &stderr_str }
-                );
-                eprintln!("{}", err_msg);
-                final_message.push_str(&format!(
-                    "Failed {} for package '{}'.",
-                     match args.mode {
-                        UninstallMode::DryRunSafe | UninstallMode::DryRunForce => "dry run",
-                        _ => "uninstall"
-                    },
-                    args.package_name
-                ));
-                final_details.push_str(&details_for_this_step);
-            }
+    // The removal first, then autoremove if orphan cleanup applies, with one authentication for both.
+    let mut plans = plan::uninstall(&args)?;
+    if args.staged() {
+        if args.cleanup_orphans {
+            return Err("Orphan cleanup cannot be staged for the next boot; run it after the reboot.".into());
         }
+        let dnf5 = probes::is_dnf5(&app).await;
+        plans = plans.iter().map(|removal| plan::offline(removal, dnf5)).collect::<Result<_, _>>()?;
+    }
+    let plans = plans
+        .iter()
+        .map(|plan| options::apply_options(plan, options.as_ref()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let captures = match process::run_plans_streaming(&app, &plans, "uninstall").await {
+        Ok(captures) => captures,
         Err(e) => {
             overall_success = false;
-            let err_msg = format!("Error executing command for '{}': {}", args.package_name, e);
-            eprintln!("{}", err_msg);
+            let err_msg = format!("Error executing command for '{}': {}", args.target(), e);
+            error!("{}", err_msg);
             final_message = err_msg.clone();
             final_details = err_msg;
+            Vec::new()
+        }
+    };
+    let simulated = captures.first().is_some_and(|capture| capture.simulated);
+
+    if let Some(output) = captures.first() {
+        let details_for_this_step = format!("STDOUT:\n{}\nSTDERR:\n{}", output.stdout, output.stderr);
+        let explicit_dry_run = matches!(args.mode(), UninstallMode::DryRunSafe | UninstallMode::DryRunForce);
+        let succeeded = if explicit_dry_run { process::dry_run_succeeded(output.code, &output.stdout, &output.stderr) } else { output.success };
+
+        if succeeded {
+            let success_msg = format!(
+                "{} operation for '{}' completed successfully.",
+                if explicit_dry_run { "Dry run" } else { "Uninstall" },
+                args.target()
+            );
+            info!("{}", success_msg);
+            final_message.push_str(&success_msg);
+            final_details.push_str(&details_for_this_step);
+            if explicit_dry_run {
+                final_details = output.stdout.clone(); // For dry run, stdout is usually the most relevant detail
+            }
+        } else {
+            overall_success = false;
+            let step = if explicit_dry_run { "dry run" } else { "uninstall" };
+            error!(
+                "Failed {} for package '{}'. Exit code: {}.\nDetails:\n{}",
+                step,
+                args.target(),
+                output.code,
+                output.error_text()
+            );
+            final_message.push_str(&format!("Failed {} for package '{}'.", step, args.target()));
+            final_details.push_str(&details_for_this_step);
         }
     }
 
-    // Handle cleanup_orphans for Safe mode after successful uninstall
-    if overall_success && matches!(args.mode, UninstallMode::Safe) && args.cleanup_orphans {
-        println!("Attempting to cleanup orphans after uninstalling '{}'", args.package_name);
+    // Orphan cleanup, which only ran if the removal succeeded.
+    if plans.len() > 1 && overall_success {
         final_details.push_str("\n\n--- Autoremove (Orphans) ---\n");
-
-        let autoremove_output_result = shell
-            .command("pkexec")
-            .args(["dnf", "autoremove", "--assumeyes"])
-            .output()
-            .await;
-
-        match autoremove_output_result {
-            Ok(output) => {
-                let stdout_str = String::from_utf8_lossy(&output.stdout).into_owned();
-                let stderr_str = String::from_utf8_lossy(&output.stderr).into_owned();
-                let autoremove_details = format!("STDOUT:\n{}\nSTDERR:\n{}", stdout_str, stderr_str);
-                final_details.push_str(&autoremove_details);
-
-                if output.status.success() {
-                    println!("Orphan cleanup successful.");
+        match captures.get(1) {
+            Some(output) => {
+                final_details.push_str(&format!("STDOUT:\n{}\nSTDERR:\n{}", output.stdout, output.stderr));
+                if output.success {
+                    info!("Orphan cleanup successful.");
                     final_message.push_str("\nOrphan cleanup successful.");
                 } else {
                     overall_success = false; // Mark overall as failed if autoremove fails
-                    let err_msg = format!(
+                    error!(
                         "Orphan cleanup failed after uninstalling '{}'. Exit code: {}.\n{}",
-                        args.package_name,
-                        output.status.code().unwrap_or(-1),
-                        if stderr_str.is_empty() { &stdout_str } else { &stderr_str }
+                        args.target(),
+                        output.code,
+                        output.error_text()
                     );
-                    eprintln!("{}", err_msg);
                     final_message.push_str("\nOrphan cleanup failed.");
                 }
             }
-            Err(e) => {
+            None => {
                 overall_success = false;
-                let err_msg = format!("Error executing dnf autoremove: {}", e);
-                eprintln!("{}", err_msg);
-                final_message.push_str(&format!("\nError during orphan cleanup: {}", e));
-                final_details.push_str(&format!("\nError during orphan cleanup: {}", e));
+                error!("Orphan cleanup did not run after uninstalling '{}'.", args.target());
+                final_message.push_str("\nOrphan cleanup did not run.");
             }
         }
     }
 
-    // After all operations, including potential autoremove
-    if overall_success && !matches!(args.mode, UninstallMode::DryRunSafe | UninstallMode::DryRunForce) {
-        println!("Uninstall successful, attempting to clear package cache.");
+    // A staged removal has not changed anything yet.
+    if overall_success && args.staged() {
+        final_message.push_str(&format!("\nThe removal of {} is staged and will be applied on the next reboot.", args.target()));
+    } else if overall_success && !matches!(args.mode(), UninstallMode::DryRunSafe | UninstallMode::DryRunForce) {
+        info!("Uninstall successful, updating package cache.");
         final_message.push_str(&format!("
-Uninstall of {} successful.", args.package_name)); // Add confirmation to user message
-        match get_cache_path(&app) {
-            Ok(cache_path) => {
-                if cache_path.exists() {
-                    if let Err(e) = fs::remove_file(&cache_path) {
-                        let cache_err_msg = format!("
-Warning: Failed to delete package cache file at {:?}: {}", cache_path, e);
-                        eprintln!("{}", cache_err_msg);
-                        final_message.push_str(&cache_err_msg);
-                        // Don't make the whole operation fail for this, but log it.
-                    } else {
-                        println!("Successfully deleted package cache file.");
-                        final_message.push_str("
-Package cache cleared for next refresh.");
-                    }
-                } else {
-                    println!("Package cache file not found, no deletion needed.");
-                     final_message.push_str("
-Package cache was not present.");
+Uninstall of {} successful.", args.target())); // Add confirmation to user message
+        match app.state::<cache::CacheService>().update_after_removal(&app).await {
+            Ok(note) => {
+                info!("Updated package cache in place.");
+                if !note.is_empty() {
+                    final_message.push_str(&format!("\n{}", note));
                 }
             }
             Err(e) => {
-                let cache_path_err_msg = format!("
-Warning: Failed to get cache path for deletion: {}", e);
-                eprintln!("{}", cache_path_err_msg);
-                final_message.push_str(&cache_path_err_msg);
+                let cache_err_msg = format!("\nWarning: Failed to update package cache: {}", e);
+                error!("{}", cache_err_msg);
+                final_message.push_str(&cache_err_msg);
+                // Don't make the whole operation fail for this, but log it.
             }
         }
     }
 
-    Ok(PackageOperationResult {
+    if preview {
+        final_message.push_str("\nReview the dry run above, then confirm to uninstall.");
+    }
+    let mut result = PackageOperationResult {
         success: overall_success,
         message: final_message.trim().to_string(), // Trim leading/trailing newlines
         details: Some(final_details),
-    })
+        confirmation_required: preview,
+        simulated,
+        reboot_required: overall_success && args.staged() && !simulated,
+    };
+    if matches!(args.mode(), UninstallMode::Safe | UninstallMode::Force) && !args.staged() {
+        restarts::check_reboot(&app, &mut result, &args.packages).await;
+    }
+    if overall_success && matches!(args.mode(), UninstallMode::Safe | UninstallMode::Force) && args.packages.iter().any(|package| kernels::is_kernel_package(package)) {
+        kernels::append_boot_menu_report(&app, &mut result).await;
+    }
+    Ok(result)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let is_daemon = daemon::is_daemon();
+    if !is_daemon && daemon::attach_to_daemon() {
+        return;
+    }
+    let activated_listener = daemon::activated_listener();
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_shell::init())
-        .setup(|_app| { 
+        .setup(move |app| {
+            if let Err(e) = logging::init(app.handle()) {
+                eprintln!("Warning: Logging to a file is unavailable: {}", e);
+            }
+            app.manage(metrics::MetricsState::default());
+            app.manage(dbus_status::DbusStatusState::default());
+            services::manage(app)?;
+            tauri::async_runtime::spawn(dbus_status::start(app.handle().clone()));
+            tauri::async_runtime::spawn(maintenance::run_scheduler(app.handle().clone()));
+            tauri::async_runtime::spawn(pins::run_review_reminders(app.handle().clone()));
+            tauri::async_runtime::spawn(rpc::start(app.handle().clone(), activated_listener));
+            tauri::async_runtime::spawn(probes::probe_on_version_change(app.handle().clone()));
+            tauri::async_runtime::spawn(watcher::watch_rpmdb(app.handle().clone()));
+            tauri::async_runtime::spawn(appstream::preload(app.handle().clone()));
+            if !is_daemon {
+                daemon::show_main_window(app.handle())?;
+            }
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -727,10 +1186,162 @@ pub fn run() {
             list_installed_packages, 
             list_user_installed_packages,
             manage_package_update,
-            execute_package_uninstall
+            execute_package_uninstall,
+            metrics::set_metrics_endpoint,
+            metrics::get_metrics_endpoint_status,
+            package_info::get_package_details,
+            package_info::get_package_files,
+            package_info::get_package_scriptlets,
+            package_info::verify_package,
+            package_info::list_unverified_packages,
+            maintenance::get_maintenance_windows,
+            maintenance::set_maintenance_windows,
+            maintenance::get_maintenance_journal,
+            rollout::plan_staged_rollout,
+            rollout::apply_rollout_phase_one,
+            rollout::apply_rollout_phase_two,
+            snapshot::get_snapshot_support,
+            snapshot::get_post_update_checks,
+            snapshot::set_post_update_checks,
+            snapshot::update_with_snapshot,
+            known_bad::get_known_bad_subscriptions,
+            known_bad::set_known_bad_subscriptions,
+            known_bad::get_personal_known_bad,
+            known_bad::set_personal_known_bad,
+            known_bad::check_known_bad_updates,
+            repoquery::run_advanced_query,
+            repos::list_repositories,
+            repos::set_repository_enabled,
+            repos::add_repository,
+            repos::get_rpmfusion_status,
+            repos::enable_rpmfusion,
+            restarts::list_service_restarts,
+            restarts::apply_service_restarts,
+            themes::discover_desktop_extras,
+            gpg_keys::list_gpg_keys,
+            gpg_keys::get_gpg_key_details,
+            gpg_keys::remove_gpg_key,
+            gpg_keys::import_gpg_key,
+            hardware::suggest_hardware_drivers,
+            codecs::resolve_codec,
+            install::install_packages,
+            packagekit::packagekit_package_action,
+            packagekit::cancel_packagekit_operation,
+            parse_failures::list_parse_failures,
+            parse_failures::clear_parse_failures,
+            probes::get_syntax_probe_report,
+            probes::rerun_syntax_probes,
+            dnf5daemon::dnf5daemon_package_action,
+            dnf5daemon::query_packages_native,
+            installroot::describe_install_root,
+            installroot::install_into_root,
+            installroot::list_root_packages,
+            installroot::remove_from_root,
+            kickstart::generate_kickstart,
+            roles::apply_role,
+            roles::evaluate_role,
+            roles::list_roles,
+            flathub::search_flathub,
+            cache::search_package_cache,
+            software::list_all_software,
+            features::get_feature_matrix,
+            zypper::zypper_package_action,
+            zypper::zypper_search,
+            zypper::zypper_list_orphans,
+            apt::apt_package_action,
+            apt::apt_list_installed,
+            apt::apt_list_orphans,
+            pacman::pacman_package_action,
+            pacman::pacman_list_explicit,
+            pacman::pacman_list_orphans,
+            userspace::list_userspace_packages,
+            userspace::update_userspace_packages,
+            orchestrator::update_everything,
+            logging::get_recent_logs,
+            logging::open_log_file,
+            app_operations::list_app_operations,
+            queue::apply_transaction,
+            flatpak::add_flatpak_remote,
+            flatpak::install_flatpak,
+            flatpak::list_flatpak_remotes,
+            flatpak::list_flatpaks,
+            flatpak::remove_flatpak_remote,
+            flatpak::uninstall_flatpak,
+            flatpak::update_flatpaks,
+            fonts::list_installed_fonts,
+            fonts::search_font_packages,
+            provides::find_owner_of_file,
+            provides::what_provides,
+            plan::preview_command,
+            history::list_dnf_history,
+            history::get_transaction_info,
+            history::get_dnf_log,
+            audit::export_reproduction_script,
+            cleanup::list_orphan_packages,
+            cleanup::list_leaf_packages,
+            cleanup::cleanup_orphans,
+            cleanup::list_weak_only_packages,
+            cleanup::remove_weak_only_packages,
+            usage::list_unused_apps,
+            health::check_system_health,
+            kernels::get_kernel_overview,
+            kernels::set_default_kernel,
+            kernels::get_boot_menu,
+            updates::update_all_packages,
+            dnf_cache::get_dnf_cache_size,
+            dnf_cache::clean_dnf_cache,
+            repos::refresh_metadata,
+            system_upgrade::get_system_upgrade_status,
+            system_upgrade::check_system_upgrade,
+            system_upgrade::download_system_upgrade,
+            system_upgrade::reboot_into_system_upgrade,
+            system_upgrade::cancel_system_upgrade,
+            offline::reboot_into_offline_update,
+            offline::cancel_offline_update,
+            appstream::get_appstream_info,
+            groups::list_groups,
+            groups::get_group_contents,
+            groups::install_group,
+            groups::remove_group,
+            updates::check_for_updates,
+            advisories::get_update_advisories,
+            session::get_session_state,
+            session::save_session_view,
+            session::reattach_operation,
+            settings::get_settings,
+            settings::get_effective_settings,
+            settings::set_settings,
+            settings::update_settings,
+            settings::get_refresh_concurrency,
+            settings::set_refresh_concurrency,
+            coordination::get_pending_system_transactions,
+            coordination::get_packagekit_state,
+            coordination::set_packagekit_auto_download,
+            dnf_conf::get_dnf_excludes,
+            dnf_conf::set_dnf_excludes,
+            cleanup_wizard::run_cleanup_analysis,
+            versionlock::get_versionlock_status,
+            versionlock::add_versionlock,
+            versionlock::remove_versionlock,
+            versionlock::install_versionlock_plugin,
+            pins::list_pins,
+            pins::get_stale_pins,
+            pins::add_pin,
+            pins::remove_pin,
+            pins::renew_pin,
+            daemon::get_daemon_status,
+            daemon::set_daemon_enabled
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(move |_app, event| {
+            // The daemon outlives its windows; only an explicit exit (code set) ends it.
+            if let tauri::RunEvent::ExitRequested { api, code: None, .. } = event {
+                if is_daemon {
+                    api.prevent_exit();
+                }
+            }
+        });
 }
 
 // Test for extract_base_package_name
@@ -769,11 +1380,74 @@ mod tests {
         /usr/bin/perl\n\
         perl(strict)\n\
         perl(warnings)";
-        let deps = parse_rpm_requires_output(rpm_output, "my-main-package");
-        assert!(deps.contains(&DisplayablePackage { name: "rpmlib".to_string() }));
-        assert!(deps.contains(&DisplayablePackage { name: "libc.so.6".to_string() }));
-        assert!(deps.contains(&DisplayablePackage { name: "my-own-package-dep".to_string() }));
-        assert!(deps.contains(&DisplayablePackage { name: "perl".to_string() })); // from /usr/bin/perl
-        assert!(deps.contains(&DisplayablePackage { name: "perl(strict)".to_string() })); // full perl module name
+        let (deps, _) = parse_rpm_requires_output(rpm_output, "my-main-package");
+        let dep = |name: &str, kind| DisplayablePackage { name: name.to_string(), kind, module_provider: None };
+        assert!(deps.contains(&dep("rpmlib", DependencyKind::Internal)));
+        assert!(deps.contains(&dep("libc.so.6", DependencyKind::Library)));
+        assert!(deps.contains(&dep("my-own-package-dep", DependencyKind::Package)));
+        assert!(deps.contains(&dep("perl", DependencyKind::BinaryPath))); // from /usr/bin/perl
+        assert!(deps.contains(&dep("perl(strict)", DependencyKind::LanguageModule))); // full perl module name
+    }
+
+    #[test]
+    fn test_parse_rpm_batch_output() {
+        let batch_output = "@@package\thtop\t412000\nlibc.so.6()(64bit)\nlibncursesw.so.6()(64bit)\n@@end\n\
+                            package missing-pkg is not installed\n\
+                            @@package\tglibc\t6000000\n@@end\n";
+        let entries = parse_rpm_batch_output(batch_output);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries["htop"].size, Some(412000));
+        assert_eq!(entries["htop"].requires, "libc.so.6()(64bit)\nlibncursesw.so.6()(64bit)\n");
+        assert!(entries["glibc"].requires.is_empty());
+    }
+
+    #[test]
+    fn test_parse_rpm_categories() {
+        let categories = parse_rpm_categories("gnome-shell|User Interface/Desktops\nglibc|Unspecified\n");
+        assert_eq!(categories["gnome-shell"], PackageCategory::DesktopEnvironment);
+        assert_eq!(categories.len(), 2);
+    }
+
+    #[test]
+    fn test_rich_requirement_names() {
+        assert_eq!(rich_requirement_names("((a >= 1.0 or b) with c)"), Some(plan::strings(&["a", "b", "c"])));
+        assert_eq!(rich_requirement_names("(a if (b or c) else perl(D::E))"), Some(plan::strings(&["a", "perl(D::E)"])));
+        assert_eq!(rich_requirement_names("(a or b"), None);
+
+        let (deps, unparsed) = parse_rpm_requires_output("(python3-foo if python3)\n", "main");
+        assert!(unparsed.is_empty());
+        assert_eq!(deps, vec![DisplayablePackage { name: "python3-foo".to_string(), kind: DependencyKind::Package, module_provider: None }]);
+        let (_, unparsed) = parse_rpm_requires_output("(a or b\nfoo bar baz qux\n", "main");
+        assert_eq!(unparsed, vec!["(a or b", "foo bar baz qux"]);
+    }
+
+    // What execute_package_uninstall, manage_package_update and update_all_packages hand to a
+    // command-line backend other than dnf.
+    #[test]
+    fn test_package_commands_on_other_backends() {
+        use packagekit::PackageAction;
+        let plans = |action, packages: &[String], dry_run| {
+            [zypper::action_plan(action, packages, dry_run), apt::action_plan(action, packages, dry_run), pacman::action_plan(action, packages, dry_run)]
+                .map(|plan| plan.command_line().to_string())
+        };
+
+        let uninstall: UninstallArgs = serde_json::from_str(r#"{"packages": ["htop"], "mode": "Safe", "cleanup_orphans": true}"#).unwrap();
+        assert_eq!(
+            plans(PackageAction::Remove, &uninstall.packages, uninstall.backend_dry_run().unwrap()),
+            ["pkexec zypper --non-interactive remove --clean-deps htop", "pkexec apt-get -y remove --autoremove htop", "pkexec pacman -Rs --noconfirm htop"]
+        );
+        let dry_run: UninstallArgs = serde_json::from_str(r#"{"packages": ["htop"], "mode": "DryRunSafe", "cleanup_orphans": false}"#).unwrap();
+        assert!(dry_run.backend_dry_run().unwrap());
+        let force: UninstallArgs = serde_json::from_str(r#"{"packages": ["htop"], "mode": "Force", "cleanup_orphans": false}"#).unwrap();
+        assert!(force.backend_dry_run().is_err());
+
+        assert_eq!(
+            plans(PackageAction::Update, &plan::strings(&["htop"]), false),
+            ["pkexec zypper --non-interactive update htop", "pkexec apt-get -y install --only-upgrade htop", "pkexec pacman -S --needed --noconfirm htop"]
+        );
+        assert_eq!(
+            plans(PackageAction::Update, &[], false),
+            ["pkexec zypper --non-interactive update", "pkexec apt-get -y upgrade", "pkexec pacman -Syu --noconfirm"]
+        );
     }
 }
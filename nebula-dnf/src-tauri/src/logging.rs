@@ -0,0 +1,120 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+use tauri_plugin_opener::OpenerExt;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{Builder, Rotation};
+use tracing_subscriber::fmt::writer::MakeWriterExt;
+
+use crate::error::NebulaError;
+
+// One file per day, e.g. logs/nebula-dnf.2026-10-17.log, of which the last week is kept.
+const LOG_DIR_NAME: &str = "logs";
+const LOG_FILE_PREFIX: &str = "nebula-dnf";
+const LOG_FILE_SUFFIX: &str = "log";
+const MAX_LOG_FILES: usize = 7;
+const DEFAULT_RECENT_LINES: usize = 200;
+
+// Flushes the buffered log lines when the app exits.
+pub struct LogGuard {
+    _guard: WorkerGuard,
+}
+
+fn log_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    crate::app_data_file(app, LOG_DIR_NAME)
+}
+
+// Sends log events to the daily log file and to stderr, as the println!s did. Called once from
+// setup; events before it (the daemon attach) only reach stderr if anything.
+pub fn init(app: &tauri::AppHandle) -> Result<(), String> {
+    let dir = log_dir(app)?;
+    let appender = Builder::new()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(&dir)
+        .map_err(|e| format!("Failed to create log file in {:?}: {}", dir, e))?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+    tracing_subscriber::fmt()
+        .with_writer(writer.and(std::io::stderr))
+        .with_ansi(false)
+        .with_max_level(tracing::Level::INFO)
+        .try_init()
+        .map_err(|e| format!("Failed to set up logging: {}", e))?;
+    app.manage(LogGuard { _guard: guard });
+    Ok(())
+}
+
+fn is_log_file(name: &str) -> bool {
+    name.starts_with(&format!("{}.", LOG_FILE_PREFIX)) && name.ends_with(&format!(".{}", LOG_FILE_SUFFIX))
+}
+
+// Oldest first; the dates in the names sort like the days.
+fn log_files(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let entries = fs::read_dir(dir).map_err(|e| format!("Failed to read {:?}: {}", dir, e))?;
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.file_name().and_then(|name| name.to_str()).is_some_and(is_log_file))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+// The last `count` lines of the files, read newest first so only as many files as needed are opened.
+fn last_lines(files: &[PathBuf], count: usize) -> Result<Vec<String>, String> {
+    let mut lines: Vec<String> = Vec::new();
+    for file in files.iter().rev() {
+        if lines.len() >= count {
+            break;
+        }
+        let contents = fs::read_to_string(file).map_err(|e| format!("Failed to read {:?}: {}", file, e))?;
+        let mut older: Vec<String> = contents.lines().map(String::from).collect();
+        older.append(&mut lines);
+        lines = older;
+    }
+    let skip = lines.len().saturating_sub(count);
+    Ok(lines.split_off(skip))
+}
+
+// The most recent log lines, oldest first, for the frontend to show or attach to a bug report.
+#[tauri::command]
+pub async fn get_recent_logs(app: tauri::AppHandle, lines: Option<usize>) -> Result<Vec<String>, NebulaError> {
+    Ok(last_lines(&log_files(&log_dir(&app)?)?, lines.unwrap_or(DEFAULT_RECENT_LINES))?)
+}
+
+// Opens today's log file, or the newest one, in the default text editor.
+#[tauri::command]
+pub async fn open_log_file(app: tauri::AppHandle) -> Result<(), NebulaError> {
+    let file = log_files(&log_dir(&app)?)?.pop().ok_or("No log file has been written yet.")?;
+    app.opener()
+        .open_path(file.to_string_lossy(), None::<&str>)
+        .map_err(|e| format!("Failed to open {:?}: {}", file, e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_logs() {
+        assert!(is_log_file("nebula-dnf.2026-10-17.log"));
+        assert!(!is_log_file("nebula-dnf.2026-10-17.log.tmp"));
+
+        let dir = std::env::temp_dir().join(format!("nebula-dnf-logs-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("nebula-dnf.2026-10-16.log"), "one\ntwo\nthree\n").unwrap();
+        fs::write(dir.join("nebula-dnf.2026-10-17.log"), "four\nfive\n").unwrap();
+        fs::write(dir.join("cache.db"), "").unwrap();
+        let files = log_files(&dir).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(last_lines(&files, 3).unwrap(), ["three", "four", "five"]);
+        assert_eq!(last_lines(&files, 50).unwrap().len(), 5);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,291 @@
+use chrono::{Datelike, Duration as ChronoDuration, Local, NaiveDate, Timelike, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+use tracing::{error, info, warn};
+
+use crate::error::NebulaError;
+use crate::plan;
+use crate::process::run_plan;
+use crate::scope::{self, Scope};
+use crate::services::OperationManager;
+use crate::{append_json_line, app_data_file, load_json_file, load_json_lines, save_json_file};
+
+const WINDOWS_FILE_NAME: &str = "maintenance_windows.json";
+const STATE_FILE_NAME: &str = "maintenance_state.json";
+const JOURNAL_FILE_NAME: &str = "maintenance_journal.jsonl";
+const SCHEDULER_TICK: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateClass {
+    SecurityOnly,
+    All,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MaintenanceWindow {
+    id: String,
+    days: Vec<Weekday>,
+    start: String, // "HH:MM", local time
+    end: String,   // "HH:MM", may be earlier than start for windows crossing midnight
+    update_class: UpdateClass,
+    enabled: bool,
+}
+
+// Scheduler bookkeeping, kept apart from the user's window definitions.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+struct SchedulerState {
+    last_runs: HashMap<String, NaiveDate>, // window id -> date the last run's window started
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MaintenanceJournalEntry {
+    #[serde(default = "user_scope")]
+    scope: Scope, // entries written before windows had a scope were all per-user
+    window_id: String,
+    update_class: UpdateClass,
+    started_at: String,  // RFC 3339
+    finished_at: String, // RFC 3339
+    success: bool,
+    message: String,
+    details: String,
+}
+
+fn user_scope() -> Scope {
+    Scope::User
+}
+
+// Scheduler state key; system windows get a prefix so ids can't collide with the user's.
+fn state_key(scope: Scope, window_id: &str) -> String {
+    match scope {
+        Scope::User => window_id.to_string(),
+        Scope::System => format!("system/{}", window_id),
+    }
+}
+
+fn parse_hh_mm(value: &str) -> Option<u32> {
+    let (hours, minutes) = value.trim().split_once(':')?;
+    let hours: u32 = hours.parse().ok()?;
+    let minutes: u32 = minutes.parse().ok()?;
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+fn validate_windows(windows: &[MaintenanceWindow]) -> Result<(), String> {
+    for (index, window) in windows.iter().enumerate() {
+        validate_window(window)?;
+        if windows[..index].iter().any(|earlier| earlier.id.trim() == window.id.trim()) {
+            return Err(format!("Two maintenance windows have the id '{}'.", window.id.trim()));
+        }
+    }
+    Ok(())
+}
+
+fn validate_window(window: &MaintenanceWindow) -> Result<(), String> {
+    if window.id.trim().is_empty() {
+        return Err("Maintenance window id must not be empty.".to_string());
+    }
+    if window.days.is_empty() {
+        return Err(format!("Maintenance window '{}' has no days selected.", window.id));
+    }
+    let start = parse_hh_mm(&window.start).ok_or_else(|| format!("Invalid start time '{}' (expected HH:MM).", window.start))?;
+    let end = parse_hh_mm(&window.end).ok_or_else(|| format!("Invalid end time '{}' (expected HH:MM).", window.end))?;
+    if start == end {
+        return Err(format!("Maintenance window '{}' has zero length.", window.id));
+    }
+    Ok(())
+}
+
+// If `now` falls inside an occurrence of the window, returns the date that occurrence started on.
+// Windows crossing midnight belong to the day they start on.
+fn active_occurrence(window: &MaintenanceWindow, today: NaiveDate, weekday: Weekday, minute_of_day: u32) -> Option<NaiveDate> {
+    let start = parse_hh_mm(&window.start)?;
+    let end = parse_hh_mm(&window.end)?;
+    if start < end {
+        (window.days.contains(&weekday) && minute_of_day >= start && minute_of_day < end).then_some(today)
+    } else if minute_of_day >= start && window.days.contains(&weekday) {
+        Some(today)
+    } else if minute_of_day < end && window.days.contains(&weekday.pred()) {
+        Some(today - ChronoDuration::days(1))
+    } else {
+        None
+    }
+}
+
+// Nobody is there to answer an authentication prompt, so the update only runs through the helper,
+// and only when a polkit rule authorizes it without one.
+async fn apply_updates(app: &tauri::AppHandle, window: &MaintenanceWindow) -> (bool, String, String) {
+    let security_only = window.update_class == UpdateClass::SecurityOnly;
+    let pinned = match crate::pins::pinned_packages(app) {
+        Ok(pinned) => pinned,
+        Err(e) => return (false, "Could not start scheduled updates.".to_string(), e),
+    };
+    let upgrade = plan::upgrade_all(security_only, &pinned);
+    if !crate::helper::installed() || crate::helper::helper_plan(std::slice::from_ref(&upgrade)).is_none() {
+        let details = format!("Install {} to run scheduled updates unattended.", crate::helper::HELPER_PATH);
+        return (false, "Scheduled updates were not applied: the nebula-dnf helper is not installed.".to_string(), details);
+    }
+    if !crate::helper::authorized_without_prompt(app).await {
+        let details = "Add a polkit rule returning polkit.Result.YES for com.nebula-dnf.manage, or apply the updates from the Updates page.".to_string();
+        return (false, "Scheduled updates were not applied: they would need an authentication prompt.".to_string(), details);
+    }
+    match run_plan(app, &upgrade).await.map_err(String::from) {
+        Ok(out) if out.success => (true, "Scheduled updates applied successfully.".to_string(), out.stdout),
+        Ok(out) => (
+            false,
+            format!("Scheduled updates failed with exit code {}.", out.code),
+            format!("STDOUT:\n{}\nSTDERR:\n{}", out.stdout, out.stderr),
+        ),
+        Err(e) => (false, "Could not start scheduled updates.".to_string(), e),
+    }
+}
+
+async fn run_window(app: &tauri::AppHandle, scope: Scope, window: &MaintenanceWindow) -> MaintenanceJournalEntry {
+    let started_at = Local::now().to_rfc3339();
+    info!("Maintenance window '{}' is open, applying {:?} updates.", window.id, window.update_class);
+    let _busy = app.state::<OperationManager>().begin(format!("Scheduled maintenance ({})", window.id));
+
+    // Nobody is there to confirm an unattended run, so when the settings ask for updates to be
+    // reviewed first the window leaves them pending and journals what it would have applied.
+    let (success, message, details) = match crate::settings::requires_preview(app, crate::settings::GuardedAction::Update, false) {
+        Ok(true) => (
+            false,
+            "Updates were not applied: the settings ask for them to be reviewed and confirmed first.".to_string(),
+            crate::updates::fetch_update_changelog(app, &[]).await,
+        ),
+        Err(e) => (false, "Could not start scheduled updates.".to_string(), e),
+        Ok(false) => apply_updates(app, window).await,
+    };
+
+    MaintenanceJournalEntry {
+        scope,
+        window_id: window.id.clone(),
+        update_class: window.update_class,
+        started_at,
+        finished_at: Local::now().to_rfc3339(),
+        success,
+        message,
+        details,
+    }
+}
+
+fn load_windows(app: &tauri::AppHandle, scope: Scope) -> Result<Vec<MaintenanceWindow>, String> {
+    Ok(load_json_file(&scope::config_file(app, scope, WINDOWS_FILE_NAME)?)?.unwrap_or_default())
+}
+
+// Machine-wide windows from /etc/nebulasys plus the user's own.
+async fn scheduler_tick(app: &tauri::AppHandle) -> Result<(), String> {
+    let mut windows: Vec<(Scope, MaintenanceWindow)> = Vec::new();
+    for scope in [Scope::System, Scope::User] {
+        windows.extend(load_windows(app, scope)?.into_iter().map(|w| (scope, w)));
+    }
+    if windows.is_empty() {
+        return Ok(());
+    }
+    let state_path = app_data_file(app, STATE_FILE_NAME)?;
+    let mut state: SchedulerState = load_json_file(&state_path)?.unwrap_or_default();
+
+    let now = Local::now();
+    let minute_of_day = now.hour() * 60 + now.minute();
+    for (scope, window) in windows.iter().filter(|(_, w)| w.enabled) {
+        let Some(occurrence) = active_occurrence(window, now.date_naive(), now.weekday(), minute_of_day) else { continue };
+        let key = state_key(*scope, &window.id);
+        if state.last_runs.get(&key) == Some(&occurrence) {
+            continue; // Already handled this occurrence
+        }
+        // Record before running so a crash mid-update doesn't retry in a loop.
+        state.last_runs.insert(key, occurrence);
+        save_json_file(&state_path, &state)?;
+
+        let entry = run_window(app, *scope, window).await;
+        if let Err(e) = append_json_line(&app_data_file(app, JOURNAL_FILE_NAME)?, &entry) {
+            warn!("{}", e);
+        }
+        if let Err(e) = app.emit("maintenance-finished", &entry) {
+            warn!("Failed to emit maintenance-finished event: {}", e);
+        }
+        if let Err(e) = notify_desktop(&entry).await {
+            warn!("Failed to send the maintenance notification: {}", e);
+        }
+    }
+    Ok(())
+}
+
+// The event above only reaches an open window; the desktop notification reaches the user either way.
+async fn notify_desktop(entry: &MaintenanceJournalEntry) -> zbus::Result<()> {
+    let connection = zbus::Connection::session().await?;
+    let notifications = zbus::Proxy::new(&connection, "org.freedesktop.Notifications", "/org/freedesktop/Notifications", "org.freedesktop.Notifications").await?;
+    let icon = if entry.success { "software-update-available" } else { "dialog-warning" };
+    let summary = format!("Maintenance window '{}'", entry.window_id);
+    let hints: HashMap<&str, zbus::zvariant::Value> = HashMap::new();
+    let _id: u32 = notifications.call("Notify", &("NebulaSys", 0u32, icon, summary.as_str(), entry.message.as_str(), Vec::<&str>::new(), hints, -1i32)).await?;
+    Ok(())
+}
+
+// Background loop started at app setup; checks once a minute whether a window is open.
+pub async fn run_scheduler(app: tauri::AppHandle) {
+    loop {
+        if let Err(e) = scheduler_tick(&app).await {
+            error!("Maintenance scheduler error: {}", e);
+        }
+        tokio::time::sleep(SCHEDULER_TICK).await;
+    }
+}
+
+#[tauri::command]
+pub async fn get_maintenance_windows(app: tauri::AppHandle, scope: Scope) -> Result<Vec<MaintenanceWindow>, NebulaError> {
+    Ok(load_windows(&app, scope)?)
+}
+
+// System windows apply to every user of the machine and are written with pkexec.
+#[tauri::command]
+pub async fn set_maintenance_windows(app: tauri::AppHandle, scope: Scope, windows: Vec<MaintenanceWindow>) -> Result<(), NebulaError> {
+    validate_windows(&windows)?;
+    info!("Saving {} {:?} maintenance window(s).", windows.len(), scope);
+    Ok(scope::save_config_file(&app, scope, WINDOWS_FILE_NAME, &windows).await?)
+}
+
+// Most recent journal entries first.
+#[tauri::command]
+pub async fn get_maintenance_journal(app: tauri::AppHandle, limit: Option<usize>) -> Result<Vec<MaintenanceJournalEntry>, NebulaError> {
+    let mut entries: Vec<MaintenanceJournalEntry> = load_json_lines(&app_data_file(&app, JOURNAL_FILE_NAME)?)?;
+    entries.reverse();
+    entries.truncate(limit.unwrap_or(50));
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(start: &str, end: &str) -> MaintenanceWindow {
+        MaintenanceWindow {
+            id: "test".to_string(),
+            days: vec![Weekday::Sun],
+            start: start.to_string(),
+            end: end.to_string(),
+            update_class: UpdateClass::All,
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_active_occurrence() {
+        let sunday = NaiveDate::from_ymd_opt(2024, 6, 2).unwrap();
+        let monday = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+        let same_day = window("02:00", "04:00");
+        assert_eq!(active_occurrence(&same_day, sunday, Weekday::Sun, 3 * 60), Some(sunday));
+        assert_eq!(active_occurrence(&same_day, sunday, Weekday::Sun, 4 * 60), None);
+        assert_eq!(active_occurrence(&same_day, monday, Weekday::Mon, 3 * 60), None);
+
+        let overnight = window("23:00", "01:00");
+        assert_eq!(active_occurrence(&overnight, sunday, Weekday::Sun, 23 * 60 + 30), Some(sunday));
+        assert_eq!(active_occurrence(&overnight, monday, Weekday::Mon, 30), Some(sunday));
+        assert_eq!(active_occurrence(&overnight, sunday, Weekday::Sun, 30), None);
+
+        assert!(validate_windows(&[same_day.clone(), overnight.clone()]).is_err());
+        let mut nightly = overnight;
+        nightly.id = "nightly".to_string();
+        assert!(validate_windows(&[same_day, nightly]).is_ok());
+    }
+}
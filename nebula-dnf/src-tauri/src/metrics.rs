@@ -0,0 +1,234 @@
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::async_runtime::JoinHandle;
+use tauri::{Manager, State};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use tracing::{error, info};
+
+use crate::error::NebulaError;
+use crate::process::run_command;
+
+const DEFAULT_METRICS_PORT: u16 = 9188;
+// dnf queries are expensive, so scrapes reuse the last snapshot for this long.
+const SNAPSHOT_MAX_AGE: Duration = Duration::from_secs(300);
+const MAX_REQUEST_HEAD_BYTES: usize = 8192;
+
+// Update posture of this machine at the time it was collected.
+// Fields are None when the underlying query failed, and are then left out of the exposition.
+#[derive(Debug, Clone, Default)]
+struct PostureSnapshot {
+    pending_updates: Option<u64>,
+    security_advisories: Option<u64>,
+    orphaned_packages: Option<u64>,
+    last_refresh_age_secs: Option<u64>,
+    collected_at: Option<SystemTime>,
+}
+
+struct RunningEndpoint {
+    port: u16,
+    task: JoinHandle<()>,
+}
+
+#[derive(Default)]
+pub struct MetricsState {
+    endpoint: Mutex<Option<RunningEndpoint>>,
+    snapshot: Arc<Mutex<PostureSnapshot>>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct MetricsEndpointStatus {
+    enabled: bool,
+    address: Option<String>,
+}
+
+// Counts distinct advisory ids (first column) in `dnf updateinfo list` output.
+fn count_advisories(output: &str) -> u64 {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("Last metadata expiration check:"))
+        .filter_map(|line| line.split_whitespace().next())
+        .collect::<HashSet<_>>()
+        .len() as u64
+}
+
+fn count_nonempty_lines(output: &str) -> u64 {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("Last metadata expiration check:"))
+        .count() as u64
+}
+
+async fn collect_snapshot(app: &tauri::AppHandle) -> PostureSnapshot {
+    info!("Collecting update posture snapshot for metrics endpoint.");
+    let mut snapshot = PostureSnapshot::default();
+
+    match crate::updates::fetch_pending_updates(app).await {
+        Ok(updates) => snapshot.pending_updates = Some(updates.len() as u64),
+        Err(e) => error!("Metrics: {}", e),
+    }
+
+    match run_command(app, "dnf", &["updateinfo", "list", "--security", "--quiet"]).await {
+        Ok(out) if out.success => snapshot.security_advisories = Some(count_advisories(&out.stdout)),
+        Ok(out) => error!("Metrics: dnf updateinfo failed ({}): {}", out.code, out.error_text().trim()),
+        Err(e) => error!("Metrics: {}", e),
+    }
+
+    match run_command(app, "dnf", &["repoquery", "--unneeded", "--quiet"]).await {
+        Ok(out) if out.success => snapshot.orphaned_packages = Some(count_nonempty_lines(&out.stdout)),
+        Ok(out) => error!("Metrics: dnf repoquery --unneeded failed ({}): {}", out.code, out.error_text().trim()),
+        Err(e) => error!("Metrics: {}", e),
+    }
+
+    // The package cache records when it was last fully refreshed.
+    snapshot.last_refresh_age_secs = app.state::<crate::cache::CacheService>()
+        .refreshed_at()
+        .ok()
+        .flatten()
+        .map(|refreshed| SystemTime::now().duration_since(UNIX_EPOCH + Duration::from_secs(refreshed)).map(|age| age.as_secs()).unwrap_or(0));
+
+    snapshot.collected_at = Some(SystemTime::now());
+    snapshot
+}
+
+fn push_gauge(out: &mut String, name: &str, help: &str, value: Option<u64>) {
+    if let Some(value) = value {
+        out.push_str(&format!("# HELP {} {}\n# TYPE {} gauge\n{} {}\n", name, help, name, name, value));
+    }
+}
+
+// Renders the snapshot in the Prometheus text exposition format (version 0.0.4).
+fn render_prometheus(snapshot: &PostureSnapshot) -> String {
+    let mut out = String::new();
+    push_gauge(&mut out, "nebulasys_pending_updates", "Number of packages with an available update.", snapshot.pending_updates);
+    push_gauge(&mut out, "nebulasys_security_advisories", "Number of unapplied security advisories.", snapshot.security_advisories);
+    push_gauge(&mut out, "nebulasys_orphaned_packages", "Number of installed packages no longer needed by anything.", snapshot.orphaned_packages);
+    push_gauge(&mut out, "nebulasys_last_refresh_age_seconds", "Seconds since the package cache was last refreshed.", snapshot.last_refresh_age_secs);
+    let collected = snapshot
+        .collected_at
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+    push_gauge(&mut out, "nebulasys_snapshot_timestamp_seconds", "Unix time at which these metrics were collected.", collected);
+    out
+}
+
+async fn current_exposition(app: &tauri::AppHandle, snapshot: &Mutex<PostureSnapshot>) -> String {
+    // Holding the lock while collecting makes concurrent scrapes wait for one collection.
+    let mut guard = snapshot.lock().await;
+    let is_stale = guard
+        .collected_at
+        .and_then(|t| SystemTime::now().duration_since(t).ok())
+        .is_none_or(|age| age > SNAPSHOT_MAX_AGE);
+    if is_stale {
+        *guard = collect_snapshot(app).await;
+    }
+    render_prometheus(&guard)
+}
+
+async fn handle_connection(mut stream: TcpStream, app: tauri::AppHandle, snapshot: Arc<Mutex<PostureSnapshot>>) {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") && head.len() < MAX_REQUEST_HEAD_BYTES {
+        match stream.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => head.extend_from_slice(&buf[..n]),
+        }
+    }
+    let head = String::from_utf8_lossy(&head);
+    let mut request_line = head.lines().next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default();
+    let path = request_line.next().unwrap_or_default();
+
+    let (status, content_type, body) = if method == "GET" && (path == "/metrics" || path == "/") {
+        ("200 OK", "text/plain; version=0.0.4; charset=utf-8", current_exposition(&app, &snapshot).await)
+    } else {
+        ("404 Not Found", "text/plain; charset=utf-8", "Not found. Metrics are served at /metrics.\n".to_string())
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    if let Err(e) = stream.write_all(response.as_bytes()).await {
+        error!("Metrics: failed to write response: {}", e);
+    }
+}
+
+#[tauri::command]
+pub async fn set_metrics_endpoint(
+    app: tauri::AppHandle,
+    state: State<'_, MetricsState>,
+    enabled: bool,
+    port: Option<u16>,
+) -> Result<MetricsEndpointStatus, NebulaError> {
+    let mut endpoint = state.endpoint.lock().await;
+    if let Some(running) = endpoint.take() {
+        info!("Stopping metrics endpoint on port {}.", running.port);
+        running.task.abort();
+    }
+    if !enabled {
+        return Ok(MetricsEndpointStatus { enabled: false, address: None });
+    }
+
+    let port = port.unwrap_or(DEFAULT_METRICS_PORT);
+    // Only ever bind to loopback; the endpoint is meant for a local scraper or an SSH tunnel.
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .await
+        .map_err(|e| format!("Failed to bind metrics endpoint to 127.0.0.1:{}: {}", port, e))?;
+    info!("Metrics endpoint listening on http://127.0.0.1:{}/metrics", port);
+
+    let snapshot = state.snapshot.clone();
+    let task = tauri::async_runtime::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tauri::async_runtime::spawn(handle_connection(stream, app.clone(), snapshot.clone()));
+                }
+                Err(e) => error!("Metrics: failed to accept connection: {}", e),
+            }
+        }
+    });
+    *endpoint = Some(RunningEndpoint { port, task });
+
+    Ok(MetricsEndpointStatus {
+        enabled: true,
+        address: Some(format!("http://127.0.0.1:{}/metrics", port)),
+    })
+}
+
+#[tauri::command]
+pub async fn get_metrics_endpoint_status(state: State<'_, MetricsState>) -> Result<MetricsEndpointStatus, NebulaError> {
+    let endpoint = state.endpoint.lock().await;
+    Ok(MetricsEndpointStatus {
+        enabled: endpoint.is_some(),
+        address: endpoint.as_ref().map(|e| format!("http://127.0.0.1:{}/metrics", e.port)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_prometheus_skips_unknown_values() {
+        let snapshot = PostureSnapshot {
+            pending_updates: Some(4),
+            security_advisories: None,
+            orphaned_packages: Some(0),
+            last_refresh_age_secs: None,
+            collected_at: None,
+        };
+        let text = render_prometheus(&snapshot);
+        assert!(text.contains("nebulasys_pending_updates 4\n"));
+        assert!(text.contains("nebulasys_orphaned_packages 0\n"));
+        assert!(!text.contains("nebulasys_security_advisories"));
+        assert!(!text.contains("nebulasys_last_refresh_age_seconds"));
+    }
+}
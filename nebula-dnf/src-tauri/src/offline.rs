@@ -0,0 +1,53 @@
+use crate::error::NebulaError;
+use crate::plan;
+use crate::process::run_plan;
+use crate::PackageOperationResult;
+
+// Updates staged with `offline` (see plan::offline) wait until these reboot into them or drop them.
+
+async fn run_offline_command(app: &tauri::AppHandle, clean: bool) -> Result<PackageOperationResult, String> {
+    let out = run_plan(app, &plan::offline_command(crate::probes::is_dnf5(app).await, clean)).await?;
+    Ok(PackageOperationResult {
+        success: out.success,
+        message: match (out.success, clean) {
+            (true, false) => "Rebooting to apply the staged updates.".to_string(),
+            (true, true) => "The staged updates were discarded.".to_string(),
+            (false, _) => format!("{} failed: {}", if clean { "Discarding the staged updates" } else { "Rebooting into the staged updates" }, out.error_text().trim()),
+        },
+        details: Some(format!("STDOUT:\n{}\nSTDERR:\n{}", out.stdout, out.stderr)),
+        confirmation_required: false,
+        simulated: out.simulated,
+        reboot_required: false,
+    })
+}
+
+#[tauri::command]
+pub async fn reboot_into_offline_update(app: tauri::AppHandle) -> Result<PackageOperationResult, NebulaError> {
+    Ok(run_offline_command(&app, false).await?)
+}
+
+#[tauri::command]
+pub async fn cancel_offline_update(app: tauri::AppHandle) -> Result<PackageOperationResult, NebulaError> {
+    Ok(run_offline_command(&app, true).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coordination::{check_staged_update, PendingSystemTransaction, PendingTransactionKind};
+
+    #[test]
+    fn test_cancel_with_staged_update() {
+        let staged = [PendingSystemTransaction {
+            kind: PendingTransactionKind::OfflineUpdate,
+            source: "dnf5 offline".to_string(),
+            detail: "A dnf5 offline transaction is staged and waits for `dnf offline reboot`.".to_string(),
+        }];
+        for dnf5 in [true, false] {
+            assert!(check_staged_update(&[plan::offline_command(dnf5, true)], &staged).is_ok());
+            assert!(check_staged_update(&[plan::offline_command(dnf5, false)], &staged).is_ok());
+        }
+        let install = plan::install_packages(&plan::strings(&["htop"]), false).unwrap();
+        assert!(matches!(check_staged_update(&[plan::offline(&install, true).unwrap()], &staged), Err(NebulaError::LockHeld { .. })));
+    }
+}
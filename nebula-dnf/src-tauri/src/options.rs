@@ -0,0 +1,129 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::plan::CommandPlan;
+
+// Environment variables an operation may set. DNF_VAR_<name> defines a dnf substitution variable.
+const ALLOWED_ENV: &[&str] = &["LANG", "LC_ALL", "TMPDIR", "http_proxy", "https_proxy", "no_proxy"];
+const DNF_VAR_PREFIX: &str = "DNF_VAR_";
+// dnf configuration keys that may be overridden with --setopt for a single operation.
+const ALLOWED_SETOPT: &[&str] = &["tsflags", "installroot", "releasever", "install_weak_deps", "keepcache", "skip_if_unavailable", "best", "max_parallel_downloads"];
+const ALLOWED_TSFLAGS: &[&str] = &["nodocs", "justdb", "noscripts", "notriggers", "nocontexts", "nocaps"];
+
+static DNF_VAR_NAME_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Za-z0-9_]+$").unwrap());
+static OPTION_VALUE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Za-z0-9_.,:/@+=-]*$").unwrap());
+
+// Extra environment and dnf settings for one mutating operation, e.g. for image builds:
+// `{ setopt: { tsflags: "nodocs", install_weak_deps: "False" } }`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct AdvancedOptions {
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    #[serde(default)]
+    pub setopt: BTreeMap<String, String>,
+}
+
+fn validate_env(name: &str, value: &str) -> Result<(), String> {
+    let allowed = ALLOWED_ENV.contains(&name)
+        || name.strip_prefix(DNF_VAR_PREFIX).is_some_and(|var| DNF_VAR_NAME_RE.is_match(var));
+    if !allowed {
+        return Err(format!("Environment variable '{}' is not allowed.", name));
+    }
+    if !OPTION_VALUE_RE.is_match(value) {
+        return Err(format!("Invalid value for {}: '{}'", name, value));
+    }
+    Ok(())
+}
+
+fn validate_setopt(key: &str, value: &str) -> Result<(), String> {
+    if !ALLOWED_SETOPT.contains(&key) {
+        return Err(format!("dnf option '{}' cannot be set here.", key));
+    }
+    let valid = match key {
+        "tsflags" => value.split(',').all(|flag| ALLOWED_TSFLAGS.contains(&flag)),
+        "installroot" => value.starts_with('/') && !value.split('/').any(|part| part == ".."),
+        "releasever" => value == "rawhide" || value.parse::<u16>().is_ok(),
+        "max_parallel_downloads" => value.parse::<u8>().is_ok_and(|n| (1..=20).contains(&n)),
+        "install_weak_deps" | "keepcache" | "skip_if_unavailable" | "best" => {
+            matches!(value, "True" | "False" | "true" | "false" | "1" | "0")
+        }
+        _ => false,
+    };
+    if valid && OPTION_VALUE_RE.is_match(value) {
+        Ok(())
+    } else {
+        Err(format!("Invalid value for {}: '{}'", key, value))
+    }
+}
+
+impl AdvancedOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, value) in &self.env {
+            validate_env(name, value)?;
+        }
+        for (key, value) in &self.setopt {
+            validate_setopt(key, value)?;
+        }
+        Ok(())
+    }
+
+    // The plan with `--setopt=key=value` appended to its dnf command and the environment passed
+    // through `env`, since pkexec does not keep the caller's environment.
+    pub fn apply(&self, plan: &CommandPlan) -> Result<CommandPlan, String> {
+        self.validate()?;
+        if self.env.is_empty() && self.setopt.is_empty() {
+            return Ok(plan.clone());
+        }
+        let mut command = if plan.privileged { plan.args.clone() } else { std::iter::once(plan.program.clone()).chain(plan.args.iter().cloned()).collect() };
+        if !self.setopt.is_empty() {
+            if command.first().map(String::as_str) != Some("dnf") {
+                return Err(format!("dnf options do not apply to `{}`.", plan.command_line()));
+            }
+            command.extend(self.setopt.iter().map(|(key, value)| format!("--setopt={}={}", key, value)));
+        }
+        if !self.env.is_empty() {
+            let mut with_env: Vec<String> = self.env.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+            with_env.extend(command);
+            command = with_env;
+            command.insert(0, "env".to_string());
+        }
+        let (program, args) = command.split_first().ok_or("Empty command.")?;
+        Ok(if plan.privileged { CommandPlan::privileged(program, args.to_vec()) } else { CommandPlan::new(program, args.to_vec()) })
+    }
+}
+
+// `options` as the frontend passes it to a command: absent means none.
+pub fn apply_options(plan: &CommandPlan, options: Option<&AdvancedOptions>) -> Result<CommandPlan, String> {
+    match options {
+        Some(options) => options.apply(plan),
+        None => Ok(plan.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan::strings;
+
+    #[test]
+    fn test_apply_options() {
+        let plan = CommandPlan::privileged("dnf", strings(&["install", "--assumeyes", "htop"]));
+        let options = AdvancedOptions {
+            env: BTreeMap::from([("DNF_VAR_site".to_string(), "lab".to_string())]),
+            setopt: BTreeMap::from([("tsflags".to_string(), "nodocs".to_string())]),
+        };
+        let applied = options.apply(&plan).unwrap();
+        assert_eq!(applied.command_line(), "pkexec env DNF_VAR_site=lab dnf install --assumeyes htop --setopt=tsflags=nodocs");
+        assert!(applied.is_package_transaction());
+        assert_eq!(applied.simulated().unwrap().command_line(), "env DNF_VAR_site=lab dnf install htop --setopt=tsflags=nodocs --assumeno");
+
+        let bad_env = AdvancedOptions { env: BTreeMap::from([("LD_PRELOAD".to_string(), "/tmp/x.so".to_string())]), ..Default::default() };
+        assert!(bad_env.apply(&plan).is_err());
+        let bad_setopt = AdvancedOptions { setopt: BTreeMap::from([("gpgcheck".to_string(), "0".to_string())]), ..Default::default() };
+        assert!(bad_setopt.apply(&plan).is_err());
+        let bad_root = AdvancedOptions { setopt: BTreeMap::from([("installroot".to_string(), "/mnt/../etc".to_string())]), ..Default::default() };
+        assert!(bad_root.validate().is_err());
+    }
+}
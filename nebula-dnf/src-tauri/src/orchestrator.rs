@@ -0,0 +1,255 @@
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager};
+use tracing::warn;
+
+use crate::error::NebulaError;
+use crate::packagekit::PackageAction;
+use crate::plan::{self, strings, CommandPlan};
+use crate::process::run_plan_streaming;
+use crate::scope::Scope;
+use crate::services::{BackendRegistry, OperationManager};
+use crate::settings::PackageBackend;
+use crate::transaction::parse_transaction_table;
+use crate::usage::resolve_binary;
+use crate::PackageOperationResult;
+
+// Operation name for the "operation-output" lines of the streamed stages.
+const OPERATION: &str = "update_everything";
+
+// What "update everything" covers, in the order it runs: system packages first, since firmware
+// and Flatpak runtimes sometimes depend on them.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateStage {
+    System,    // the selected package backend
+    Flatpak,   // system and user installations
+    Firmware,  // fwupd
+    Userspace, // Homebrew and Nix profiles
+}
+
+const STAGES: [UpdateStage; 4] = [UpdateStage::System, UpdateStage::Flatpak, UpdateStage::Firmware, UpdateStage::Userspace];
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum StageStatus {
+    Succeeded,
+    Failed,
+    Skipped,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct StageResult {
+    stage: UpdateStage,
+    status: StageStatus,
+    message: String,
+    details: Option<String>,
+    rollback: Option<String>, // how to undo what the stage changed, when it changed anything
+}
+
+// Payload of the "update-everything-progress" event, sent as each stage starts and finishes.
+#[derive(Debug, Serialize, Clone)]
+struct StageProgress {
+    stage: UpdateStage,
+    index: usize, // 1-based, of `total`
+    total: usize,
+    status: Option<StageStatus>, // None while running
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct UpdateEverythingResult {
+    result: PackageOperationResult, // the summary across stages
+    stages: Vec<StageResult>,
+}
+
+// How to undo a stage; the system stage's depends on the backend that ran it.
+fn rollback_note(stage: UpdateStage, backend: PackageBackend) -> &'static str {
+    match stage {
+        UpdateStage::System => match backend {
+            PackageBackend::Dnf | PackageBackend::Dnf5Daemon => "Undo the system update with `dnf history undo last`, or roll back the snapshot if one was taken.",
+            PackageBackend::PackageKit => "PackageKit has no undo; `pkcon get-transactions` lists what changed. Roll back the snapshot if one was taken.",
+            PackageBackend::Zypper => "Roll back with `snapper rollback` where the root is on Btrfs, or downgrade a package with `zypper install --oldpackage <package>-<version>`.",
+            PackageBackend::Apt => "apt has no undo; /var/log/apt/history.log lists what changed, and `apt-get install <package>=<version>` downgrades a package.",
+            PackageBackend::Pacman => "Downgrade a package from the cache with `pacman -U /var/cache/pacman/pkg/<file>`; /var/log/pacman.log lists what changed.",
+        },
+        UpdateStage::Flatpak => "Return a Flatpak to an earlier build with `flatpak update --commit=<commit> <ref>`; `flatpak remote-info --log` lists them.",
+        UpdateStage::Firmware => "Reinstall earlier firmware with `fwupdmgr downgrade`, where the device allows it.",
+        UpdateStage::Userspace => "Roll the Nix profile back with `nix profile rollback`; Homebrew keeps no earlier versions after `brew cleanup`.",
+    }
+}
+
+fn stage_result(stage: UpdateStage, backend: PackageBackend, result: Result<PackageOperationResult, String>, dry_run: bool) -> StageResult {
+    match result {
+        Ok(result) => StageResult {
+            stage,
+            status: if result.success { StageStatus::Succeeded } else { StageStatus::Failed },
+            rollback: (result.success && !dry_run && !result.simulated).then(|| rollback_note(stage, backend).to_string()),
+            message: result.message,
+            details: result.details,
+        },
+        Err(e) => StageResult { stage, status: StageStatus::Failed, message: e, details: None, rollback: None },
+    }
+}
+
+fn skipped(stage: UpdateStage, reason: &str) -> StageResult {
+    StageResult { stage, status: StageStatus::Skipped, message: reason.to_string(), details: None, rollback: None }
+}
+
+// Merges the results of a stage that runs several commands.
+fn combine(results: Vec<PackageOperationResult>) -> PackageOperationResult {
+    PackageOperationResult {
+        success: results.iter().all(|result| result.success),
+        message: results.iter().map(|result| result.message.as_str()).collect::<Vec<_>>().join(" "),
+        details: Some(results.iter().filter_map(|result| result.details.as_deref()).collect::<Vec<_>>().join("\n")),
+        confirmation_required: false,
+        simulated: results.iter().any(|result| result.simulated),
+        reboot_required: results.iter().any(|result| result.reboot_required),
+    }
+}
+
+// fwupd authorizes updates through polkit itself, like Flatpak, so this runs unprivileged. Dry
+// runs list the pending updates instead.
+pub fn firmware_plan(dry_run: bool) -> CommandPlan {
+    if dry_run {
+        CommandPlan::new("fwupdmgr", strings(&["get-updates", "--no-unreported-check"]))
+    } else {
+        CommandPlan::new("fwupdmgr", strings(&["update", "--assume-yes", "--no-reboot-check"]))
+    }
+}
+
+async fn update_system(app: &tauri::AppHandle, dry_run: bool) -> Result<PackageOperationResult, String> {
+    if let Some(result) = app.state::<BackendRegistry>().package_action(PackageAction::Update, &[], dry_run).await? {
+        return Ok(result);
+    }
+    let upgrade = plan::upgrade_all(false, &crate::pins::pinned_packages(app)?);
+    let upgrade = if dry_run { upgrade.simulated().unwrap_or(upgrade) } else { upgrade };
+    let out = run_plan_streaming(app, &upgrade, OPERATION).await?;
+    let mut result = PackageOperationResult {
+        success: out.success,
+        message: match (out.success, dry_run || out.simulated) {
+            (true, true) => "System dry run finished.".to_string(),
+            (true, false) => "System packages updated.".to_string(),
+            (false, _) => format!("System update failed with exit code {}.", out.code),
+        },
+        details: Some(format!("STDOUT:\n{}\nSTDERR:\n{}", out.stdout, out.stderr)),
+        confirmation_required: false,
+        simulated: out.simulated,
+        reboot_required: false,
+    };
+    if !dry_run {
+        let changed: Vec<String> = parse_transaction_table(&out.stdout).0.iter().map(|item| item.name().to_string()).collect();
+        crate::restarts::check_reboot(app, &mut result, &changed).await;
+    }
+    Ok(result)
+}
+
+async fn update_firmware(app: &tauri::AppHandle, dry_run: bool) -> Result<PackageOperationResult, String> {
+    let out = run_plan_streaming(app, &firmware_plan(dry_run), OPERATION).await?;
+    // 2: nothing to do
+    let success = out.success || out.code == 2;
+    Ok(PackageOperationResult {
+        success,
+        message: match (success, out.code) {
+            (true, 2) => "Firmware is up to date.".to_string(),
+            (true, _) if dry_run || out.simulated => "Firmware dry run finished.".to_string(),
+            (true, _) => "Firmware updated; some devices finish on the next reboot.".to_string(),
+            (false, code) => format!("Firmware update failed with exit code {}.", code),
+        },
+        details: Some(format!("STDOUT:\n{}\nSTDERR:\n{}", out.stdout, out.stderr)),
+        confirmation_required: false,
+        simulated: out.simulated,
+        reboot_required: false,
+    })
+}
+
+async fn run_stage(app: &tauri::AppHandle, stage: UpdateStage, backend: PackageBackend, dry_run: bool) -> StageResult {
+    let result = match stage {
+        UpdateStage::System => update_system(app, dry_run).await,
+        UpdateStage::Flatpak if resolve_binary("flatpak").is_none() => return skipped(stage, "Flatpak is not installed."),
+        UpdateStage::Flatpak => {
+            let mut results = Vec::new();
+            for scope in [Scope::System, Scope::User] {
+                match crate::flatpak::update_flatpaks(app.clone(), Vec::new(), scope, dry_run).await {
+                    Ok(result) => results.push(result),
+                    Err(e) => return stage_result(stage, backend, Err(e.to_string()), dry_run),
+                }
+            }
+            Ok(combine(results))
+        }
+        UpdateStage::Firmware if resolve_binary("fwupdmgr").is_none() => return skipped(stage, "fwupd is not installed."),
+        UpdateStage::Firmware => update_firmware(app, dry_run).await,
+        UpdateStage::Userspace if crate::userspace::installed_managers().is_empty() => return skipped(stage, "Neither Homebrew nor Nix is installed."),
+        UpdateStage::Userspace => crate::userspace::update_userspace_packages(app.clone(), dry_run).await.map(combine).map_err(String::from),
+    };
+    stage_result(stage, backend, result, dry_run)
+}
+
+fn summary(stages: &[StageResult], dry_run: bool) -> PackageOperationResult {
+    let count = |status: StageStatus| stages.iter().filter(|stage| stage.status == status).count();
+    let failed: Vec<String> = stages.iter().filter(|stage| stage.status == StageStatus::Failed).map(|stage| format!("{:?}", stage.stage)).collect();
+    let message = if failed.is_empty() {
+        format!("{} {} stage(s) finished; {} skipped.", if dry_run { "Dry run:" } else { "Everything is up to date:" }, count(StageStatus::Succeeded), count(StageStatus::Skipped))
+    } else {
+        format!("{} of {} stage(s) failed: {}.", failed.len(), stages.len(), failed.join(", "))
+    };
+    PackageOperationResult {
+        success: failed.is_empty(),
+        message,
+        details: Some(stages.iter().map(|stage| format!("{:?}: {}", stage.stage, stage.message)).collect::<Vec<_>>().join("\n")),
+        confirmation_required: false,
+        simulated: dry_run,
+        reboot_required: false,
+    }
+}
+
+// Updates everything in turn: system packages, Flatpaks, firmware, then Homebrew and Nix. Stages
+// the settings opt out of are skipped, and a failed stage does not stop the ones after it.
+// Progress is sent as "update-everything-progress" events and command output as
+// "operation-output" lines.
+#[tauri::command]
+pub async fn update_everything(app: tauri::AppHandle, dry_run: bool, confirmed: Option<bool>) -> Result<UpdateEverythingResult, NebulaError> {
+    if !dry_run && crate::settings::requires_preview(&app, crate::settings::GuardedAction::Update, confirmed.unwrap_or(false))? {
+        let changelog = crate::updates::fetch_update_changelog(&app, &[]).await;
+        return Ok(UpdateEverythingResult {
+            result: crate::settings::preview_result("Review the pending system updates, then confirm to update everything.".to_string(), changelog),
+            stages: Vec::new(),
+        });
+    }
+    let opted_out = crate::settings::skipped_update_stages(&app)?;
+    let backend = app.state::<BackendRegistry>().selected()?;
+    let _busy = (!dry_run).then(|| app.state::<OperationManager>().begin("Updating everything".to_string()));
+    let mut stages = Vec::new();
+    for (index, stage) in STAGES.iter().copied().enumerate() {
+        let progress = |status| StageProgress { stage, index: index + 1, total: STAGES.len(), status };
+        if let Err(e) = app.emit("update-everything-progress", progress(None)) {
+            warn!("Failed to emit update-everything-progress event: {}", e);
+        }
+        let result = if opted_out.contains(&stage) { skipped(stage, "Turned off in settings.") } else { run_stage(&app, stage, backend, dry_run).await };
+        if let Err(e) = app.emit("update-everything-progress", progress(Some(result.status))) {
+            warn!("Failed to emit update-everything-progress event: {}", e);
+        }
+        stages.push(result);
+    }
+    Ok(UpdateEverythingResult { result: summary(&stages, dry_run), stages })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_everything_summary() {
+        let ok = |message: &str| PackageOperationResult { success: true, message: message.to_string(), details: None, confirmation_required: false, simulated: false, reboot_required: false };
+        let stages = vec![
+            stage_result(UpdateStage::System, PackageBackend::Dnf, Ok(ok("System packages updated.")), false),
+            stage_result(UpdateStage::Flatpak, PackageBackend::Dnf, Err("flatpak failed".to_string()), false),
+            skipped(UpdateStage::Firmware, "fwupd is not installed."),
+        ];
+        assert!(stages[0].rollback.as_deref().is_some_and(|note| note.contains("dnf history undo")));
+        let on_apt = stage_result(UpdateStage::System, PackageBackend::Apt, Ok(ok("System packages updated.")), false);
+        assert!(on_apt.rollback.as_deref().is_some_and(|note| note.contains("apt-get install") && !note.contains("dnf")));
+        assert!(stages[1].rollback.is_none());
+        let result = summary(&stages, false);
+        assert!(!result.success);
+        assert_eq!(result.message, "1 of 3 stage(s) failed: Flatpak.");
+        assert!(stage_result(UpdateStage::System, PackageBackend::Dnf, Ok(ok("done")), true).rollback.is_none());
+        assert_eq!(firmware_plan(false).command_line(), "fwupdmgr update --assume-yes --no-reboot-check");
+    }
+}
@@ -0,0 +1,448 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashSet;
+use tracing::info;
+
+use crate::error::NebulaError;
+use crate::process::run_command;
+use crate::validate_package_name;
+
+// Marks the end of one package's record in the query output, since descriptions span lines.
+const RECORD_END: &str = "@@NEBULA-RECORD-END@@";
+
+// The header signature of current packages, falling back to the older header+payload one.
+// Prints e.g. "RSA/SHA256, Tue 16 Apr 2024 10:00:00 AM UTC, Key ID 0727707ea15b79cc".
+const SIGNATURE_TAG: &str = "%|RSAHEADER?{%{RSAHEADER:pgpsig}}:{%|DSAHEADER?{%{DSAHEADER:pgpsig}}:{%|SIGPGP?{%{SIGPGP:pgpsig}}:{(none)}|}|}|";
+
+// One tag per line, DESCRIPTION last because it is the only multi-line field.
+static DETAILS_QUERY_FORMAT: Lazy<String> = Lazy::new(|| {
+    format!("%{{NAME}}\n%{{EPOCH}}\n%{{VERSION}}\n%{{RELEASE}}\n%{{ARCH}}\n%{{SIZE}}\n%{{LICENSE}}\n%{{VENDOR}}\n%{{URL}}\n%{{SUMMARY}}\n%{{INSTALLTIME}}\n%{{SOURCERPM}}\n{}\n%{{DESCRIPTION}}\n{}\n", SIGNATURE_TAG, RECORD_END)
+});
+
+// Like `rpm -ql`, plus the file flags and mode needed to tell configs/docs/directories apart.
+const FILES_QUERY_FORMAT: &str = "[%{FILENAMES}\t%{FILEFLAGS:fflags}\t%{FILEMODES:perms}\n]";
+
+// Section headers printed by `rpm -q --scripts`, e.g.
+// "postinstall scriptlet (using /bin/sh):" or "postuninstall program: /sbin/ldconfig".
+// Restricted to known phases so script bodies can't be mistaken for headers.
+static SCRIPTLET_HEADER_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(pre|post)(install|uninstall|trans|untrans) (scriptlet|program)(?: \(using ([^)]+)\))?:\s*(.*)$").unwrap()
+});
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct PackageDetails {
+    name: String,
+    epoch: Option<u32>,
+    version: String,
+    release: String,
+    arch: String,
+    size_bytes: u64,
+    license: Option<String>,
+    vendor: Option<String>,
+    url: Option<String>,
+    summary: String,
+    description: String,
+    install_time: Option<u64>, // Unix timestamp
+    source_rpm: Option<String>,
+    signature: PackageSignature,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+pub enum SignatureStatus {
+    Signed,
+    Unsigned,
+    UnknownKey, // signed, but with a key that was never imported
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct PackageSignature {
+    status: SignatureStatus,
+    key_id: Option<String>,  // as rpm prints it, 16 hex digits
+    details: Option<String>, // algorithm, date and key id as rpm prints them
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct UnverifiedPackage {
+    name: String,
+    signature: PackageSignature,
+}
+
+// `known_keys` are the short ids of imported keys (gpg-pubkey versions), lowercase.
+fn parse_signature(raw: &str, known_keys: &HashSet<String>) -> PackageSignature {
+    let Some(details) = tag_value(raw) else {
+        return PackageSignature { status: SignatureStatus::Unsigned, key_id: None, details: None };
+    };
+    let key_id = details.rsplit_once("Key ID ").map(|(_, id)| id.trim().to_lowercase());
+    let known = key_id.as_ref().is_some_and(|id| known_keys.iter().any(|known| id.ends_with(known.as_str())));
+    PackageSignature {
+        status: if known { SignatureStatus::Signed } else { SignatureStatus::UnknownKey },
+        key_id,
+        details: Some(details),
+    }
+}
+
+// rpm prints "(none)" for unset tags.
+fn tag_value(raw: &str) -> Option<String> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() || trimmed == "(none)" {
+        None
+    } else {
+        Some(trimmed.to_string())
+    }
+}
+
+fn parse_details_record(record: &str, known_keys: &HashSet<String>) -> Option<PackageDetails> {
+    let mut lines = record.trim_start_matches('\n').splitn(14, '\n');
+    let mut next = || lines.next().unwrap_or_default();
+    let name = tag_value(next())?;
+    let epoch = tag_value(next()).and_then(|e| e.parse().ok());
+    let version = next().trim().to_string();
+    let release = next().trim().to_string();
+    let arch = next().trim().to_string();
+    let size_bytes = next().trim().parse().unwrap_or(0);
+    let license = tag_value(next());
+    let vendor = tag_value(next());
+    let url = tag_value(next());
+    let summary = next().trim().to_string();
+    let install_time = tag_value(next()).and_then(|t| t.parse().ok());
+    let source_rpm = tag_value(next());
+    let signature = parse_signature(next(), known_keys);
+    let description = next().trim_end().to_string();
+
+    Some(PackageDetails {
+        name,
+        epoch,
+        version,
+        release,
+        arch,
+        size_bytes,
+        license,
+        vendor,
+        url,
+        summary,
+        description,
+        install_time,
+        source_rpm,
+        signature,
+    })
+}
+
+fn parse_details_output(output: &str, known_keys: &HashSet<String>) -> Vec<PackageDetails> {
+    output.split(RECORD_END).filter_map(|record| parse_details_record(record, known_keys)).collect()
+}
+
+// Files owned by a package, grouped for display. Directories are not listed.
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct PackageFiles {
+    binaries: Vec<String>,
+    configs: Vec<String>,
+    docs: Vec<String>,
+    libraries: Vec<String>,
+    other: Vec<String>,
+}
+
+const BINARY_DIRS: [&str; 5] = ["/usr/bin/", "/usr/sbin/", "/bin/", "/sbin/", "/usr/libexec/"];
+const LIBRARY_DIRS: [&str; 4] = ["/usr/lib/", "/usr/lib64/", "/lib/", "/lib64/"];
+const DOC_DIRS: [&str; 4] = ["/usr/share/doc/", "/usr/share/licenses/", "/usr/share/man/", "/usr/share/info/"];
+
+// Parses lines of "path<TAB>flags<TAB>perms" as produced by FILES_QUERY_FORMAT.
+fn parse_files_output(output: &str) -> PackageFiles {
+    let mut files = PackageFiles::default();
+    for line in output.lines() {
+        let mut fields = line.split('\t');
+        let path = fields.next().unwrap_or_default().trim();
+        let flags = fields.next().unwrap_or_default();
+        let perms = fields.next().unwrap_or_default();
+        if !path.starts_with('/') || perms.starts_with('d') {
+            continue; // "(contains no files)" or a directory
+        }
+        let path_string = path.to_string();
+        // rpm file flags: c = config, d = doc, l = license, r = readme
+        if flags.contains('c') || path.starts_with("/etc/") {
+            files.configs.push(path_string);
+        } else if flags.contains(['d', 'l', 'r']) || DOC_DIRS.iter().any(|dir| path.starts_with(dir)) {
+            files.docs.push(path_string);
+        } else if BINARY_DIRS.iter().any(|dir| path.starts_with(dir)) {
+            files.binaries.push(path_string);
+        } else if path.contains(".so") || LIBRARY_DIRS.iter().any(|dir| path.starts_with(dir)) {
+            files.libraries.push(path_string);
+        } else {
+            files.other.push(path_string);
+        }
+    }
+    files
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct PackageScriptlet {
+    phase: String,               // e.g. "postinstall"
+    interpreter: Option<String>, // e.g. "/bin/sh"; for "program" scriptlets, the program itself
+    body: String,
+}
+
+fn parse_scripts_output(output: &str) -> Vec<PackageScriptlet> {
+    let mut scriptlets: Vec<PackageScriptlet> = Vec::new();
+    for line in output.lines() {
+        if let Some(caps) = SCRIPTLET_HEADER_RE.captures(line) {
+            let phase = format!("{}{}", &caps[1], &caps[2]);
+            let rest = caps.get(5).map_or("", |m| m.as_str()).trim();
+            // "program: /sbin/ldconfig" has no body, the program is run directly.
+            let (interpreter, body) = if &caps[3] == "program" {
+                (Some(rest.to_string()).filter(|p| !p.is_empty()), String::new())
+            } else {
+                (caps.get(4).map(|m| m.as_str().to_string()), rest.to_string())
+            };
+            scriptlets.push(PackageScriptlet { phase, interpreter, body });
+        } else if let Some(current) = scriptlets.last_mut() {
+            if !current.body.is_empty() {
+                current.body.push('\n');
+            }
+            current.body.push_str(line);
+        }
+    }
+    for scriptlet in &mut scriptlets {
+        scriptlet.body = scriptlet.body.trim_end().to_string();
+    }
+    scriptlets
+}
+
+// The scripts a package runs on install/removal, so they can be audited before trusting it.
+#[tauri::command]
+pub async fn get_package_scriptlets(app: tauri::AppHandle, name: String) -> Result<Vec<PackageScriptlet>, NebulaError> {
+    validate_package_name(&name)?;
+    info!("Fetching scriptlets of package '{}'.", name);
+    let out = run_command(&app, "rpm", &["-q", "--scripts", &name]).await?;
+    if !out.success {
+        return Err(NebulaError::command_failed(out.code, &out.stderr, format!("Package '{}' is not installed: {}", name, out.error_text().trim())));
+    }
+    Ok(parse_scripts_output(&out.stdout))
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct FileVerification {
+    path: String,
+    file_kind: Option<String>, // "config", "doc", "ghost", "license" or "readme"
+    missing: bool,
+    changes: Vec<String>,      // e.g. ["size", "digest", "mtime"]
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct PackageVerification {
+    package: String,
+    intact: bool,
+    files: Vec<FileVerification>,
+    messages: Vec<String>, // anything else rpm reported, e.g. unsatisfied dependencies
+}
+
+// Positions in rpm -V's attribute column: S.5....T. etc.
+const VERIFY_ATTRIBUTES: [(char, &str); 9] = [
+    ('S', "size"),
+    ('M', "mode"),
+    ('5', "digest"),
+    ('D', "device"),
+    ('L', "symlink"),
+    ('U', "owner"),
+    ('G', "group"),
+    ('T', "mtime"),
+    ('P', "capabilities"),
+];
+
+fn file_kind_name(marker: &str) -> Option<String> {
+    let kind = match marker {
+        "c" => "config",
+        "d" => "doc",
+        "g" => "ghost",
+        "l" => "license",
+        "r" => "readme",
+        _ => return None,
+    };
+    Some(kind.to_string())
+}
+
+fn parse_verify_line(line: &str) -> Option<FileVerification> {
+    let (status, rest) = line.split_once(char::is_whitespace)?;
+    let rest = rest.trim_start();
+    // An optional one-letter file attribute precedes the path.
+    let (kind, path) = match rest.split_once(char::is_whitespace) {
+        Some((marker, path)) if marker.len() == 1 && path.trim_start().starts_with('/') => (file_kind_name(marker), path.trim_start()),
+        _ => (None, rest),
+    };
+    if !path.starts_with('/') {
+        return None;
+    }
+    if status == "missing" {
+        return Some(FileVerification { path: path.to_string(), file_kind: kind, missing: true, changes: Vec::new() });
+    }
+    if status.chars().count() != VERIFY_ATTRIBUTES.len() {
+        return None;
+    }
+    let changes = status
+        .chars()
+        .zip(VERIFY_ATTRIBUTES.iter())
+        .filter(|(flag, (expected, _))| flag == expected || *flag == '?')
+        .map(|(flag, (_, name))| if flag == '?' { format!("{} (unreadable)", name) } else { name.to_string() })
+        .collect();
+    Some(FileVerification { path: path.to_string(), file_kind: kind, missing: false, changes })
+}
+
+fn parse_verify_output(package: &str, output: &str) -> PackageVerification {
+    let mut files = Vec::new();
+    let mut messages = Vec::new();
+    for line in output.lines().filter(|l| !l.trim().is_empty()) {
+        match parse_verify_line(line) {
+            Some(file) => files.push(file),
+            None => messages.push(line.trim().to_string()),
+        }
+    }
+    PackageVerification {
+        package: package.to_string(),
+        intact: files.is_empty() && messages.is_empty(),
+        files,
+        messages,
+    }
+}
+
+// Checks installed files against the rpm database and reports what was modified or removed.
+#[tauri::command]
+pub async fn verify_package(app: tauri::AppHandle, name: String) -> Result<PackageVerification, NebulaError> {
+    validate_package_name(&name)?;
+    info!("Verifying package '{}'.", name);
+    let out = run_command(&app, "rpm", &["-V", &name]).await?;
+    // rpm -V exits non-zero when it finds discrepancies, so only treat "not installed" as an error.
+    if out.stdout.contains("is not installed") || out.stderr.contains("is not installed") {
+        return Err(format!("Package '{}' is not installed.", name).into());
+    }
+    Ok(parse_verify_output(&name, &out.stdout))
+}
+
+#[tauri::command]
+pub async fn get_package_files(app: tauri::AppHandle, name: String) -> Result<PackageFiles, NebulaError> {
+    validate_package_name(&name)?;
+    info!("Listing files of package '{}'.", name);
+    let out = run_command(&app, "rpm", &["-q", "--queryformat", FILES_QUERY_FORMAT, &name]).await?;
+    if !out.success {
+        return Err(NebulaError::command_failed(out.code, &out.stderr, format!("Package '{}' is not installed: {}", name, out.error_text().trim())));
+    }
+    Ok(parse_files_output(&out.stdout))
+}
+
+#[tauri::command]
+pub async fn get_package_details(app: tauri::AppHandle, name: String) -> Result<PackageDetails, NebulaError> {
+    validate_package_name(&name)?;
+    crate::probes::ensure_supported(&app, crate::probes::ProbedFeature::RpmQueryFormat)?;
+    info!("Fetching package details for '{}'.", name);
+    let out = run_command(&app, "rpm", &["-q", "--queryformat", &DETAILS_QUERY_FORMAT, &name]).await?;
+    if !out.success {
+        return Err(NebulaError::command_failed(out.code, &out.stderr, format!("Package '{}' is not installed: {}", name, out.error_text().trim())));
+    }
+    let known_keys = crate::gpg_keys::imported_key_ids(&app).await?;
+    // Multi-version packages (e.g. kernel) print one record per installed instance;
+    // report the most recently installed one.
+    Ok(parse_details_output(&out.stdout, &known_keys)
+        .into_iter()
+        .max_by_key(|details| details.install_time.unwrap_or(0))
+        .ok_or_else(|| format!("Could not parse rpm output for package '{}'.", name))?)
+}
+
+// Installed packages that are unsigned or signed with a key rpm does not know, so third-party
+// installs stand out in the package list.
+#[tauri::command]
+pub async fn list_unverified_packages(app: tauri::AppHandle) -> Result<Vec<UnverifiedPackage>, NebulaError> {
+    let format = format!("%{{NAME}}\t{}\n", SIGNATURE_TAG);
+    let out = run_command(&app, "rpm", &["-qa", "--queryformat", &format]).await?;
+    if !out.success {
+        return Err(NebulaError::command_failed(out.code, &out.stderr, format!("rpm -qa failed: {}", out.error_text().trim())));
+    }
+    let known_keys = crate::gpg_keys::imported_key_ids(&app).await?;
+    let mut packages: Vec<UnverifiedPackage> = out
+        .stdout
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        // the imported keys themselves show up as unsigned gpg-pubkey packages
+        .filter(|(name, _)| *name != "gpg-pubkey")
+        .map(|(name, raw)| UnverifiedPackage { name: name.to_string(), signature: parse_signature(raw, &known_keys) })
+        .filter(|package| package.signature.status != SignatureStatus::Signed)
+        .collect();
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    packages.dedup_by(|a, b| a.name == b.name);
+    Ok(packages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_details_output() {
+        let output = "bash\n(none)\n5.2.26\n3.fc40\nx86_64\n8745432\nGPL-3.0-or-later\nFedora Project\nhttps://www.gnu.org/software/bash\nThe GNU Bourne Again shell\n1714000000\nbash-5.2.26-3.fc40.src.rpm\nRSA/SHA256, Tue 16 Apr 2024 10:00:00 AM UTC, Key ID 0727707ea15b79cc\nThe GNU Bourne Again shell (Bash) is a shell.\n\nSecond paragraph.\n@@NEBULA-RECORD-END@@\n";
+        let known_keys = HashSet::from(["a15b79cc".to_string()]);
+        let parsed = parse_details_output(output, &known_keys);
+        assert_eq!(parsed.len(), 1);
+        let details = &parsed[0];
+        assert_eq!(details.name, "bash");
+        assert_eq!(details.epoch, None);
+        assert_eq!(details.size_bytes, 8745432);
+        assert_eq!(details.vendor.as_deref(), Some("Fedora Project"));
+        assert_eq!(details.install_time, Some(1714000000));
+        assert_eq!(details.description, "The GNU Bourne Again shell (Bash) is a shell.\n\nSecond paragraph.");
+        assert_eq!(details.signature.status, SignatureStatus::Signed);
+        assert_eq!(details.signature.key_id.as_deref(), Some("0727707ea15b79cc"));
+        assert_eq!(parse_signature("(none)", &known_keys).status, SignatureStatus::Unsigned);
+        assert_eq!(parse_signature("RSA/SHA256, Mon 01 Jan 2024, Key ID 1234567890abcdef", &known_keys).status, SignatureStatus::UnknownKey);
+    }
+
+    #[test]
+    fn test_parse_files_output_groups_by_type() {
+        let output = "/etc/bashrc\tcn\t-rw-r--r--\n\
+        /usr/bin/bash\t\t-rwxr-xr-x\n\
+        /usr/lib64/libfoo.so.1\t\tlrwxrwxrwx\n\
+        /usr/share/doc/bash\t\tdrwxr-xr-x\n\
+        /usr/share/doc/bash/README\td\t-rw-r--r--\n\
+        /usr/share/licenses/bash/COPYING\tl\t-rw-r--r--\n\
+        /usr/share/bash-completion/completions/bash\t\t-rw-r--r--\n";
+        let files = parse_files_output(output);
+        assert_eq!(files.configs, vec!["/etc/bashrc"]);
+        assert_eq!(files.binaries, vec!["/usr/bin/bash"]);
+        assert_eq!(files.libraries, vec!["/usr/lib64/libfoo.so.1"]);
+        assert_eq!(files.docs.len(), 2);
+        assert_eq!(files.other, vec!["/usr/share/bash-completion/completions/bash"]);
+    }
+
+    #[test]
+    fn test_parse_scripts_output() {
+        let output = "preinstall scriptlet (using /bin/sh):\n\
+        getent group foo >/dev/null || groupadd -r foo\n\
+        exit 0\n\
+        postinstall program: /sbin/ldconfig\n\
+        posttrans scriptlet (using <lua>):\n\
+        print(\"done\")\n";
+        let scriptlets = parse_scripts_output(output);
+        assert_eq!(scriptlets.len(), 3);
+        assert_eq!(scriptlets[0].phase, "preinstall");
+        assert_eq!(scriptlets[0].interpreter.as_deref(), Some("/bin/sh"));
+        assert_eq!(scriptlets[0].body, "getent group foo >/dev/null || groupadd -r foo\nexit 0");
+        assert_eq!(scriptlets[1].phase, "postinstall");
+        assert_eq!(scriptlets[1].interpreter.as_deref(), Some("/sbin/ldconfig"));
+        assert!(scriptlets[1].body.is_empty());
+        assert_eq!(scriptlets[2].interpreter.as_deref(), Some("<lua>"));
+    }
+
+    #[test]
+    fn test_parse_verify_output() {
+        let output = "S.5....T.  c /etc/ssh/sshd_config\n\
+        missing     /usr/share/doc/openssh/README\n\
+        .M.......    /usr/bin/ssh\n\
+        ..?......    /usr/libexec/openssh/ssh-keysign\n";
+        let report = parse_verify_output("openssh", output);
+        assert!(!report.intact);
+        assert_eq!(report.files.len(), 4);
+        assert_eq!(report.files[0].file_kind.as_deref(), Some("config"));
+        assert_eq!(report.files[0].changes, vec!["size", "digest", "mtime"]);
+        assert!(report.files[1].missing);
+        assert_eq!(report.files[1].path, "/usr/share/doc/openssh/README");
+        assert_eq!(report.files[2].changes, vec!["mode"]);
+        assert_eq!(report.files[3].changes, vec!["digest (unreadable)"]);
+        assert!(report.messages.is_empty());
+    }
+}
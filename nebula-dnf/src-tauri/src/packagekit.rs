@@ -0,0 +1,254 @@
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager};
+use zbus::zvariant::{DynamicType, OwnedObjectPath};
+use tracing::warn;
+
+use crate::backends::{self, PackageBackend};
+use crate::error::NebulaError;
+use crate::plan::CommandPlan;
+use crate::process::OperationOutputLine;
+use crate::services::OperationManager;
+use crate::settings;
+use crate::PackageOperationResult;
+
+const PACKAGEKIT_BUS_NAME: &str = "org.freedesktop.PackageKit";
+const PACKAGEKIT_PATH: &str = "/org/freedesktop/PackageKit";
+const DAEMON_INTERFACE: &str = "org.freedesktop.PackageKit";
+const TRANSACTION_INTERFACE: &str = "org.freedesktop.PackageKit.Transaction";
+
+// PkFilterEnum and PkTransactionFlagEnum are sent as bitfields of 1 << value.
+const FILTER_NONE: u64 = 1 << 1;
+const FILTER_INSTALLED: u64 = 1 << 2;
+const FILTER_NOT_INSTALLED: u64 = 1 << 3;
+const FILTER_NEWEST: u64 = 1 << 16;
+const FLAG_ONLY_TRUSTED: u64 = 1 << 1;
+const FLAG_SIMULATE: u64 = 1 << 2;
+// PkExitEnum
+const EXIT_SUCCESS: u32 = 1;
+const EXIT_CANCELLED: u32 = 3;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum PackageAction {
+    Install,
+    Remove,
+    Update,
+}
+
+#[derive(Debug, Default)]
+struct TransactionOutcome {
+    exit: u32,
+    packages: Vec<(u32, String)>, // (PkInfoEnum, package id) from Package signals
+    error: Option<String>,
+}
+
+// "name;version;arch;repo" -> ("name", "name-version.arch")
+fn parse_package_id(id: &str) -> (String, String) {
+    let mut parts = id.split(';');
+    let name = parts.next().unwrap_or_default().to_string();
+    let version = parts.next().unwrap_or_default();
+    let arch = parts.next().unwrap_or_default();
+    let display = match (version.is_empty(), arch.is_empty()) {
+        (true, _) => name.clone(),
+        (false, true) => format!("{}-{}", name, version),
+        (false, false) => format!("{}-{}.{}", name, version, arch),
+    };
+    (name, display)
+}
+
+// PkInfoEnum values as they show up in transaction output.
+fn info_label(info: u32) -> &'static str {
+    match info {
+        1 => "Installed",
+        2 => "Available",
+        10 => "Downloading",
+        11 => "Updating",
+        12 => "Installing",
+        13 => "Removing",
+        14 => "Cleaning up",
+        15 => "Obsoleting",
+        19 => "Reinstalling",
+        20 => "Downgrading",
+        _ => "Package",
+    }
+}
+
+fn emit_line(app: &tauri::AppHandle, operation: &str, line: &str) {
+    if let Err(e) = app.emit("operation-output", OperationOutputLine::new(operation, line, false)) {
+        warn!("Failed to emit operation-output event: {}", e);
+    }
+}
+
+// Runs one PackageKit transaction method to completion, forwarding its Package and ItemProgress
+// signals as "operation-output" lines.
+async fn run_transaction<B>(app: &tauri::AppHandle, operation: &str, method: &str, body: &B) -> zbus::Result<TransactionOutcome>
+where
+    B: Serialize + DynamicType,
+{
+    let connection = zbus::Connection::system().await?;
+    let daemon = zbus::Proxy::new(&connection, PACKAGEKIT_BUS_NAME, PACKAGEKIT_PATH, DAEMON_INTERFACE).await?;
+    let path: OwnedObjectPath = daemon.call("CreateTransaction", &()).await?;
+    let transaction = zbus::Proxy::new(&connection, PACKAGEKIT_BUS_NAME, path.as_str(), TRANSACTION_INTERFACE).await?;
+    // subscribed before the call so no signal is missed
+    let mut signals = transaction.receive_all_signals().await?;
+    let operations = app.state::<OperationManager>();
+    operations.track_transaction(operation, path.clone());
+    let result = async {
+        transaction.call_method(method, body).await?;
+        let mut outcome = TransactionOutcome::default();
+        while let Some(message) = signals.next().await {
+            let header = message.header();
+            match header.member().map(|member| member.as_str()) {
+                Some("Package") => {
+                    let (info, id, _summary): (u32, String, String) = message.body().deserialize()?;
+                    emit_line(app, operation, &format!("{} {}", info_label(info), parse_package_id(&id).1));
+                    outcome.packages.push((info, id));
+                }
+                Some("ItemProgress") => {
+                    let (id, _status, percentage): (String, u32, u32) = message.body().deserialize()?;
+                    if percentage <= 100 {
+                        emit_line(app, operation, &format!("{} {}%", parse_package_id(&id).1, percentage));
+                    }
+                }
+                Some("ErrorCode") => {
+                    let (_code, details): (u32, String) = message.body().deserialize()?;
+                    emit_line(app, operation, &details);
+                    outcome.error = Some(details);
+                }
+                Some("Finished") => {
+                    let (exit, _runtime): (u32, u32) = message.body().deserialize()?;
+                    outcome.exit = exit;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        Ok(outcome)
+    }
+    .await;
+    operations.finish_transaction(operation);
+    result
+}
+
+fn dbus_error(e: zbus::Error) -> String {
+    format!("PackageKit request failed: {}", e)
+}
+
+// Package ids for names, as Resolve or GetUpdates report them.
+async fn package_ids(app: &tauri::AppHandle, action: PackageAction, packages: &[String]) -> Result<Vec<String>, String> {
+    let outcome = match action {
+        PackageAction::Install => run_transaction(app, "packagekit_resolve", "Resolve", &(FILTER_NOT_INSTALLED | FILTER_NEWEST, packages)).await,
+        PackageAction::Remove => run_transaction(app, "packagekit_resolve", "Resolve", &(FILTER_INSTALLED, packages)).await,
+        PackageAction::Update => run_transaction(app, "packagekit_resolve", "GetUpdates", &(FILTER_NONE,)).await,
+    }
+    .map_err(dbus_error)?;
+    let mut ids: Vec<String> = outcome
+        .packages
+        .into_iter()
+        .map(|(_, id)| id)
+        .filter(|id| packages.is_empty() || packages.contains(&parse_package_id(id).0))
+        .collect();
+    ids.sort_unstable();
+    ids.dedup();
+    match packages.iter().find(|name| !ids.iter().any(|id| parse_package_id(id).0 == **name)) {
+        Some(missing) if action != PackageAction::Update => Err(format!("PackageKit found no package '{}' to {}.", missing, format!("{:?}", action).to_lowercase())),
+        _ => Ok(ids),
+    }
+}
+
+// Installs, removes or updates packages through packagekitd, which handles authorization once per
+// policy instead of a pkexec prompt per command.
+pub struct PackageKit;
+
+// Its transactions run over D-Bus rather than through the process runner, so they are recorded here.
+impl PackageBackend for PackageKit {
+    async fn run(&self, app: &tauri::AppHandle, action: PackageAction, packages: &[String], dry_run: bool) -> Result<PackageOperationResult, String> {
+        let result = run_package_action(app, action, packages, dry_run).await?;
+        if !dry_run {
+            crate::app_operations::record(app, settings::PackageBackend::PackageKit, action, packages, &result).await;
+        }
+        Ok(result)
+    }
+}
+
+async fn run_package_action(app: &tauri::AppHandle, action: PackageAction, packages: &[String], dry_run: bool) -> Result<PackageOperationResult, String> {
+    let simulated = !dry_run && crate::settings::simulation_mode(app)?;
+    let ids = package_ids(app, action, packages).await?;
+    if ids.is_empty() {
+        return Ok(PackageOperationResult {
+            success: true,
+            message: "Nothing to do.".to_string(),
+            details: None,
+            confirmation_required: false,
+            simulated,
+            reboot_required: false,
+        });
+    }
+    let flags = FLAG_ONLY_TRUSTED | if dry_run || simulated { FLAG_SIMULATE } else { 0 };
+    let operation = format!("packagekit_{:?}", action).to_lowercase();
+    let _busy = (!dry_run).then(|| app.state::<OperationManager>().begin(format!("{:?} {} through PackageKit", action, packages.join(", "))));
+    let outcome = match action {
+        PackageAction::Install => run_transaction(app, &operation, "InstallPackages", &(flags, &ids)).await,
+        // allow_deps removes what depends on the packages, as `dnf remove` does; autoremove off
+        PackageAction::Remove => run_transaction(app, &operation, "RemovePackages", &(flags, &ids, true, false)).await,
+        PackageAction::Update => run_transaction(app, &operation, "UpdatePackages", &(flags, &ids)).await,
+    }
+    .map_err(dbus_error)?;
+
+    let mut method = vec![format!("{:?}Packages", action)];
+    method.extend(ids.iter().cloned());
+    crate::audit::record_command(app, &CommandPlan::new("packagekit", method), outcome.exit == EXIT_SUCCESS, outcome.exit as i32);
+    let success = outcome.exit == EXIT_SUCCESS;
+    let details: Vec<String> = outcome.packages.iter().map(|(info, id)| format!("{} {}", info_label(*info), parse_package_id(id).1)).collect();
+    Ok(PackageOperationResult {
+        success,
+        message: match (success, dry_run || simulated) {
+            (true, true) => format!("Dry run finished: {} change(s) would be made.", details.len()),
+            (true, false) => format!("{:?} finished for {}.", action, if packages.is_empty() { "all packages".to_string() } else { packages.join(", ") }),
+            (false, _) if outcome.exit == EXIT_CANCELLED => "The operation was cancelled.".to_string(),
+            (false, _) => format!("PackageKit failed: {}", outcome.error.as_deref().unwrap_or("unknown error")),
+        },
+        details: Some(details.join("\n")),
+        confirmation_required: false,
+        simulated,
+        reboot_required: false,
+    })
+}
+
+#[tauri::command]
+pub async fn packagekit_package_action(
+    app: tauri::AppHandle,
+    action: PackageAction,
+    packages: Vec<String>,
+    dry_run: bool,
+    confirmed: Option<bool>,
+) -> Result<PackageOperationResult, NebulaError> {
+    Ok(backends::action_command(&PackageKit, &app, action, &packages, dry_run, confirmed).await?)
+}
+
+// Cancels a running PackageKit operation by the name its output events carry, e.g. "packagekit_install".
+#[tauri::command]
+pub async fn cancel_packagekit_operation(app: tauri::AppHandle, operation: String) -> Result<(), NebulaError> {
+    let path = app.state::<OperationManager>().transaction(&operation);
+    let Some(path) = path else {
+        return Err(format!("No PackageKit operation '{}' is running.", operation).into());
+    };
+    let connection = zbus::Connection::system().await.map_err(dbus_error)?;
+    let transaction = zbus::Proxy::new(&connection, PACKAGEKIT_BUS_NAME, path.as_str(), TRANSACTION_INTERFACE).await.map_err(dbus_error)?;
+    transaction.call_method("Cancel", &()).await.map_err(dbus_error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_package_id() {
+        assert_eq!(parse_package_id("htop;3.3.0-3.fc40;x86_64;fedora"), ("htop".to_string(), "htop-3.3.0-3.fc40.x86_64".to_string()));
+        assert_eq!(parse_package_id("bash;5.2.26-3.fc40;x86_64;installed:fedora").0, "bash");
+        assert_eq!(parse_package_id("htop").1, "htop");
+        assert_eq!(info_label(12), "Installing");
+        assert_eq!(FLAG_ONLY_TRUSTED | FLAG_SIMULATE, 6);
+    }
+}
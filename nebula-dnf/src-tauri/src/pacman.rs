@@ -0,0 +1,207 @@
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::backends::{self, PackageBackend};
+use crate::error::NebulaError;
+use crate::packagekit::PackageAction;
+use crate::plan::{strings, CommandPlan};
+use crate::process::run_command;
+use crate::usage::resolve_binary;
+use crate::{DependencyKind, DisplayablePackage, PackageCategory, PackageOperationResult, UserPackageWithDependencies};
+
+// Experimental: the core operations on Arch and its derivatives, through pacman, with expac for
+// sizes and dependencies when it is installed. Dry runs use --print, which needs no root.
+
+// Tab-separated; %m is the installed size in bytes, %G the groups and %D the dependencies.
+const EXPAC_FORMAT: &str = "%n\t%v\t%m\t%G\t%D";
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct PacmanPackage {
+    name: String,
+    version: String,
+    installed_size: Option<u64>, // bytes
+    groups: Vec<String>,
+    depends: Vec<String>,
+}
+
+// "pacman -Qe" style lines: "htop 3.3.0-1".
+fn parse_name_version(output: &str) -> Vec<PacmanPackage> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(name, version)| PacmanPackage {
+            name: name.to_string(),
+            version: version.trim().to_string(),
+            installed_size: None,
+            groups: Vec::new(),
+            depends: Vec::new(),
+        })
+        .collect()
+}
+
+// "glibc ncurses>=6 libnl" -> ["glibc", "ncurses", "libnl"]; expac prints "None" for an empty list.
+fn list_field(field: &str) -> Vec<String> {
+    field
+        .split_whitespace()
+        .filter(|item| *item != "None")
+        .map(|item| item.split(['<', '>', '=']).next().unwrap_or_default().to_string())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+fn parse_expac(output: &str) -> Vec<PacmanPackage> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [name, version, size, groups, depends] = fields[..] else { return None };
+            Some(PacmanPackage {
+                name: name.to_string(),
+                version: version.to_string(),
+                installed_size: size.trim().parse().ok(),
+                groups: list_field(groups),
+                depends: list_field(depends),
+            })
+        })
+        .collect()
+}
+
+// Arch has no per-package section, only the groups a few packages belong to.
+fn category_from_groups(groups: &[String]) -> PackageCategory {
+    let in_group = |prefixes: &[&str]| groups.iter().any(|group| prefixes.iter().any(|prefix| group.starts_with(prefix)));
+    if in_group(&["gnome", "kde", "plasma", "xfce4", "lxqt", "mate", "cinnamon"]) {
+        PackageCategory::DesktopEnvironment
+    } else if in_group(&["base-devel"]) {
+        PackageCategory::Development
+    } else if in_group(&["xorg", "base"]) {
+        PackageCategory::System
+    } else {
+        PackageCategory::Unknown
+    }
+}
+
+// The explicitly installed packages (`pacman -Qe`), with their details from expac when it is
+// installed and without dependencies otherwise.
+async fn explicit(app: &tauri::AppHandle) -> Result<Vec<PacmanPackage>, String> {
+    let out = run_command(app, "pacman", &["-Qe"]).await?;
+    if !out.success {
+        return Err(format!("pacman -Qe failed: {}", out.error_text().trim()));
+    }
+    let explicit = parse_name_version(&out.stdout);
+    if resolve_binary("expac").is_none() {
+        return Ok(explicit);
+    }
+    let mut args = vec!["-Q", "-l", " ", EXPAC_FORMAT];
+    args.extend(explicit.iter().map(|package| package.name.as_str()));
+    let out = run_command(app, "expac", &args).await?;
+    if !out.success {
+        return Err(format!("expac failed: {}", out.error_text().trim()));
+    }
+    let names: HashSet<&str> = explicit.iter().map(|package| package.name.as_str()).collect();
+    Ok(parse_expac(&out.stdout).into_iter().filter(|package| names.contains(package.name.as_str())).collect())
+}
+
+// The explicitly installed packages in the shape the package list and its cache use.
+async fn user_installed_packages(app: &tauri::AppHandle) -> Result<Vec<UserPackageWithDependencies>, String> {
+    let mut packages: Vec<UserPackageWithDependencies> = explicit(app)
+        .await?
+        .into_iter()
+        .map(|package| UserPackageWithDependencies {
+            category: category_from_groups(&package.groups),
+            dependencies: package
+                .depends
+                .into_iter()
+                .map(|name| DisplayablePackage { name, kind: DependencyKind::Package, module_provider: None })
+                .collect(),
+            incomplete: false,
+            installed_size: package.installed_size,
+            appstream: None,
+            name: package.name,
+        })
+        .collect();
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(packages)
+}
+
+// Real runs take --noconfirm and root; dry runs --print the targets instead, unprivileged, and
+// skip the sync (-y) since that writes the databases.
+pub fn action_plan(action: PackageAction, packages: &[String], dry_run: bool) -> CommandPlan {
+    let mut args = match (action, packages.is_empty()) {
+        (PackageAction::Install, _) | (PackageAction::Update, false) => strings(&["-S", "--needed"]),
+        // like dnf's clean_requirements_on_remove
+        (PackageAction::Remove, _) => strings(&["-Rs"]),
+        (PackageAction::Update, true) => strings(&[if dry_run { "-Su" } else { "-Syu" }]),
+    };
+    args.push(if dry_run { "--print" } else { "--noconfirm" }.to_string());
+    args.extend(packages.iter().cloned());
+    if dry_run {
+        CommandPlan::new("pacman", args)
+    } else {
+        CommandPlan::privileged("pacman", args)
+    }
+}
+
+// Installs, removes or updates packages with pacman, and lists the explicitly installed ones.
+pub struct Pacman;
+
+impl PackageBackend for Pacman {
+    async fn list(&self, app: &tauri::AppHandle) -> Result<Option<Vec<UserPackageWithDependencies>>, String> {
+        Ok(Some(user_installed_packages(app).await?))
+    }
+
+    async fn run(&self, app: &tauri::AppHandle, action: PackageAction, packages: &[String], dry_run: bool) -> Result<PackageOperationResult, String> {
+        backends::run_cli(app, "pacman", action_plan(action, packages, dry_run), action, packages, dry_run).await
+    }
+}
+
+#[tauri::command]
+pub async fn pacman_package_action(
+    app: tauri::AppHandle,
+    action: PackageAction,
+    packages: Vec<String>,
+    dry_run: bool,
+    confirmed: Option<bool>,
+) -> Result<PackageOperationResult, NebulaError> {
+    Ok(backends::action_command(&Pacman, &app, action, &packages, dry_run, confirmed).await?)
+}
+
+#[tauri::command]
+pub async fn pacman_list_explicit(app: tauri::AppHandle) -> Result<Vec<PacmanPackage>, NebulaError> {
+    Ok(explicit(&app).await?)
+}
+
+// Dependencies nothing requires anymore (`pacman -Qdtq`).
+#[tauri::command]
+pub async fn pacman_list_orphans(app: tauri::AppHandle) -> Result<Vec<DisplayablePackage>, NebulaError> {
+    let out = run_command(&app, "pacman", &["-Qdtq"]).await?;
+    // exits 1 with no output when there are none
+    if !out.success && (out.code != 1 || !out.stdout.trim().is_empty()) {
+        return Err(NebulaError::command_failed(out.code, &out.stderr, format!("pacman -Qdtq failed: {}", out.error_text().trim())));
+    }
+    Ok(out
+        .stdout
+        .lines()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| DisplayablePackage { name: name.to_string(), kind: DependencyKind::Package, module_provider: None })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pacman_parsing() {
+        assert_eq!(parse_name_version("htop 3.3.0-1\nvim 9.1.0-2\n").len(), 2);
+
+        let packages = parse_expac("htop\t3.3.0-1\t421888\tNone\tglibc libcap libnl ncurses>=6\ngedit\t46.2-1\t9000000\tgnome-extra\tgtksourceview5 libpeas\n");
+        assert_eq!(packages[0].installed_size, Some(421888));
+        assert_eq!(packages[0].depends, ["glibc", "libcap", "libnl", "ncurses"]);
+        assert!(packages[0].groups.is_empty());
+        assert_eq!(category_from_groups(&packages[1].groups), PackageCategory::DesktopEnvironment);
+
+        assert_eq!(action_plan(PackageAction::Remove, &strings(&["htop"]), true).command_line(), "pacman -Rs --print htop");
+        assert_eq!(action_plan(PackageAction::Update, &[], false).command_line(), "pkexec pacman -Syu --noconfirm");
+    }
+}
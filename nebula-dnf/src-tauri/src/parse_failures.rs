@@ -0,0 +1,162 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+use tracing::warn;
+
+use crate::error::NebulaError;
+use crate::{app_data_file, append_json_line, load_json_lines};
+
+const PARSE_FAILURES_FILE_NAME: &str = "parse_failures.jsonl";
+// Lines kept per record; one odd package should not fill the log.
+const MAX_SAMPLE_LINES: usize = 20;
+// Records kept in the log; older ones are dropped as new ones come in.
+const MAX_RECORDS: usize = 200;
+const DEFAULT_LIST_LIMIT: usize = 100;
+
+// Held while the log is read and written back, so concurrent records neither lose nor
+// duplicate each other.
+static LOG_LOCK: Mutex<()> = Mutex::new(());
+
+// Output lines a parser could not interpret, kept so the parser can be fixed against what the
+// installed dnf/rpm actually print.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ParseFailure {
+    timestamp: String, // RFC 3339
+    parser: String,    // the command whose output it was, e.g. "dnf history list"
+    app_version: String,
+    lines: Vec<String>,
+    omitted: usize, // lines beyond MAX_SAMPLE_LINES
+}
+
+fn sample(lines: &[String]) -> (Vec<String>, usize) {
+    let mut kept: Vec<String> = Vec::new();
+    for line in lines.iter().map(|line| line.trim_end()) {
+        if !kept.iter().any(|existing| existing == line) {
+            kept.push(line.to_string());
+        }
+    }
+    let omitted = kept.len().saturating_sub(MAX_SAMPLE_LINES);
+    kept.truncate(MAX_SAMPLE_LINES);
+    (kept, omitted)
+}
+
+fn same_failure(a: &ParseFailure, b: &ParseFailure) -> bool {
+    a.parser == b.parser && a.lines == b.lines
+}
+
+// Adds `entry` unless the same failure is already logged (None). Otherwise returns whether older
+// records were dropped to stay within MAX_RECORDS, i.e. whether the file has to be rewritten
+// rather than appended to.
+fn merge(existing: &mut Vec<ParseFailure>, entry: ParseFailure) -> Option<bool> {
+    if existing.iter().any(|known| same_failure(known, &entry)) {
+        return None;
+    }
+    existing.push(entry);
+    let excess = existing.len().saturating_sub(MAX_RECORDS);
+    existing.drain(..excess);
+    Some(excess > 0)
+}
+
+fn save(path: &Path, existing: &mut Vec<ParseFailure>, entry: ParseFailure) -> Result<(), String> {
+    match merge(existing, entry) {
+        None => Ok(()),
+        Some(false) => append_json_line(path, existing.last().expect("merge just added the entry")),
+        Some(true) => {
+            let mut contents = String::new();
+            for failure in existing.iter() {
+                contents.push_str(&serde_json::to_string(failure).map_err(|e| format!("Failed to serialize record: {}", e))?);
+                contents.push('\n');
+            }
+            // written aside and renamed over, so the log is never left half-written
+            let staged = path.with_extension("jsonl.tmp");
+            std::fs::write(&staged, contents).map_err(|e| format!("Failed to write {:?}: {}", staged, e))?;
+            std::fs::rename(&staged, path).map_err(|e| format!("Failed to replace {:?}: {}", path, e))
+        }
+    }
+}
+
+// Logs what `parser` left unparsed, once per distinct failure, and returns whether there was
+// anything, i.e. whether the result built from that output is incomplete. Best effort, like the
+// audit trail.
+pub async fn record(app: &tauri::AppHandle, parser: &str, unparsed: &[String]) -> bool {
+    if unparsed.is_empty() {
+        return false;
+    }
+    let (lines, omitted) = sample(unparsed);
+    let entry = ParseFailure {
+        timestamp: Local::now().to_rfc3339(),
+        parser: parser.to_string(),
+        app_version: app.package_info().version.to_string(),
+        lines,
+        omitted,
+    };
+    let result = match app_data_file(app, PARSE_FAILURES_FILE_NAME) {
+        Ok(path) => tauri::async_runtime::spawn_blocking(move || {
+            let _lock = LOG_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let mut existing: Vec<ParseFailure> = load_json_lines(&path)?;
+            save(&path, &mut existing, entry)
+        })
+        .await
+        .unwrap_or_else(|e| Err(format!("parse failure task failed: {}", e))),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        warn!("Failed to record parse failure: {}", e);
+    }
+    true
+}
+
+// Newest first; the last `limit` records (default 100).
+#[tauri::command]
+pub async fn list_parse_failures(app: tauri::AppHandle, limit: Option<usize>) -> Result<Vec<ParseFailure>, NebulaError> {
+    let mut failures: Vec<ParseFailure> = load_json_lines(&app_data_file(&app, PARSE_FAILURES_FILE_NAME)?)?;
+    failures.reverse();
+    failures.truncate(limit.unwrap_or(DEFAULT_LIST_LIMIT));
+    Ok(failures)
+}
+
+#[tauri::command]
+pub async fn clear_parse_failures(app: tauri::AppHandle) -> Result<(), NebulaError> {
+    let path = app_data_file(&app, PARSE_FAILURES_FILE_NAME)?;
+    match std::fs::remove_file(&path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(format!("Failed to remove {:?}: {}", path, e).into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample() {
+        let lines: Vec<String> = (0..30).map(|i| format!("odd line {}  ", i % 25)).collect();
+        let (kept, omitted) = sample(&lines);
+        assert_eq!(kept.len(), MAX_SAMPLE_LINES);
+        assert_eq!(kept[0], "odd line 0");
+        assert_eq!(omitted, 5);
+        assert_eq!(sample(&["x".to_string(), "x".to_string()]), (vec!["x".to_string()], 0));
+    }
+
+    #[test]
+    fn test_merge() {
+        let failure = |line: &str| ParseFailure {
+            timestamp: String::new(),
+            parser: "rpm -qR".to_string(),
+            app_version: String::new(),
+            lines: vec![line.to_string()],
+            omitted: 0,
+        };
+        let mut existing = vec![failure("foo: odd")];
+        assert_eq!(merge(&mut existing, failure("foo: odd")), None);
+        assert_eq!(merge(&mut existing, failure("bar: odd")), Some(false));
+        assert_eq!(existing.len(), 2);
+
+        let mut full: Vec<ParseFailure> = (0..MAX_RECORDS).map(|i| failure(&i.to_string())).collect();
+        assert_eq!(merge(&mut full, failure("new")), Some(true));
+        assert_eq!(full.len(), MAX_RECORDS);
+        assert_eq!(full[0].lines, ["1"]);
+        assert_eq!(full.last().unwrap().lines, ["new"]);
+    }
+}
@@ -0,0 +1,161 @@
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::Emitter;
+use tracing::{error, info, warn};
+
+use crate::error::NebulaError;
+use crate::{app_data_file, load_json_file, save_json_file, validate_package_name};
+
+const PINS_FILE_NAME: &str = "pins.json";
+const REMINDER_CHECK_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+// App-level pin: the package is excluded from every update the app runs until the pin is removed.
+// Unlike versionlock this carries a reason and a date by which the user should reconsider it.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PackagePin {
+    package: String,
+    version: String, // the version the user wants to stay on, informational
+    reason: String,
+    review_date: NaiveDate,
+}
+
+fn load_pins(app: &tauri::AppHandle) -> Result<Vec<PackagePin>, String> {
+    Ok(load_json_file(&app_data_file(app, PINS_FILE_NAME)?)?.unwrap_or_default())
+}
+
+fn save_pins(app: &tauri::AppHandle, pins: &[PackagePin]) -> Result<(), String> {
+    save_json_file(&app_data_file(app, PINS_FILE_NAME)?, &pins)
+}
+
+// Names to pass as `--exclude` to every update. Errors rather than returning an empty list,
+// so an unreadable pins file never silently lifts the pins.
+pub fn pinned_packages(app: &tauri::AppHandle) -> Result<Vec<String>, String> {
+    Ok(load_pins(app)?.into_iter().map(|pin| pin.package).collect())
+}
+
+// Backends other than the dnf CLI cannot be told to exclude packages, so an update through one
+// is refused while it would move a pinned package: any full update, or one naming a pin.
+pub fn refuse_unexcluded_update(pinned: &[String], packages: &[String]) -> Result<(), String> {
+    let moved: Vec<&str> = if packages.is_empty() {
+        pinned.iter().map(String::as_str).collect()
+    } else {
+        pinned.iter().filter(|pin| packages.contains(pin)).map(String::as_str).collect()
+    };
+    if moved.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "Pinned packages would be updated: {}. Only the dnf backend can leave them out; switch to it or remove the pins first.",
+        moved.join(", ")
+    ))
+}
+
+fn stale_pins(pins: &[PackagePin], today: NaiveDate) -> Vec<PackagePin> {
+    pins.iter().filter(|pin| pin.review_date <= today).cloned().collect()
+}
+
+// Background loop started at app setup; emits "pins-review-due" at most once a day while any
+// pin is past its review date.
+pub async fn run_review_reminders(app: tauri::AppHandle) {
+    let mut last_reminded: Option<NaiveDate> = None;
+    loop {
+        let today = Local::now().date_naive();
+        if last_reminded != Some(today) {
+            match load_pins(&app) {
+                Ok(pins) => {
+                    let stale = stale_pins(&pins, today);
+                    if !stale.is_empty() {
+                        if let Err(e) = app.emit("pins-review-due", &stale) {
+                            warn!("Failed to emit pins-review-due event: {}", e);
+                        }
+                        last_reminded = Some(today);
+                    }
+                }
+                Err(e) => error!("Pin reminder error: {}", e),
+            }
+        }
+        tokio::time::sleep(REMINDER_CHECK_INTERVAL).await;
+    }
+}
+
+#[tauri::command]
+pub async fn list_pins(app: tauri::AppHandle) -> Result<Vec<PackagePin>, NebulaError> {
+    Ok(load_pins(&app)?)
+}
+
+#[tauri::command]
+pub async fn get_stale_pins(app: tauri::AppHandle) -> Result<Vec<PackagePin>, NebulaError> {
+    Ok(stale_pins(&load_pins(&app)?, Local::now().date_naive()))
+}
+
+// Adds a pin, replacing any existing pin for the same package.
+#[tauri::command]
+pub async fn add_pin(app: tauri::AppHandle, pin: PackagePin) -> Result<Vec<PackagePin>, NebulaError> {
+    validate_package_name(&pin.package)?;
+    if pin.version.trim().is_empty() {
+        return Err("A pin needs the version to stay on.".into());
+    }
+    if pin.review_date <= Local::now().date_naive() {
+        return Err("The review date must be in the future.".into());
+    }
+    let mut pins = load_pins(&app)?;
+    pins.retain(|existing| existing.package != pin.package);
+    info!("Pinning {} at {} until {}.", pin.package, pin.version, pin.review_date);
+    pins.push(pin);
+    pins.sort_by(|a, b| a.package.cmp(&b.package));
+    save_pins(&app, &pins)?;
+    Ok(pins)
+}
+
+#[tauri::command]
+pub async fn remove_pin(app: tauri::AppHandle, package: String) -> Result<Vec<PackagePin>, NebulaError> {
+    let mut pins = load_pins(&app)?;
+    pins.retain(|pin| pin.package != package);
+    save_pins(&app, &pins)?;
+    Ok(pins)
+}
+
+// Keeps a pin after review by moving its review date forward.
+#[tauri::command]
+pub async fn renew_pin(app: tauri::AppHandle, package: String, review_date: NaiveDate) -> Result<Vec<PackagePin>, NebulaError> {
+    if review_date <= Local::now().date_naive() {
+        return Err("The review date must be in the future.".into());
+    }
+    let mut pins = load_pins(&app)?;
+    let pin = pins
+        .iter_mut()
+        .find(|pin| pin.package == package)
+        .ok_or_else(|| format!("'{}' is not pinned.", package))?;
+    pin.review_date = review_date;
+    save_pins(&app, &pins)?;
+    Ok(pins)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stale_pins() {
+        let pin = |package: &str, review: &str| PackagePin {
+            package: package.to_string(),
+            version: "1.0".to_string(),
+            reason: String::new(),
+            review_date: review.parse().unwrap(),
+        };
+        let pins = vec![pin("mesa", "2024-05-01"), pin("kernel", "2024-06-01")];
+        let stale = stale_pins(&pins, "2024-05-01".parse().unwrap());
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].package, "mesa");
+    }
+
+    #[test]
+    fn test_refuse_unexcluded_update() {
+        let pinned = vec!["mesa".to_string()];
+        assert!(refuse_unexcluded_update(&pinned, &[]).is_err());
+        assert!(refuse_unexcluded_update(&pinned, &["mesa".to_string()]).is_err());
+        assert!(refuse_unexcluded_update(&pinned, &["htop".to_string()]).is_ok());
+        assert!(refuse_unexcluded_update(&[], &[]).is_ok());
+    }
+}
@@ -0,0 +1,657 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::dnf_cache::CacheKind;
+use crate::error::NebulaError;
+use crate::{validate_package_name, UninstallArgs, UninstallMode};
+
+// dnf subcommands that resolve a transaction and ask for confirmation before running it.
+const DNF_TRANSACTION_COMMANDS: &[&str] = &[
+    "do", "install", "remove", "erase", "update", "upgrade", "downgrade", "reinstall", "autoremove", "distro-sync", "swap", "group", "shell",
+];
+
+// zypper subcommands that take --dry-run; zypper plans put --non-interactive before them.
+const ZYPPER_TRANSACTION_COMMANDS: &[&str] = &["install", "remove", "update"];
+
+// flatpak subcommands that change an installation. Flatpak authorizes system-wide changes itself
+// through polkit, so these run unprivileged.
+const FLATPAK_CHANGE_COMMANDS: &[&str] = &["install", "uninstall", "update", "remote-add", "remote-delete"];
+
+// comps group and environment ids, e.g. "c-development".
+static GROUP_ID_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[a-z0-9_][a-z0-9_.-]*$").unwrap());
+
+// Helper installed with the com.nebula-dnf.query polkit action: a fixed set of read-only queries
+// that can be authorized separately from package management.
+pub const QUERY_HELPER_PATH: &str = "/usr/libexec/nebula-dnf/nebula-dnf-query";
+
+// Printed to stdout and stderr between the steps of a batched plan, so the output can be split.
+pub const BATCH_STEP_MARKER: &str = "@@nebula-dnf-next-step";
+
+// The exact argv the backend will run for an operation. Every mutating operation builds its
+// commands through here, so what `preview_command` shows is what actually gets executed.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct CommandPlan {
+    pub program: String,
+    pub args: Vec<String>,
+    pub privileged: bool,
+    command_line: String, // shell-quoted rendering of program + args, for display
+}
+
+impl CommandPlan {
+    pub fn new(program: &str, args: Vec<String>) -> Self {
+        let command_line = std::iter::once(program.to_string())
+            .chain(args.iter().map(|arg| shell_quote(arg)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        CommandPlan {
+            program: program.to_string(),
+            privileged: program == "pkexec",
+            args,
+            command_line,
+        }
+    }
+
+    // Runs `program args...` as root through pkexec.
+    pub fn privileged(program: &str, args: Vec<String>) -> Self {
+        let mut full_args = vec![program.to_string()];
+        full_args.extend(args);
+        CommandPlan::new("pkexec", full_args)
+    }
+
+    // A read-only query run as root through the query helper, e.g. `query(&["history-list"])`.
+    pub fn query(args: &[&str]) -> Self {
+        CommandPlan::privileged(QUERY_HELPER_PATH, strings(args))
+    }
+
+    // Several root commands as one `pkexec sh -c` script, so a multi-step operation asks for
+    // authentication once. The steps stop at the first failure, as `&&` chains do. Every word is
+    // shell-quoted, and the plans come from the validated builders here. None unless there are at
+    // least two plans and all of them run as root.
+    pub fn batched(plans: &[CommandPlan]) -> Option<CommandPlan> {
+        if plans.len() < 2 || plans.iter().any(|plan| !plan.privileged || plan.is_query()) {
+            return None;
+        }
+        let separator = format!(" && echo {0} && echo {0} >&2 && ", BATCH_STEP_MARKER);
+        let script = plans
+            .iter()
+            .map(|plan| plan.args.iter().map(|arg| shell_quote(arg)).collect::<Vec<_>>().join(" "))
+            .collect::<Vec<_>>()
+            .join(&separator);
+        Some(CommandPlan::privileged("sh", vec!["-c".to_string(), script]))
+    }
+
+    // The command pkexec runs, without an `env NAME=value` prefix; empty for unprivileged plans.
+    pub fn root_command(&self) -> &[String] {
+        if self.privileged { split_env(&self.args).1 } else { &[] }
+    }
+
+    fn is_query(&self) -> bool {
+        self.privileged && self.args.first().is_some_and(|tool| tool == QUERY_HELPER_PATH)
+    }
+
+    // A root dnf/rpm/zypper/apt-get/pacman run, or a helper call, that changes the installed package
+    // set or stages a change for the next boot. Cache maintenance and the reboot or clean of a
+    // staged transaction (`dnf makecache`, `dnf clean`, `dnf offline clean`, ...) are not.
+    pub fn is_package_transaction(&self) -> bool {
+        let Some((tool, rest)) = self.root_command().split_first() else { return false };
+        match tool.as_str() {
+            "dnf" => {
+                let mut words = rest.iter().map(String::as_str).filter(|arg| !arg.starts_with('-'));
+                match words.next() {
+                    Some("system-upgrade" | "offline-upgrade") => words.next() == Some("download"),
+                    Some(command) => DNF_TRANSACTION_COMMANDS.contains(&command) || matches!(command, "history" | "module"),
+                    None => false,
+                }
+            }
+            "rpm" | "zypper" | "apt-get" | "pacman" => true,
+            tool => tool == crate::helper::HELPER_PATH,
+        }
+    }
+
+    fn is_flatpak_change(&self) -> bool {
+        self.program == "flatpak" && self.args.first().is_some_and(|command| FLATPAK_CHANGE_COMMANDS.contains(&command.as_str()))
+    }
+
+    // fwupd, like Flatpak, authorizes through polkit and has no dry-run form.
+    fn is_firmware_change(&self) -> bool {
+        self.program == "fwupdmgr" && self.args.first().is_some_and(|command| matches!(command.as_str(), "update" | "install" | "downgrade"))
+    }
+
+    // Homebrew and Nix run as the user and by full path, since they live outside the system bin dirs.
+    fn userspace_tool(&self) -> Option<&str> {
+        let tool = std::path::Path::new(&self.program).file_name()?.to_str()?;
+        let command: Vec<&str> = self.args.iter().take(2).map(String::as_str).collect();
+        match (tool, command.as_slice()) {
+            ("brew", ["upgrade" | "install" | "uninstall", ..]) | ("nix", ["profile", "upgrade" | "install" | "remove"]) => Some(tool),
+            _ => None,
+        }
+    }
+
+    // What runs instead in simulation mode: the dry-run form of a package transaction, or just a
+    // note for other root commands and Flatpak changes, which have no dry-run form. None for other
+    // unprivileged commands and read-only queries, which run as they are.
+    pub fn simulated(&self) -> Option<CommandPlan> {
+        if self.is_flatpak_change() || self.is_firmware_change() {
+            return Some(self.simulation_note());
+        }
+        match self.userspace_tool() {
+            Some("brew") => {
+                let mut args = self.args.clone();
+                if !args.iter().any(|arg| arg == "--dry-run") {
+                    args.insert(1, "--dry-run".to_string());
+                }
+                return Some(CommandPlan::new(&self.program, args));
+            }
+            Some(_) => return Some(self.simulation_note()),
+            None => {}
+        }
+        if !self.privileged || self.is_query() {
+            return None;
+        }
+        let (env, command) = split_env(&self.args);
+        let (tool, rest) = command.split_first()?;
+        let mut args = rest.to_vec();
+        match tool.as_str() {
+            // Only transactions honor --assumeno; e.g. `dnf versionlock add` would still write its lock.
+            "dnf" if DNF_TRANSACTION_COMMANDS.contains(&rest.first().map(String::as_str).unwrap_or_default()) => {
+                args.retain(|arg| arg != "--assumeyes" && arg != "-y");
+                args.push("--assumeno".to_string());
+            }
+            "rpm" if matches!(rest.first().map(String::as_str), Some("-e" | "-i" | "-U" | "-F")) => args.push("--test".to_string()),
+            // zypper wants root even to resolve a dry run, so this one stays privileged.
+            "zypper" if ZYPPER_TRANSACTION_COMMANDS.contains(&rest.get(1).map(String::as_str).unwrap_or_default()) => {
+                if !args.iter().any(|arg| arg == "--dry-run") {
+                    args.insert(2, "--dry-run".to_string());
+                }
+                return Some(CommandPlan::privileged(tool, args));
+            }
+            // apt plans lead with -y; -s simulates instead and needs no root.
+            "apt-get" if rest.first().is_some_and(|arg| arg == "-y") => args[0] = "-s".to_string(),
+            // pacman plans carry --noconfirm; --print lists the targets unprivileged, without the
+            // database sync of -Syu.
+            "pacman" if rest.iter().any(|arg| arg == "--noconfirm") => {
+                for arg in args.iter_mut() {
+                    match arg.as_str() {
+                        "--noconfirm" => *arg = "--print".to_string(),
+                        "-Syu" => *arg = "-Su".to_string(),
+                        _ => {}
+                    }
+                }
+            }
+            _ => return Some(self.simulation_note()),
+        }
+        if env.is_empty() {
+            return Some(CommandPlan::new(tool, args));
+        }
+        let mut env_args = env[1..].to_vec();
+        env_args.push(tool.clone());
+        env_args.extend(args);
+        Some(CommandPlan::new("env", env_args))
+    }
+
+    fn simulation_note(&self) -> CommandPlan {
+        CommandPlan::new("echo", vec![format!("Simulation mode: not running {}", self.command_line)])
+    }
+
+    pub fn arg_refs(&self) -> Vec<&str> {
+        self.args.iter().map(String::as_str).collect()
+    }
+
+    pub fn command_line(&self) -> &str {
+        &self.command_line
+    }
+}
+
+// Quotes a word for POSIX sh if it contains anything beyond a conservative safe set.
+pub fn shell_quote(word: &str) -> String {
+    let is_safe = !word.is_empty()
+        && word.chars().all(|c| c.is_ascii_alphanumeric() || "_-+=./:@%,^~".contains(c));
+    if is_safe {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', r"'\''"))
+    }
+}
+
+// Splits an `env NAME=value... command` argv (see options::AdvancedOptions) into the env
+// prefix, which is empty when there is none, and the command itself.
+fn split_env(args: &[String]) -> (&[String], &[String]) {
+    if args.first().is_none_or(|arg| arg != "env") {
+        return (&[], args);
+    }
+    let assignments = args[1..].iter().take_while(|arg| arg.contains('=')).count();
+    args.split_at(1 + assignments)
+}
+
+pub fn strings(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+// `pinned` is the list from pins::pinned_packages; updating a pinned package directly is refused.
+pub fn update_package(package_name: &str, pinned: &[String]) -> Result<CommandPlan, String> {
+    validate_package_name(package_name)?;
+    if pinned.iter().any(|p| p == package_name) {
+        return Err(format!("'{}' is pinned. Remove or renew the pin to update it.", package_name));
+    }
+    Ok(CommandPlan::privileged("dnf", strings(&["update", package_name, "--assumeyes"])))
+}
+
+pub fn upgrade_all(security_only: bool, pinned: &[String]) -> CommandPlan {
+    CommandPlan::privileged("dnf", upgrade_args(security_only, pinned))
+}
+
+// Upgrades the given packages, or everything when `packages` is empty. Pinned packages are excluded.
+pub fn upgrade_packages(packages: &[String], security_only: bool, pinned: &[String]) -> Result<CommandPlan, String> {
+    for package in packages {
+        validate_package_name(package)?;
+    }
+    let mut args = upgrade_args(security_only, pinned);
+    args.extend(packages.iter().cloned());
+    Ok(CommandPlan::privileged("dnf", args))
+}
+
+fn upgrade_args(security_only: bool, pinned: &[String]) -> Vec<String> {
+    let mut args = strings(&["upgrade", "--assumeyes"]);
+    if security_only {
+        args.push("--security".to_string());
+    }
+    args.extend(pinned.iter().map(|package| format!("--exclude={}", package)));
+    args
+}
+
+// The removal itself, followed by `dnf autoremove` when orphan cleanup was requested for a safe removal.
+pub fn uninstall(args: &UninstallArgs) -> Result<Vec<CommandPlan>, String> {
+    if args.packages.is_empty() {
+        return Err("No packages given.".to_string());
+    }
+    for package in &args.packages {
+        validate_package_name(package)?;
+    }
+    // Every name goes into one invocation, so dnf resolves them as one transaction.
+    let with_packages = |head: &[&str], tail: &[&str]| {
+        let mut words = strings(head);
+        words.extend(args.packages.iter().cloned());
+        words.extend(strings(tail));
+        words
+    };
+    let removal = match args.mode() {
+        UninstallMode::Safe => CommandPlan::privileged("dnf", with_packages(&["remove"], &["--assumeyes"])),
+        UninstallMode::Force => CommandPlan::privileged("rpm", with_packages(&["-e", "--nodeps"], &[])),
+        UninstallMode::DryRunSafe => CommandPlan::new("dnf", with_packages(&["remove"], &["--assumeno"])),
+        UninstallMode::DryRunForce => CommandPlan::new("rpm", with_packages(&["-e", "--nodeps"], &["--test"])),
+    };
+    let mut plans = vec![removal];
+    if matches!(args.mode(), UninstallMode::Safe) && args.cleanup_orphans {
+        plans.push(autoremove());
+    }
+    Ok(plans)
+}
+
+// An install or upgrade plan that only downloads the packages into dnf's cache
+// (`--downloadonly`), e.g. on a metered connection. Running the same operation later takes them
+// from the cache, so it needs no connection beyond the metadata.
+pub fn download_only(plan: &CommandPlan) -> Result<CommandPlan, String> {
+    let is_fetch = plan.privileged && plan.args.first().is_some_and(|tool| tool == "dnf")
+        && plan.args.get(1).is_some_and(|command| matches!(command.as_str(), "install" | "update" | "upgrade"));
+    if !is_fetch {
+        return Err(format!("`{}` cannot be run download-only.", plan.command_line()));
+    }
+    let mut args = plan.args[1..].to_vec();
+    args.insert(1, "--downloadonly".to_string());
+    Ok(CommandPlan::privileged("dnf", args))
+}
+
+// A transaction plan staged for the next boot instead of run now, as GNOME Software applies
+// updates: dnf5 takes `--offline` on any transaction, dnf4 only upgrades, through the
+// offline-upgrade plugin. offline_reboot then boots into it.
+pub fn offline(plan: &CommandPlan, dnf5: bool) -> Result<CommandPlan, String> {
+    let command = plan.args.get(1).map(String::as_str).unwrap_or_default();
+    if !plan.privileged || plan.args.first().is_none_or(|tool| tool != "dnf") {
+        return Err(format!("`{}` cannot be staged for the next boot.", plan.command_line()));
+    }
+    let mut args = plan.args[1..].to_vec();
+    match (command, dnf5) {
+        ("install" | "remove" | "update" | "upgrade" | "do", true) => args.insert(1, "--offline".to_string()),
+        ("update" | "upgrade", false) => {
+            args[0] = "download".to_string();
+            args.insert(0, "offline-upgrade".to_string());
+        }
+        (_, false) => return Err("Only updates can be staged for the next boot with dnf4.".to_string()),
+        (_, true) => return Err(format!("`{}` cannot be staged for the next boot.", plan.command_line())),
+    }
+    Ok(CommandPlan::privileged("dnf", args))
+}
+
+// Reboots into the staged transaction, or with `clean` drops it.
+pub fn offline_command(dnf5: bool, clean: bool) -> CommandPlan {
+    let action = if clean { "clean" } else { "reboot" };
+    CommandPlan::privileged("dnf", strings(&[if dnf5 { "offline" } else { "offline-upgrade" }, action]))
+}
+
+// With `dry_run` the install is only resolved (`--assumeno`), unprivileged.
+pub fn install_packages(packages: &[String], dry_run: bool) -> Result<CommandPlan, String> {
+    if packages.is_empty() {
+        return Err("No packages given.".to_string());
+    }
+    for package in packages {
+        validate_package_name(package)?;
+    }
+    let mut args = strings(&["install", if dry_run { "--assumeno" } else { "--assumeyes" }]);
+    args.extend(packages.iter().cloned());
+    Ok(if dry_run { CommandPlan::new("dnf", args) } else { CommandPlan::privileged("dnf", args) })
+}
+
+// With `dry_run` the removal is only resolved (`--assumeno`), unprivileged.
+pub fn remove_packages(packages: &[String], dry_run: bool) -> Result<CommandPlan, String> {
+    if packages.is_empty() {
+        return Err("No packages given.".to_string());
+    }
+    for package in packages {
+        validate_package_name(package)?;
+    }
+    let mut args = strings(&["remove", if dry_run { "--assumeno" } else { "--assumeyes" }]);
+    args.extend(packages.iter().cloned());
+    Ok(if dry_run { CommandPlan::new("dnf", args) } else { CommandPlan::privileged("dnf", args) })
+}
+
+pub fn validate_group_id(group: &str) -> Result<(), String> {
+    if GROUP_ID_RE.is_match(group) {
+        Ok(())
+    } else {
+        Err(format!("Invalid group id: '{}'", group))
+    }
+}
+
+// Installs comps groups (as "@id") together with individual packages in one transaction.
+pub fn install_groups_and_packages(groups: &[String], packages: &[String], dry_run: bool) -> Result<CommandPlan, String> {
+    for group in groups {
+        validate_group_id(group)?;
+    }
+    if groups.is_empty() {
+        return install_packages(packages, dry_run);
+    }
+    for package in packages {
+        validate_package_name(package)?;
+    }
+    let mut args = strings(&["install", if dry_run { "--assumeno" } else { "--assumeyes" }]);
+    args.extend(groups.iter().map(|group| format!("@{}", group)));
+    args.extend(packages.iter().cloned());
+    Ok(if dry_run { CommandPlan::new("dnf", args) } else { CommandPlan::privileged("dnf", args) })
+}
+
+// Removes comps groups or environments, given as ids, with the packages they installed.
+pub fn remove_groups(groups: &[String], dry_run: bool) -> Result<CommandPlan, String> {
+    for group in groups {
+        validate_group_id(group)?;
+    }
+    let mut args = strings(&["remove", if dry_run { "--assumeno" } else { "--assumeyes" }]);
+    args.extend(groups.iter().map(|group| format!("@{}", group)));
+    Ok(if dry_run { CommandPlan::new("dnf", args) } else { CommandPlan::privileged("dnf", args) })
+}
+
+// How a queue with both installs and removals becomes one transaction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QueueTransaction<'a> {
+    Do,              // dnf5's `dnf do`
+    Shell(&'a Path), // dnf4's `dnf shell`, running the script from queue_shell_script staged at this path
+    Separate,        // dnf5 before `dnf do` was added: an install, then a removal
+}
+
+// The `dnf shell` script applying the queue: "install a b", "remove c", then "run".
+pub fn queue_shell_script(install: &[String], remove: &[String]) -> Result<String, String> {
+    let mut script = String::new();
+    for (command, packages) in [("install", install), ("remove", remove)] {
+        for package in packages {
+            validate_package_name(package)?;
+        }
+        if !packages.is_empty() {
+            script.push_str(&format!("{} {}\n", command, packages.join(" ")));
+        }
+    }
+    script.push_str("run\n");
+    Ok(script)
+}
+
+// A queue of installs and removals, applied as one transaction (see QueueTransaction) so
+// dependencies are resolved once. Only the Separate fallback gives two plans, which
+// process::run_plans_streaming runs under one authentication.
+pub fn apply_queue(install: &[String], remove: &[String], transaction: QueueTransaction, dry_run: bool) -> Result<Vec<CommandPlan>, String> {
+    for package in install.iter().chain(remove) {
+        validate_package_name(package)?;
+    }
+    if let Some(both) = install.iter().find(|package| remove.contains(package)) {
+        return Err(format!("'{}' is queued for both install and removal.", both));
+    }
+    let confirm = if dry_run { "--assumeno" } else { "--assumeyes" };
+    let args = match (install.is_empty(), remove.is_empty(), transaction) {
+        (true, true, _) => return Err("The transaction queue is empty.".to_string()),
+        (false, true, _) => return Ok(vec![install_packages(install, dry_run)?]),
+        (true, false, _) => return Ok(vec![remove_packages(remove, dry_run)?]),
+        (false, false, QueueTransaction::Do) => {
+            let mut args = strings(&["do", confirm, "--action=install"]);
+            args.extend(install.iter().cloned());
+            args.push("--action=remove".to_string());
+            args.extend(remove.iter().cloned());
+            args
+        }
+        (false, false, QueueTransaction::Shell(script)) => vec!["shell".to_string(), confirm.to_string(), script.to_string_lossy().into_owned()],
+        (false, false, QueueTransaction::Separate) => return Ok(vec![install_packages(install, dry_run)?, remove_packages(remove, dry_run)?]),
+    };
+    Ok(vec![if dry_run { CommandPlan::new("dnf", args) } else { CommandPlan::privileged("dnf", args) }])
+}
+
+// Downloads the packages of another Fedora release for `dnf system-upgrade reboot` to install.
+pub fn system_upgrade_download(releasever: u32) -> CommandPlan {
+    CommandPlan::privileged("dnf", vec!["system-upgrade".to_string(), "download".to_string(), format!("--releasever={}", releasever), "--assumeyes".to_string()])
+}
+
+pub fn system_upgrade_reboot() -> CommandPlan {
+    CommandPlan::privileged("dnf", strings(&["system-upgrade", "reboot"]))
+}
+
+pub fn system_upgrade_clean() -> CommandPlan {
+    CommandPlan::privileged("dnf", strings(&["system-upgrade", "clean"]))
+}
+
+// Downloads fresh metadata for every enabled repository, however recent the cached copy is.
+pub fn refresh_metadata() -> CommandPlan {
+    CommandPlan::privileged("dnf", strings(&["makecache", "--refresh"]))
+}
+
+pub fn clean_cache(kind: CacheKind) -> CommandPlan {
+    CommandPlan::privileged("dnf", strings(&["clean", kind.as_arg()]))
+}
+
+pub fn autoremove() -> CommandPlan {
+    CommandPlan::privileged("dnf", strings(&["autoremove", "--assumeyes"]))
+}
+
+// Removes packages together with any dependencies that only they needed.
+pub fn remove_with_unneeded(packages: &[String], dry_run: bool) -> Result<CommandPlan, String> {
+    if packages.is_empty() {
+        return Err("No packages given.".to_string());
+    }
+    for package in packages {
+        validate_package_name(package)?;
+    }
+    let mut args = strings(&["remove", "--setopt=clean_requirements_on_remove=True"]);
+    args.extend(packages.iter().cloned());
+    if dry_run {
+        args.push("--assumeno".to_string());
+        Ok(CommandPlan::new("dnf", args))
+    } else {
+        args.push("--assumeyes".to_string());
+        Ok(CommandPlan::privileged("dnf", args))
+    }
+}
+
+// Changelog entries the pending updates would bring in; all of them when `packages` is empty.
+pub fn changelog_preview(packages: &[String]) -> Result<CommandPlan, String> {
+    for package in packages {
+        validate_package_name(package)?;
+    }
+    let mut args = strings(&["changelog", "--upgrades", "--quiet"]);
+    args.extend(packages.iter().cloned());
+    Ok(CommandPlan::new("dnf", args))
+}
+
+// Resolves the autoremove transaction and prints it without changing anything.
+pub fn autoremove_preview() -> CommandPlan {
+    CommandPlan::new("dnf", strings(&["autoremove", "--assumeno"]))
+}
+
+// Operations that can be previewed. Tagged by "operation" so the frontend can pass
+// e.g. `{ operation: "UpdatePackage", package_name: "bash" }`.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "operation")]
+pub enum OperationRequest {
+    UpdatePackage { package_name: String },
+    UpgradePackages { packages: Vec<String>, security_only: bool },
+    Uninstall(UninstallArgs),
+    SnapshotUpdate,
+    CleanupOrphans { dry_run: bool },
+    SetDefaultKernel { release: String },
+}
+
+pub fn plan_operation(request: &OperationRequest, pinned: &[String]) -> Result<Vec<CommandPlan>, String> {
+    match request {
+        OperationRequest::UpdatePackage { package_name } => Ok(vec![update_package(package_name, pinned)?]),
+        OperationRequest::UpgradePackages { packages, security_only } => Ok(vec![upgrade_packages(packages, *security_only, pinned)?]),
+        OperationRequest::Uninstall(args) => uninstall(args),
+        OperationRequest::SnapshotUpdate => Ok(crate::snapshot::snapshot_update_plans(pinned)),
+        OperationRequest::CleanupOrphans { dry_run: true } => Ok(vec![autoremove_preview()]),
+        OperationRequest::CleanupOrphans { dry_run: false } => Ok(vec![autoremove()]),
+        OperationRequest::SetDefaultKernel { release } => Ok(vec![crate::kernels::set_default_kernel_plan(release)?]),
+    }
+}
+
+// Returns the commands an operation would run (and whether they need root), without running them.
+// Root steps show up combined, the way process::run_plans_streaming runs them.
+#[tauri::command]
+pub async fn preview_command(app: tauri::AppHandle, mut request: OperationRequest) -> Result<Vec<CommandPlan>, NebulaError> {
+    if let OperationRequest::Uninstall(args) = &mut request {
+        args.resolve_mode(&app)?;
+    }
+    let plans = plan_operation(&request, &crate::pins::pinned_packages(&app)?)?;
+    Ok(crate::process::root_batch(&plans).map_or(plans, |batch| vec![batch]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uninstall_plans() {
+        let args = UninstallArgs {
+            packages: strings(&["htop"]),
+            mode: Some(UninstallMode::Safe),
+            cleanup_orphans: true,
+            confirmed: true,
+            offline: false,
+        };
+        let plans = uninstall(&args).unwrap();
+        assert_eq!(plans.len(), 2);
+        assert!(plans[0].privileged);
+        assert_eq!(plans[0].command_line(), "pkexec dnf remove htop --assumeyes");
+        assert_eq!(plans[1].command_line(), "pkexec dnf autoremove --assumeyes");
+
+        let queue = apply_queue(&strings(&["gimp", "inkscape"]), &strings(&["krita"]), QueueTransaction::Do, false).unwrap();
+        assert_eq!(queue[0].command_line(), "pkexec dnf do --assumeyes --action=install gimp inkscape --action=remove krita");
+        let script = Path::new("/tmp/nebulasys-queue");
+        let queue = apply_queue(&strings(&["gimp"]), &strings(&["krita"]), QueueTransaction::Shell(script), false).unwrap();
+        assert_eq!(queue[0].command_line(), "pkexec dnf shell --assumeyes /tmp/nebulasys-queue");
+        assert!(queue[0].is_package_transaction());
+        assert_eq!(queue[0].simulated().unwrap().command_line(), "dnf shell /tmp/nebulasys-queue --assumeno");
+        assert_eq!(queue_shell_script(&strings(&["gimp", "inkscape"]), &strings(&["krita"])).unwrap(), "install gimp inkscape\nremove krita\nrun\n");
+        assert!(queue_shell_script(&strings(&["gimp; reboot"]), &[]).is_err());
+        assert_eq!(apply_queue(&strings(&["gimp"]), &strings(&["krita"]), QueueTransaction::Separate, true).unwrap().len(), 2);
+        assert!(apply_queue(&strings(&["gimp"]), &strings(&["gimp"]), QueueTransaction::Do, false).is_err());
+
+        let batch = CommandPlan::batched(&plans).unwrap();
+        assert_eq!(
+            batch.command_line(),
+            "pkexec sh -c 'dnf remove htop --assumeyes && echo @@nebula-dnf-next-step && echo @@nebula-dnf-next-step >&2 && dnf autoremove --assumeyes'"
+        );
+        assert!(CommandPlan::batched(&plans[..1]).is_none());
+
+        let batch: UninstallArgs = serde_json::from_str(r#"{"packages":["htop","btop"],"mode":"DryRunForce","cleanup_orphans":false}"#).unwrap();
+        assert_eq!(uninstall(&batch).unwrap()[0].command_line(), "rpm -e --nodeps htop btop --test");
+        let single: UninstallArgs = serde_json::from_str(r#"{"package_name":"htop","cleanup_orphans":false}"#).unwrap();
+        assert_eq!(single.packages, ["htop"]);
+
+        let bad = UninstallArgs { packages: strings(&["htop", "--all"]), ..args };
+        assert!(uninstall(&bad).is_err());
+    }
+
+    #[test]
+    fn test_pinned_packages_are_excluded() {
+        let pinned = vec!["mesa-dri-drivers".to_string()];
+        let plan = upgrade_all(true, &pinned);
+        assert_eq!(plan.command_line(), "pkexec dnf upgrade --assumeyes --security --exclude=mesa-dri-drivers");
+        let fetch = download_only(&plan).unwrap();
+        assert_eq!(fetch.command_line(), "pkexec dnf upgrade --downloadonly --assumeyes --security --exclude=mesa-dri-drivers");
+        assert_eq!(fetch.simulated().unwrap().command_line(), "dnf upgrade --downloadonly --security --exclude=mesa-dri-drivers --assumeno");
+        assert!(download_only(&autoremove()).is_err());
+        let removal = remove_packages(&strings(&["htop"]), false).unwrap();
+        assert_eq!(offline(&removal, true).unwrap().command_line(), "pkexec dnf remove --offline --assumeyes htop");
+        assert!(offline(&removal, false).is_err());
+        assert_eq!(offline(&plan, false).unwrap().command_line(), "pkexec dnf offline-upgrade download --assumeyes --security --exclude=mesa-dri-drivers");
+        assert_eq!(offline(&plan, true).unwrap().command_line(), "pkexec dnf upgrade --offline --assumeyes --security --exclude=mesa-dri-drivers");
+        assert!(offline(&autoremove(), false).is_err());
+        assert_eq!(offline_command(true, false).command_line(), "pkexec dnf offline reboot");
+        assert!(update_package("mesa-dri-drivers", &pinned).is_err());
+        assert!(update_package("bash", &pinned).is_ok());
+    }
+
+    #[test]
+    fn test_simulated_plans() {
+        let update = update_package("bash", &[]).unwrap().simulated().unwrap();
+        assert!(!update.privileged);
+        assert_eq!(update.command_line(), "dnf update bash --assumeno");
+        let force = CommandPlan::privileged("rpm", strings(&["-e", "--nodeps", "htop"])).simulated().unwrap();
+        assert_eq!(force.command_line(), "rpm -e --nodeps htop --test");
+        let grubby = CommandPlan::privileged("grubby", strings(&["--set-default", "/boot/vmlinuz-6.8.9"])).simulated().unwrap();
+        assert_eq!(grubby.program, "echo");
+        assert!(autoremove_preview().simulated().is_none());
+        assert!(CommandPlan::query(&["history-list"]).simulated().is_none());
+        let install = install_packages(&strings(&["hplip"]), true).unwrap();
+        assert_eq!(install.command_line(), "dnf install --assumeno hplip");
+        let brew = CommandPlan::new("/home/linuxbrew/.linuxbrew/bin/brew", strings(&["upgrade"])).simulated().unwrap();
+        assert_eq!(brew.command_line(), "/home/linuxbrew/.linuxbrew/bin/brew upgrade --dry-run");
+        let nix = CommandPlan::new("/nix/var/nix/profiles/default/bin/nix", strings(&["profile", "upgrade", ".*"])).simulated().unwrap();
+        assert_eq!(nix.program, "echo");
+        assert_eq!(crate::orchestrator::firmware_plan(false).simulated().unwrap().program, "echo");
+        assert!(crate::orchestrator::firmware_plan(true).simulated().is_none());
+        let flatpak = CommandPlan::new("flatpak", strings(&["uninstall", "--system", "--noninteractive", "org.gnome.Builder"])).simulated().unwrap();
+        assert_eq!(flatpak.program, "echo");
+        assert!(CommandPlan::new("flatpak", strings(&["list", "--user"])).simulated().is_none());
+        let zypper = CommandPlan::privileged("zypper", strings(&["--non-interactive", "remove", "--clean-deps", "htop"])).simulated().unwrap();
+        assert_eq!(zypper.command_line(), "pkexec zypper --non-interactive remove --dry-run --clean-deps htop");
+        let apt = CommandPlan::privileged("apt-get", strings(&["-y", "install", "htop"])).simulated().unwrap();
+        assert_eq!(apt.command_line(), "apt-get -s install htop");
+        let pacman = CommandPlan::privileged("pacman", strings(&["-Syu", "--noconfirm"])).simulated().unwrap();
+        assert_eq!(pacman.command_line(), "pacman -Su --print");
+    }
+
+    #[test]
+    fn test_package_transactions() {
+        assert!(CommandPlan::privileged("dnf", strings(&["install", "--assumeyes", "htop"])).is_package_transaction());
+        assert!(system_upgrade_download(41).is_package_transaction());
+        assert!(offline(&upgrade_all(false, &[]), false).unwrap().is_package_transaction());
+        assert!(!refresh_metadata().is_package_transaction());
+        assert!(!clean_cache(CacheKind::All).is_package_transaction());
+        assert!(!system_upgrade_clean().is_package_transaction());
+        assert!(!system_upgrade_reboot().is_package_transaction());
+        assert!(!offline_command(true, true).is_package_transaction());
+        assert!(!CommandPlan::new("dnf", strings(&["install", "--assumeno", "htop"])).is_package_transaction());
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("bash"), "bash");
+        assert_eq!(shell_quote("%{name}\n"), "'%{name}\n'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+}
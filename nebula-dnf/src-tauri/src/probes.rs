@@ -0,0 +1,233 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::error::NebulaError;
+use crate::process::{run_command, CommandCapture};
+use crate::{app_data_file, load_json_file, save_json_file};
+
+const PROBES_FILE_NAME: &str = "syntax_probes.json";
+
+// Features built on dnf/rpm command syntax that has changed between releases before.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ProbedFeature {
+    UserInstalledQuery, // repoquery --userinstalled with a queryformat
+    InstalledGroups,    // group list --installed
+    History,            // history list table
+    RpmQueryFormat,     // rpm -q --queryformat with tab-separated tags
+}
+
+// A cheap, read-only command and what its output has to look like.
+struct Probe {
+    id: &'static str,
+    feature: ProbedFeature,
+    program: &'static str,
+    args: &'static [&'static str],
+    check: fn(&CommandCapture) -> Result<(), String>,
+}
+
+const PROBES: &[Probe] = &[
+    Probe {
+        id: "repoquery-userinstalled",
+        feature: ProbedFeature::UserInstalledQuery,
+        program: "dnf",
+        // The query kickstart export runs. No package is user-installed everywhere, so it lists
+        // them all; a system without one has nothing to check the queryformat against.
+        args: &["repoquery", "--userinstalled", "--quiet", "--queryformat", "%{name}\n"],
+        check: check_bare_names,
+    },
+    Probe {
+        id: "group-list-installed",
+        feature: ProbedFeature::InstalledGroups,
+        program: "dnf",
+        // Listing the groups would load the comps metadata; the help shows whether the flag exists.
+        args: &["group", "list", "--help"],
+        check: check_installed_flag,
+    },
+    Probe {
+        id: "history-list",
+        feature: ProbedFeature::History,
+        program: "dnf",
+        args: &["history", "list"],
+        check: check_history_header,
+    },
+    Probe {
+        id: "rpm-queryformat",
+        feature: ProbedFeature::RpmQueryFormat,
+        program: "rpm",
+        args: &["-q", "--queryformat", "%{NAME}\t%{ARCH}\n", "rpm"],
+        check: check_tab_row,
+    },
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ProbeResult {
+    id: String,
+    feature: ProbedFeature,
+    passed: bool,
+    detail: Option<String>, // why it failed
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ProbeReport {
+    dnf_version: String,
+    rpm_version: String,
+    probed_at: String, // RFC 3339
+    results: Vec<ProbeResult>,
+}
+
+fn check_success(out: &CommandCapture) -> Result<(), String> {
+    if out.success {
+        Ok(())
+    } else {
+        Err(format!("exit code {}: {}", out.code, out.error_text().trim()))
+    }
+}
+
+fn check_bare_names(out: &CommandCapture) -> Result<(), String> {
+    check_success(out)?;
+    let mut names = out.stdout.lines().map(str::trim).filter(|line| !line.is_empty()).peekable();
+    if names.peek().is_none() {
+        return Err("no packages listed to check the queryformat against".to_string());
+    }
+    match names.find(|line| line.contains(char::is_whitespace)) {
+        Some(line) => Err(format!("queryformat was not applied: '{}'", line)),
+        None => Ok(()),
+    }
+}
+
+fn check_installed_flag(out: &CommandCapture) -> Result<(), String> {
+    check_success(out)?;
+    if out.stdout.contains("--installed") { Ok(()) } else { Err("`group list` has no --installed option".to_string()) }
+}
+
+// Some setups keep the history database root-only; a refusal says nothing about the syntax.
+fn check_history_header(out: &CommandCapture) -> Result<(), String> {
+    if !out.success && out.stderr.to_lowercase().contains("permission denied") {
+        return Ok(());
+    }
+    check_success(out)?;
+    match out.stdout.lines().map(str::trim).find(|line| !line.is_empty()) {
+        Some(header) if !header.starts_with("ID") => Err(format!("unexpected table header: '{}'", header)),
+        _ => Ok(()),
+    }
+}
+
+fn check_tab_row(out: &CommandCapture) -> Result<(), String> {
+    check_success(out)?;
+    let first = out.stdout.lines().next().unwrap_or_default();
+    match first.split('\t').collect::<Vec<_>>().as_slice() {
+        [name, arch] if !name.is_empty() && !arch.is_empty() => Ok(()),
+        _ => Err(format!("unexpected row: '{}'", first)),
+    }
+}
+
+// dnf5 reports "dnf5 version 5.2.8.1", dnf4 just "4.21.1".
+pub async fn is_dnf5(app: &tauri::AppHandle) -> bool {
+    let version = tool_version(app, "dnf").await;
+    version.starts_with("dnf5") || version.starts_with('5')
+}
+
+// `dnf do` came with dnf5 5.2.7; older dnf5 builds reject it as an unknown command.
+pub async fn has_dnf_do(app: &tauri::AppHandle) -> bool {
+    matches!(run_command(app, "dnf", &["do", "--help"]).await, Ok(out) if out.success)
+}
+
+async fn tool_version(app: &tauri::AppHandle, program: &str) -> String {
+    match run_command(app, program, &["--version"]).await {
+        Ok(out) if out.success => out.stdout.lines().next().unwrap_or_default().trim().to_string(),
+        _ => "unknown".to_string(),
+    }
+}
+
+async fn run_probes(app: &tauri::AppHandle, dnf_version: String, rpm_version: String) -> Result<ProbeReport, String> {
+    let mut results = Vec::new();
+    for probe in PROBES {
+        let outcome = match run_command(app, probe.program, probe.args).await {
+            Ok(out) => (probe.check)(&out),
+            Err(e) => Err(e.to_string()),
+        };
+        results.push(ProbeResult { id: probe.id.to_string(), feature: probe.feature, passed: outcome.is_ok(), detail: outcome.err() });
+    }
+    let report = ProbeReport { dnf_version, rpm_version, probed_at: Local::now().to_rfc3339(), results };
+    save_json_file(&app_data_file(app, PROBES_FILE_NAME)?, &report)?;
+    Ok(report)
+}
+
+fn load_report(app: &tauri::AppHandle) -> Result<Option<ProbeReport>, String> {
+    load_json_file(&app_data_file(app, PROBES_FILE_NAME)?)
+}
+
+// At startup: probes again only when the dnf or rpm version differs from the last run.
+pub async fn probe_on_version_change(app: tauri::AppHandle) {
+    let dnf_version = tool_version(&app, "dnf").await;
+    let rpm_version = tool_version(&app, "rpm").await;
+    let known = load_report(&app).ok().flatten().is_some_and(|report| report.dnf_version == dnf_version && report.rpm_version == rpm_version);
+    if known {
+        return;
+    }
+    match run_probes(&app, dnf_version, rpm_version).await {
+        Ok(report) => {
+            for result in report.results.iter().filter(|result| !result.passed) {
+                warn!("Syntax probe '{}' failed, disabling {:?}: {}", result.id, result.feature, result.detail.as_deref().unwrap_or(""));
+            }
+        }
+        Err(e) => warn!("Failed to record syntax probe results: {}", e),
+    }
+}
+
+// Err when the last probes found this dnf/rpm incompatible with what `feature` parses, so it
+// fails with a clear message instead of returning wrong or empty results. Unprobed counts as fine.
+pub fn ensure_supported(app: &tauri::AppHandle, feature: ProbedFeature) -> Result<(), String> {
+    let Some(report) = load_report(app)? else { return Ok(()) };
+    match report.results.iter().find(|result| result.feature == feature && !result.passed) {
+        Some(failed) => Err(format!(
+            "This feature is disabled: {} / {} did not pass the '{}' compatibility check ({}).",
+            report.dnf_version,
+            report.rpm_version,
+            failed.id,
+            failed.detail.as_deref().unwrap_or("no details")
+        )),
+        None => Ok(()),
+    }
+}
+
+#[tauri::command]
+pub async fn get_syntax_probe_report(app: tauri::AppHandle) -> Result<Option<ProbeReport>, NebulaError> {
+    Ok(load_report(&app)?)
+}
+
+// Runs the probes now, e.g. after a fix, whatever the versions.
+#[tauri::command]
+pub async fn rerun_syntax_probes(app: tauri::AppHandle) -> Result<ProbeReport, NebulaError> {
+    let dnf_version = tool_version(&app, "dnf").await;
+    let rpm_version = tool_version(&app, "rpm").await;
+    Ok(run_probes(&app, dnf_version, rpm_version).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probe_checks() {
+        let capture = |success: bool, stdout: &str, stderr: &str| CommandCapture {
+            success,
+            code: if success { 0 } else { 1 },
+            stdout: stdout.to_string(),
+            stderr: stderr.to_string(),
+            simulated: false,
+        };
+        assert!(check_bare_names(&capture(true, "rpm\nbash\n", "")).is_ok());
+        assert!(check_bare_names(&capture(true, "rpm-4.19.1-1.fc40.x86_64 : The RPM package management system\n", "")).is_err());
+        assert!(check_bare_names(&capture(true, "\n", "")).is_err());
+        assert!(check_installed_flag(&capture(true, "usage: dnf group list [--available] [--installed] [--hidden]\n", "")).is_ok());
+        assert!(check_installed_flag(&capture(true, "usage: dnf group list [--available]\n", "")).is_err());
+        assert!(check_history_header(&capture(true, "ID     | Command line | Date and time\n", "")).is_ok());
+        assert!(check_history_header(&capture(false, "", "Error: permission denied\n")).is_ok());
+        assert!(check_history_header(&capture(true, "Transaction 3 ...\n", "")).is_err());
+        assert!(check_tab_row(&capture(true, "rpm\tx86_64\n", "")).is_ok());
+        assert!(check_tab_row(&capture(true, "rpm x86_64\n", "")).is_err());
+        assert!(check_success(&capture(false, "", "Unknown argument")).is_err());
+    }
+}
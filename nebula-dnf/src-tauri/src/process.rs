@@ -0,0 +1,340 @@
+use serde::Serialize;
+use tauri::Emitter;
+use tauri_plugin_shell::process::CommandEvent;
+use tauri_plugin_shell::ShellExt;
+use tracing::{info, warn};
+
+use crate::error::NebulaError;
+use crate::plan::{CommandPlan, BATCH_STEP_MARKER};
+
+// Payload of the "operation-output" event, one per line of output from a streamed command.
+#[derive(Debug, Serialize, Clone)]
+pub struct OperationOutputLine {
+    operation: String,
+    line: String,
+    is_stderr: bool,
+}
+
+impl OperationOutputLine {
+    pub fn new(operation: &str, line: &str, is_stderr: bool) -> Self {
+        OperationOutputLine { operation: operation.to_string(), line: line.to_string(), is_stderr }
+    }
+}
+
+// Captured result of a finished subprocess, with stdout/stderr already decoded.
+#[derive(Debug, Clone)]
+pub struct CommandCapture {
+    pub success: bool,
+    pub code: i32,
+    pub stdout: String,
+    pub stderr: String,
+    pub simulated: bool, // simulation mode ran a dry-run stand-in instead of the requested command
+}
+
+impl CommandCapture {
+    // Stderr if the command wrote anything there, stdout otherwise. Useful for error messages
+    // since dnf prints some failures to stdout.
+    pub fn error_text(&self) -> &str {
+        if self.stderr.trim().is_empty() { &self.stdout } else { &self.stderr }
+    }
+}
+
+// Windows-1252 for bytes 0x80..=0x9F; from 0xA0 up it agrees with Latin-1. The five positions
+// it leaves undefined keep their C1 control character.
+const CP1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{8D}', '\u{017D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{9D}', '\u{017E}', '\u{0178}',
+];
+
+fn decode_cp1252(byte: u8) -> char {
+    match byte {
+        0x80..=0x9F => CP1252_HIGH[(byte - 0x80) as usize],
+        _ => char::from(byte),
+    }
+}
+
+// Decodes subprocess output line by line: as UTF-8 where the line is valid UTF-8, as
+// Windows-1252 otherwise. Changelogs and descriptions of older or third-party packages are often
+// Latin-1, which from_utf8_lossy would turn into U+FFFD for good; per line, one such entry does
+// not spoil the rest of the output.
+pub fn decode_output(bytes: &[u8]) -> String {
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return text.to_string();
+    }
+    let mut text = String::with_capacity(bytes.len() + bytes.len() / 2);
+    for line in bytes.split_inclusive(|byte| *byte == b'\n') {
+        match std::str::from_utf8(line) {
+            Ok(line) => text.push_str(line),
+            Err(_) => text.extend(line.iter().map(|byte| decode_cp1252(*byte))),
+        }
+    }
+    text
+}
+
+// Runs a command to completion and captures its output.
+// Only fails if the process could not be spawned; a non-zero exit is reported via `success`/`code`.
+pub async fn run_command(app: &tauri::AppHandle, program: &str, args: &[&str]) -> Result<CommandCapture, NebulaError> {
+    let output = app
+        .shell()
+        .command(program)
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| NebulaError::spawn_failed(program, e))?;
+
+    Ok(CommandCapture {
+        success: output.status.success(),
+        code: output.status.code().unwrap_or(-1),
+        stdout: decode_output(&output.stdout),
+        stderr: decode_output(&output.stderr),
+        simulated: false,
+    })
+}
+
+// Swaps in the plan's dry-run stand-in while simulation mode is on, and otherwise routes it through
+// the privileged helper when that covers it. The bool says whether it simulated.
+pub fn prepare_plan(app: &tauri::AppHandle, plan: &CommandPlan) -> Result<(CommandPlan, bool), String> {
+    let (plan, simulated) = apply_simulation_mode(app, plan)?;
+    Ok(if simulated { (plan, true) } else { (crate::helper::route(plan), false) })
+}
+
+// Swaps in the plan's dry-run stand-in while simulation mode is on. The bool says whether it did.
+fn apply_simulation_mode(app: &tauri::AppHandle, plan: &CommandPlan) -> Result<(CommandPlan, bool), String> {
+    if crate::settings::simulation_mode(app)? {
+        if let Some(simulated) = plan.simulated() {
+            return Ok((simulated, true));
+        }
+    }
+    Ok((plan.clone(), false))
+}
+
+// `dnf --assumeno` exits 1 after printing the transaction it declined. For a dry run that is the
+// expected outcome, so it counts as success.
+pub fn dry_run_succeeded(code: i32, stdout: &str, stderr: &str) -> bool {
+    code == 0 || (code == 1 && (stdout.contains("Operation aborted") || stderr.contains("Operation aborted")))
+}
+
+// pkexec exits 126 when the authentication dialog was dismissed and 127 when authorization was
+// refused or failed; the programs it runs here exit with neither.
+fn authorization_error(plan: &CommandPlan, capture: &CommandCapture) -> Result<(), NebulaError> {
+    let message = match (plan.program.as_str(), capture.code) {
+        ("pkexec", 126) => "The authentication was dismissed.",
+        ("pkexec", 127) => "Not authorized to make this change.",
+        _ => return Ok(()),
+    };
+    Err(NebulaError::PermissionDenied { message: format!("{} ({})", message, capture.error_text().trim()) })
+}
+
+// Package transactions are also recorded in the app's operation history, as the plan asked for
+// them (before the helper routing).
+pub async fn run_plan(app: &tauri::AppHandle, requested: &CommandPlan) -> Result<CommandCapture, NebulaError> {
+    let (plan, simulated) = prepare_plan(app, requested)?;
+    crate::coordination::ensure_plans_can_run(std::slice::from_ref(&plan)).await?;
+    info!("Executing: {}", plan.command_line());
+    let mut capture = run_command(app, &plan.program, &plan.arg_refs()).await?;
+    crate::audit::record_command(app, &plan, capture.success, capture.code);
+    authorization_error(&plan, &capture)?;
+    if simulated {
+        capture.simulated = true;
+        capture.success = dry_run_succeeded(capture.code, &capture.stdout, &capture.stderr);
+    }
+    crate::app_operations::record_plan(app, requested, &capture).await;
+    Ok(capture)
+}
+
+// The output of a batched plan, one capture per step that ran. All but the last succeeded; the
+// last has the script's exit code.
+fn split_batch_output(capture: &CommandCapture) -> Vec<CommandCapture> {
+    let sections = |text: &str| -> Vec<String> {
+        let mut sections = vec![String::new()];
+        for line in text.split_inclusive('\n') {
+            if line.trim_end() == BATCH_STEP_MARKER {
+                sections.push(String::new());
+            } else if let Some(section) = sections.last_mut() {
+                section.push_str(line);
+            }
+        }
+        sections
+    };
+    let mut stderr = sections(&capture.stderr).into_iter();
+    let stdout = sections(&capture.stdout);
+    let steps = stdout.len();
+    stdout
+        .into_iter()
+        .enumerate()
+        .map(|(i, stdout)| {
+            let last = i + 1 == steps;
+            CommandCapture {
+                success: !last || capture.success,
+                code: if last { capture.code } else { 0 },
+                stdout,
+                stderr: stderr.next().unwrap_or_default(),
+                simulated: false,
+            }
+        })
+        .collect()
+}
+
+// The single root call doing all of `plans`: the privileged helper when it is installed and covers
+// them, or else a batched pkexec script when there are several and all need root.
+pub fn root_batch(plans: &[CommandPlan]) -> Option<CommandPlan> {
+    crate::helper::helper_plan(plans).filter(|_| crate::helper::installed()).or_else(|| CommandPlan::batched(plans))
+}
+
+// Runs the plans in order until one fails, returning a capture per plan that ran, and streams
+// their output like run_plan_streaming. Root steps run as one call (see root_batch), so there is
+// a single authentication prompt; in simulation mode each step runs on its own as its dry-run
+// stand-in.
+pub async fn run_plans_streaming(app: &tauri::AppHandle, plans: &[CommandPlan], operation: &str) -> Result<Vec<CommandCapture>, NebulaError> {
+    let batch = if crate::settings::simulation_mode(app)? { None } else { root_batch(plans) };
+    let Some(batch) = batch else {
+        let mut captures = Vec::new();
+        for plan in plans {
+            let capture = run_plan_streaming(app, plan, operation).await?;
+            let success = capture.success;
+            captures.push(capture);
+            if !success {
+                break;
+            }
+        }
+        return Ok(captures);
+    };
+    crate::coordination::ensure_plans_can_run(plans).await?;
+    info!("Executing (batched): {}", batch.command_line());
+    let capture = stream_command(app, &batch, operation).await?;
+    crate::audit::record_command(app, &batch, capture.success, capture.code);
+    authorization_error(&batch, &capture)?;
+    let captures = split_batch_output(&capture);
+    for (plan, capture) in plans.iter().zip(&captures) {
+        crate::app_operations::record_plan(app, plan, capture).await;
+    }
+    Ok(captures)
+}
+
+// Runs `plan` as is, emitting every output line as an "operation-output" event while it runs,
+// and dnf's progress lines also as "operation-progress" events. The step markers of a batched
+// plan are kept in the capture for split_batch_output but not shown.
+async fn stream_command(app: &tauri::AppHandle, plan: &CommandPlan, operation: &str) -> Result<CommandCapture, NebulaError> {
+    let (mut events, child) = app
+        .shell()
+        .command(&plan.program)
+        .args(&plan.args)
+        .spawn()
+        .map_err(|e| NebulaError::spawn_failed(&plan.program, e))?;
+    let mut tracker = crate::session::OperationTracker::start(app, operation, plan, child.pid());
+
+    let mut stdout = String::new();
+    let mut stderr = String::new();
+    let mut code = None;
+    while let Some(event) = events.recv().await {
+        let (bytes, is_stderr) = match event {
+            CommandEvent::Stdout(bytes) => (bytes, false),
+            CommandEvent::Stderr(bytes) => (bytes, true),
+            CommandEvent::Terminated(payload) => {
+                code = payload.code;
+                continue;
+            }
+            CommandEvent::Error(e) => {
+                stderr.push_str(&format!("{}\n", e));
+                continue;
+            }
+            _ => continue,
+        };
+        let line = decode_output(&bytes).trim_end_matches(['\r', '\n']).to_string();
+        let buffer = if is_stderr { &mut stderr } else { &mut stdout };
+        buffer.push_str(&line);
+        buffer.push('\n');
+        if line == BATCH_STEP_MARKER {
+            continue;
+        }
+        tracker.log_line(&line, is_stderr);
+        if let Some(progress) = crate::progress::parse_progress_line(operation, &line) {
+            if let Err(e) = app.emit("operation-progress", &progress) {
+                warn!("Failed to emit operation-progress event: {}", e);
+            }
+        }
+        let payload = OperationOutputLine { operation: operation.to_string(), line, is_stderr };
+        if let Err(e) = app.emit("operation-output", &payload) {
+            warn!("Failed to emit operation-output event: {}", e);
+        }
+    }
+
+    tracker.finish();
+    Ok(CommandCapture {
+        success: code == Some(0),
+        code: code.unwrap_or(-1),
+        stdout,
+        stderr,
+        simulated: false,
+    })
+}
+
+// Like run_plan, but streams the output (see stream_command) so long operations can show live
+// progress.
+pub async fn run_plan_streaming(app: &tauri::AppHandle, requested: &CommandPlan, operation: &str) -> Result<CommandCapture, NebulaError> {
+    let (plan, simulated) = prepare_plan(app, requested)?;
+    crate::coordination::ensure_plans_can_run(std::slice::from_ref(&plan)).await?;
+    info!("Executing (streaming): {}", plan.command_line());
+    let mut capture = stream_command(app, &plan, operation).await?;
+    crate::audit::record_command(app, &plan, capture.success, capture.code);
+    authorization_error(&plan, &capture)?;
+    if simulated {
+        capture.simulated = true;
+        capture.success = dry_run_succeeded(capture.code, &capture.stdout, &capture.stderr);
+    }
+    crate::app_operations::record_plan(app, requested, &capture).await;
+    Ok(capture)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan::strings;
+
+    #[test]
+    fn test_authorization_errors() {
+        let capture = |code| CommandCapture { success: false, code, stdout: String::new(), stderr: "Not authorized".to_string(), simulated: false };
+        let root = CommandPlan::privileged("dnf", strings(&["install", "--assumeyes", "htop"]));
+        assert!(matches!(authorization_error(&root, &capture(126)), Err(NebulaError::PermissionDenied { .. })));
+        assert!(matches!(authorization_error(&root, &capture(127)), Err(NebulaError::PermissionDenied { .. })));
+        assert!(authorization_error(&root, &capture(1)).is_ok());
+        assert!(authorization_error(&CommandPlan::new("sh", strings(&["-c", "missing"])), &capture(127)).is_ok());
+    }
+
+    #[test]
+    fn test_decode_output() {
+        // Every byte decodes to exactly one character, and never to the replacement character.
+        for byte in 0..=255u8 {
+            let text = decode_output(&[b'x', byte]);
+            assert_eq!(text.chars().count(), 2, "byte {:#x}", byte);
+            assert!(!text.contains('\u{FFFD}'), "byte {:#x}", byte);
+        }
+        assert_eq!(decode_output("Jos\u{e9} \u{2014} ok\n".as_bytes()), "Jos\u{e9} \u{2014} ok\n");
+        // A Latin-1 changelog author between UTF-8 lines, as `rpm -q --changelog` prints it for
+        // packages built before their spec files were converted.
+        let changelog = b"* Tue Jan 01 2008 J\xf6rg Sommer <joerg@example.org>\n- caf\xc3\xa9 support\n";
+        assert_eq!(decode_output(changelog), "* Tue Jan 01 2008 J\u{f6}rg Sommer <joerg@example.org>\n- caf\u{e9} support\n");
+        assert_eq!(decode_output(b"\x93smart quotes\x94 \x80 5\r\n"), "\u{201C}smart quotes\u{201D} \u{20AC} 5\r\n");
+        // A UTF-8 sequence cut off at the end of a line is decoded, not dropped.
+        assert_eq!(decode_output(b"size 5 \xc3"), "size 5 \u{c3}");
+    }
+
+    #[test]
+    fn test_split_batch_output() {
+        let batch = CommandCapture {
+            success: false,
+            code: 1,
+            stdout: format!("Removed: htop\n{}\nNothing to do.\n", BATCH_STEP_MARKER),
+            stderr: format!("{}\nError: autoremove failed\n", BATCH_STEP_MARKER),
+            simulated: false,
+        };
+        let steps = split_batch_output(&batch);
+        assert_eq!(steps.len(), 2);
+        assert!(steps[0].success);
+        assert_eq!(steps[0].stdout, "Removed: htop\n");
+        assert_eq!((steps[1].success, steps[1].code), (false, 1));
+        assert_eq!(steps[1].error_text(), "Error: autoremove failed\n");
+    }
+}
@@ -0,0 +1,147 @@
+use serde::Serialize;
+
+// What a progress line is about. dnf4 and dnf5 name the transaction steps slightly differently;
+// both map onto these.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressPhase {
+    Metadata, // repository metadata downloads, one line per repository
+    Download,
+    Prepare, // dnf5's "Prepare transaction" and the rpm transaction check
+    Install,
+    Upgrade,
+    Remove,
+    Cleanup, // the old versions of upgraded packages
+    Verify,
+}
+
+// Payload of the "operation-progress" event, sent next to the "operation-output" line it was
+// parsed from. `percent` is for the whole phase: item `current` of `total`, plus the part of the
+// current item done when dnf prints a percentage for it.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct OperationProgress {
+    operation: String,
+    phase: ProgressPhase,
+    item: Option<String>, // the package, or for Metadata the repository name
+    current: Option<u32>,
+    total: Option<u32>,
+    percent: Option<f64>,
+    speed: Option<String>, // as dnf prints it, e.g. "1.2 MB/s"
+}
+
+fn phase_of(word: &str) -> Option<ProgressPhase> {
+    match word {
+        "Installing" | "Reinstalling" | "Downgrading" => Some(ProgressPhase::Install),
+        "Upgrading" => Some(ProgressPhase::Upgrade),
+        "Removing" | "Erasing" | "Obsoleting" => Some(ProgressPhase::Remove),
+        "Cleanup" | "Cleaning" => Some(ProgressPhase::Cleanup),
+        "Verifying" | "Verify" => Some(ProgressPhase::Verify),
+        "Preparing" | "Prepare" => Some(ProgressPhase::Prepare),
+        _ => None,
+    }
+}
+
+// "3/24", with or without the padding dnf5 adds inside its brackets.
+fn parse_counter(text: &str) -> Option<(u32, u32)> {
+    let (current, total) = text.trim().split_once('/')?;
+    let (current, total) = (current.trim().parse().ok()?, total.trim().parse().ok()?);
+    (1..=total).contains(&current).then_some((current, total))
+}
+
+// A metadata download line starts with the repository name, followed by a percentage (dnf5) or
+// the download speed (dnf4). dnf4's "Total" line after package downloads looks the same.
+fn repository_name(words: &[&str]) -> Option<String> {
+    let first = words.iter().position(|word| word.ends_with('%') || word.ends_with("/s"))?;
+    let end = if words[first].ends_with("/s") { first.checked_sub(1)? } else { first };
+    let name = words[..end].join(" ");
+    (!name.is_empty() && name != "Total").then_some(name)
+}
+
+// Parses one line of dnf4 or dnf5 output:
+//   "(3/24): htop-3.3.0-1.fc40.x86_64.rpm  45% [====      ] 1.2 MB/s | 200 kB  00:03 ETA"
+//   "[ 3/24] htop-0:3.3.0-1.fc40.x86_64  100% |   1.2 MiB/s | 200.0 KiB |  00m00s"
+//   "  Installing       : htop-3.3.0-1.fc40.x86_64                  3/24"
+//   "[3/5] Installing htop-0:3.3.0-1.fc40.x86_64  100% |  10.0 MiB/s | 200.0 KiB |  00m00s"
+//   "Fedora 40 - x86_64                               12 MB/s |  20 MB     00:01"
+//   "Fedora 40 - x86_64                     100% |  12.0 MiB/s |  20.0 MiB |  00m02s"
+// Progress bars are redrawn with carriage returns, so only the last redraw of a line counts.
+pub fn parse_progress_line(operation: &str, line: &str) -> Option<OperationProgress> {
+    let line = line.rsplit('\r').next().unwrap_or(line).trim();
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let item_percent = words
+        .iter()
+        .filter_map(|word| word.strip_suffix('%')?.parse::<f64>().ok())
+        .find(|percent| (0.0..=100.0).contains(percent));
+    let speed = words.windows(2).find(|pair| pair[1].ends_with("/s")).map(|pair| format!("{} {}", pair[0], pair[1]));
+
+    let (phase, counter, item) = if let Some(rest) = line.strip_prefix('(') {
+        // dnf4 downloads
+        let (counter, rest) = rest.split_once("):")?;
+        (ProgressPhase::Download, Some(parse_counter(counter)?), rest.split_whitespace().next().map(String::from))
+    } else if let Some(rest) = line.strip_prefix('[') {
+        // dnf5: downloads, then the transaction steps named after the counter
+        let (counter, rest) = rest.split_once(']')?;
+        let mut words = rest.split_whitespace().peekable();
+        let phase = words.peek().and_then(|word| phase_of(word));
+        if phase.is_some() {
+            words.next();
+        }
+        // "Verify package files" and "Prepare transaction" are about no single package
+        let item = if matches!(phase, Some(ProgressPhase::Verify | ProgressPhase::Prepare)) { None } else { words.next().map(String::from) };
+        (phase.unwrap_or(ProgressPhase::Download), Some(parse_counter(counter)?), item)
+    } else if let Some((step, rest)) = line.split_once(" : ") {
+        // dnf4 transaction steps; scriptlet lines repeat the counter of their package and are skipped
+        let phase = phase_of(step.split_whitespace().next()?)?;
+        let item = rest.split_whitespace().next().filter(|word| parse_counter(word).is_none()).map(String::from);
+        (phase, rest.split_whitespace().last().and_then(parse_counter), item)
+    } else if line.contains(" | ") {
+        (ProgressPhase::Metadata, None, Some(repository_name(&words)?))
+    } else {
+        return None;
+    };
+
+    let percent = match (counter, item_percent) {
+        (Some((current, total)), Some(item)) => Some((f64::from(current - 1) + item / 100.0) / f64::from(total) * 100.0),
+        (Some((current, total)), None) => Some(f64::from(current) / f64::from(total) * 100.0),
+        (None, item) => item,
+    };
+    Some(OperationProgress {
+        operation: operation.to_string(),
+        phase,
+        item,
+        current: counter.map(|(current, _)| current),
+        total: counter.map(|(_, total)| total),
+        percent,
+        speed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_progress_line() {
+        let download = parse_progress_line("update_all", "(2/4): htop-3.3.0-1.fc40.x86_64.rpm  50% [=====     ] 1.2 MB/s | 200 kB  00:03 ETA").unwrap();
+        assert_eq!((download.phase, download.current, download.total), (ProgressPhase::Download, Some(2), Some(4)));
+        assert_eq!(download.percent, Some(37.5));
+        assert_eq!(download.speed.as_deref(), Some("1.2 MB/s"));
+
+        let install = parse_progress_line("update_all", "  Installing       : htop-3.3.0-1.fc40.x86_64        3/4 ").unwrap();
+        assert_eq!((install.phase, install.percent, install.speed), (ProgressPhase::Install, Some(75.0), None));
+        assert_eq!(install.item.as_deref(), Some("htop-3.3.0-1.fc40.x86_64"));
+
+        let dnf5 = parse_progress_line("update_all", "old bar\r[1/2] Removing htop-0:3.3.0-1.fc40.x86_64  100% |   0.0   B/s |   0.0   B |  00m00s").unwrap();
+        assert_eq!((dnf5.phase, dnf5.current, dnf5.percent), (ProgressPhase::Remove, Some(1), Some(50.0)));
+        assert_eq!(parse_progress_line("update_all", "[ 3/24] htop-0:3.3.0-1.fc40.x86_64  100% | 1.2 MiB/s | 200.0 KiB |  00m00s").unwrap().phase, ProgressPhase::Download);
+
+        let dnf4_repo = parse_progress_line("refresh_metadata", "Fedora 40 - x86_64                               12 MB/s |  20 MB     00:01").unwrap();
+        assert_eq!((dnf4_repo.phase, dnf4_repo.item.as_deref(), dnf4_repo.percent), (ProgressPhase::Metadata, Some("Fedora 40 - x86_64"), None));
+        let dnf5_repo = parse_progress_line("refresh_metadata", " Fedora 40 - x86_64 - Updates   100% |  12.0 MiB/s |  20.0 MiB |  00m02s").unwrap();
+        assert_eq!((dnf5_repo.item.as_deref(), dnf5_repo.percent), (Some("Fedora 40 - x86_64 - Updates"), Some(100.0)));
+        assert!(parse_progress_line("update_all", "Total                                    5.1 MB/s |  10 MB     00:02").is_none());
+
+        assert!(parse_progress_line("update_all", "Dependencies resolved.").is_none());
+        assert!(parse_progress_line("update_all", "Transaction Summary").is_none());
+        assert!(parse_progress_line("update_all", "  Running scriptlet: htop-3.3.0-1.fc40.x86_64        3/4 ").is_none());
+    }
+}
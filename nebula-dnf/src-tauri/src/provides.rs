@@ -0,0 +1,211 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use tracing::info;
+
+use crate::error::NebulaError;
+use crate::process::run_command;
+use crate::repoquery::validate_query_target;
+use crate::extract_base_package_name;
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct OwningPackage {
+    name: String,
+    version: String, // VERSION-RELEASE
+    arch: String,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ProviderMatch {
+    nevra: String,
+    name: String,
+    summary: String,
+    repo: String,
+    installed: bool,
+    matched_by: Option<String>, // the provide or filename dnf matched on
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Ecosystem {
+    Perl,
+    Python,
+    Ruby,
+    NodeJs,
+    Go,
+    Rust,
+    OCaml,
+}
+
+// The installed package behind a language-module requirement such as "perl(Foo::Bar)".
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct ModuleProvider {
+    ecosystem: Ecosystem,
+    package: Option<String>, // None if no installed package provides it
+}
+
+// Ecosystem of a module capability, from its namespace: perl(...), python3.12dist(...), rubygem(...), ...
+pub fn module_ecosystem(capability: &str) -> Option<Ecosystem> {
+    let (namespace, _) = capability.split_once('(')?;
+    match namespace {
+        "perl" => Some(Ecosystem::Perl),
+        "python" => Some(Ecosystem::Python),
+        ns if ns.starts_with("python3") => Some(Ecosystem::Python),
+        ns if ns.starts_with("ruby") => Some(Ecosystem::Ruby),
+        "npm" => Some(Ecosystem::NodeJs),
+        "golang" => Some(Ecosystem::Go),
+        "crate" => Some(Ecosystem::Rust),
+        "ocaml" | "ocamlx" => Some(Ecosystem::OCaml),
+        _ => None,
+    }
+}
+
+// Parses `[%{PROVIDENAME}\t%{NAME}\n]` output, keeping only module provides.
+fn parse_module_provider_map(output: &str) -> HashMap<String, String> {
+    let mut providers = HashMap::new();
+    for line in output.lines() {
+        let Some((capability, package)) = line.split_once('\t') else { continue };
+        if module_ecosystem(capability).is_some() {
+            providers.entry(capability.to_string()).or_insert_with(|| package.trim().to_string());
+        }
+    }
+    providers
+}
+
+// One rpm call for every installed provide, instead of a whatprovides query per requirement.
+pub async fn fetch_module_providers(app: &tauri::AppHandle) -> Result<HashMap<String, String>, String> {
+    let out = run_command(app, "rpm", &["-qa", "--queryformat", "[%{PROVIDENAME}\t%{NAME}\n]"]).await?;
+    if !out.success {
+        return Err(format!("rpm -qa provides query failed: {}", out.error_text().trim()));
+    }
+    Ok(parse_module_provider_map(&out.stdout))
+}
+
+pub fn resolve_module(capability: &str, providers: &HashMap<String, String>) -> Option<ModuleProvider> {
+    Some(ModuleProvider {
+        ecosystem: module_ecosystem(capability)?,
+        package: providers.get(capability).cloned(),
+    })
+}
+
+fn parse_owner_output(output: &str) -> Vec<OwningPackage> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            Some(OwningPackage {
+                name: fields.next()?.trim().to_string(),
+                version: fields.next()?.trim().to_string(),
+                arch: fields.next()?.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+// Parses `dnf provides` blocks, which start with "nevra : summary" followed by indented-looking
+// "Key : value" lines (Repo, Matched from, Provide/Filename). Works for dnf4 and dnf5 spacing.
+fn parse_provides_output(output: &str) -> Vec<ProviderMatch> {
+    let mut matches: Vec<ProviderMatch> = Vec::new();
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once(" : ").or_else(|| line.split_once(": ")) else { continue };
+        let key = key.trim();
+        let value = value.trim();
+        match key {
+            "Repo" => {
+                if let Some(current) = matches.last_mut() {
+                    current.repo = value.to_string();
+                    current.installed = value == "@System";
+                }
+            }
+            "Provide" | "Filename" | "Other" => {
+                if let Some(current) = matches.last_mut() {
+                    current.matched_by = Some(value.to_string());
+                }
+            }
+            "Matched from" | "Matched From" => {}
+            _ if !line.starts_with(char::is_whitespace) && !key.contains(' ') && !key.is_empty() => {
+                matches.push(ProviderMatch {
+                    nevra: key.to_string(),
+                    name: extract_base_package_name(key),
+                    summary: value.to_string(),
+                    repo: String::new(),
+                    installed: false,
+                    matched_by: None,
+                });
+            }
+            _ => {}
+        }
+    }
+    matches
+}
+
+// Which installed package(s) own a file on disk.
+#[tauri::command]
+pub async fn find_owner_of_file(app: tauri::AppHandle, path: String) -> Result<Vec<OwningPackage>, NebulaError> {
+    if !Path::new(&path).is_absolute() {
+        return Err(format!("Expected an absolute path, got '{}'.", path).into());
+    }
+    info!("Looking up owner of '{}'.", path);
+    let out = run_command(&app, "rpm", &["-qf", "--queryformat", "%{NAME}\t%{VERSION}-%{RELEASE}\t%{ARCH}\n", "--", &path]).await?;
+    if !out.success {
+        // e.g. "file /foo is not owned by any package"
+        let reason = format!("{}{}", out.stdout, out.stderr);
+        if reason.contains("not owned by any package") {
+            return Ok(Vec::new());
+        }
+        return Err(format!("rpm -qf failed: {}", reason.trim()).into());
+    }
+    Ok(parse_owner_output(&out.stdout))
+}
+
+// Installed and available packages providing a capability, file path or glob.
+#[tauri::command]
+pub async fn what_provides(app: tauri::AppHandle, capability: String) -> Result<Vec<ProviderMatch>, NebulaError> {
+    validate_query_target(&capability)?;
+    info!("Looking up providers of '{}'.", capability);
+    let out = run_command(&app, "dnf", &["provides", "--quiet", &capability]).await?;
+    if !out.success {
+        // dnf exits non-zero when nothing matches.
+        if out.error_text().contains("No matches found") || out.error_text().contains("No match") {
+            return Ok(Vec::new());
+        }
+        return Err(NebulaError::command_failed(out.code, &out.stderr, format!("dnf provides failed: {}", out.error_text().trim())));
+    }
+    Ok(parse_provides_output(&out.stdout))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_provides_output() {
+        let output = "bash-5.2.26-3.fc40.x86_64 : The GNU Bourne Again shell\n\
+        Repo        : @System\n\
+        Matched from:\n\
+        Filename    : /usr/bin/bash\n\
+        \n\
+        bash-5.2.32-1.fc40.x86_64 : The GNU Bourne Again shell\n\
+        Repo        : updates\n\
+        Matched from:\n\
+        Filename    : /usr/bin/bash\n";
+        let matches = parse_provides_output(output);
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].name, "bash");
+        assert!(matches[0].installed);
+        assert_eq!(matches[0].matched_by.as_deref(), Some("/usr/bin/bash"));
+        assert_eq!(matches[1].repo, "updates");
+        assert!(!matches[1].installed);
+    }
+
+    #[test]
+    fn test_resolve_module() {
+        let output = "perl(strict)\tperl-libs\nperl-libs(x86-64)\tperl-libs\npython3.12dist(requests)\tpython3-requests\nlibc.so.6()(64bit)\tglibc\n";
+        let providers = parse_module_provider_map(output);
+        assert_eq!(providers.len(), 2);
+        let resolved = resolve_module("python3.12dist(requests)", &providers).unwrap();
+        assert_eq!(resolved.ecosystem, Ecosystem::Python);
+        assert_eq!(resolved.package.as_deref(), Some("python3-requests"));
+        assert_eq!(resolve_module("perl(Missing::Module)", &providers).unwrap().package, None);
+        assert!(resolve_module("libc.so.6()(64bit)", &providers).is_none());
+    }
+}
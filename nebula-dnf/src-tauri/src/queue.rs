@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+use tauri::Manager;
+
+use crate::error::NebulaError;
+use crate::plan::{queue_shell_script, CommandPlan, QueueTransaction};
+use crate::process::{dry_run_succeeded, run_plan, run_plans_streaming, CommandCapture};
+use crate::services::OperationManager;
+use crate::settings::{GuardedAction, PackageBackend};
+use crate::transaction::{parse_transaction_table, TransactionItem};
+use crate::PackageOperationResult;
+
+// Installs and removals the user collected before applying them together.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct TransactionQueue {
+    #[serde(default)]
+    install: Vec<String>,
+    #[serde(default)]
+    remove: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct QueueResult {
+    result: PackageOperationResult,
+    transaction: Vec<TransactionItem>, // what dnf resolved, or did
+    incomplete: bool,                  // some rows of dnf's table could not be parsed
+}
+
+fn describe(queue: &TransactionQueue) -> String {
+    let mut parts = Vec::new();
+    if !queue.install.is_empty() {
+        parts.push(format!("install {}", queue.install.join(", ")));
+    }
+    if !queue.remove.is_empty() {
+        parts.push(format!("remove {}", queue.remove.join(", ")));
+    }
+    parts.join("; ")
+}
+
+// Resolves each plan without running it; dnf4's two steps are resolved one by one.
+async fn resolve(app: &tauri::AppHandle, plans: &[CommandPlan]) -> Result<Vec<CommandCapture>, String> {
+    let mut captures = Vec::new();
+    for plan in plans {
+        let mut capture = run_plan(app, plan).await?;
+        capture.success = dry_run_succeeded(capture.code, &capture.stdout, &capture.stderr);
+        captures.push(capture);
+    }
+    Ok(captures)
+}
+
+// Applies the queue as one dnf transaction (see plan::apply_queue). With `dry_run`, or when the
+// settings want removals previewed and this is not the confirmed call, the transaction is only
+// resolved and returned for review. With `offline` the transaction is staged for the next boot.
+#[tauri::command]
+pub async fn apply_transaction(app: tauri::AppHandle, queue: TransactionQueue, dry_run: bool, confirmed: Option<bool>, offline: Option<bool>) -> Result<QueueResult, NebulaError> {
+    if app.state::<crate::services::BackendRegistry>().selected()? != PackageBackend::Dnf {
+        return Err("The transaction queue needs the dnf backend.".into());
+    }
+    let preview = !dry_run && !queue.remove.is_empty() && crate::settings::requires_preview(&app, GuardedAction::Uninstall, confirmed.unwrap_or(false))?;
+    let resolve_only = dry_run || preview;
+    let dnf5 = crate::probes::is_dnf5(&app).await;
+    let mixed = !queue.install.is_empty() && !queue.remove.is_empty();
+    // dnf4 reads the queue as a `dnf shell` script; the staged file has to outlive the run.
+    let script = if mixed && !dnf5 { Some(crate::scope::stage_contents(&queue_shell_script(&queue.install, &queue.remove)?)?) } else { None };
+    let transaction = match &script {
+        Some(script) => QueueTransaction::Shell(script.path()),
+        None if mixed && dnf5 && !crate::probes::has_dnf_do(&app).await => QueueTransaction::Separate,
+        None => QueueTransaction::Do,
+    };
+    let mut plans = crate::plan::apply_queue(&queue.install, &queue.remove, transaction, resolve_only)?;
+    let offline = offline.unwrap_or(false) && !resolve_only;
+    if offline {
+        plans = plans.iter().map(|plan| crate::plan::offline(plan, dnf5)).collect::<Result<_, _>>()?;
+    }
+    let target = describe(&queue);
+    let _busy = (!resolve_only).then(|| app.state::<OperationManager>().begin(format!("Applying queued changes: {}", target)));
+    let captures = if resolve_only { resolve(&app, &plans).await? } else { run_plans_streaming(&app, &plans, "apply_transaction").await? };
+
+    let success = captures.len() == plans.len() && captures.iter().all(|capture| capture.success);
+    let stdout: String = captures.iter().map(|capture| capture.stdout.as_str()).collect();
+    let (transaction, unparsed) = parse_transaction_table(&stdout);
+    let incomplete = crate::parse_failures::record(&app, "dnf transaction table", &unparsed).await;
+    let simulated = captures.iter().any(|capture| capture.simulated);
+    let code = captures.last().map_or(-1, |capture| capture.code);
+    let message = match (success, resolve_only || simulated) {
+        (true, true) if preview => format!("Review the queued changes ({}), then confirm to apply them.", target),
+        (true, true) => format!("Dry run finished for the queued changes: {}.", target),
+        (true, false) if offline => format!("The queued changes ({}) are staged and will be applied on the next reboot.", target),
+        (true, false) => format!("Applied the queued changes: {}.", target),
+        (false, _) => format!("Failed to apply the queued changes ({}). Exit code: {}.", target, code),
+    };
+    let details = captures.iter().map(|capture| format!("STDOUT:\n{}\nSTDERR:\n{}", capture.stdout, capture.stderr)).collect::<Vec<_>>().join("\n");
+    let mut result = PackageOperationResult { success, message, details: Some(details), confirmation_required: preview, simulated, reboot_required: success && offline && !simulated };
+    // A staged transaction has not changed anything yet.
+    if !resolve_only && !offline {
+        let changed: Vec<String> = queue.install.iter().chain(&queue.remove).cloned().collect();
+        crate::restarts::check_reboot(&app, &mut result, &changed).await;
+    }
+    Ok(QueueResult { result, transaction, incomplete })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_queue() {
+        let queue: TransactionQueue = serde_json::from_str(r#"{"install":["gimp","inkscape"],"remove":["krita"]}"#).unwrap();
+        assert_eq!(describe(&queue), "install gimp, inkscape; remove krita");
+        let installs_only: TransactionQueue = serde_json::from_str(r#"{"install":["gimp"]}"#).unwrap();
+        assert_eq!(describe(&installs_only), "install gimp");
+    }
+}
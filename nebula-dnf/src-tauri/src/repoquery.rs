@@ -0,0 +1,175 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::error::NebulaError;
+use crate::process::run_command;
+
+// Capabilities and package specs accepted as query targets: names, globs, sonames,
+// file paths and module provides like "perl(Foo::Bar)" or "libc.so.6()(64bit)".
+static QUERY_TARGET_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[a-zA-Z0-9_/*(][a-zA-Z0-9._+:/*()~^-]*$").unwrap());
+
+// Reverse relation to query: e.g. `Requires` lists packages that require the target.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub enum QueryRelation {
+    Requires,
+    Provides,
+    Recommends,
+    Suggests,
+    Conflicts,
+    Obsoletes,
+}
+
+impl QueryRelation {
+    fn flag(self) -> &'static str {
+        match self {
+            QueryRelation::Requires => "--whatrequires",
+            QueryRelation::Provides => "--whatprovides",
+            QueryRelation::Recommends => "--whatrecommends",
+            QueryRelation::Suggests => "--whatsuggests",
+            QueryRelation::Conflicts => "--whatconflicts",
+            QueryRelation::Obsoletes => "--whatobsoletes",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub enum QueryScope {
+    Installed,
+    Available,
+    All,
+}
+
+// Whitelisted --queryformat tags; these spell the same in dnf4 and dnf5.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub enum QueryField {
+    Name,
+    Epoch,
+    Version,
+    Release,
+    Arch,
+    Summary,
+    License,
+    Url,
+    SourceRpm,
+}
+
+impl QueryField {
+    fn tag(self) -> &'static str {
+        match self {
+            QueryField::Name => "name",
+            QueryField::Epoch => "epoch",
+            QueryField::Version => "version",
+            QueryField::Release => "release",
+            QueryField::Arch => "arch",
+            QueryField::Summary => "summary",
+            QueryField::License => "license",
+            QueryField::Url => "url",
+            QueryField::SourceRpm => "sourcerpm",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AdvancedQuery {
+    target: String,
+    relation: Option<QueryRelation>, // None queries `target` as a package spec
+    scope: QueryScope,
+    fields: Vec<QueryField>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct AdvancedQueryResult {
+    columns: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+pub fn validate_query_target(target: &str) -> Result<(), String> {
+    if QUERY_TARGET_RE.is_match(target) {
+        Ok(())
+    } else {
+        Err(format!("Invalid query target: '{}'", target))
+    }
+}
+
+fn build_query_args(query: &AdvancedQuery) -> Result<Vec<String>, String> {
+    validate_query_target(&query.target)?;
+    if query.fields.is_empty() {
+        return Err("At least one output field is required.".to_string());
+    }
+    let query_format = query
+        .fields
+        .iter()
+        .map(|field| format!("%{{{}}}", field.tag()))
+        .collect::<Vec<_>>()
+        .join("\t");
+
+    let mut args = vec!["repoquery".to_string(), "--quiet".to_string()];
+    match query.scope {
+        QueryScope::Installed => args.push("--installed".to_string()),
+        QueryScope::Available => args.push("--available".to_string()),
+        QueryScope::All => {}
+    }
+    args.push("--queryformat".to_string());
+    args.push(format!("{}\n", query_format));
+    match query.relation {
+        Some(relation) => {
+            args.push(relation.flag().to_string());
+            args.push(query.target.clone());
+        }
+        None => {
+            args.push("--".to_string());
+            args.push(query.target.clone());
+        }
+    }
+    Ok(args)
+}
+
+fn parse_rows(output: &str, column_count: usize) -> Vec<Vec<String>> {
+    let mut rows: Vec<Vec<String>> = output
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with("Last metadata expiration check:"))
+        .map(|line| line.split('\t').map(|v| v.trim().to_string()).collect::<Vec<_>>())
+        .filter(|row| row.len() == column_count)
+        .collect();
+    rows.sort();
+    rows.dedup();
+    rows
+}
+
+// repoquery-level queries for power users, limited to whitelisted flags and fields.
+#[tauri::command]
+pub async fn run_advanced_query(app: tauri::AppHandle, query: AdvancedQuery) -> Result<AdvancedQueryResult, NebulaError> {
+    let args = build_query_args(&query)?;
+    info!("Running advanced query: dnf {:?}", args);
+    let arg_refs: Vec<&str> = args.iter().map(String::as_str).collect();
+    let out = run_command(&app, "dnf", &arg_refs).await?;
+    if !out.success {
+        return Err(NebulaError::command_failed(out.code, &out.stderr, format!("dnf repoquery failed: {}", out.error_text().trim())));
+    }
+    Ok(AdvancedQueryResult {
+        columns: query.fields.iter().map(|f| f.tag().to_string()).collect(),
+        rows: parse_rows(&out.stdout, query.fields.len()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_query_args_rejects_option_injection() {
+        let query = |target: &str| AdvancedQuery {
+            target: target.to_string(),
+            relation: Some(QueryRelation::Requires),
+            scope: QueryScope::Installed,
+            fields: vec![QueryField::Name, QueryField::Version],
+        };
+        assert!(build_query_args(&query("--setopt=x")).is_err());
+        assert!(build_query_args(&query("bash; rm -rf /")).is_err());
+        let args = build_query_args(&query("libc.so.6()(64bit)")).unwrap();
+        assert_eq!(args[args.len() - 2], "--whatrequires");
+        assert!(args.contains(&"%{name}\t%{version}\n".to_string()));
+    }
+}
@@ -0,0 +1,348 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::time::SystemTime;
+use tauri::Manager;
+
+use crate::error::NebulaError;
+use crate::plan::{strings, CommandPlan};
+use crate::process::{dry_run_succeeded, run_command, run_plan, run_plan_streaming};
+use crate::services::OperationManager;
+use crate::PackageOperationResult;
+
+// Where dnf4 and dnf5 keep downloaded metadata, one "<repo id>-<hash>" directory per repository.
+pub const METADATA_CACHE_DIRS: &[&str] = &["/var/cache/libdnf5", "/var/cache/dnf"];
+const RPMFUSION_MIRROR: &str = "https://mirrors.rpmfusion.org";
+// A repository base URL or the URL of a .repo file; no whitespace or quotes.
+static REPO_SOURCE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"^(https?|file)://[^\s'"]+$"#).unwrap());
+
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct Repository {
+    id: String,
+    name: String,
+    enabled: bool,
+    baseurl: Option<String>,
+    metalink: Option<String>,
+    mirrorlist: Option<String>,
+    package_count: Option<u64>,
+    metadata_updated: Option<String>, // as dnf prints it
+    metadata_age_secs: Option<u64>,   // since the cached metadata was downloaded
+    config_file: Option<String>,
+}
+
+impl Repository {
+    pub fn is_rpmfusion(&self) -> bool {
+        self.id.starts_with("rpmfusion-")
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    // Where packages come from, as the .repo key naming it and its value; baseurl wins.
+    pub fn source(&self) -> Option<(&'static str, &str)> {
+        self.baseurl.as_deref().map(|url| ("baseurl", url))
+            .or_else(|| self.metalink.as_deref().map(|url| ("metalink", url)))
+            .or_else(|| self.mirrorlist.as_deref().map(|url| ("mirrorlist", url)))
+    }
+}
+
+fn non_empty_string(value: &str) -> Option<String> {
+    // dnf4 appends " (2 more)" when a repo has several base URLs
+    value.split_whitespace().next().map(String::from)
+}
+
+// Parses dnf4 `repolist --all -v` ("Repo-id : fedora") and dnf5 `repo info --all`
+// ("Repo ID : fedora"). Both print one "Key : value" block per repository.
+fn parse_repo_info(output: &str) -> Vec<Repository> {
+    let mut repos: Vec<Repository> = Vec::new();
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once(':') else { continue };
+        let (key, value) = (key.trim(), value.trim());
+        if key == "Repo-id" || key == "Repo ID" {
+            repos.push(Repository { id: value.to_string(), ..Default::default() });
+            continue;
+        }
+        let Some(repo) = repos.last_mut() else { continue };
+        match key {
+            "Repo-name" | "Name" => repo.name = value.to_string(),
+            "Repo-status" | "Status" => repo.enabled = value == "enabled",
+            "Repo-baseurl" | "Base URL" => repo.baseurl = non_empty_string(value),
+            "Repo-metalink" | "Metalink" => repo.metalink = non_empty_string(value),
+            "Repo-mirrors" | "Mirrorlist" => repo.mirrorlist = non_empty_string(value),
+            "Repo-pkgs" | "Total packages" => repo.package_count = value.replace(',', "").parse().ok(),
+            "Repo-updated" | "Last metadata update" => repo.metadata_updated = Some(value.to_string()),
+            "Repo-filename" | "Config file" => repo.config_file = non_empty_string(value),
+            _ => {}
+        }
+    }
+    repos
+}
+
+fn metadata_age_secs(repo_id: &str) -> Option<u64> {
+    let prefix = format!("{}-", repo_id);
+    METADATA_CACHE_DIRS
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            // the suffix is a hash, which keeps "fedora" from matching "fedora-source-<hash>"
+            let name = entry.file_name().to_string_lossy().into_owned();
+            name.strip_prefix(&prefix).is_some_and(|hash| !hash.is_empty() && hash.chars().all(|c| c.is_ascii_hexdigit()))
+        })
+        .filter_map(|entry| std::fs::metadata(entry.path().join("repodata").join("repomd.xml")).ok()?.modified().ok())
+        .max()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .map(|age| age.as_secs())
+}
+
+pub async fn fetch_repositories(app: &tauri::AppHandle) -> Result<Vec<Repository>, String> {
+    let out = run_command(app, "dnf", &["repolist", "--all", "-v"]).await?;
+    let mut repos = if out.success { parse_repo_info(&out.stdout) } else { Vec::new() };
+    if repos.is_empty() {
+        // dnf5's repolist has no verbose mode; the same details come from `repo info`.
+        let out = run_command(app, "dnf", &["repo", "info", "--all"]).await?;
+        if !out.success {
+            return Err(format!("Failed to list repositories: {}", out.error_text().trim()));
+        }
+        repos = parse_repo_info(&out.stdout);
+    }
+    for repo in &mut repos {
+        repo.metadata_age_secs = metadata_age_secs(&repo.id);
+    }
+    repos.sort_by(|a, b| b.enabled.cmp(&a.enabled).then_with(|| a.id.cmp(&b.id)));
+    Ok(repos)
+}
+
+#[tauri::command]
+pub async fn list_repositories(app: tauri::AppHandle) -> Result<Vec<Repository>, NebulaError> {
+    Ok(fetch_repositories(&app).await?)
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct MetadataRefreshResult {
+    result: PackageOperationResult,
+    repositories: Vec<Repository>, // the enabled ones, with their new metadata timestamps
+}
+
+// Runs `dnf makecache --refresh`, e.g. before checking for updates. Each repository's download
+// is streamed as an "operation-progress" event in the Metadata phase, next to the
+// "operation-output" lines.
+#[tauri::command]
+pub async fn refresh_metadata(app: tauri::AppHandle) -> Result<MetadataRefreshResult, NebulaError> {
+    let _busy = app.state::<OperationManager>().begin("Refreshing repository metadata".to_string());
+    let out = run_plan_streaming(&app, &crate::plan::refresh_metadata(), "refresh_metadata").await?;
+    if out.success && !out.simulated {
+        crate::updates::invalidate_updates_cache(&app);
+    }
+    let mut repositories = fetch_repositories(&app).await?;
+    repositories.retain(|repo| repo.enabled);
+    Ok(MetadataRefreshResult {
+        result: PackageOperationResult {
+            success: out.success,
+            message: if out.success {
+                format!("Refreshed the metadata of {} repositories.", repositories.len())
+            } else {
+                format!("Failed to refresh repository metadata: {}", out.error_text().trim())
+            },
+            details: Some(format!("STDOUT:\n{}\nSTDERR:\n{}", out.stdout, out.stderr)),
+            confirmation_required: false,
+            simulated: out.simulated,
+            reboot_required: false,
+        },
+        repositories,
+    })
+}
+
+// dnf4's config-manager plugin takes --set-enabled/--set-disabled; dnf5's uses setopt.
+fn set_enabled_plan(repo_id: &str, enabled: bool, dnf5: bool) -> CommandPlan {
+    if dnf5 {
+        let option = format!("{}.enabled={}", repo_id, if enabled { 1 } else { 0 });
+        CommandPlan::privileged("dnf", strings(&["config-manager", "setopt", &option]))
+    } else {
+        let flag = if enabled { "--set-enabled" } else { "--set-disabled" };
+        CommandPlan::privileged("dnf", strings(&["config-manager", flag, repo_id]))
+    }
+}
+
+// Enables or disables a repository and drops cached update results, which depend on it.
+#[tauri::command]
+pub async fn set_repository_enabled(app: tauri::AppHandle, repo_id: String, enabled: bool) -> Result<Vec<Repository>, NebulaError> {
+    if !fetch_repositories(&app).await?.iter().any(|repo| repo.id == repo_id) {
+        return Err(format!("Unknown repository '{}'.", repo_id).into());
+    }
+    let plan = set_enabled_plan(&repo_id, enabled, crate::probes::is_dnf5(&app).await);
+    let out = run_plan(&app, &plan).await?;
+    if !out.success {
+        return Err(NebulaError::command_failed(out.code, &out.stderr, format!("Failed to change repository '{}': {}", repo_id, out.error_text().trim())));
+    }
+    crate::updates::invalidate_updates_cache(&app);
+    Ok(fetch_repositories(&app).await?)
+}
+
+// dnf4 takes either form with --add-repo; dnf5's addrepo needs to know which one it gets.
+fn add_repo_plan(source: &str, dnf5: bool) -> Result<CommandPlan, String> {
+    if !REPO_SOURCE_RE.is_match(source) {
+        return Err(format!("Not a repository URL: '{}'", source));
+    }
+    if !dnf5 {
+        return Ok(CommandPlan::privileged("dnf", strings(&["config-manager", "--add-repo", source])));
+    }
+    let arg = if source.ends_with(".repo") { format!("--from-repofile={}", source) } else { format!("--set=baseurl={}", source) };
+    Ok(CommandPlan::privileged("dnf", strings(&["config-manager", "addrepo", &arg])))
+}
+
+// Adds a repository from a base URL or a .repo file URL and returns the ids it created
+// (a .repo file can define several).
+#[tauri::command]
+pub async fn add_repository(app: tauri::AppHandle, source: String) -> Result<Vec<String>, NebulaError> {
+    let source = source.trim();
+    let plan = add_repo_plan(source, crate::probes::is_dnf5(&app).await)?;
+    let before: HashSet<String> = fetch_repositories(&app).await?.into_iter().map(|repo| repo.id).collect();
+    let out = run_plan(&app, &plan).await?;
+    if !out.success {
+        return Err(NebulaError::command_failed(out.code, &out.stderr, format!("Failed to add repository from {}: {}", source, out.error_text().trim())));
+    }
+    if out.simulated {
+        return Ok(Vec::new());
+    }
+    crate::updates::invalidate_updates_cache(&app);
+    let added: Vec<String> = fetch_repositories(&app).await?.into_iter().map(|repo| repo.id).filter(|id| !before.contains(id)).collect();
+    if added.is_empty() {
+        return Err(format!("No new repository was added from {}; it may already be configured.", source).into());
+    }
+    Ok(added)
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RpmFusionStatus {
+    fedora_version: Option<u32>,
+    free: bool,    // any rpmfusion-free* repository is configured, enabled or not
+    nonfree: bool,
+}
+
+// The release package that sets up e.g. rpmfusion-nonfree for `fedora_version`.
+fn rpmfusion_release_url(section: &str, fedora_version: u32) -> String {
+    format!("{}/{section}/fedora/rpmfusion-{section}-release-{}.noarch.rpm", RPMFUSION_MIRROR, fedora_version)
+}
+
+pub async fn fedora_version(app: &tauri::AppHandle) -> Result<Option<u32>, String> {
+    // prints "%fedora" unexpanded on anything that is not Fedora
+    let out = run_command(app, "rpm", &["-E", "%fedora"]).await?;
+    Ok(out.stdout.trim().parse().ok())
+}
+
+#[tauri::command]
+pub async fn get_rpmfusion_status(app: tauri::AppHandle) -> Result<RpmFusionStatus, NebulaError> {
+    let repos = fetch_repositories(&app).await?;
+    Ok(RpmFusionStatus {
+        fedora_version: fedora_version(&app).await?,
+        free: repos.iter().any(|repo| repo.id.starts_with("rpmfusion-free")),
+        nonfree: repos.iter().any(|repo| repo.id.starts_with("rpmfusion-nonfree")),
+    })
+}
+
+// Installs the RPM Fusion release packages that are missing (nonfree only if asked for). With
+// `dry_run` the install is only resolved, to show what would be added.
+#[tauri::command]
+pub async fn enable_rpmfusion(app: tauri::AppHandle, nonfree: bool, dry_run: bool) -> Result<PackageOperationResult, NebulaError> {
+    let status = get_rpmfusion_status(app.clone()).await?;
+    let Some(version) = status.fedora_version else {
+        return Err("RPM Fusion can only be set up on Fedora.".into());
+    };
+    let mut urls = Vec::new();
+    if !status.free {
+        urls.push(rpmfusion_release_url("free", version));
+    }
+    if nonfree && !status.nonfree {
+        urls.push(rpmfusion_release_url("nonfree", version));
+    }
+    if urls.is_empty() {
+        return Ok(PackageOperationResult {
+            success: true,
+            message: "RPM Fusion is already set up.".to_string(),
+            details: None,
+            confirmation_required: false,
+            simulated: false,
+            reboot_required: false,
+        });
+    }
+
+    let mut args = strings(&["install", if dry_run { "--assumeno" } else { "--assumeyes" }]);
+    args.extend(urls);
+    let plan = if dry_run { CommandPlan::new("dnf", args) } else { CommandPlan::privileged("dnf", args) };
+    let out = run_plan(&app, &plan).await?;
+    let success = if dry_run { dry_run_succeeded(out.code, &out.stdout, &out.stderr) } else { out.success };
+    if success && !dry_run && !out.simulated {
+        crate::updates::invalidate_updates_cache(&app);
+    }
+    Ok(PackageOperationResult {
+        success,
+        message: match (success, dry_run) {
+            (true, true) => "Dry run finished. Review the packages that would be installed.".to_string(),
+            (true, false) => "RPM Fusion repositories enabled.".to_string(),
+            (false, _) => format!("{} failed: {}", plan.command_line(), out.error_text().trim()),
+        },
+        details: Some(format!("STDOUT:\n{}\nSTDERR:\n{}", out.stdout, out.stderr)),
+        confirmation_required: false,
+        simulated: out.simulated,
+        reboot_required: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_repo_info() {
+        let dnf4 = "Loaded plugins: builddep, changelog\n\
+            Repo-id            : fedora\n\
+            Repo-name          : Fedora 40 - x86_64\n\
+            Repo-status        : enabled\n\
+            Repo-updated       : Fri 12 Apr 2024 10:00:00 AM CEST\n\
+            Repo-pkgs          : 74,881\n\
+            Repo-metalink      : https://mirrors.fedoraproject.org/metalink?repo=fedora-40&arch=x86_64\n\
+            Repo-filename      : /etc/yum.repos.d/fedora.repo\n\
+            \n\
+            Repo-id            : fedora-source\n\
+            Repo-name          : Fedora 40 - Source\n\
+            Repo-status        : disabled\n\
+            Repo-baseurl       : http://mirror.example/fedora/ (2 more)\n";
+        let repos = parse_repo_info(dnf4);
+        assert_eq!(repos.len(), 2);
+        assert!(repos[0].enabled);
+        assert_eq!(repos[0].package_count, Some(74881));
+        assert_eq!(repos[0].metalink.as_deref(), Some("https://mirrors.fedoraproject.org/metalink?repo=fedora-40&arch=x86_64"));
+        assert!(!repos[1].enabled);
+        assert_eq!(repos[1].baseurl.as_deref(), Some("http://mirror.example/fedora/"));
+
+        let dnf5 = "Repo ID              : updates\nName                 : Fedora 40 - x86_64 - Updates\nStatus               : enabled\n\
+            URLs                 :\n  Base URL           : \n  Metalink           : https://mirrors.fedoraproject.org/metalink?repo=updates-released-f40\n\
+            Repodata info        :\n  Available packages : 27012\n  Total packages     : 27012\n";
+        let repos = parse_repo_info(dnf5);
+        assert_eq!(repos[0].id, "updates");
+        assert_eq!(repos[0].baseurl, None);
+        assert_eq!(repos[0].package_count, Some(27012));
+
+        assert_eq!(set_enabled_plan("updates-testing", true, false).command_line(), "pkexec dnf config-manager --set-enabled updates-testing");
+        assert_eq!(set_enabled_plan("updates-testing", true, true).command_line(), "pkexec dnf config-manager setopt updates-testing.enabled=1");
+
+        let dnf5 = add_repo_plan("https://download.docker.com/linux/fedora/docker-ce.repo", true).unwrap();
+        assert_eq!(dnf5.command_line(), "pkexec dnf config-manager addrepo --from-repofile=https://download.docker.com/linux/fedora/docker-ce.repo");
+        let dnf4 = add_repo_plan("https://repo.example/fedora/40/", false).unwrap();
+        assert_eq!(dnf4.command_line(), "pkexec dnf config-manager --add-repo https://repo.example/fedora/40/");
+        assert!(add_repo_plan("--setopt=gpgcheck=0", true).is_err());
+        assert!(add_repo_plan("https://repo.example/x y", false).is_err());
+        assert_eq!(
+            rpmfusion_release_url("nonfree", 40),
+            "https://mirrors.rpmfusion.org/nonfree/fedora/rpmfusion-nonfree-release-40.noarch.rpm"
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use tracing::warn;
+
+use crate::error::NebulaError;
+use crate::plan::{strings, CommandPlan};
+use crate::process::{run_command, run_plan};
+use crate::PackageOperationResult;
+
+// e.g. "sshd.service", "getty@tty1.service"
+static SERVICE_UNIT_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[a-zA-Z0-9:_.@-]+\.service$").unwrap());
+
+// Packages whose new version only takes effect after a reboot; the same list
+// `dnf needs-restarting -r` checks against the boot time.
+const REBOOT_PACKAGES: &[&str] = &[
+    "kernel",
+    "kernel-core",
+    "kernel-rt",
+    "glibc",
+    "linux-firmware",
+    "systemd",
+    "dbus",
+    "dbus-broker",
+    "dbus-daemon",
+    "microcode_ctl",
+];
+
+// Restarting these ends the desktop session (or worse); they are left for the next reboot.
+const SESSION_CRITICAL_UNITS: &[&str] = &[
+    "dbus.service",
+    "dbus-broker.service",
+    "systemd-logind.service",
+    "gdm.service",
+    "sddm.service",
+    "lightdm.service",
+    "display-manager.service",
+    "polkit.service",
+];
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ServiceRestart {
+    unit: String,
+    restartable: bool, // false for units whose restart would end the session
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct UnitRestartResult {
+    unit: String,
+    success: bool,
+    message: String,
+    simulated: bool,
+}
+
+fn is_session_critical(unit: &str) -> bool {
+    SESSION_CRITICAL_UNITS.contains(&unit) || unit.starts_with("user@")
+}
+
+// `dnf needs-restarting --services` prints one unit per line, after any plugin chatter.
+fn parse_service_units(output: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| SERVICE_UNIT_RE.is_match(line))
+        .filter(|unit| seen.insert(unit.to_string()))
+        .map(String::from)
+        .collect()
+}
+
+// Orders `units` so each one restarts after the units it is ordered After= (among those being
+// restarted), using `systemctl show -p Id -p After` output. Cycles fall back to the given order.
+fn restart_order(units: &[String], show_output: &str) -> Vec<String> {
+    let mut after: HashMap<String, Vec<String>> = HashMap::new();
+    let mut current: Option<String> = None;
+    for line in show_output.lines() {
+        if let Some(id) = line.strip_prefix("Id=") {
+            current = Some(id.trim().to_string());
+        } else if let (Some(deps), Some(id)) = (line.strip_prefix("After="), current.as_ref()) {
+            let deps = deps.split_whitespace().filter(|dep| units.iter().any(|u| u == dep) && dep != id);
+            after.entry(id.clone()).or_default().extend(deps.map(String::from));
+        }
+    }
+
+    let mut ordered: Vec<String> = Vec::new();
+    let mut pending: Vec<String> = units.to_vec();
+    while !pending.is_empty() {
+        let ready = pending
+            .iter()
+            .position(|unit| after.get(unit).is_none_or(|deps| deps.iter().all(|dep| ordered.contains(dep))))
+            .unwrap_or(0);
+        ordered.push(pending.remove(ready));
+    }
+    ordered
+}
+
+fn needs_reboot_for(changed: &[String]) -> bool {
+    changed.iter().any(|package| REBOOT_PACKAGES.contains(&package.as_str()))
+}
+
+// `dnf needs-restarting -r` exits with 1 both when a reboot is needed and when the command is
+// missing (dnf4 without dnf-plugins-core: "No such command: needs-restarting" on stderr). Only
+// the former lists the core packages updated since boot on stdout.
+fn reboot_reported(code: i32, stdout: &str) -> bool {
+    code == 1 && !stdout.trim().is_empty()
+}
+
+// Sets `result.reboot_required` after a transaction that ran and succeeded: when it changed one
+// of REBOOT_PACKAGES, or when `dnf needs-restarting -r` reports one updated since boot, which
+// also covers packages the transaction pulled in as dependencies.
+pub async fn check_reboot(app: &tauri::AppHandle, result: &mut PackageOperationResult, changed: &[String]) {
+    if !result.success || result.simulated || result.confirmation_required {
+        return;
+    }
+    result.reboot_required = needs_reboot_for(changed)
+        || match run_command(app, "dnf", &["needs-restarting", "-r"]).await {
+            Ok(out) => reboot_reported(out.code, &out.stdout),
+            Err(e) => {
+                warn!("Could not check whether a reboot is needed: {}", e);
+                false
+            }
+        };
+}
+
+// Services still running code from libraries that have since been updated.
+#[tauri::command]
+pub async fn list_service_restarts(app: tauri::AppHandle) -> Result<Vec<ServiceRestart>, NebulaError> {
+    let out = run_command(&app, "dnf", &["needs-restarting", "--services", "--quiet"]).await?;
+    let units = parse_service_units(&out.stdout);
+    if units.is_empty() && !out.success {
+        return Err(NebulaError::command_failed(out.code, &out.stderr, format!("dnf needs-restarting failed: {}", out.error_text().trim())));
+    }
+    Ok(units
+        .into_iter()
+        .map(|unit| ServiceRestart { restartable: !is_session_critical(&unit), unit })
+        .collect())
+}
+
+// Restarts the given units one by one, dependencies first, and reports how each went.
+// Session-critical units are refused rather than restarted.
+#[tauri::command]
+pub async fn apply_service_restarts(app: tauri::AppHandle, units: Vec<String>) -> Result<Vec<UnitRestartResult>, NebulaError> {
+    if let Some(bad) = units.iter().find(|unit| !SERVICE_UNIT_RE.is_match(unit)) {
+        return Err(format!("Not a service unit: '{}'", bad).into());
+    }
+    let mut show_args = vec!["show", "-p", "Id", "-p", "After", "--"];
+    show_args.extend(units.iter().map(String::as_str));
+    let show = run_command(&app, "systemctl", &show_args).await?;
+
+    let mut results = Vec::new();
+    for unit in restart_order(&units, &show.stdout) {
+        if is_session_critical(&unit) {
+            results.push(UnitRestartResult {
+                message: "Not restarted: this would end the current session. Reboot instead.".to_string(),
+                unit,
+                success: false,
+                simulated: false,
+            });
+            continue;
+        }
+        let plan = CommandPlan::privileged("systemctl", strings(&["restart", "--", &unit]));
+        let out = run_plan(&app, &plan).await?;
+        results.push(UnitRestartResult {
+            message: if out.success { "Restarted.".to_string() } else { out.error_text().trim().to_string() },
+            unit,
+            success: out.success,
+            simulated: out.simulated,
+        });
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restart_order() {
+        let output = "Updating Subscription Management repositories.\nauditd.service\nNetworkManager.service\nsshd.service\nsshd.service\n";
+        let units = parse_service_units(output);
+        assert_eq!(units, vec!["auditd.service", "NetworkManager.service", "sshd.service"]);
+
+        let show = "Id=auditd.service\nAfter=local-fs.target\n\nId=NetworkManager.service\nAfter=dbus.socket auditd.service\n\n\
+                    Id=sshd.service\nAfter=network.target NetworkManager.service sshd-keygen.target\n";
+        let units = strings(&["sshd.service", "NetworkManager.service", "auditd.service"]);
+        assert_eq!(restart_order(&units, show), vec!["auditd.service", "NetworkManager.service", "sshd.service"]);
+        assert!(is_session_critical("user@1000.service"));
+        assert!(needs_reboot_for(&strings(&["bash", "kernel-core"])));
+        assert!(!needs_reboot_for(&strings(&["kernel-headers", "systemd-libs"])));
+        let core_updated = "Core libraries or services have been updated since boot-up:\n  * kernel\n\nReboot is required to fully utilize these updates.\n";
+        assert!(reboot_reported(1, core_updated));
+        assert!(!reboot_reported(1, ""));
+        assert!(!reboot_reported(0, "No core libraries or services have been updated since boot-up.\n"));
+    }
+}
@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tauri::Manager;
+
+use crate::error::NebulaError;
+use crate::kickstart::{installed_groups, user_installed_names};
+use crate::process::{dry_run_succeeded, run_plan, run_plan_streaming};
+use crate::scope::{config_file, Scope};
+use crate::services::OperationManager;
+use crate::PackageOperationResult;
+
+// Roles shipped with the app; admins can add roles or replace these by id in the system config.
+const BUILTIN_ROLES: &str = include_str!("../roles/roles.json");
+const ROLES_FILE_NAME: &str = "roles.json";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RoleTemplate {
+    id: String,
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    groups: Vec<String>, // comps group or environment ids
+    #[serde(default)]
+    packages: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct RoleEvaluation {
+    role: RoleTemplate,
+    missing_groups: Vec<String>,
+    missing_packages: Vec<String>,
+    extra_packages: Vec<String>, // user-installed packages the role does not list
+    satisfied: bool,
+}
+
+fn load_roles(app: &tauri::AppHandle) -> Result<Vec<RoleTemplate>, String> {
+    let mut roles: Vec<RoleTemplate> = serde_json::from_str(BUILTIN_ROLES).map_err(|e| format!("Invalid built-in roles: {}", e))?;
+    let custom: Vec<RoleTemplate> = crate::load_json_file(&config_file(app, Scope::System, ROLES_FILE_NAME)?)?.unwrap_or_default();
+    for role in custom {
+        roles.retain(|existing| existing.id != role.id);
+        roles.push(role);
+    }
+    Ok(roles)
+}
+
+fn find_role(app: &tauri::AppHandle, role_id: &str) -> Result<RoleTemplate, String> {
+    load_roles(app)?
+        .into_iter()
+        .find(|role| role.id == role_id)
+        .ok_or_else(|| format!("Unknown role: '{}'", role_id))
+}
+
+fn evaluate(role: RoleTemplate, groups: &HashSet<String>, installed: &HashSet<String>, user_installed: &[String]) -> RoleEvaluation {
+    let missing_groups: Vec<String> = role.groups.iter().filter(|group| !groups.contains(*group)).cloned().collect();
+    let missing_packages: Vec<String> = role.packages.iter().filter(|package| !installed.contains(*package)).cloned().collect();
+    let extra_packages = user_installed.iter().filter(|name| !role.packages.contains(name)).cloned().collect();
+    RoleEvaluation {
+        satisfied: missing_groups.is_empty() && missing_packages.is_empty(),
+        role,
+        missing_groups,
+        missing_packages,
+        extra_packages,
+    }
+}
+
+#[tauri::command]
+pub async fn list_roles(app: tauri::AppHandle) -> Result<Vec<RoleTemplate>, NebulaError> {
+    Ok(load_roles(&app)?)
+}
+
+// How far this system is from a role: what it lacks, and what was installed beyond it.
+#[tauri::command]
+pub async fn evaluate_role(app: tauri::AppHandle, role_id: String) -> Result<RoleEvaluation, NebulaError> {
+    let role = find_role(&app, &role_id)?;
+    let (environments, groups) = installed_groups(&app).await?;
+    let groups: HashSet<String> = environments.into_iter().chain(groups).collect();
+    let installed = crate::rpmdb::installed_names(&app).await?;
+    Ok(evaluate(role, &groups, &installed, &user_installed_names(&app).await?))
+}
+
+// Installs what the role lacks in one transaction. Extra packages are left alone; removing
+// software the user chose is for them to decide package by package.
+#[tauri::command]
+pub async fn apply_role(app: tauri::AppHandle, role_id: String, dry_run: bool) -> Result<PackageOperationResult, NebulaError> {
+    let evaluation = evaluate_role(app.clone(), role_id).await?;
+    let name = evaluation.role.name.clone();
+    if evaluation.satisfied {
+        return Ok(PackageOperationResult {
+            success: true,
+            message: format!("This system already has everything the {} role lists.", name),
+            details: None,
+            confirmation_required: false,
+            simulated: false,
+            reboot_required: false,
+        });
+    }
+    let plan = crate::plan::install_groups_and_packages(&evaluation.missing_groups, &evaluation.missing_packages, dry_run)?;
+    let out = if dry_run {
+        run_plan(&app, &plan).await?
+    } else {
+        let _busy = app.state::<OperationManager>().begin(format!("Applying the {} role", name));
+        run_plan_streaming(&app, &plan, "apply_role").await?
+    };
+    let success = if dry_run { dry_run_succeeded(out.code, &out.stdout, &out.stderr) } else { out.success };
+    Ok(PackageOperationResult {
+        success,
+        message: match (success, dry_run) {
+            (true, true) => format!("Dry run finished for the {} role. Review the transaction before applying it.", name),
+            (true, false) => format!("Applied the {} role.", name),
+            (false, _) => format!("Failed to apply the {} role. Exit code: {}.", name, out.code),
+        },
+        details: Some(format!("STDOUT:\n{}\nSTDERR:\n{}", out.stdout, out.stderr)),
+        confirmation_required: false,
+        simulated: out.simulated,
+        reboot_required: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate_role() {
+        let roles: Vec<RoleTemplate> = serde_json::from_str(BUILTIN_ROLES).unwrap();
+        let devbox = roles.into_iter().find(|role| role.id == "devbox").unwrap();
+        let groups = HashSet::from(["development-tools".to_string(), "c-development".to_string()]);
+        let installed: HashSet<String> = devbox.packages.iter().filter(|p| *p != "gdb").cloned().collect();
+        let evaluation = evaluate(devbox, &groups, &installed, &["git".to_string(), "htop".to_string()]);
+        assert_eq!(evaluation.missing_groups, vec!["container-management"]);
+        assert_eq!(evaluation.missing_packages, vec!["gdb"]);
+        assert_eq!(evaluation.extra_packages, vec!["htop"]);
+        assert!(!evaluation.satisfied);
+
+        let plan = crate::plan::install_groups_and_packages(&evaluation.missing_groups, &evaluation.missing_packages, true).unwrap();
+        assert_eq!(plan.command_line(), "dnf install --assumeno @container-management gdb");
+    }
+}
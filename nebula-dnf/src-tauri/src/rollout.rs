@@ -0,0 +1,222 @@
+use chrono::{DateTime, Duration as ChronoDuration, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tauri::Manager;
+use tracing::info;
+
+use crate::error::NebulaError;
+use crate::plan;
+use crate::cleanup::fetch_leaf_names;
+use crate::process::run_plan;
+use crate::services::OperationManager;
+use crate::settings::GuardedAction;
+use crate::updates::fetch_pending_updates;
+use crate::{app_data_file, load_json_file, save_json_file, PackageOperationResult};
+
+const STATE_FILE_NAME: &str = "rollout_state.json";
+const PROTECTED_DIR: &str = "/etc/dnf/protected.d";
+// Phase two unlocks on its own this long after phase one, or earlier with explicit confirmation.
+const PHASE_TWO_DELAY_HOURS: i64 = 24;
+
+// Packages treated as core even if nothing in the protected.d config names them.
+const CORE_PACKAGE_PREFIXES: [&str; 14] = [
+    "kernel", "glibc", "systemd", "dnf", "rpm", "libdnf", "grub2", "shim", "dracut", "selinux-policy",
+    "linux-firmware", "NetworkManager", "dbus", "polkit",
+];
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RolloutEntry {
+    name: String,
+    version: String,
+    reason: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct StagedRolloutPlan {
+    phase_one: Vec<RolloutEntry>,
+    phase_two: Vec<RolloutEntry>,
+    phase_one_completed_at: Option<String>,
+    phase_two_unlocked_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+struct RolloutState {
+    phase_one_completed_at: Option<DateTime<Local>>,
+    phase_one_packages: Vec<String>,
+}
+
+fn parse_protected_list(contents: &str) -> impl Iterator<Item = String> + '_ {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+}
+
+fn read_protected_packages() -> HashSet<String> {
+    let mut protected = HashSet::new();
+    let Ok(entries) = std::fs::read_dir(PROTECTED_DIR) else { return protected };
+    for entry in entries.flatten() {
+        if let Ok(contents) = std::fs::read_to_string(entry.path()) {
+            protected.extend(parse_protected_list(&contents));
+        }
+    }
+    protected
+}
+
+fn core_reason(name: &str, protected: &HashSet<String>, leaves: &HashSet<String>) -> Option<String> {
+    if protected.contains(name) {
+        Some("Protected by dnf configuration".to_string())
+    } else if CORE_PACKAGE_PREFIXES.iter().any(|prefix| name == *prefix || name.starts_with(&format!("{}-", prefix))) {
+        Some("Core system component".to_string())
+    } else if !leaves.contains(name) {
+        Some("Other installed packages depend on it".to_string())
+    } else {
+        None
+    }
+}
+
+async fn build_plan(app: &tauri::AppHandle) -> Result<(Vec<RolloutEntry>, Vec<RolloutEntry>), String> {
+    let updates = fetch_pending_updates(app).await?;
+    let protected = read_protected_packages();
+    let leaves = fetch_leaf_names(app).await?;
+
+    let mut phase_one = Vec::new();
+    let mut phase_two = Vec::new();
+    for update in updates {
+        match core_reason(&update.name, &protected, &leaves) {
+            Some(reason) => phase_two.push(RolloutEntry { name: update.name, version: update.version, reason }),
+            None => phase_one.push(RolloutEntry {
+                name: update.name,
+                version: update.version,
+                reason: "Leaf application, nothing depends on it".to_string(),
+            }),
+        }
+    }
+    Ok((phase_one, phase_two))
+}
+
+fn phase_two_unlock_time(state: &RolloutState) -> Option<DateTime<Local>> {
+    state.phase_one_completed_at.map(|t| t + ChronoDuration::hours(PHASE_TWO_DELAY_HOURS))
+}
+
+async fn run_upgrade(app: &tauri::AppHandle, packages: &[String]) -> Result<PackageOperationResult, String> {
+    let pinned = crate::pins::pinned_packages(app)?;
+    let out = run_plan(app, &plan::upgrade_packages(packages, false, &pinned)?).await?;
+    let details = format!("STDOUT:\n{}\nSTDERR:\n{}", out.stdout, out.stderr);
+    Ok(PackageOperationResult {
+        success: out.success,
+        message: if out.success {
+            format!("Upgraded {} package(s).", packages.len())
+        } else {
+            format!("Upgrade failed with exit code {}.", out.code)
+        },
+        details: Some(details),
+        confirmation_required: false,
+        simulated: out.simulated,
+        reboot_required: false,
+    })
+}
+
+#[tauri::command]
+pub async fn plan_staged_rollout(app: tauri::AppHandle) -> Result<StagedRolloutPlan, NebulaError> {
+    info!("Computing staged rollout plan.");
+    let (phase_one, phase_two) = build_plan(&app).await?;
+    let state: RolloutState = load_json_file(&app_data_file(&app, STATE_FILE_NAME)?)?.unwrap_or_default();
+    Ok(StagedRolloutPlan {
+        phase_one,
+        phase_two,
+        phase_one_completed_at: state.phase_one_completed_at.map(|t| t.to_rfc3339()),
+        phase_two_unlocked_at: phase_two_unlock_time(&state).map(|t| t.to_rfc3339()),
+    })
+}
+
+// Phase one: upgrade only leaf applications.
+#[tauri::command]
+pub async fn apply_rollout_phase_one(app: tauri::AppHandle, confirmed: Option<bool>) -> Result<PackageOperationResult, NebulaError> {
+    let (phase_one, _) = build_plan(&app).await?;
+    if phase_one.is_empty() {
+        return Ok(PackageOperationResult {
+            success: true,
+            message: "No leaf application updates pending; phase two can be applied directly.".to_string(),
+            details: None,
+            confirmation_required: false,
+            simulated: false,
+            reboot_required: false,
+        });
+    }
+    let packages: Vec<String> = phase_one.into_iter().map(|entry| entry.name).collect();
+    if crate::settings::requires_preview(&app, GuardedAction::Update, confirmed.unwrap_or(false))? {
+        let changelog = crate::updates::fetch_update_changelog(&app, &packages).await;
+        return Ok(crate::settings::preview_result(format!("Review the changes in the {} leaf application update(s), then confirm phase one.", packages.len()), changelog));
+    }
+    info!("Applying rollout phase one to {} package(s).", packages.len());
+    let _busy = app.state::<OperationManager>().begin("Staged rollout: phase one".to_string());
+    let result = run_upgrade(&app, &packages).await?;
+    if result.success {
+        let state = RolloutState {
+            phase_one_completed_at: Some(Local::now()),
+            phase_one_packages: packages,
+        };
+        save_json_file(&app_data_file(&app, STATE_FILE_NAME)?, &state)?;
+    }
+    Ok(result)
+}
+
+// Phase two: everything else. Requires phase one first, then either the delay to have
+// passed or `confirmed` to be set by an explicit second confirmation in the UI. That
+// confirmation also stands for the review the settings may ask for before updates.
+#[tauri::command]
+pub async fn apply_rollout_phase_two(app: tauri::AppHandle, confirmed: bool) -> Result<PackageOperationResult, NebulaError> {
+    let state_path = app_data_file(&app, STATE_FILE_NAME)?;
+    let state: RolloutState = load_json_file(&state_path)?.unwrap_or_default();
+    let Some(unlock_time) = phase_two_unlock_time(&state) else {
+        return Err("Phase one of the staged rollout has not been completed yet.".into());
+    };
+    if !confirmed && Local::now() < unlock_time {
+        return Err(format!(
+            "Phase two unlocks at {}. Confirm explicitly to apply it earlier.",
+            unlock_time.to_rfc3339()
+        ).into());
+    }
+
+    let (_, phase_two) = build_plan(&app).await?;
+    let packages: Vec<String> = phase_two.into_iter().map(|entry| entry.name).collect();
+    if packages.is_empty() {
+        save_json_file(&state_path, &RolloutState::default())?;
+        return Ok(PackageOperationResult {
+            success: true,
+            message: "No remaining updates; staged rollout complete.".to_string(),
+            details: None,
+            confirmation_required: false,
+            simulated: false,
+            reboot_required: false,
+        });
+    }
+    if crate::settings::requires_preview(&app, GuardedAction::Update, confirmed)? {
+        let changelog = crate::updates::fetch_update_changelog(&app, &packages).await;
+        return Ok(crate::settings::preview_result(format!("Review the changes in the {} remaining update(s), then confirm phase two.", packages.len()), changelog));
+    }
+    info!("Applying rollout phase two to {} package(s).", packages.len());
+    let _busy = app.state::<OperationManager>().begin("Staged rollout: phase two".to_string());
+    let result = run_upgrade(&app, &packages).await?;
+    if result.success {
+        save_json_file(&state_path, &RolloutState::default())?;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_core_reason() {
+        let protected: HashSet<String> = parse_protected_list("# comment\ndnf\n\nsudo\n").collect();
+        let leaves: HashSet<String> = ["firefox", "kernel-core", "sudo"].iter().map(|s| s.to_string()).collect();
+        assert!(core_reason("firefox", &protected, &leaves).is_none());
+        assert!(core_reason("sudo", &protected, &leaves).is_some());
+        assert!(core_reason("kernel-core", &protected, &leaves).is_some());
+        assert!(core_reason("libpng", &protected, &leaves).is_some());
+    }
+}
@@ -0,0 +1,216 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::PathBuf;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tracing::{error, info, warn};
+
+use crate::error::NebulaError;
+use crate::{
+    advisories, audit, cleanup, coordination, health, history, kernels, package_info, pins, plan, provides, settings, software, updates,
+};
+
+const SOCKET_FILE_NAME: &str = "rpc.sock";
+
+// JSON-RPC 2.0 error codes.
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const COMMAND_FAILED: i64 = -32000;
+
+#[derive(Debug, PartialEq)]
+struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>, // a failed command's NebulaError, for clients that branch on its kind
+}
+
+// $XDG_RUNTIME_DIR is private to the user, which keeps other users from even reaching the socket;
+// the peer UID check below is the actual authentication.
+pub fn socket_path() -> Result<PathBuf, String> {
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR").ok_or("XDG_RUNTIME_DIR is not set")?;
+    Ok(PathBuf::from(runtime_dir).join("nebulasys").join(SOCKET_FILE_NAME))
+}
+
+// Named parameters use the Rust (snake_case) names, e.g. {"package_name": "htop"}. A missing
+// parameter reads as null, which is what Option parameters expect.
+fn param<T: DeserializeOwned>(params: &Value, name: &str) -> Result<T, RpcError> {
+    let value = params.get(name).cloned().unwrap_or(Value::Null);
+    serde_json::from_value(value).map_err(|e| RpcError {
+        code: INVALID_PARAMS,
+        message: format!("Invalid parameter '{}': {}", name, e),
+        data: None,
+    })
+}
+
+fn encode<T: Serialize, E: Into<NebulaError>>(result: Result<T, E>) -> Result<Value, RpcError> {
+    let value = result.map_err(|error| {
+        let error: NebulaError = error.into();
+        RpcError { code: COMMAND_FAILED, message: error.to_string(), data: serde_json::to_value(&error).ok() }
+    })?;
+    serde_json::to_value(value).map_err(|e| RpcError { code: COMMAND_FAILED, message: e.to_string(), data: None })
+}
+
+// Calls a Tauri command function with the named parameters, in order.
+macro_rules! call {
+    ($command:path, $app:expr, $params:expr $(, $name:literal)*) => {
+        encode($command($app $(, param($params, $name)?)*).await)
+    };
+}
+
+// Methods carry the same names and parameters as the Tauri commands, so every frontend drives
+// the same code paths (plans, pins, settings, simulation mode, audit).
+async fn dispatch(app: tauri::AppHandle, method: &str, params: &Value) -> Result<Value, RpcError> {
+    match method {
+        "list_user_installed_packages" => call!(crate::list_user_installed_packages, app, params, "force_refresh", "include_internal"),
+        "manage_package_update" => call!(crate::manage_package_update, app, params, "package_name", "confirmed", "options", "offline"),
+        "execute_package_uninstall" => call!(crate::execute_package_uninstall, app, params, "args", "options"),
+        "list_all_software" => call!(software::list_all_software, app, params),
+        "get_package_details" => call!(package_info::get_package_details, app, params, "name"),
+        "get_package_files" => call!(package_info::get_package_files, app, params, "name"),
+        "what_provides" => call!(provides::what_provides, app, params, "capability"),
+        "find_owner_of_file" => call!(provides::find_owner_of_file, app, params, "path"),
+        "preview_command" => call!(plan::preview_command, app, params, "request"),
+        "check_for_updates" => call!(updates::check_for_updates, app, params, "force_refresh"),
+        "update_all_packages" => call!(updates::update_all_packages, app, params, "confirmed", "options", "download_only", "offline"),
+        "get_update_advisories" => call!(advisories::get_update_advisories, app, params, "package"),
+        "list_dnf_history" => call!(history::list_dnf_history, app, params),
+        "get_transaction_info" => call!(history::get_transaction_info, app, params, "id"),
+        "export_reproduction_script" => call!(audit::export_reproduction_script, app, params, "last"),
+        "list_orphan_packages" => call!(cleanup::list_orphan_packages, app, params),
+        "cleanup_orphans" => call!(cleanup::cleanup_orphans, app, params, "dry_run", "confirmed"),
+        "check_system_health" => call!(health::check_system_health, app, params),
+        "get_kernel_overview" => call!(kernels::get_kernel_overview, app, params),
+        "set_default_kernel" => call!(kernels::set_default_kernel, app, params, "release"),
+        "list_pins" => call!(pins::list_pins, app, params),
+        "add_pin" => call!(pins::add_pin, app, params, "pin"),
+        "remove_pin" => call!(pins::remove_pin, app, params, "package"),
+        "get_effective_settings" => call!(settings::get_effective_settings, app, params),
+        "get_packagekit_state" => call!(coordination::get_packagekit_state, app, params),
+        "get_pending_system_transactions" => encode(coordination::get_pending_system_transactions().await),
+        // a GUI launched while the daemon runs asks it to open its window instead
+        "show_window" => encode(crate::daemon::show_main_window(&app)),
+        _ => Err(RpcError { code: METHOD_NOT_FOUND, message: format!("Unknown method '{}'", method), data: None }),
+    }
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": error.code, "message": error.message, "data": error.data } }),
+    }
+}
+
+// Splits a request line into (id, method, params). Errors carry the id when it could be read.
+fn parse_request(line: &str) -> Result<(Value, String, Value), (Value, RpcError)> {
+    let request: Value = serde_json::from_str(line)
+        .map_err(|e| (Value::Null, RpcError { code: PARSE_ERROR, message: e.to_string(), data: None }))?;
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+        return Err((id, RpcError { code: INVALID_REQUEST, message: "Missing 'method'".to_string(), data: None }));
+    };
+    let params = request.get("params").cloned().unwrap_or_else(|| json!({}));
+    if !params.is_object() {
+        return Err((id, RpcError { code: INVALID_PARAMS, message: "'params' must be an object of named parameters".to_string(), data: None }));
+    }
+    Ok((id, method.to_string(), params))
+}
+
+// One request per line, one response per line, until the client closes the connection.
+async fn handle_connection(stream: UnixStream, app: tauri::AppHandle, own_uid: u32) {
+    match stream.peer_cred() {
+        Ok(cred) if cred.uid() == own_uid => {}
+        Ok(cred) => {
+            error!("RPC: rejected connection from uid {}.", cred.uid());
+            return;
+        }
+        Err(e) => {
+            error!("RPC: could not read peer credentials: {}", e);
+            return;
+        }
+    }
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match parse_request(&line) {
+            Ok((id, method, params)) => response(id, dispatch(app.clone(), &method, &params).await),
+            Err((id, error)) => response(id, Err(error)),
+        };
+        if writer.write_all(format!("{}\n", reply).as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
+
+fn bind_socket() -> Result<UnixListener, String> {
+    let path = socket_path()?;
+    let dir = path.parent().ok_or("Invalid socket path")?;
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+    std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700)).map_err(|e| format!("Failed to restrict {:?}: {}", dir, e))?;
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).map_err(|e| format!("Failed to bind {:?}: {}", path, e))?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).map_err(|e| format!("Failed to restrict {:?}: {}", path, e))?;
+    Ok(listener)
+}
+
+// Started at app setup. Uses `activated`, the socket systemd passed when socket-activated (daemon
+// mode), otherwise binds its own, replacing a stale socket from a previous run.
+pub async fn start(app: tauri::AppHandle, activated: Option<std::os::unix::net::UnixListener>) {
+    let listener = async {
+        let listener = match activated {
+            Some(listener) => {
+                listener.set_nonblocking(true).map_err(|e| format!("Failed to use the activated socket: {}", e))?;
+                UnixListener::from_std(listener).map_err(|e| format!("Failed to use the activated socket: {}", e))?
+            }
+            None => bind_socket()?,
+        };
+        let path = socket_path()?;
+        // The owner of /proc/self is this process's effective uid.
+        let own_uid = std::fs::metadata("/proc/self").map_err(|e| format!("Failed to determine own uid: {}", e))?.uid();
+        info!("JSON-RPC interface listening on {:?}", path);
+        Ok::<_, String>((listener, own_uid))
+    }
+    .await;
+
+    let (listener, own_uid) = match listener {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!("JSON-RPC interface unavailable: {}", e);
+            return;
+        }
+    };
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tauri::async_runtime::spawn(handle_connection(stream, app.clone(), own_uid));
+            }
+            Err(e) => error!("RPC: failed to accept connection: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request_and_params() {
+        let (id, method, params) = parse_request(r#"{"jsonrpc":"2.0","id":7,"method":"get_update_advisories","params":{"package":"firefox"}}"#).unwrap();
+        assert_eq!(id, json!(7));
+        assert_eq!(method, "get_update_advisories");
+        assert_eq!(param::<String>(&params, "package").unwrap(), "firefox");
+        assert_eq!(param::<Option<bool>>(&params, "confirmed").unwrap(), None);
+        assert_eq!(param::<bool>(&params, "dry_run").unwrap_err().code, INVALID_PARAMS);
+
+        let (id, error) = parse_request(r#"{"id":"a","params":[]}"#).unwrap_err();
+        assert_eq!(id, json!("a"));
+        assert_eq!(error.code, INVALID_REQUEST);
+        assert_eq!(parse_request("not json").unwrap_err().1.code, PARSE_ERROR);
+    }
+}
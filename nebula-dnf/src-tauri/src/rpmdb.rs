@@ -0,0 +1,231 @@
+use libloading::Library;
+use once_cell::sync::Lazy;
+use std::collections::HashSet;
+use std::ffi::{c_char, c_int, c_void, CStr};
+use std::sync::Mutex;
+use tracing::warn;
+
+use crate::process::run_command;
+
+// librpm is loaded at run time, so builds need no rpm-devel and a system without it still works
+// through the `rpm` subprocess paths.
+const LIBRPM_NAMES: &[&str] = &["librpm.so.10", "librpm.so.9", "librpm.so"];
+
+// rpmTag_e / rpmDbiTag_e values
+const RPMDBI_PACKAGES: c_int = 0;
+const RPMTAG_NAME: c_int = 1000;
+const RPMTAG_SIZE: c_int = 1009;
+const RPMTAG_GROUP: c_int = 1016;
+const RPMTAG_REQUIREFLAGS: c_int = 1048;
+const RPMTAG_REQUIRENAME: c_int = 1049;
+const RPMTAG_REQUIREVERSION: c_int = 1050;
+const HEADERGET_MINMEM: c_int = 1;
+// rpmsenseFlags comparison bits
+const RPMSENSE_LESS: u32 = 1 << 1;
+const RPMSENSE_GREATER: u32 = 1 << 2;
+const RPMSENSE_EQUAL: u32 = 1 << 3;
+
+type Handle = *mut c_void;
+
+static LIBRPM: Lazy<Option<Library>> = Lazy::new(|| {
+    // SAFETY: librpm runs no initialisation code with preconditions on load.
+    LIBRPM_NAMES.iter().find_map(|name| unsafe { Library::new(name) }.ok())
+});
+// librpm keeps global state (macros, the rpmdb handle); one reader at a time.
+static RPMDB_LOCK: Mutex<bool> = Mutex::new(false); // whether the rpm config was read
+
+// One installed package, with the fields the package list needs from its header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstalledHeader {
+    pub name: String,
+    pub group: String,
+    pub size: u64, // installed size in bytes
+    pub requires: Vec<String>, // as `rpm -qR` prints them, e.g. "bash >= 5.0"
+}
+
+fn requirement(name: &str, flags: u32, version: &str) -> String {
+    let operator = match (flags & RPMSENSE_LESS != 0, flags & RPMSENSE_GREATER != 0, flags & RPMSENSE_EQUAL != 0) {
+        (true, false, true) => "<=",
+        (true, false, false) => "<",
+        (false, true, true) => ">=",
+        (false, true, false) => ">",
+        (false, false, true) => "=",
+        _ => return name.to_string(),
+    };
+    if version.is_empty() {
+        name.to_string()
+    } else {
+        format!("{} {} {}", name, operator, version)
+    }
+}
+
+unsafe fn symbol<T: Copy>(lib: &Library, name: &[u8]) -> Result<T, String> {
+    lib.get::<T>(name)
+        .map(|symbol| *symbol)
+        .map_err(|e| format!("librpm lacks {}: {}", String::from_utf8_lossy(name), e))
+}
+
+unsafe fn owned(text: *const c_char) -> String {
+    if text.is_null() {
+        String::new()
+    } else {
+        crate::process::decode_output(CStr::from_ptr(text).to_bytes())
+    }
+}
+
+struct Api {
+    header_get_string: unsafe extern "C" fn(Handle, c_int) -> *const c_char,
+    header_get_number: unsafe extern "C" fn(Handle, c_int) -> u64,
+    header_get: unsafe extern "C" fn(Handle, c_int, Handle, c_int) -> c_int,
+    td_new: unsafe extern "C" fn() -> Handle,
+    td_free: unsafe extern "C" fn(Handle) -> Handle,
+    td_next_string: unsafe extern "C" fn(Handle) -> *const c_char,
+    td_next_uint32: unsafe extern "C" fn(Handle) -> *mut u32,
+}
+
+impl Api {
+    unsafe fn strings(&self, header: Handle, tag: c_int) -> Vec<String> {
+        let td = (self.td_new)();
+        let mut values = Vec::new();
+        if (self.header_get)(header, tag, td, HEADERGET_MINMEM) == 1 {
+            loop {
+                let value = (self.td_next_string)(td);
+                if value.is_null() {
+                    break;
+                }
+                values.push(owned(value));
+            }
+        }
+        (self.td_free)(td);
+        values
+    }
+
+    unsafe fn numbers(&self, header: Handle, tag: c_int) -> Vec<u32> {
+        let td = (self.td_new)();
+        let mut values = Vec::new();
+        if (self.header_get)(header, tag, td, HEADERGET_MINMEM) == 1 {
+            loop {
+                let value = (self.td_next_uint32)(td);
+                if value.is_null() {
+                    break;
+                }
+                values.push(*value);
+            }
+        }
+        (self.td_free)(td);
+        values
+    }
+
+    unsafe fn package(&self, header: Handle) -> InstalledHeader {
+        let names = self.strings(header, RPMTAG_REQUIRENAME);
+        let flags = self.numbers(header, RPMTAG_REQUIREFLAGS);
+        let versions = self.strings(header, RPMTAG_REQUIREVERSION);
+        let requires = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| requirement(name, flags.get(i).copied().unwrap_or(0), versions.get(i).map(String::as_str).unwrap_or("")))
+            .collect();
+        InstalledHeader {
+            name: owned((self.header_get_string)(header, RPMTAG_NAME)),
+            group: owned((self.header_get_string)(header, RPMTAG_GROUP)),
+            size: (self.header_get_number)(header, RPMTAG_SIZE),
+            requires,
+        }
+    }
+}
+
+// Walks the rpmdb once, in this process.
+fn read_rpmdb(lib: &Library) -> Result<Vec<InstalledHeader>, String> {
+    let mut config_read = RPMDB_LOCK.lock().unwrap();
+    // SAFETY: the signatures match rpm's public C API (rpm 4.9 and later), every iterator and
+    // transaction set is freed once, and headers are only used while their iterator is alive.
+    unsafe {
+        if !*config_read {
+            let read_config: unsafe extern "C" fn(*const c_char, *const c_char) -> c_int = symbol(lib, b"rpmReadConfigFiles\0")?;
+            if read_config(std::ptr::null(), std::ptr::null()) != 0 {
+                return Err("librpm could not read its configuration.".to_string());
+            }
+            *config_read = true;
+        }
+        let ts_create: unsafe extern "C" fn() -> Handle = symbol(lib, b"rpmtsCreate\0")?;
+        let ts_free: unsafe extern "C" fn(Handle) -> Handle = symbol(lib, b"rpmtsFree\0")?;
+        let init_iterator: unsafe extern "C" fn(Handle, c_int, *const c_void, usize) -> Handle = symbol(lib, b"rpmtsInitIterator\0")?;
+        let next: unsafe extern "C" fn(Handle) -> Handle = symbol(lib, b"rpmdbNextIterator\0")?;
+        let free_iterator: unsafe extern "C" fn(Handle) -> Handle = symbol(lib, b"rpmdbFreeIterator\0")?;
+        let api = Api {
+            header_get_string: symbol(lib, b"headerGetString\0")?,
+            header_get_number: symbol(lib, b"headerGetNumber\0")?,
+            header_get: symbol(lib, b"headerGet\0")?,
+            td_new: symbol(lib, b"rpmtdNew\0")?,
+            td_free: symbol(lib, b"rpmtdFree\0")?,
+            td_next_string: symbol(lib, b"rpmtdNextString\0")?,
+            td_next_uint32: symbol(lib, b"rpmtdNextUint32\0")?,
+        };
+
+        let ts = ts_create();
+        let iterator = init_iterator(ts, RPMDBI_PACKAGES, std::ptr::null(), 0);
+        if iterator.is_null() {
+            ts_free(ts);
+            return Err("Failed to open the rpm database.".to_string());
+        }
+        let mut packages = Vec::new();
+        loop {
+            let header = next(iterator);
+            if header.is_null() {
+                break;
+            }
+            packages.push(api.package(header));
+        }
+        free_iterator(iterator);
+        ts_free(ts);
+        Ok(packages)
+    }
+}
+
+// Whether librpm could be loaded at all; the read itself can still fail.
+pub fn librpm_available() -> bool {
+    LIBRPM.is_some()
+}
+
+// Every installed package from one read of the rpmdb, or None when librpm is unavailable or
+// the read failed, in which case callers fall back to running `rpm`.
+pub async fn installed_packages() -> Option<Vec<InstalledHeader>> {
+    let lib = LIBRPM.as_ref()?;
+    match tauri::async_runtime::spawn_blocking(move || read_rpmdb(lib)).await {
+        Ok(Ok(packages)) => Some(packages),
+        Ok(Err(e)) => {
+            warn!("Reading the rpmdb through librpm failed, using rpm instead: {}", e);
+            None
+        }
+        Err(e) => {
+            warn!("rpmdb read task failed: {}", e);
+            None
+        }
+    }
+}
+
+// Names of the installed packages, from the rpmdb or else `rpm -qa`.
+pub async fn installed_names(app: &tauri::AppHandle) -> Result<HashSet<String>, String> {
+    if let Some(packages) = installed_packages().await {
+        return Ok(packages.into_iter().map(|header| header.name).collect());
+    }
+    let out = run_command(app, "rpm", &["-qa", "--queryformat", "%{NAME}\n"]).await?;
+    if !out.success {
+        return Err(format!("rpm -qa failed: {}", out.error_text().trim()));
+    }
+    Ok(out.stdout.lines().map(str::trim).filter(|name| !name.is_empty()).map(String::from).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requirement() {
+        assert_eq!(requirement("bash", RPMSENSE_GREATER | RPMSENSE_EQUAL, "5.0"), "bash >= 5.0");
+        assert_eq!(requirement("rpmlib(PayloadIsXz)", RPMSENSE_LESS | RPMSENSE_EQUAL | (1 << 24), "5.2-1"), "rpmlib(PayloadIsXz) <= 5.2-1");
+        assert_eq!(requirement("glibc", RPMSENSE_EQUAL, "2.39-8.fc40"), "glibc = 2.39-8.fc40");
+        assert_eq!(requirement("libc.so.6()(64bit)", 1 << 14, ""), "libc.so.6()(64bit)");
+        assert!(crate::is_simple_requirement(&requirement("python3", RPMSENSE_LESS, "3.13")));
+    }
+}
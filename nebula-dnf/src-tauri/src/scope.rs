@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::app_data_file;
+use crate::plan::{strings, CommandPlan};
+use crate::process::run_plan;
+
+// Machine-wide configuration lives here; admins may also provision it by hand.
+const SYSTEM_CONFIG_DIR: &str = "/etc/nebulasys";
+
+// Which installation or configuration an operation targets. Commands that support both take
+// this explicitly; there is deliberately no default.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Scope {
+    User,
+    System,
+}
+
+impl Scope {
+    // The matching flatpak installation flag.
+    pub fn flatpak_flag(self) -> &'static str {
+        match self {
+            Scope::User => "--user",
+            Scope::System => "--system",
+        }
+    }
+}
+
+pub fn config_file(app: &tauri::AppHandle, scope: Scope, file_name: &str) -> Result<PathBuf, String> {
+    match scope {
+        Scope::User => app_data_file(app, file_name),
+        Scope::System => Ok(PathBuf::from(SYSTEM_CONFIG_DIR).join(file_name)),
+    }
+}
+
+// Writes a JSON config file for the given scope. System files are root-owned, so they are
+// staged in a temp file and installed with pkexec.
+pub async fn save_config_file<T: Serialize>(app: &tauri::AppHandle, scope: Scope, file_name: &str, data: &T) -> Result<(), String> {
+    let target = config_file(app, scope, file_name)?;
+    if scope == Scope::User {
+        return crate::save_json_file(&target, data);
+    }
+    let contents = serde_json::to_string_pretty(data).map_err(|e| format!("Failed to serialize {}: {}", file_name, e))?;
+    write_root_file(app, &target, &contents).await
+}
+
+// The contents in a new temp file with a random name, created exclusively and readable only by
+// us, so nothing else can swap or read what root is about to install. Removed on drop.
+pub(crate) fn stage_contents(contents: &str) -> Result<tempfile::NamedTempFile, String> {
+    let mut staged = tempfile::Builder::new().prefix("nebulasys-").tempfile().map_err(|e| format!("Failed to create a temp file: {}", e))?;
+    staged.write_all(contents.as_bytes()).and_then(|()| staged.flush()).map_err(|e| format!("Failed to write {:?}: {}", staged.path(), e))?;
+    Ok(staged)
+}
+
+// Replaces a root-owned file (mode 0644) through pkexec, staging the contents in a temp file.
+pub async fn write_root_file(app: &tauri::AppHandle, target: &Path, contents: &str) -> Result<(), String> {
+    let staged = stage_contents(contents)?;
+    let plan = CommandPlan::privileged(
+        "install",
+        strings(&["-D", "-m", "0644", &staged.path().to_string_lossy(), &target.to_string_lossy()]),
+    );
+    let out = run_plan(app, &plan).await?;
+    if out.success {
+        Ok(())
+    } else {
+        Err(format!("Failed to write {:?}: {}", target, out.error_text().trim()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_staged_contents_are_private() {
+        let staged = stage_contents("excludepkgs=kernel*\n").unwrap();
+        assert_eq!(std::fs::metadata(staged.path()).unwrap().permissions().mode() & 0o777, 0o600);
+        assert_eq!(std::fs::read_to_string(staged.path()).unwrap(), "excludepkgs=kernel*\n");
+        let path = staged.path().to_path_buf();
+        drop(staged);
+        assert!(!path.exists());
+    }
+}
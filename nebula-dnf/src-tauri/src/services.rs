@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::Manager;
+use zbus::zvariant::OwnedObjectPath;
+
+use crate::apt::Apt;
+use crate::backends::{self, PackageBackend as _};
+use crate::cache::CacheService;
+use crate::dbus_status::OperationGuard;
+use crate::dnf5daemon::Dnf5Daemon;
+use crate::packagekit::{PackageAction, PackageKit};
+use crate::pacman::Pacman;
+use crate::settings::PackageBackend;
+use crate::zypper::Zypper;
+use crate::{PackageOperationResult, UserPackageWithDependencies};
+
+// Puts the shared services into managed state once at startup. Commands reach them through
+// `app.state::<T>()` (the JSON-RPC socket calls the same command functions with only the
+// AppHandle, so they cannot take `State` parameters).
+pub fn manage(app: &tauri::App) -> Result<(), String> {
+    let handle = app.handle();
+    app.manage(CacheService::new(handle)?);
+    app.manage(BackendRegistry { app: handle.clone() });
+    app.manage(OperationManager { app: handle.clone(), transactions: Mutex::new(HashMap::new()) });
+    Ok(())
+}
+
+// The package backends and which one the settings select.
+pub struct BackendRegistry {
+    app: tauri::AppHandle,
+}
+
+impl BackendRegistry {
+    pub fn selected(&self) -> Result<PackageBackend, String> {
+        crate::settings::package_backend(&self.app)
+    }
+
+    // Runs the action on the selected backend; None when the dnf CLI is selected, which callers
+    // drive with their own command plans.
+    pub async fn package_action(&self, action: PackageAction, packages: &[String], dry_run: bool) -> Result<Option<PackageOperationResult>, String> {
+        let app = &self.app;
+        let result = match self.selected()? {
+            PackageBackend::PackageKit => backends::package_action(&PackageKit, app, action, packages, dry_run).await?,
+            PackageBackend::Dnf5Daemon => backends::package_action(&Dnf5Daemon, app, action, packages, dry_run).await?,
+            PackageBackend::Zypper => backends::package_action(&Zypper, app, action, packages, dry_run).await?,
+            PackageBackend::Apt => backends::package_action(&Apt, app, action, packages, dry_run).await?,
+            PackageBackend::Pacman => backends::package_action(&Pacman, app, action, packages, dry_run).await?,
+            PackageBackend::Dnf => return Ok(None),
+        };
+        Ok(Some(result))
+    }
+
+    // What the package commands run on a backend other than the dnf CLI, see
+    // backends::guarded_action. None when dnf is selected.
+    pub async fn package_command(&self, action: PackageAction, packages: &[String], dry_run: bool, preview: bool) -> Result<Option<PackageOperationResult>, String> {
+        let app = &self.app;
+        let result = match self.selected()? {
+            PackageBackend::PackageKit => backends::guarded_action(&PackageKit, app, action, packages, dry_run, preview).await?,
+            PackageBackend::Dnf5Daemon => backends::guarded_action(&Dnf5Daemon, app, action, packages, dry_run, preview).await?,
+            PackageBackend::Zypper => backends::guarded_action(&Zypper, app, action, packages, dry_run, preview).await?,
+            PackageBackend::Apt => backends::guarded_action(&Apt, app, action, packages, dry_run, preview).await?,
+            PackageBackend::Pacman => backends::guarded_action(&Pacman, app, action, packages, dry_run, preview).await?,
+            PackageBackend::Dnf => return Ok(None),
+        };
+        Ok(Some(result))
+    }
+
+    // The selected backend's package list; None where it comes from the rpmdb.
+    pub async fn list(&self) -> Result<Option<Vec<UserPackageWithDependencies>>, String> {
+        match self.selected()? {
+            PackageBackend::Apt => Apt.list(&self.app).await,
+            PackageBackend::Pacman => Pacman.list(&self.app).await,
+            PackageBackend::Dnf | PackageBackend::PackageKit | PackageBackend::Dnf5Daemon | PackageBackend::Zypper => Ok(None),
+        }
+    }
+}
+
+// Operations in flight: what the D-Bus status reports as running, and the daemon transactions
+// that can be cancelled, by the operation name their output events carry.
+pub struct OperationManager {
+    app: tauri::AppHandle,
+    transactions: Mutex<HashMap<String, OwnedObjectPath>>,
+}
+
+impl OperationManager {
+    // Marks an operation as running until the returned guard is dropped.
+    pub fn begin(&self, description: String) -> OperationGuard {
+        crate::dbus_status::begin_operation(&self.app, description)
+    }
+
+    pub fn any_running(&self) -> bool {
+        crate::dbus_status::operation_in_progress(&self.app)
+    }
+
+    pub fn track_transaction(&self, operation: &str, path: OwnedObjectPath) {
+        self.transactions.lock().unwrap().insert(operation.to_string(), path);
+    }
+
+    pub fn finish_transaction(&self, operation: &str) {
+        self.transactions.lock().unwrap().remove(operation);
+    }
+
+    pub fn transaction(&self, operation: &str) -> Option<OwnedObjectPath> {
+        self.transactions.lock().unwrap().get(operation).cloned()
+    }
+}
@@ -0,0 +1,215 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+use tracing::warn;
+
+use crate::error::NebulaError;
+use crate::plan::CommandPlan;
+use crate::process::OperationOutputLine;
+use crate::services::OperationManager;
+use crate::{app_data_file, load_json_file, save_json_file};
+
+const SESSION_FILE_NAME: &str = "session.json";
+const OPERATION_LOG_DIR: &str = "operations";
+const REATTACH_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+// Serializes read-modify-write cycles on the session file between concurrent operations.
+static SESSION_LOCK: Mutex<()> = Mutex::new(());
+
+// A streamed operation that was started and has not been seen to finish. Its output is also
+// written to `log_file`, so it can be replayed after a restart.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TrackedOperation {
+    id: String,
+    operation: String, // the name used in "operation-output" events, e.g. "update_all"
+    command_line: String,
+    process_name: String, // what /proc/<pid>/comm shows while it runs (pkexec execs the tool)
+    pid: Option<u32>,
+    started_at: String,
+    log_file: String,
+}
+
+// What the frontend needs to put the user back where they were. Filters are frontend-defined.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+struct SessionState {
+    filters: HashMap<String, String>,
+    last_selected_package: Option<String>,
+    operations: Vec<TrackedOperation>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct OperationStatus {
+    #[serde(flatten)]
+    operation: TrackedOperation,
+    still_running: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SessionReport {
+    filters: HashMap<String, String>,
+    last_selected_package: Option<String>,
+    operations: Vec<OperationStatus>,
+}
+
+fn load_session(app: &tauri::AppHandle) -> Result<SessionState, String> {
+    Ok(load_json_file(&app_data_file(app, SESSION_FILE_NAME)?)?.unwrap_or_default())
+}
+
+fn update_session(app: &tauri::AppHandle, change: impl FnOnce(&mut SessionState)) -> Result<(), String> {
+    let _lock = SESSION_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut session = load_session(app)?;
+    change(&mut session);
+    save_json_file(&app_data_file(app, SESSION_FILE_NAME)?, &session)
+}
+
+fn process_running(pid: u32, process_name: &str) -> bool {
+    // Comparing the name guards against the pid having been reused since.
+    std::fs::read_to_string(format!("/proc/{}/comm", pid))
+        .map(|comm| comm.trim() == process_name)
+        .unwrap_or(false)
+}
+
+// Writes every line of a streamed operation to its log and keeps it listed in the session until
+// `finish` is called. Best effort: tracking problems never fail the operation itself.
+pub struct OperationTracker {
+    app: tauri::AppHandle,
+    id: String,
+    log: Option<File>,
+}
+
+impl OperationTracker {
+    pub fn start(app: &tauri::AppHandle, operation: &str, plan: &CommandPlan, pid: u32) -> Self {
+        let id = format!("{}-{}", operation, Local::now().format("%Y%m%d%H%M%S%3f"));
+        let program = if plan.privileged { plan.args.first().cloned().unwrap_or_default() } else { plan.program.clone() };
+        // comm is the file name, cut to 15 bytes (e.g. "nebula-dnf-help" for the helper)
+        let process_name: String = program.rsplit('/').next().unwrap_or_default().chars().take(15).collect();
+        let log = app_data_file(app, &format!("{}/{}.log", OPERATION_LOG_DIR, id)).and_then(|path| {
+            std::fs::create_dir_all(path.parent().unwrap_or(&path)).map_err(|e| e.to_string())?;
+            let file = OpenOptions::new().create(true).append(true).open(&path).map_err(|e| e.to_string())?;
+            Ok((path, file))
+        });
+        let (log_file, log) = match log {
+            Ok((path, file)) => (path.to_string_lossy().into_owned(), Some(file)),
+            Err(e) => {
+                warn!("Failed to create operation log: {}", e);
+                (String::new(), None)
+            }
+        };
+        let tracked = TrackedOperation {
+            id: id.clone(),
+            operation: operation.to_string(),
+            command_line: plan.command_line().to_string(),
+            process_name,
+            pid: Some(pid),
+            started_at: Local::now().to_rfc3339(),
+            log_file,
+        };
+        if let Err(e) = update_session(app, |session| session.operations.push(tracked)) {
+            warn!("Failed to record operation in session: {}", e);
+        }
+        OperationTracker { app: app.clone(), id, log }
+    }
+
+    pub fn log_line(&mut self, line: &str, is_stderr: bool) {
+        if let Some(log) = &mut self.log {
+            let _ = writeln!(log, "{}{}", if is_stderr { "E " } else { "O " }, line);
+        }
+    }
+
+    pub fn finish(self) {
+        forget_operation(&self.app, &self.id);
+    }
+}
+
+fn forget_operation(app: &tauri::AppHandle, id: &str) {
+    if let Err(e) = update_session(app, |session| session.operations.retain(|op| op.id != id)) {
+        warn!("Failed to update session: {}", e);
+    }
+}
+
+// Log lines are "O <line>" for stdout and "E <line>" for stderr.
+fn parse_log_line(line: &str) -> (bool, &str) {
+    match line.split_once(' ') {
+        Some(("E", rest)) => (true, rest),
+        Some(("O", rest)) => (false, rest),
+        _ => (false, line),
+    }
+}
+
+#[tauri::command]
+pub async fn get_session_state(app: tauri::AppHandle) -> Result<SessionReport, NebulaError> {
+    let session = load_session(&app)?;
+    let operations = session
+        .operations
+        .into_iter()
+        .map(|operation| OperationStatus {
+            still_running: operation.pid.is_some_and(|pid| process_running(pid, &operation.process_name)),
+            operation,
+        })
+        .collect();
+    Ok(SessionReport {
+        filters: session.filters,
+        last_selected_package: session.last_selected_package,
+        operations,
+    })
+}
+
+#[tauri::command]
+pub async fn save_session_view(
+    app: tauri::AppHandle,
+    filters: HashMap<String, String>,
+    last_selected_package: Option<String>,
+) -> Result<(), NebulaError> {
+    Ok(update_session(&app, |session| {
+        session.filters = filters;
+        session.last_selected_package = last_selected_package;
+    })?)
+}
+
+// Replays an operation's logged output as "operation-output" events, then waits for its process
+// to exit. Output produced after the previous app instance exited was not captured; the returned
+// flag says whether the process was still running when we reattached.
+#[tauri::command]
+pub async fn reattach_operation(app: tauri::AppHandle, id: String) -> Result<bool, NebulaError> {
+    let operation = load_session(&app)?
+        .operations
+        .into_iter()
+        .find(|op| op.id == id)
+        .ok_or_else(|| format!("No tracked operation '{}'.", id))?;
+
+    let log = std::fs::read_to_string(&operation.log_file).unwrap_or_default();
+    for line in log.lines() {
+        let (is_stderr, line) = parse_log_line(line);
+        let payload = OperationOutputLine::new(&operation.operation, line, is_stderr);
+        if let Err(e) = app.emit("operation-output", &payload) {
+            warn!("Failed to emit operation-output event: {}", e);
+        }
+    }
+
+    let running = |op: &TrackedOperation| op.pid.is_some_and(|pid| process_running(pid, &op.process_name));
+    let was_running = running(&operation);
+    let _busy = was_running.then(|| app.state::<OperationManager>().begin(format!("Reattached: {}", operation.command_line)));
+    while running(&operation) {
+        tokio::time::sleep(REATTACH_POLL_INTERVAL).await;
+    }
+    forget_operation(&app, &id);
+    Ok(was_running)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log_line() {
+        assert_eq!(parse_log_line("O Upgrading: bash"), (false, "Upgrading: bash"));
+        assert_eq!(parse_log_line("E warning: cache is stale"), (true, "warning: cache is stale"));
+        assert_eq!(parse_log_line("Transaction"), (false, "Transaction"));
+    }
+}
@@ -0,0 +1,263 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::error::NebulaError;
+use crate::orchestrator::UpdateStage;
+use crate::scope::{config_file, save_config_file, Scope};
+use crate::{load_json_file, PackageOperationResult, UninstallMode};
+
+const SETTINGS_FILE_NAME: &str = "settings.json";
+const DEFAULT_CACHE_MAX_AGE_SECS: u64 = 6 * 60 * 60;
+const DEFAULT_REFRESH_CONCURRENCY: usize = 5;
+const MAX_REFRESH_CONCURRENCY: usize = 32;
+const OS_RELEASE_PATH: &str = "/etc/os-release";
+
+// ID and ID_LIKE from os-release, e.g. ["ubuntu", "debian"]; read once.
+static DISTRO_IDS: Lazy<Vec<String>> = Lazy::new(|| std::fs::read_to_string(OS_RELEASE_PATH).map(|contents| os_release_ids(&contents)).unwrap_or_default());
+
+// Safety behavior applied by the backend whichever UI path triggered an action. Each flag makes
+// the first, unconfirmed call return a preview with `confirmation_required` set; the action only
+// runs when the frontend repeats the call with `confirmed`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ActionDefaults {
+    uninstall_dry_run_first: bool,
+    update_shows_changelog: bool,
+    autoremove_previews: bool,
+}
+
+impl Default for ActionDefaults {
+    fn default() -> Self {
+        ActionDefaults {
+            uninstall_dry_run_first: true,
+            update_shows_changelog: true,
+            autoremove_previews: true,
+        }
+    }
+}
+
+// What carries out the package operations that support more than one: dnf/rpm run through
+// pkexec, packagekitd over D-Bus, libdnf5 through dnf5daemon-server, or, experimentally, zypper
+// on openSUSE, apt/dpkg on Debian and Ubuntu and pacman on Arch.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PackageBackend {
+    #[default]
+    Dnf,
+    PackageKit,
+    Dnf5Daemon,
+    Zypper,
+    Apt,
+    Pacman,
+}
+
+// For the frontend; the backend only stores it.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ThemeHint {
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct AppSettings {
+    action_defaults: ActionDefaults,
+    // Every root command runs as its dry run instead (see CommandPlan::simulated). For demos,
+    // screenshots and first-time users; results report it through their `simulated` flag.
+    simulation_mode: bool,
+    // Opt-in: look at when desktop apps were last run to suggest unused ones for removal.
+    usage_insights: bool,
+    package_backend: Option<PackageBackend>, // None picks by distribution, see default_backend
+    // How old the package cache may get before a listing refreshes it in the background;
+    // None for the default of six hours.
+    cache_max_age_secs: Option<u64>,
+    // How many rpm queries a cache refresh runs at once; None for the default of five. Higher
+    // finishes sooner on fast disks, lower keeps slow machines responsive.
+    refresh_concurrency: Option<usize>,
+    // What a removal that does not name a mode does; None for Safe.
+    default_uninstall_mode: Option<UninstallMode>,
+    theme: ThemeHint,
+    // Stages update_everything leaves out, e.g. Firmware on machines where fwupd updates are
+    // handled elsewhere.
+    skipped_update_stages: Vec<UpdateStage>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GuardedAction {
+    Uninstall,
+    Update,
+    Autoremove,
+}
+
+fn load_scope_settings(app: &tauri::AppHandle, scope: Scope) -> Result<Option<AppSettings>, String> {
+    load_json_file(&config_file(app, scope, SETTINGS_FILE_NAME)?)
+}
+
+// The user's settings if they saved any, otherwise what the admin provisioned system-wide.
+pub fn effective_settings(app: &tauri::AppHandle) -> Result<AppSettings, String> {
+    match load_scope_settings(app, Scope::User)? {
+        Some(settings) => Ok(settings),
+        None => Ok(load_scope_settings(app, Scope::System)?.unwrap_or_default()),
+    }
+}
+
+pub fn simulation_mode(app: &tauri::AppHandle) -> Result<bool, String> {
+    Ok(effective_settings(app)?.simulation_mode)
+}
+
+pub fn usage_insights(app: &tauri::AppHandle) -> Result<bool, String> {
+    Ok(effective_settings(app)?.usage_insights)
+}
+
+fn os_release_ids(os_release: &str) -> Vec<String> {
+    os_release
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .filter(|(key, _)| *key == "ID" || *key == "ID_LIKE")
+        .flat_map(|(_, value)| value.trim_matches('"').split_whitespace().map(str::to_string).collect::<Vec<_>>())
+        .collect()
+}
+
+fn default_backend(distro_ids: &[String]) -> PackageBackend {
+    if distro_ids.iter().any(|id| id.contains("suse")) {
+        PackageBackend::Zypper
+    } else if distro_ids.iter().any(|id| id == "debian" || id == "ubuntu") {
+        PackageBackend::Apt
+    } else if distro_ids.iter().any(|id| id == "arch") {
+        PackageBackend::Pacman
+    } else {
+        PackageBackend::Dnf
+    }
+}
+
+pub fn package_backend(app: &tauri::AppHandle) -> Result<PackageBackend, String> {
+    Ok(effective_settings(app)?.package_backend.unwrap_or_else(|| default_backend(&DISTRO_IDS)))
+}
+
+pub fn cache_max_age(app: &tauri::AppHandle) -> Result<Duration, String> {
+    Ok(Duration::from_secs(effective_settings(app)?.cache_max_age_secs.unwrap_or(DEFAULT_CACHE_MAX_AGE_SECS)))
+}
+
+pub fn default_uninstall_mode(app: &tauri::AppHandle) -> Result<UninstallMode, String> {
+    Ok(effective_settings(app)?.default_uninstall_mode.unwrap_or(UninstallMode::Safe))
+}
+
+pub fn skipped_update_stages(app: &tauri::AppHandle) -> Result<Vec<UpdateStage>, String> {
+    Ok(effective_settings(app)?.skipped_update_stages)
+}
+
+fn clamp_concurrency(value: Option<usize>) -> usize {
+    value.unwrap_or(DEFAULT_REFRESH_CONCURRENCY).clamp(1, MAX_REFRESH_CONCURRENCY)
+}
+
+pub fn refresh_concurrency(app: &tauri::AppHandle) -> Result<usize, String> {
+    Ok(clamp_concurrency(effective_settings(app)?.refresh_concurrency))
+}
+
+fn preview_required(defaults: &ActionDefaults, action: GuardedAction) -> bool {
+    match action {
+        GuardedAction::Uninstall => defaults.uninstall_dry_run_first,
+        GuardedAction::Update => defaults.update_shows_changelog,
+        GuardedAction::Autoremove => defaults.autoremove_previews,
+    }
+}
+
+// Whether an unconfirmed `action` has to be turned into its preview.
+pub fn requires_preview(app: &tauri::AppHandle, action: GuardedAction, confirmed: bool) -> Result<bool, String> {
+    Ok(!confirmed && preview_required(&effective_settings(app)?.action_defaults, action))
+}
+
+pub fn preview_result(message: String, details: String) -> PackageOperationResult {
+    PackageOperationResult {
+        success: true,
+        message,
+        details: Some(details),
+        confirmation_required: true,
+        simulated: false,
+        reboot_required: false,
+    }
+}
+
+#[tauri::command]
+pub async fn get_settings(app: tauri::AppHandle, scope: Scope) -> Result<AppSettings, NebulaError> {
+    Ok(load_scope_settings(&app, scope)?.unwrap_or_default())
+}
+
+#[tauri::command]
+pub async fn get_effective_settings(app: tauri::AppHandle) -> Result<AppSettings, NebulaError> {
+    Ok(effective_settings(&app)?)
+}
+
+#[tauri::command]
+pub async fn set_settings(app: tauri::AppHandle, scope: Scope, settings: AppSettings) -> Result<AppSettings, NebulaError> {
+    save_config_file(&app, scope, SETTINGS_FILE_NAME, &settings).await?;
+    Ok(settings)
+}
+
+// Applies the fields in `changes` to the settings saved in `scope` and keeps the rest, e.g.
+// `{ "simulation_mode": true }`. Fails without saving when a field does not fit.
+#[tauri::command]
+pub async fn update_settings(app: tauri::AppHandle, scope: Scope, changes: serde_json::Map<String, serde_json::Value>) -> Result<AppSettings, NebulaError> {
+    let current = load_scope_settings(&app, scope)?.unwrap_or_default();
+    let settings = merge_settings(&current, changes)?;
+    save_config_file(&app, scope, SETTINGS_FILE_NAME, &settings).await?;
+    Ok(settings)
+}
+
+fn merge_settings(current: &AppSettings, changes: serde_json::Map<String, serde_json::Value>) -> Result<AppSettings, String> {
+    let mut merged = serde_json::to_value(current).map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    let fields = merged.as_object_mut().ok_or("Settings are not an object.")?;
+    for (key, value) in changes {
+        if !fields.contains_key(&key) {
+            return Err(format!("Unknown setting '{}'.", key));
+        }
+        fields.insert(key, value);
+    }
+    serde_json::from_value(merged).map_err(|e| format!("Invalid settings: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_refresh_concurrency(app: tauri::AppHandle) -> Result<usize, NebulaError> {
+    Ok(refresh_concurrency(&app)?)
+}
+
+// Changes just the refresh concurrency in `scope`, keeping the other settings there; None goes
+// back to the default. Returns the value now in effect.
+#[tauri::command]
+pub async fn set_refresh_concurrency(app: tauri::AppHandle, scope: Scope, concurrency: Option<usize>) -> Result<usize, NebulaError> {
+    let mut settings = load_scope_settings(&app, scope)?.unwrap_or_default();
+    settings.refresh_concurrency = concurrency.map(|value| clamp_concurrency(Some(value)));
+    save_config_file(&app, scope, SETTINGS_FILE_NAME, &settings).await?;
+    Ok(refresh_concurrency(&app)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_settings_keep_safe_defaults() {
+        let settings: AppSettings = serde_json::from_str(r#"{"action_defaults":{"update_shows_changelog":false}}"#).unwrap();
+        assert!(!preview_required(&settings.action_defaults, GuardedAction::Update));
+        assert!(preview_required(&settings.action_defaults, GuardedAction::Uninstall));
+        assert!(preview_required(&settings.action_defaults, GuardedAction::Autoremove));
+        assert_eq!(clamp_concurrency(settings.refresh_concurrency), DEFAULT_REFRESH_CONCURRENCY);
+        assert_eq!(clamp_concurrency(Some(0)), 1);
+
+        let changes = |json: &str| serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(json).unwrap();
+        let merged = merge_settings(&settings, changes(r#"{"simulation_mode":true,"default_uninstall_mode":"DryRunSafe"}"#)).unwrap();
+        assert!(merged.simulation_mode);
+        assert_eq!(merged.default_uninstall_mode, Some(UninstallMode::DryRunSafe));
+        assert_eq!(merged.action_defaults, settings.action_defaults);
+        assert!(merge_settings(&settings, changes(r#"{"no_such_setting":1}"#)).is_err());
+        assert!(merge_settings(&settings, changes(r#"{"refresh_concurrency":"fast"}"#)).is_err());
+
+        let distro = |os_release: &str| default_backend(&os_release_ids(os_release));
+        assert_eq!(distro("NAME=\"openSUSE Tumbleweed\"\nID=\"opensuse-tumbleweed\"\nID_LIKE=\"opensuse suse\"\n"), PackageBackend::Zypper);
+        assert_eq!(distro("NAME=\"Linux Mint\"\nID=linuxmint\nID_LIKE=\"ubuntu debian\"\n"), PackageBackend::Apt);
+        assert_eq!(distro("NAME=\"Manjaro Linux\"\nID=manjaro\nID_LIKE=arch\n"), PackageBackend::Pacman);
+        assert_eq!(distro("NAME=\"Fedora Linux\"\nID=fedora\n"), PackageBackend::Dnf);
+    }
+}
@@ -0,0 +1,302 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::Manager;
+use tracing::{error, info};
+
+use crate::error::NebulaError;
+use crate::plan::{self, strings, CommandPlan};
+use crate::process::{run_command, run_plan};
+use crate::services::OperationManager;
+use crate::{app_data_file, load_json_file, save_json_file, PackageOperationResult};
+
+// Snapshots are taken with snapper on its "root" config, which requires a btrfs root set up
+// for snapper (the Fedora default layout works once `snapper -c root create-config /` was run).
+const SNAPPER_CONFIG: &str = "root";
+const CHECKS_FILE_NAME: &str = "post_update_checks.json";
+const PRE_DESCRIPTION: &str = "NebulaSys: before updates";
+const POST_DESCRIPTION: &str = "NebulaSys: after updates";
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SnapshotSupport {
+    pub available: bool,
+    pub reason: Option<String>,
+}
+
+// User-configurable checks run after updates; any failure triggers a rollback.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PostUpdateChecks {
+    services: Vec<String>,         // systemd units that must be active
+    mounts: Vec<String>,           // mount points that must be mounted
+    custom_script: Option<String>, // absolute path to an executable, must exit 0
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CheckResult {
+    check: String,
+    passed: bool,
+    output: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SnapshotUpdateResult {
+    pre_snapshot: Option<u32>, // None while the update is only previewed
+    post_snapshot: Option<u32>,
+    update: PackageOperationResult,
+    checks: Vec<CheckResult>,
+    rolled_back: bool,
+    message: String,
+}
+
+// What follows the update: a simulated run created no snapshots and gets no checks or rollback.
+#[derive(Debug, PartialEq)]
+enum Verdict {
+    Simulated,
+    Passed,
+    RollBack,
+}
+
+// A failed dnf run may still have changed files, so it is rolled back like a failed check.
+fn verdict(update: &PackageOperationResult, checks: &[CheckResult]) -> Verdict {
+    if update.simulated {
+        Verdict::Simulated
+    } else if update.success && checks.iter().all(|check| check.passed) {
+        Verdict::Passed
+    } else {
+        Verdict::RollBack
+    }
+}
+
+pub async fn detect_snapshot_support(app: &tauri::AppHandle) -> SnapshotSupport {
+    match run_command(app, "snapper", &["--csvout", "list-configs"]).await {
+        Ok(out) if out.success => {
+            let has_root = out.stdout.lines().skip(1).any(|line| line.split(',').next() == Some(SNAPPER_CONFIG));
+            SnapshotSupport {
+                available: has_root,
+                reason: (!has_root).then(|| format!("snapper has no '{}' config.", SNAPPER_CONFIG)),
+            }
+        }
+        Ok(out) => SnapshotSupport { available: false, reason: Some(out.error_text().trim().to_string()) },
+        Err(_) => SnapshotSupport { available: false, reason: Some("snapper is not installed.".to_string()) },
+    }
+}
+
+fn snapper_create_plan(kind: &str, description: &str, pre_number: Option<&str>) -> CommandPlan {
+    let mut args = strings(&["-c", SNAPPER_CONFIG, "create", "--type", kind, "--print-number", "--cleanup-algorithm", "number", "--description", description]);
+    if let Some(pre_number) = pre_number {
+        args.extend(strings(&["--pre-number", pre_number]));
+    }
+    CommandPlan::privileged("snapper", args)
+}
+
+fn undochange_plan(pre: &str, post: &str) -> CommandPlan {
+    CommandPlan::privileged("snapper", strings(&["-c", SNAPPER_CONFIG, "undochange", &format!("{}..{}", pre, post)]))
+}
+
+// What update_with_snapshot runs, for previews. The rollback only runs if a check fails.
+pub fn snapshot_update_plans(pinned: &[String]) -> Vec<CommandPlan> {
+    vec![
+        snapper_create_plan("pre", PRE_DESCRIPTION, None),
+        plan::upgrade_all(false, pinned),
+        snapper_create_plan("post", POST_DESCRIPTION, Some("<pre>")),
+        undochange_plan("<pre>", "<post>"),
+    ]
+}
+
+async fn create_snapshot(app: &tauri::AppHandle, plan: &CommandPlan) -> Result<u32, String> {
+    let out = run_plan(app, plan).await?;
+    if !out.success {
+        return Err(format!("Failed to create snapshot: {}", out.error_text().trim()));
+    }
+    if out.simulated {
+        return Ok(0); // nothing was created; 0 is snapper's name for the live system
+    }
+    out.stdout
+        .trim()
+        .parse()
+        .map_err(|_| format!("Unexpected snapper output: {}", out.stdout.trim()))
+}
+
+// Reverts all files changed between the two snapshots.
+async fn undo_changes(app: &tauri::AppHandle, pre: u32, post: u32) -> Result<(), String> {
+    let out = run_plan(app, &undochange_plan(&pre.to_string(), &post.to_string())).await?;
+    if out.success {
+        Ok(())
+    } else {
+        Err(format!("snapper undochange {}..{} failed: {}", pre, post, out.error_text().trim()))
+    }
+}
+
+async fn run_checks(app: &tauri::AppHandle, checks: &PostUpdateChecks) -> Vec<CheckResult> {
+    let mut results = Vec::new();
+    for unit in &checks.services {
+        let result = run_command(app, "systemctl", &["is-active", "--", unit]).await;
+        results.push(match result {
+            Ok(out) => CheckResult { check: format!("service {}", unit), passed: out.success, output: out.stdout.trim().to_string() },
+            Err(e) => CheckResult { check: format!("service {}", unit), passed: false, output: e.to_string() },
+        });
+    }
+    for mount in &checks.mounts {
+        let result = run_command(app, "findmnt", &["--mountpoint", mount]).await;
+        results.push(match result {
+            Ok(out) => CheckResult { check: format!("mount {}", mount), passed: out.success, output: out.stdout.trim().to_string() },
+            Err(e) => CheckResult { check: format!("mount {}", mount), passed: false, output: e.to_string() },
+        });
+    }
+    if let Some(script) = &checks.custom_script {
+        let result = run_command(app, script, &[]).await;
+        results.push(match result {
+            Ok(out) => CheckResult {
+                check: format!("script {}", script),
+                passed: out.success,
+                output: format!("{}{}", out.stdout, out.stderr).trim().to_string(),
+            },
+            Err(e) => CheckResult { check: format!("script {}", script), passed: false, output: e.to_string() },
+        });
+    }
+    results
+}
+
+#[tauri::command]
+pub async fn get_snapshot_support(app: tauri::AppHandle) -> Result<SnapshotSupport, NebulaError> {
+    Ok(detect_snapshot_support(&app).await)
+}
+
+#[tauri::command]
+pub async fn get_post_update_checks(app: tauri::AppHandle) -> Result<PostUpdateChecks, NebulaError> {
+    Ok(load_json_file(&app_data_file(&app, CHECKS_FILE_NAME)?)?.unwrap_or_default())
+}
+
+#[tauri::command]
+pub async fn set_post_update_checks(app: tauri::AppHandle, checks: PostUpdateChecks) -> Result<(), NebulaError> {
+    if let Some(script) = &checks.custom_script {
+        if !Path::new(script).is_absolute() || !Path::new(script).is_file() {
+            return Err(format!("Custom check script must be an absolute path to an existing file: {}", script).into());
+        }
+    }
+    if let Some(bad) = checks.services.iter().chain(&checks.mounts).find(|v| v.trim().is_empty() || v.starts_with('-')) {
+        return Err(format!("Invalid check entry: '{}'", bad).into());
+    }
+    Ok(save_json_file(&app_data_file(&app, CHECKS_FILE_NAME)?, &checks)?)
+}
+
+// Snapshot, apply all updates, run the configured checks, and undo the update if any check fails.
+#[tauri::command]
+pub async fn update_with_snapshot(app: tauri::AppHandle, confirmed: Option<bool>) -> Result<SnapshotUpdateResult, NebulaError> {
+    let support = detect_snapshot_support(&app).await;
+    if !support.available {
+        return Err(format!(
+            "Snapshots are not available: {}",
+            support.reason.unwrap_or_else(|| "unknown reason".to_string())
+        ).into());
+    }
+    let checks: PostUpdateChecks = load_json_file(&app_data_file(&app, CHECKS_FILE_NAME)?)?.unwrap_or_default();
+    let pinned = crate::pins::pinned_packages(&app)?;
+    // The review comes before the pre snapshot, so a previewed update leaves no snapshots behind.
+    if crate::settings::requires_preview(&app, crate::settings::GuardedAction::Update, confirmed.unwrap_or(false))? {
+        let changelog = crate::updates::fetch_update_changelog(&app, &[]).await;
+        let message = "Review the changes in the pending updates, then confirm the snapshot-protected update.".to_string();
+        return Ok(SnapshotUpdateResult {
+            pre_snapshot: None,
+            post_snapshot: None,
+            update: crate::settings::preview_result(message.clone(), changelog),
+            checks: Vec::new(),
+            rolled_back: false,
+            message,
+        });
+    }
+    let _busy = app.state::<OperationManager>().begin("Snapshot-protected update".to_string());
+
+    let pre = create_snapshot(&app, &snapper_create_plan("pre", PRE_DESCRIPTION, None)).await?;
+    info!("Created pre-update snapshot #{}.", pre);
+
+    let out = run_plan(&app, &plan::upgrade_all(false, &pinned)).await?;
+    let update = PackageOperationResult {
+        success: out.success,
+        message: if out.success { "Updates applied.".to_string() } else { format!("Update failed with exit code {}.", out.code) },
+        details: Some(format!("STDOUT:\n{}\nSTDERR:\n{}", out.stdout, out.stderr)),
+        confirmation_required: false,
+        simulated: out.simulated,
+        reboot_required: false,
+    };
+
+    let post = create_snapshot(&app, &snapper_create_plan("post", POST_DESCRIPTION, Some(&pre.to_string()))).await?;
+    info!("Created post-update snapshot #{}.", post);
+
+    let check_results = if update.success && !update.simulated { run_checks(&app, &checks).await } else { Vec::new() };
+    match verdict(&update, &check_results) {
+        Verdict::Simulated => {
+            return Ok(SnapshotUpdateResult {
+                pre_snapshot: None,
+                post_snapshot: None,
+                update,
+                checks: check_results,
+                rolled_back: false,
+                message: "Simulation: no snapshots were taken and nothing was updated, so the checks and rollback were skipped.".to_string(),
+            });
+        }
+        Verdict::Passed => {
+            return Ok(SnapshotUpdateResult {
+                pre_snapshot: Some(pre),
+                post_snapshot: Some(post),
+                update,
+                checks: check_results,
+                rolled_back: false,
+                message: "Updates applied and all checks passed.".to_string(),
+            });
+        }
+        Verdict::RollBack => {}
+    }
+
+    error!("Post-update verification failed, rolling back snapshot #{}..#{}.", pre, post);
+    let (rolled_back, message) = match undo_changes(&app, pre, post).await {
+        Ok(()) => (true, format!("Verification failed; changes were rolled back to snapshot #{}. A reboot is recommended.", pre)),
+        Err(e) => (false, format!("Verification failed and the rollback also failed: {}", e)),
+    };
+    Ok(SnapshotUpdateResult {
+        pre_snapshot: Some(pre),
+        post_snapshot: Some(post),
+        update,
+        checks: check_results,
+        rolled_back,
+        message,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verdict() {
+        let update = |success: bool, simulated: bool| PackageOperationResult {
+            success,
+            message: String::new(),
+            details: None,
+            confirmation_required: false,
+            simulated,
+            reboot_required: false,
+        };
+        let check = |passed: bool| CheckResult { check: "service sshd.service".to_string(), passed, output: String::new() };
+        assert_eq!(verdict(&update(true, false), &[check(true)]), Verdict::Passed);
+        assert_eq!(verdict(&update(true, false), &[check(true), check(false)]), Verdict::RollBack);
+        assert_eq!(verdict(&update(false, false), &[]), Verdict::RollBack);
+        assert_eq!(verdict(&update(true, true), &[]), Verdict::Simulated);
+        assert_eq!(verdict(&update(false, true), &[]), Verdict::Simulated);
+    }
+
+    #[test]
+    fn test_snapshot_plans() {
+        assert_eq!(undochange_plan("3", "7").command_line(), "pkexec snapper -c root undochange 3..7");
+        let plans = snapshot_update_plans(&strings(&["mesa"]));
+        let lines: Vec<&str> = plans.iter().map(CommandPlan::command_line).collect();
+        assert_eq!(
+            lines,
+            [
+                "pkexec snapper -c root create --type pre --print-number --cleanup-algorithm number --description 'NebulaSys: before updates'",
+                "pkexec dnf upgrade --assumeyes --exclude=mesa",
+                "pkexec snapper -c root create --type post --print-number --cleanup-algorithm number --description 'NebulaSys: after updates' --pre-number '<pre>'",
+                "pkexec snapper -c root undochange '<pre>..<post>'",
+            ]
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use serde::Serialize;
+
+use crate::error::NebulaError;
+use crate::flatpak::{FlatpakKind, FlatpakPackage};
+use crate::userspace::{UserspaceManager, UserspacePackage};
+use crate::{PackageCategory, UserPackageWithDependencies};
+
+// Where an installed copy of a piece of software comes from.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum SoftwareOrigin {
+    Rpm,
+    Flatpak,
+    Homebrew,
+    Nix,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct SoftwareSource {
+    origin: SoftwareOrigin,
+    id: String, // what the backend's own commands take: the package or formula name, or the Flatpak ref
+    version: Option<String>,
+    installation: Option<String>, // Flatpak "user"/"system"
+}
+
+// One piece of software however many backends installed it, e.g. Firefox as an RPM and from Flathub.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct SoftwareItem {
+    name: String,
+    category: PackageCategory,
+    origins: Vec<SoftwareOrigin>,
+    sources: Vec<SoftwareSource>,
+    #[serde(skip)]
+    keys: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct SoftwareListing {
+    items: Vec<SoftwareItem>,
+    unavailable: Vec<String>, // backends that could not be listed, with why; the rest still are
+}
+
+// "Mozilla Firefox", "firefox" and "org.mozilla.firefox" meet at "firefox".
+fn match_key(name: &str) -> String {
+    name.chars().filter(char::is_ascii_alphanumeric).collect::<String>().to_lowercase()
+}
+
+fn rpm_item(package: UserPackageWithDependencies) -> SoftwareItem {
+    SoftwareItem {
+        keys: vec![match_key(&package.name)],
+        sources: vec![SoftwareSource { origin: SoftwareOrigin::Rpm, id: package.name.clone(), version: None, installation: None }],
+        origins: vec![SoftwareOrigin::Rpm],
+        name: package.name,
+        category: package.category,
+    }
+}
+
+fn flatpak_item(package: FlatpakPackage) -> SoftwareItem {
+    let app_name = package.name.rsplit('.').next().unwrap_or(&package.name);
+    SoftwareItem {
+        keys: vec![match_key(app_name), match_key(&package.display_name)],
+        sources: vec![SoftwareSource {
+            origin: SoftwareOrigin::Flatpak,
+            id: package.flatpak_ref,
+            version: package.version,
+            installation: Some(package.installation),
+        }],
+        origins: vec![SoftwareOrigin::Flatpak],
+        name: package.display_name,
+        category: PackageCategory::Unknown,
+    }
+}
+
+fn userspace_item(package: UserspacePackage) -> SoftwareItem {
+    let origin = match package.manager {
+        UserspaceManager::Homebrew => SoftwareOrigin::Homebrew,
+        UserspaceManager::Nix => SoftwareOrigin::Nix,
+    };
+    SoftwareItem {
+        keys: vec![match_key(&package.name)],
+        sources: vec![SoftwareSource { origin, id: package.name.clone(), version: package.version, installation: None }],
+        origins: vec![origin],
+        name: package.name,
+        category: PackageCategory::Unknown,
+    }
+}
+
+// Folds items that share a match key into the first one seen; RPMs come first, so their names
+// and categories win.
+fn merge(candidates: Vec<SoftwareItem>) -> Vec<SoftwareItem> {
+    let mut items: Vec<SoftwareItem> = Vec::new();
+    for candidate in candidates {
+        match items.iter_mut().find(|item| item.keys.iter().any(|key| candidate.keys.contains(key))) {
+            Some(item) => {
+                for origin in candidate.origins {
+                    if !item.origins.contains(&origin) {
+                        item.origins.push(origin);
+                    }
+                }
+                item.sources.extend(candidate.sources);
+                item.keys.extend(candidate.keys);
+            }
+            None => items.push(candidate),
+        }
+    }
+    items.sort_by_key(|item| item.name.to_lowercase());
+    items
+}
+
+// Everything the user installed across the backends, queried concurrently: user-installed RPMs
+// (through the package cache), Flatpak apps and whatever Homebrew or a Nix profile installed, if
+// either is there. The list the main UI shows.
+#[tauri::command]
+pub async fn list_all_software(app: tauri::AppHandle) -> Result<SoftwareListing, NebulaError> {
+    let (rpms, flatpaks, (userspace, userspace_unavailable)) = tokio::join!(
+        crate::list_user_installed_packages(app.clone(), false, None),
+        crate::flatpak::fetch_flatpaks(&app),
+        crate::userspace::installed(&app)
+    );
+    let mut candidates = Vec::new();
+    let mut unavailable = userspace_unavailable;
+    match rpms {
+        Ok(packages) => candidates.extend(packages.into_iter().map(rpm_item)),
+        Err(e) => unavailable.push(format!("RPM: {}", e)),
+    }
+    match flatpaks {
+        Ok(packages) => candidates.extend(packages.into_iter().filter(|package| package.kind == FlatpakKind::App).map(flatpak_item)),
+        Err(e) => unavailable.push(format!("Flatpak: {}", e)),
+    }
+    candidates.extend(userspace.into_iter().map(userspace_item));
+    if candidates.is_empty() && !unavailable.is_empty() {
+        return Err(unavailable.join("\n").into());
+    }
+    Ok(SoftwareListing { items: merge(candidates), unavailable })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        let rpm = |name: &str| rpm_item(UserPackageWithDependencies {
+            name: name.to_string(),
+            category: PackageCategory::Network,
+            dependencies: Vec::new(),
+            incomplete: false,
+            installed_size: None,
+            appstream: None,
+        });
+        let flatpak = |id: &str, display_name: &str, installation: &str| SoftwareItem {
+            keys: vec![match_key(id.rsplit('.').next().unwrap()), match_key(display_name)],
+            name: display_name.to_string(),
+            category: PackageCategory::Unknown,
+            origins: vec![SoftwareOrigin::Flatpak],
+            sources: vec![SoftwareSource {
+                origin: SoftwareOrigin::Flatpak,
+                id: format!("app/{}/x86_64/stable", id),
+                version: None,
+                installation: Some(installation.to_string()),
+            }],
+        };
+        let items = merge(vec![
+            rpm("firefox"),
+            rpm("htop"),
+            flatpak("org.mozilla.firefox", "Firefox", "system"),
+            flatpak("org.gnome.Builder", "Builder", "user"),
+            flatpak("org.gnome.Builder", "Builder", "system"),
+        ]);
+        assert_eq!(items.iter().map(|item| item.name.as_str()).collect::<Vec<_>>(), ["Builder", "firefox", "htop"]);
+        assert_eq!(items[1].origins, [SoftwareOrigin::Rpm, SoftwareOrigin::Flatpak]);
+        assert_eq!(items[1].category, PackageCategory::Network);
+        assert_eq!(items[0].origins, [SoftwareOrigin::Flatpak]);
+        assert_eq!(items[0].sources.len(), 2);
+        assert_eq!(match_key("Mozilla-Firefox 2"), "mozillafirefox2");
+
+        let brew = userspace_item(UserspacePackage { manager: UserspaceManager::Homebrew, name: "htop".to_string(), version: Some("3.3.0".to_string()) });
+        let items = merge(vec![rpm("htop"), brew]);
+        assert_eq!(items[0].origins, [SoftwareOrigin::Rpm, SoftwareOrigin::Homebrew]);
+    }
+}
@@ -0,0 +1,315 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::Manager;
+use tracing::{info, warn};
+
+use crate::coordination::SYSTEM_UPDATE_LINK;
+use crate::error::NebulaError;
+use crate::plan;
+use crate::process::{run_command, run_plan, run_plan_streaming};
+use crate::services::OperationManager;
+use crate::{app_data_file, load_json_file, save_json_file, PackageOperationResult};
+
+const STATE_FILE_NAME: &str = "system_upgrade.json";
+// Operation name for the "operation-output" and "operation-progress" events of the download.
+const OPERATION: &str = "system_upgrade";
+// Fedora supports upgrading across at most two releases at once.
+const MAX_RELEASE_JUMP: u32 = 2;
+const GIB: u64 = 1024 * 1024 * 1024;
+// Free space wanted before starting: the downloaded packages go under /var, and the upgrade
+// itself grows the root file system. When both are one file system, it needs room for both.
+const SPACE_REQUIREMENTS: &[(&str, u64)] = &[("/var", 5 * GIB), ("/", 3 * GIB)];
+
+// download -> Downloading -> Downloaded -> reboot -> Rebooting -> (next boot) finished.
+// An interrupted download stays Downloading and resumes from the packages dnf already fetched.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum UpgradeStage {
+    Downloading,
+    Downloaded,
+    Rebooting,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct UpgradeState {
+    from_release: u32,
+    target_release: u32,
+    stage: UpgradeStage,
+    updated_at: String, // RFC 3339
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct FreeSpace {
+    mount: String,
+    available_bytes: u64,
+    required_bytes: u64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SystemUpgradeStatus {
+    current_release: Option<u32>,
+    upgrade: Option<UpgradeState>, // None when no upgrade is in progress
+    note: Option<String>,          // e.g. that the last upgrade finished
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct UpgradePreflight {
+    current_release: u32,
+    target_release: u32,
+    space: Vec<FreeSpace>,
+    problems: Vec<String>, // empty when the download can start
+}
+
+fn state_path(app: &tauri::AppHandle) -> Result<std::path::PathBuf, String> {
+    app_data_file(app, STATE_FILE_NAME)
+}
+
+fn load_state(app: &tauri::AppHandle) -> Result<Option<UpgradeState>, String> {
+    load_json_file(&state_path(app)?)
+}
+
+fn save_state(app: &tauri::AppHandle, state: Option<&UpgradeState>) -> Result<(), String> {
+    let path = state_path(app)?;
+    match state {
+        Some(state) => save_json_file(&path, state),
+        None => match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(format!("Failed to remove {:?}: {}", path, e)),
+            _ => Ok(()),
+        },
+    }
+}
+
+fn set_stage(app: &tauri::AppHandle, state: &mut UpgradeState, stage: UpgradeStage) -> Result<(), String> {
+    state.stage = stage;
+    state.updated_at = Local::now().to_rfc3339();
+    save_state(app, Some(state))
+}
+
+// What the saved state means after a reboot: running the target release finishes the upgrade;
+// still running the old one without a staged update means the upgrade was not applied, so the
+// downloaded packages can be rebooted into again.
+fn reconcile(mut state: UpgradeState, current_release: Option<u32>, update_staged: bool) -> (Option<UpgradeState>, Option<String>) {
+    if current_release == Some(state.target_release) {
+        return (None, Some(format!("Upgraded from Fedora {} to Fedora {}.", state.from_release, state.target_release)));
+    }
+    if state.stage == UpgradeStage::Rebooting && !update_staged {
+        state.stage = UpgradeStage::Downloaded;
+        let note = "The upgrade was not applied. `dnf system-upgrade log --number=-1` shows why; reboot again to retry.";
+        return (Some(state), Some(note.to_string()));
+    }
+    (Some(state), None)
+}
+
+fn validate_target(current_release: u32, target_release: u32) -> Result<(), String> {
+    if target_release <= current_release || target_release > current_release + MAX_RELEASE_JUMP {
+        return Err(format!(
+            "Fedora {} can be upgraded to Fedora {} or {}, not {}.",
+            current_release,
+            current_release + 1,
+            current_release + MAX_RELEASE_JUMP,
+            target_release
+        ));
+    }
+    Ok(())
+}
+
+// `df --output=target,avail -B1 PATH...`: a header, then the mount point and free bytes of each
+// path, in order.
+fn parse_df_output(output: &str) -> Vec<(String, u64)> {
+    output
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let (mount, available) = line.trim().rsplit_once(char::is_whitespace)?;
+            Some((mount.trim().to_string(), available.parse().ok()?))
+        })
+        .collect()
+}
+
+// Adds up the requirements of paths that share a file system.
+fn space_needed(mounts: &[(String, u64)]) -> Vec<FreeSpace> {
+    let mut space: Vec<FreeSpace> = Vec::new();
+    for ((mount, available), (_, required)) in mounts.iter().zip(SPACE_REQUIREMENTS) {
+        match space.iter_mut().find(|entry| &entry.mount == mount) {
+            Some(entry) => entry.required_bytes += required,
+            None => space.push(FreeSpace { mount: mount.clone(), available_bytes: *available, required_bytes: *required }),
+        }
+    }
+    space
+}
+
+async fn free_space(app: &tauri::AppHandle) -> Result<Vec<FreeSpace>, String> {
+    let mut args = vec!["--output=target,avail", "-B1"];
+    args.extend(SPACE_REQUIREMENTS.iter().map(|(path, _)| *path));
+    let out = run_command(app, "df", &args).await?;
+    let mounts = parse_df_output(&out.stdout);
+    if !out.success || mounts.len() != SPACE_REQUIREMENTS.len() {
+        return Err(format!("Could not check free disk space: {}", out.error_text().trim()));
+    }
+    Ok(space_needed(&mounts))
+}
+
+async fn current_release(app: &tauri::AppHandle) -> Result<u32, String> {
+    crate::repos::fedora_version(app).await?.ok_or_else(|| "Release upgrades are only available on Fedora.".to_string())
+}
+
+async fn preflight(app: &tauri::AppHandle, target_release: u32) -> Result<UpgradePreflight, String> {
+    let current_release = current_release(app).await?;
+    validate_target(current_release, target_release)?;
+    let space = free_space(app).await?;
+    let mut problems: Vec<String> = space
+        .iter()
+        .filter(|entry| entry.available_bytes < entry.required_bytes)
+        .map(|entry| format!("{} needs {} GiB free but has {} GiB.", entry.mount, entry.required_bytes / GIB, entry.available_bytes / GIB))
+        .collect();
+    if let Some(state) = load_state(app)?.filter(|state| state.target_release != target_release) {
+        problems.push(format!("An upgrade to Fedora {} is already in progress; cancel it first.", state.target_release));
+    }
+    Ok(UpgradePreflight { current_release, target_release, space, problems })
+}
+
+// The release now running and the upgrade in progress, if any. Also finishes the bookkeeping
+// after the reboot into an upgrade.
+#[tauri::command]
+pub async fn get_system_upgrade_status(app: tauri::AppHandle) -> Result<SystemUpgradeStatus, NebulaError> {
+    let current_release = crate::repos::fedora_version(&app).await?;
+    let Some(state) = load_state(&app)? else {
+        return Ok(SystemUpgradeStatus { current_release, upgrade: None, note: None });
+    };
+    let before = state.clone();
+    let (upgrade, note) = reconcile(state, current_release, Path::new(SYSTEM_UPDATE_LINK).symlink_metadata().is_ok());
+    if upgrade.as_ref() != Some(&before) {
+        save_state(&app, upgrade.as_ref())?;
+    }
+    Ok(SystemUpgradeStatus { current_release, upgrade, note })
+}
+
+// Free space and other checks for an upgrade to `target_release`, before downloading anything.
+#[tauri::command]
+pub async fn check_system_upgrade(app: tauri::AppHandle, target_release: u32) -> Result<UpgradePreflight, NebulaError> {
+    Ok(preflight(&app, target_release).await?)
+}
+
+// Downloads the packages of `target_release` with `dnf system-upgrade download`, streaming
+// progress as "operation-progress" events. Calling it again after an interruption resumes the
+// download.
+#[tauri::command]
+pub async fn download_system_upgrade(app: tauri::AppHandle, target_release: u32) -> Result<PackageOperationResult, NebulaError> {
+    let check = preflight(&app, target_release).await?;
+    if !check.problems.is_empty() {
+        return Err(check.problems.join(" ").into());
+    }
+    let previous = load_state(&app)?;
+    let mut state = previous.clone().unwrap_or(UpgradeState {
+        from_release: check.current_release,
+        target_release,
+        stage: UpgradeStage::Downloading,
+        updated_at: String::new(),
+    });
+    set_stage(&app, &mut state, UpgradeStage::Downloading)?;
+
+    let _busy = app.state::<OperationManager>().begin(format!("Downloading Fedora {}", target_release));
+    info!("Downloading the upgrade from Fedora {} to Fedora {}.", state.from_release, target_release);
+    let out = run_plan_streaming(&app, &plan::system_upgrade_download(target_release), OPERATION).await?;
+    if out.simulated {
+        save_state(&app, previous.as_ref())?;
+    } else if out.success {
+        set_stage(&app, &mut state, UpgradeStage::Downloaded)?;
+    }
+    Ok(PackageOperationResult {
+        success: out.success,
+        message: match (out.success, out.simulated) {
+            (true, true) => format!("Simulation: the upgrade to Fedora {} was not downloaded.", target_release),
+            (true, false) => format!("Fedora {} is downloaded. Reboot into the upgrade when you are ready.", target_release),
+            (false, _) => format!("Downloading Fedora {} failed with exit code {}. Try again to resume.", target_release, out.code),
+        },
+        details: Some(format!("STDOUT:\n{}\nSTDERR:\n{}", out.stdout, out.stderr)),
+        confirmation_required: false,
+        simulated: out.simulated,
+        reboot_required: false,
+    })
+}
+
+// Reboots into the downloaded upgrade (`dnf system-upgrade reboot`). The state is saved first,
+// since the app does not outlive the command when it works.
+#[tauri::command]
+pub async fn reboot_into_system_upgrade(app: tauri::AppHandle) -> Result<PackageOperationResult, NebulaError> {
+    let mut state = load_state(&app)?.filter(|state| state.stage == UpgradeStage::Downloaded).ok_or("No downloaded upgrade to reboot into.")?;
+    set_stage(&app, &mut state, UpgradeStage::Rebooting)?;
+    let out = run_plan(&app, &plan::system_upgrade_reboot()).await?;
+    if !out.success || out.simulated {
+        set_stage(&app, &mut state, UpgradeStage::Downloaded)?;
+    }
+    Ok(PackageOperationResult {
+        success: out.success,
+        message: if out.success {
+            format!("Rebooting to upgrade to Fedora {}.", state.target_release)
+        } else {
+            format!("Could not reboot into the upgrade: {}", out.error_text().trim())
+        },
+        details: Some(format!("STDOUT:\n{}\nSTDERR:\n{}", out.stdout, out.stderr)),
+        confirmation_required: false,
+        simulated: out.simulated,
+        reboot_required: false,
+    })
+}
+
+// Drops the upgrade in progress and the packages downloaded for it.
+#[tauri::command]
+pub async fn cancel_system_upgrade(app: tauri::AppHandle) -> Result<PackageOperationResult, NebulaError> {
+    let out = run_plan(&app, &plan::system_upgrade_clean()).await?;
+    if out.success && !out.simulated {
+        save_state(&app, None)?;
+    } else if !out.success {
+        warn!("dnf system-upgrade clean failed: {}", out.error_text().trim());
+    }
+    Ok(PackageOperationResult {
+        success: out.success,
+        message: if out.success { "The release upgrade was cancelled.".to_string() } else { format!("Failed to cancel the upgrade: {}", out.error_text().trim()) },
+        details: Some(format!("STDOUT:\n{}\nSTDERR:\n{}", out.stdout, out.stderr)),
+        confirmation_required: false,
+        simulated: out.simulated,
+        reboot_required: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coordination::{check_staged_update, PendingSystemTransaction, PendingTransactionKind};
+
+    #[test]
+    fn test_cancel_with_staged_upgrade() {
+        // /system-update points at the downloaded upgrade until it is applied or cleaned up
+        let staged = [PendingSystemTransaction {
+            kind: PendingTransactionKind::OfflineUpdate,
+            source: "dnf system-upgrade".to_string(),
+            detail: "An offline update will be applied on the next reboot.".to_string(),
+        }];
+        assert!(check_staged_update(&[plan::system_upgrade_clean()], &staged).is_ok());
+        assert!(check_staged_update(&[plan::system_upgrade_reboot()], &staged).is_ok());
+        assert!(check_staged_update(&[plan::system_upgrade_download(41)], &staged).is_err());
+        assert!(check_staged_update(&[plan::system_upgrade_download(41)], &[]).is_ok());
+    }
+
+    #[test]
+    fn test_upgrade_state_machine() {
+        let state = |stage| UpgradeState { from_release: 40, target_release: 41, stage, updated_at: String::new() };
+        assert_eq!(reconcile(state(UpgradeStage::Rebooting), Some(41), false).0, None);
+        let (retry, note) = reconcile(state(UpgradeStage::Rebooting), Some(40), false);
+        assert_eq!(retry.map(|state| state.stage), Some(UpgradeStage::Downloaded));
+        assert!(note.is_some());
+        assert_eq!(reconcile(state(UpgradeStage::Rebooting), Some(40), true), (Some(state(UpgradeStage::Rebooting)), None));
+        assert_eq!(reconcile(state(UpgradeStage::Downloading), Some(40), false).0, Some(state(UpgradeStage::Downloading)));
+
+        assert!(validate_target(40, 42).is_ok());
+        assert!(validate_target(40, 40).is_err());
+        assert!(validate_target(40, 43).is_err());
+
+        let df = "Mounted on        Avail\n/          4294967296\n/          4294967296\n";
+        let space = space_needed(&parse_df_output(df));
+        assert_eq!(space, [FreeSpace { mount: "/".to_string(), available_bytes: 4 * GIB, required_bytes: 8 * GIB }]);
+        assert_eq!(space_needed(&parse_df_output("Mounted on Avail\n/var 1\n/ 2\n")).len(), 2);
+        assert_eq!(plan::system_upgrade_download(41).command_line(), "pkexec dnf system-upgrade download --releasever=41 --assumeyes");
+    }
+}
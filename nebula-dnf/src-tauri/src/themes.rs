@@ -0,0 +1,175 @@
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::appstream::ComponentKind;
+use crate::error::NebulaError;
+use crate::process::run_command;
+
+const QUERY_FORMAT: &str = "%{name}\t%{version}-%{release}\t%{summary}\n";
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+pub enum Desktop {
+    Gnome,
+    Kde,
+    Other,
+}
+
+// Grouping follows AppStream component types: shell extensions and plasmoids are `addon`
+// components extending the shell, icon sets are `icon-theme`. Visual themes have no component
+// type of their own and are grouped as `theme` by their categories or keywords.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ComponentType {
+    Addon,
+    IconTheme,
+    Theme,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DiscoveredPackage {
+    name: String,
+    version: String,
+    summary: String,
+    installed: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DiscoveryGroup {
+    component_type: ComponentType,
+    extends: Option<String>, // the AppStream id an addon extends, e.g. "org.gnome.Shell"
+    packages: Vec<DiscoveredPackage>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DesktopDiscovery {
+    desktop: Desktop,
+    groups: Vec<DiscoveryGroup>,
+}
+
+// XDG_CURRENT_DESKTOP is a colon-separated list such as "ubuntu:GNOME" or "KDE".
+fn detect_desktop(current_desktop: &str) -> Desktop {
+    let names: Vec<String> = current_desktop.split(':').map(str::to_ascii_uppercase).collect();
+    if names.iter().any(|name| name == "GNOME") {
+        Desktop::Gnome
+    } else if names.iter().any(|name| name == "KDE") {
+        Desktop::Kde
+    } else {
+        Desktop::Other
+    }
+}
+
+// The groups shown for each desktop, with the shell component its add-ons extend.
+fn discovery_groups(desktop: Desktop) -> Vec<(ComponentType, Option<&'static str>)> {
+    let shell = match desktop {
+        Desktop::Gnome => Some("org.gnome.Shell"),
+        Desktop::Kde => Some("org.kde.plasmashell"),
+        Desktop::Other => None,
+    };
+    let mut groups: Vec<(ComponentType, Option<&'static str>)> = shell.map(|shell| (ComponentType::Addon, Some(shell))).into_iter().collect();
+    groups.push((ComponentType::Theme, None));
+    groups.push((ComponentType::IconTheme, None));
+    groups
+}
+
+fn in_group(component: &ComponentKind, group: ComponentType, extends: Option<&str>) -> bool {
+    match group {
+        ComponentType::Addon => component.component_type == "addon" && extends.is_some_and(|shell| component.extends.iter().any(|id| id == shell)),
+        ComponentType::IconTheme => component.component_type == "icon-theme",
+        ComponentType::Theme => {
+            component.component_type != "icon-theme" && component.categories.iter().chain(&component.keywords).any(|word| word.eq_ignore_ascii_case("theme") || word.eq_ignore_ascii_case("themes"))
+        }
+    }
+}
+
+fn parse_rows(output: &str, installed: &HashSet<String>) -> Vec<DiscoveredPackage> {
+    let mut seen = HashSet::new();
+    let mut packages: Vec<DiscoveredPackage> = output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t').map(str::trim);
+            let name = fields.next().filter(|name| !name.is_empty())?.to_string();
+            let version = fields.next()?.to_string();
+            Some(DiscoveredPackage {
+                installed: installed.contains(&name),
+                summary: fields.next().unwrap_or("").to_string(),
+                name,
+                version,
+            })
+        })
+        .filter(|package| seen.insert(package.name.clone()))
+        .collect();
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    packages
+}
+
+// Versions and summaries of the catalog's packages, from the enabled repositories.
+async fn query_group(app: &tauri::AppHandle, names: &[&str]) -> Result<Vec<DiscoveredPackage>, String> {
+    let mut args = vec!["repoquery", "--quiet", "--latest-limit=1", "--queryformat", QUERY_FORMAT, "--"];
+    args.extend_from_slice(names);
+    let out = run_command(app, "dnf", &args).await?;
+    if !out.success {
+        return Err(format!("dnf repoquery failed: {}", out.error_text().trim()));
+    }
+    let mut installed_args = vec!["repoquery", "--installed", "--quiet", "--queryformat", "%{name}\n", "--"];
+    installed_args.extend_from_slice(names);
+    let installed = run_command(app, "dnf", &installed_args).await?;
+    let installed: HashSet<String> = installed.stdout.lines().map(|line| line.trim().to_string()).collect();
+    Ok(parse_rows(&out.stdout, &installed))
+}
+
+// Themes, icon sets and shell add-ons for the running desktop, as the AppStream catalog of the
+// enabled repositories lists them.
+#[tauri::command]
+pub async fn discover_desktop_extras(app: tauri::AppHandle) -> Result<DesktopDiscovery, NebulaError> {
+    let desktop = detect_desktop(&std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default());
+    let mut groups = Vec::new();
+    for (component_type, extends) in discovery_groups(desktop) {
+        let components = crate::appstream::find_components(&app, |component| in_group(component, component_type, extends)).await;
+        let mut names: Vec<&str> = components.iter().map(|component| component.package.as_str()).collect();
+        names.sort_unstable();
+        names.dedup();
+        if names.is_empty() {
+            continue;
+        }
+        let packages = query_group(&app, &names).await?;
+        if !packages.is_empty() {
+            groups.push(DiscoveryGroup { component_type, extends: extends.map(String::from), packages });
+        }
+    }
+    Ok(DesktopDiscovery { desktop, groups })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_desktop_and_parse() {
+        assert_eq!(detect_desktop("ubuntu:GNOME"), Desktop::Gnome);
+        assert_eq!(detect_desktop("KDE"), Desktop::Kde);
+        assert_eq!(detect_desktop("XFCE"), Desktop::Other);
+        assert_eq!(discovery_groups(Desktop::Kde)[0], (ComponentType::Addon, Some("org.kde.plasmashell")));
+        assert_eq!(discovery_groups(Desktop::Other).len(), 2);
+
+        let component = |component_type: &str, extends: &[&str], categories: &[&str]| ComponentKind {
+            package: "package".to_string(),
+            component_type: component_type.to_string(),
+            extends: extends.iter().map(|id| id.to_string()).collect(),
+            categories: categories.iter().map(|category| category.to_string()).collect(),
+            keywords: Vec::new(),
+        };
+        assert!(in_group(&component("addon", &["org.gnome.Shell"], &[]), ComponentType::Addon, Some("org.gnome.Shell")));
+        assert!(!in_group(&component("addon", &["org.gnome.TextEditor"], &[]), ComponentType::Addon, Some("org.gnome.Shell")));
+        assert!(in_group(&component("icon-theme", &[], &[]), ComponentType::IconTheme, None));
+        assert!(in_group(&component("generic", &[], &["Theme"]), ComponentType::Theme, None));
+        assert!(!in_group(&component("desktop-application", &[], &["Utility"]), ComponentType::Theme, None));
+
+        let output = "papirus-icon-theme\t20240201-1.fc40\tPapirus icon theme\nadwaita-icon-theme\t46.0-1.fc40\tAdwaita icons\npapirus-icon-theme\t20240201-1.fc40\tPapirus icon theme\n";
+        let installed = HashSet::from(["adwaita-icon-theme".to_string()]);
+        let packages = parse_rows(output, &installed);
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].name, "adwaita-icon-theme");
+        assert!(packages[0].installed);
+        assert!(!packages[1].installed);
+    }
+}
@@ -0,0 +1,124 @@
+use serde::Serialize;
+
+// One row of the transaction table dnf prints before asking for confirmation.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct TransactionItem {
+    action: String, // section header without the colon, e.g. "Removing unused dependencies"
+    name: String,
+    arch: String,
+    version: String,
+    repo: String,
+    size: Option<String>,
+}
+
+impl TransactionItem {
+    pub fn action(&self) -> &str {
+        &self.action
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+// Parses the dnf4 (`====`-framed) and dnf5 transaction tables. dnf4 wraps long package names
+// onto their own line, with the remaining columns on the next one. Also returns the rows under a
+// section that did not fit the table, for parse_failures::record.
+pub fn parse_transaction_table(output: &str) -> (Vec<TransactionItem>, Vec<String>) {
+    let mut items = Vec::new();
+    let mut unparsed = Vec::new();
+    let mut action: Option<String> = None;
+    let mut wrapped_name: Option<String> = None;
+
+    for line in output.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("Transaction Summary") {
+            break;
+        }
+        if trimmed.is_empty() || trimmed.starts_with('=') || trimmed.starts_with("Package ") {
+            continue;
+        }
+        if !line.starts_with(char::is_whitespace) {
+            // "Removing:", "Upgrading:", "Installing dependencies:" ... anything else ends a section
+            action = trimmed.strip_suffix(':').map(str::to_string);
+            wrapped_name = None;
+            continue;
+        }
+        let Some(current_action) = &action else { continue };
+
+        let mut words: Vec<&str> = trimmed.split_whitespace().collect();
+        if words.len() == 1 {
+            wrapped_name = Some(words[0].to_string());
+            continue;
+        }
+        let name = match wrapped_name.take() {
+            Some(name) => name,
+            None => words.remove(0).to_string(),
+        };
+        if words.len() < 3 {
+            // " replacing  foo.x86_64 1.0-1" lines under an upgrade are expected
+            if name != "replacing" {
+                unparsed.push(line.to_string());
+            }
+            continue;
+        }
+        items.push(TransactionItem {
+            action: current_action.clone(),
+            name,
+            arch: words[0].to_string(),
+            version: words[1].to_string(),
+            repo: words[2].to_string(),
+            size: (words.len() > 3).then(|| words[3..].join(" ")),
+        });
+    }
+    (items, unparsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_transaction_table() {
+        let dnf4 = "Dependencies resolved.\n\
+            ================================================================================\n\
+            \x20Package                 Architecture  Version           Repository      Size\n\
+            ================================================================================\n\
+            Removing:\n\
+            \x20libfoo                  x86_64        1.0-1.fc40        @fedora        100 k\n\
+            Removing unused dependencies:\n\
+            \x20python3-some-very-long-package-name\n\
+            \x20                        noarch        2.3-4.fc40        @updates        12 k\n\
+            \n\
+            Transaction Summary\n\
+            ================================================================================\n\
+            Remove  2 Packages\n";
+        let (items, unparsed) = parse_transaction_table(dnf4);
+        assert_eq!(items.len(), 2);
+        assert!(unparsed.is_empty());
+        assert_eq!(items[0].action, "Removing");
+        assert_eq!(items[0].size.as_deref(), Some("100 k"));
+        assert_eq!(items[1].action, "Removing unused dependencies");
+        assert_eq!(items[1].name, "python3-some-very-long-package-name");
+        assert_eq!(items[1].version, "2.3-4.fc40");
+
+        let dnf5 = "Package     Arch   Version     Repository      Size\n\
+            Removing:\n\
+            \x20libfoo      x86_64 1.0-1.fc40  fedora     100.0 KiB\n\
+            \n\
+            Transaction Summary:\n\
+            \x20Removing:         1 package\n";
+        let (items, _) = parse_transaction_table(dnf5);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].repo, "fedora");
+        assert_eq!(items[0].size.as_deref(), Some("100.0 KiB"));
+
+        let changed = "Upgrading:\n\
+            \x20bash  x86_64  5.2.26-3.fc40  updates  1.8 M\n\
+            \x20  replacing  bash.x86_64  5.2.26-1.fc40\n\
+            \x20glibc  2.39-8.fc40\n";
+        let (items, unparsed) = parse_transaction_table(changed);
+        assert_eq!(items.len(), 1);
+        assert_eq!(unparsed, vec!["\x20glibc  2.39-8.fc40"]);
+    }
+}
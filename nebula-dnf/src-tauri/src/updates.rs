@@ -0,0 +1,300 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::Manager;
+use tracing::warn;
+
+use crate::error::NebulaError;
+use crate::packagekit::PackageAction;
+use crate::plan;
+use crate::process::{run_command, run_plan, run_plan_streaming};
+use crate::services::{BackendRegistry, OperationManager};
+use crate::settings::PackageBackend;
+use crate::transaction::{parse_transaction_table, TransactionItem};
+use crate::{app_data_file, load_json_file, save_json_file, PackageOperationResult};
+
+// Kept apart from the package cache, which is rebuilt on a different schedule.
+const UPDATES_CACHE_FILE_NAME: &str = "updates_cache.json";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AvailableUpdate {
+    name: String,
+    arch: String,
+    installed_version: Option<String>, // None if rpm no longer knows the package
+    candidate_version: String,
+    repo: String,
+    size: Option<u64>, // download size in bytes, if dnf reported it
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UpdateCheck {
+    checked_at: String, // RFC 3339
+    updates: Vec<AvailableUpdate>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct UpdateAllResult {
+    result: PackageOperationResult,
+    transaction: Vec<TransactionItem>, // what dnf upgraded, installed or removed
+    incomplete: bool,                  // some rows of dnf's table could not be parsed
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct PendingUpdate {
+    pub name: String,
+    pub arch: String,
+    pub version: String, // candidate EVR
+    pub repo: String,
+}
+
+// Parses `dnf check-update` output, ignoring the metadata banner and the trailing
+// "Obsoleting Packages" section (those entries are already listed above it).
+pub fn parse_check_update_output(output: &str) -> Vec<PendingUpdate> {
+    let mut updates = Vec::new();
+    for line in output.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("Obsoleting Packages") {
+            break;
+        }
+        if trimmed.is_empty() || trimmed.starts_with("Last metadata expiration check:") {
+            continue;
+        }
+        // Package lines look like "bash.x86_64   5.2.26-3.fc40   updates"
+        let columns: Vec<&str> = trimmed.split_whitespace().collect();
+        if columns.len() != 3 {
+            continue;
+        }
+        let (name, arch) = columns[0].rsplit_once('.').unwrap_or((columns[0], ""));
+        updates.push(PendingUpdate {
+            name: name.to_string(),
+            arch: arch.to_string(),
+            version: columns[1].to_string(),
+            repo: columns[2].to_string(),
+        });
+    }
+    updates
+}
+
+// Runs `dnf check-update`, which exits with 100 when updates are available and 0 when there are none.
+pub async fn fetch_pending_updates(app: &tauri::AppHandle) -> Result<Vec<PendingUpdate>, String> {
+    let out = run_command(app, "dnf", &["check-update", "--quiet"]).await?;
+    if out.code != 0 && out.code != 100 {
+        return Err(format!("dnf check-update failed with exit code {}: {}", out.code, out.error_text().trim()));
+    }
+    let updates = parse_check_update_output(&out.stdout);
+    crate::dbus_status::set_pending_updates(app, updates.len() as u32);
+    Ok(updates)
+}
+
+// "name\tarch\tvalue" lines keyed by (name, arch), as printed by the queries below.
+fn parse_keyed_values(output: &str) -> HashMap<(String, String), String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t').map(str::trim);
+            let name = fields.next().filter(|n| !n.is_empty())?;
+            let arch = fields.next()?;
+            let value = fields.next()?;
+            Some(((name.to_string(), arch.to_string()), value.to_string()))
+        })
+        .collect()
+}
+
+fn build_available_updates(
+    pending: Vec<PendingUpdate>,
+    installed: &HashMap<(String, String), String>,
+    sizes: &HashMap<(String, String), String>,
+) -> Vec<AvailableUpdate> {
+    pending
+        .into_iter()
+        .map(|update| {
+            let key = (update.name.clone(), update.arch.clone());
+            AvailableUpdate {
+                installed_version: installed.get(&key).cloned(),
+                size: sizes.get(&key).and_then(|size| size.parse().ok()),
+                name: update.name,
+                arch: update.arch,
+                candidate_version: update.version,
+                repo: update.repo,
+            }
+        })
+        .collect()
+}
+
+async fn query_available_updates(app: &tauri::AppHandle) -> Result<Vec<AvailableUpdate>, String> {
+    let pending = fetch_pending_updates(app).await?;
+    if pending.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut names: Vec<&str> = pending.iter().map(|u| u.name.as_str()).collect();
+    names.dedup();
+    let mut rpm_args = vec!["-q", "--queryformat", "%{NAME}\t%{ARCH}\t%|EPOCH?{%{EPOCH}:}:{}|%{VERSION}-%{RELEASE}\n", "--"];
+    rpm_args.extend(names);
+    // rpm exits non-zero if any name is not installed, but still prints the others
+    let installed = parse_keyed_values(&run_command(app, "rpm", &rpm_args).await?.stdout);
+
+    let sizes = match run_command(app, "dnf", &["repoquery", "--upgrades", "--quiet", "--queryformat", "%{name}\t%{arch}\t%{downloadsize}\n"]).await {
+        Ok(out) if out.success => parse_keyed_values(&out.stdout),
+        _ => HashMap::new(), // sizes are optional
+    };
+    Ok(build_available_updates(pending, &installed, &sizes))
+}
+
+// Called when something the update results depend on changed, e.g. the enabled repositories.
+pub fn invalidate_updates_cache(app: &tauri::AppHandle) {
+    match app_data_file(app, UPDATES_CACHE_FILE_NAME) {
+        Ok(path) if path.exists() => {
+            if let Err(e) = std::fs::remove_file(&path) {
+                warn!("Failed to remove updates cache {:?}: {}", path, e);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => warn!("{}", e),
+    }
+}
+
+// Available updates with installed and candidate versions. Served from the updates cache
+// unless `force_refresh` is set or nothing was cached yet.
+#[tauri::command]
+pub async fn check_for_updates(app: tauri::AppHandle, force_refresh: bool) -> Result<UpdateCheck, NebulaError> {
+    let cache_path = app_data_file(&app, UPDATES_CACHE_FILE_NAME)?;
+    if !force_refresh {
+        if let Some(cached) = load_json_file::<UpdateCheck>(&cache_path)? {
+            return Ok(cached);
+        }
+    }
+    let check = UpdateCheck {
+        checked_at: Local::now().to_rfc3339(),
+        updates: query_available_updates(&app).await?,
+    };
+    if let Err(e) = save_json_file(&cache_path, &check) {
+        warn!("Failed to save updates cache: {}", e);
+    }
+    Ok(check)
+}
+
+// Changelog text for the pending updates of `packages` (all pending updates when empty). Best
+// effort: a missing changelog should not block the update it is shown for.
+pub async fn fetch_update_changelog(app: &tauri::AppHandle, packages: &[String]) -> String {
+    let command = match plan::changelog_preview(packages) {
+        Ok(command) => command,
+        Err(e) => return e,
+    };
+    match run_plan(app, &command).await {
+        Ok(out) if out.success && !out.stdout.trim().is_empty() => out.stdout,
+        Ok(out) if out.success => "No changelog entries were published for these updates.".to_string(),
+        Ok(out) => format!("Changelog unavailable: {}", out.error_text().trim()),
+        Err(e) => format!("Changelog unavailable: {}", e),
+    }
+}
+
+// Upgrades everything except pinned packages. Output is streamed as "operation-output" events.
+// With `download_only` the updates are only fetched, to be applied later from the cache; with
+// `offline` they are staged and applied on the next boot.
+#[tauri::command]
+pub async fn update_all_packages(
+    app: tauri::AppHandle,
+    confirmed: Option<bool>,
+    options: Option<crate::options::AdvancedOptions>,
+    download_only: Option<bool>,
+    offline: Option<bool>,
+) -> Result<UpdateAllResult, NebulaError> {
+    let download_only = download_only.unwrap_or(false);
+    let offline = offline.unwrap_or(false);
+    if download_only && offline {
+        return Err("Choose either download-only or offline updates.".into());
+    }
+    let backends = app.state::<BackendRegistry>();
+    if backends.selected()? != PackageBackend::Dnf {
+        if options.is_some() || download_only || offline {
+            return Err("Advanced options, download-only and offline updates need the dnf backend.".into());
+        }
+        let preview = crate::settings::requires_preview(&app, crate::settings::GuardedAction::Update, confirmed.unwrap_or(false))?;
+        if let Some(result) = backends.package_command(PackageAction::Update, &[], false, preview).await? {
+            return Ok(UpdateAllResult { result, transaction: Vec::new(), incomplete: false });
+        }
+    }
+    let pinned = crate::pins::pinned_packages(&app)?;
+    let mut plan = plan::upgrade_all(false, &pinned);
+    if download_only {
+        plan = plan::download_only(&plan)?;
+    } else if offline {
+        plan = plan::offline(&plan, crate::probes::is_dnf5(&app).await)?;
+    }
+    let plan = crate::options::apply_options(&plan, options.as_ref())?;
+    // Only applying the updates needs the review; downloading them changes nothing.
+    if !download_only && crate::settings::requires_preview(&app, crate::settings::GuardedAction::Update, confirmed.unwrap_or(false))? {
+        let changelog = fetch_update_changelog(&app, &[]).await;
+        return Ok(UpdateAllResult {
+            result: crate::settings::preview_result("Review the changes in the pending updates, then confirm the update.".to_string(), changelog),
+            transaction: Vec::new(),
+            incomplete: false,
+        });
+    }
+    let activity = if download_only { "Downloading updates" } else if offline { "Staging updates" } else { "Updating all packages" };
+    let _busy = app.state::<OperationManager>().begin(activity.to_string());
+    let out = run_plan_streaming(&app, &plan, "update_all").await?;
+    let (transaction, unparsed) = parse_transaction_table(&out.stdout);
+    let incomplete = crate::parse_failures::record(&app, "dnf transaction table", &unparsed).await;
+
+    let message = if !out.success {
+        format!("System update failed with exit code {}.", out.code)
+    } else if transaction.is_empty() {
+        "The system is already up to date.".to_string()
+    } else if download_only {
+        format!("Downloaded {} change(s). Update again to apply them from the cache.", transaction.len())
+    } else if offline {
+        format!("Staged {} change(s). They are applied on the next reboot.", transaction.len())
+    } else {
+        let upgraded = transaction.iter().filter(|item| item.action().starts_with("Upgrading")).count();
+        format!("Upgraded {} package(s); {} change(s) in total.", upgraded, transaction.len())
+    };
+    if out.success && !download_only && !offline {
+        if let Err(e) = fetch_pending_updates(&app).await {
+            warn!("Could not refresh pending updates after upgrade: {}", e);
+        }
+    }
+    let mut result = PackageOperationResult {
+        success: out.success,
+        message,
+        details: Some(format!("STDOUT:\n{}\nSTDERR:\n{}", out.stdout, out.stderr)),
+        confirmation_required: false,
+        simulated: out.simulated,
+        reboot_required: offline && out.success && !out.simulated && !transaction.is_empty(),
+    };
+    if !download_only && !offline {
+        let changed: Vec<String> = transaction.iter().map(|item| item.name().to_string()).collect();
+        crate::restarts::check_reboot(&app, &mut result, &changed).await;
+    }
+    Ok(UpdateAllResult { result, transaction, incomplete })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_check_update_output() {
+        let output = "Last metadata expiration check: 0:12:01 ago on Mon 01 Jan 2024.\n\
+        \n\
+        bash.x86_64                 5.2.26-3.fc40          updates\n\
+        kernel.x86_64               6.8.9-300.fc40         updates\n\
+        Obsoleting Packages\n\
+        grub2-tools.x86_64          1:2.06-120.fc40        updates\n";
+        let updates = parse_check_update_output(output);
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].name, "bash");
+        assert_eq!(updates[0].arch, "x86_64");
+        assert_eq!(updates[1].version, "6.8.9-300.fc40");
+        assert_eq!(updates[1].repo, "updates");
+
+        let installed = parse_keyed_values("bash\tx86_64\t5.2.26-1.fc40\n");
+        let sizes = parse_keyed_values("bash\tx86_64\t1843200\nkernel\tx86_64\tunknown\n");
+        let available = build_available_updates(updates, &installed, &sizes);
+        assert_eq!(available[0].installed_version.as_deref(), Some("5.2.26-1.fc40"));
+        assert_eq!(available[0].size, Some(1843200));
+        assert_eq!(available[1].installed_version, None);
+        assert_eq!(available[1].size, None);
+    }
+}
@@ -0,0 +1,278 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use crate::error::NebulaError;
+use crate::process::run_command;
+use crate::{app_data_file, load_json_file, save_json_file};
+
+const DESKTOP_ENTRY_DIR: &str = "/usr/share/applications";
+// Last-used times seen so far, keyed by binary path. Kept so a later atime reset (e.g. the
+// binary being replaced by an update) does not make an app look unused.
+const USAGE_FILE_NAME: &str = "app_usage.json";
+const DEFAULT_UNUSED_MONTHS: u32 = 6;
+
+#[derive(Debug, Clone, PartialEq)]
+struct DesktopApp {
+    name: String,
+    desktop_file: PathBuf,
+    binary: PathBuf,
+}
+
+impl DesktopApp {
+    // The desktop file id the session uses in app unit names, e.g. "org.gnome.TextEditor".
+    fn desktop_id(&self) -> String {
+        self.desktop_file.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct UnusedApp {
+    name: String,
+    desktop_file: String,
+    binary: String,
+    package: String,
+    last_used: Option<DateTime<Utc>>, // None if the binary was never seen to run
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct UnusedAppsReport {
+    // false when /usr is mounted noatime, so last-used times only come from launches and earlier observations
+    atime_reliable: bool,
+    journal_launches: bool, // the user journal had app launches to go by
+    apps: Vec<UnusedApp>,
+}
+
+// The program an Exec= line runs, skipping an `env VAR=value` prefix.
+fn exec_program(exec: &str) -> Option<&str> {
+    let mut words = exec.split_whitespace().map(|w| w.trim_matches('"'));
+    let mut program = words.next()?;
+    if program == "env" || program.ends_with("/env") {
+        program = words.find(|w| !w.contains('='))?;
+    }
+    Some(program)
+}
+
+fn parse_desktop_entry(contents: &str) -> Option<(String, String)> {
+    let mut in_entry = false;
+    let (mut name, mut exec) = (None, None);
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_entry = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_entry {
+            continue;
+        }
+        match line.split_once('=') {
+            Some(("Name", value)) => name = Some(value.to_string()),
+            Some(("Exec", value)) => exec = exec_program(value).map(String::from),
+            Some(("NoDisplay" | "Hidden", "true")) => return None,
+            Some(("Type", value)) if value != "Application" => return None,
+            _ => {}
+        }
+    }
+    Some((name?, exec?))
+}
+
+pub fn resolve_binary(program: &str) -> Option<PathBuf> {
+    if program.starts_with('/') {
+        return Some(PathBuf::from(program)).filter(|p| p.exists());
+    }
+    ["/usr/bin", "/usr/sbin", "/usr/local/bin"]
+        .iter()
+        .map(|dir| Path::new(dir).join(program))
+        .find(|p| p.exists())
+}
+
+fn desktop_apps() -> Vec<DesktopApp> {
+    let Ok(entries) = std::fs::read_dir(DESKTOP_ENTRY_DIR) else { return Vec::new() };
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "desktop"))
+        .filter_map(|desktop_file| {
+            let (name, program) = parse_desktop_entry(&std::fs::read_to_string(&desktop_file).ok()?)?;
+            let binary = resolve_binary(&program)?;
+            Some(DesktopApp { name, desktop_file, binary })
+        })
+        .collect()
+}
+
+// Whether the mount holding `path` records access times (anything but noatime).
+fn atime_recorded(mounts: &str, path: &Path) -> bool {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let mount_point = fields.nth(1)?;
+            let options = fields.nth(1)?;
+            path.starts_with(mount_point).then_some((mount_point.len(), options))
+        })
+        .max_by_key(|(len, _)| *len)
+        .is_none_or(|(_, options)| !options.split(',').any(|o| o == "noatime"))
+}
+
+fn accessed_at(path: &Path) -> Option<DateTime<Utc>> {
+    std::fs::metadata(path).ok()?.accessed().ok().map(DateTime::<Utc>::from)
+}
+
+// Maps each path to the package owning it; paths not owned by any package are left out. One
+// query per path: a batched `rpm -qf` prints a line per owner, so its lines cannot be paired
+// back with the paths once a file has several owners or none.
+async fn owning_packages(app: &tauri::AppHandle, paths: &[String]) -> Result<HashMap<String, String>, String> {
+    let mut owners = HashMap::new();
+    for path in paths {
+        let out = run_command(app, "rpm", &["-qf", "--queryformat", "%{NAME}\n", "--", path]).await?;
+        if let Some(package) = out.stdout.lines().next().filter(|_| out.success) {
+            owners.insert(path.clone(), package.trim().to_string());
+        }
+    }
+    Ok(owners)
+}
+
+// Undoes systemd's unit name escaping, e.g. "org.gnome.Text\x2dEditor".
+fn unescape_unit_name(name: &str) -> String {
+    let mut unescaped = String::new();
+    let mut rest = name;
+    while let Some(start) = rest.find("\\x") {
+        unescaped.push_str(&rest[..start]);
+        let code = rest.get(start + 2..start + 4).and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match code {
+            Some(code) => {
+                unescaped.push(char::from(code));
+                rest = &rest[start + 4..];
+            }
+            None => {
+                unescaped.push('\\');
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+// The desktop id an app unit started by the session was named for, among `desktop_ids`. Desktops
+// launch apps as "app[-<launcher>]-<desktop id>-<random>.scope" or
+// "app[-<launcher>]-<desktop id>[@<random>].service", with dashes in the id escaped.
+fn launched_desktop_id(unit: &str, desktop_ids: &[String]) -> Option<String> {
+    let (name, scope) = match unit.strip_prefix("app-")? {
+        name if name.ends_with(".scope") => (name.trim_end_matches(".scope"), true),
+        name => (name.strip_suffix(".service")?, false),
+    };
+    let name = name.split('@').next()?;
+    let name = if scope { name.rsplit_once('-').map_or(name, |(id, _)| id) } else { name };
+    let without_launcher = name.split_once('-').map(|(_, id)| id);
+    [Some(name), without_launcher].into_iter().flatten().map(unescape_unit_name).find(|id| desktop_ids.contains(id))
+}
+
+// The latest start of each app's unit in `journal` (`journalctl -o json` lines), by desktop id.
+fn parse_launches(journal: &str, desktop_ids: &[String]) -> HashMap<String, DateTime<Utc>> {
+    let mut launches: HashMap<String, DateTime<Utc>> = HashMap::new();
+    for line in journal.lines() {
+        let Ok(entry) = serde_json::from_str::<serde_json::Value>(line) else { continue };
+        let (Some(unit), Some(micros)) = (entry["USER_UNIT"].as_str(), entry["__REALTIME_TIMESTAMP"].as_str().and_then(|t| t.parse::<i64>().ok())) else { continue };
+        let (Some(desktop_id), Some(started)) = (launched_desktop_id(unit, desktop_ids), DateTime::<Utc>::from_timestamp_micros(micros)) else { continue };
+        let latest = launches.entry(desktop_id).or_insert(started);
+        *latest = (*latest).max(started);
+    }
+    launches
+}
+
+// App launches since `since` from the user journal, where the session starts apps as units.
+// None when the journal cannot be read.
+async fn journal_launches(app: &tauri::AppHandle, since: DateTime<Utc>, desktop_ids: &[String]) -> Option<HashMap<String, DateTime<Utc>>> {
+    let since = format!("--since={}", since.format("%Y-%m-%d %H:%M:%S UTC"));
+    let args = ["--user", "--output=json", "--output-fields=USER_UNIT", "--no-pager", &since, "JOB_TYPE=start"];
+    match run_command(app, "journalctl", &args).await {
+        Ok(out) if out.success => Some(parse_launches(&out.stdout, desktop_ids)),
+        Ok(out) => {
+            warn!("Could not read app launches from the journal: {}", out.error_text().trim());
+            None
+        }
+        Err(e) => {
+            warn!("Could not read app launches from the journal: {}", e);
+            None
+        }
+    }
+}
+
+// Opt-in (settings `usage_insights`): desktop apps from system packages whose binaries have not
+// been run for `months` (default 6), as removal candidates. Nothing leaves the machine.
+#[tauri::command]
+pub async fn list_unused_apps(app: tauri::AppHandle, months: Option<u32>) -> Result<UnusedAppsReport, NebulaError> {
+    if !crate::settings::usage_insights(&app)? {
+        return Err("Usage insights are turned off. Enable them in the settings first.".into());
+    }
+    let usage_path = app_data_file(&app, USAGE_FILE_NAME)?;
+    let mut last_seen: HashMap<String, DateTime<Utc>> = load_json_file(&usage_path)?.unwrap_or_default();
+    let atime_reliable = atime_recorded(&std::fs::read_to_string("/proc/mounts").unwrap_or_default(), Path::new("/usr/bin"));
+
+    let cutoff = Utc::now() - ChronoDuration::days(30 * i64::from(months.unwrap_or(DEFAULT_UNUSED_MONTHS)));
+    let apps = desktop_apps();
+    let desktop_ids: Vec<String> = apps.iter().map(DesktopApp::desktop_id).collect();
+    let launches = journal_launches(&app, cutoff, &desktop_ids).await;
+    for desktop_app in &apps {
+        let key = desktop_app.binary.to_string_lossy().into_owned();
+        let accessed = accessed_at(&desktop_app.binary).filter(|_| atime_reliable);
+        let launched = launches.as_ref().and_then(|launches| launches.get(&desktop_app.desktop_id())).copied();
+        if let Some(used) = accessed.max(launched) {
+            let seen = last_seen.entry(key).or_insert(used);
+            *seen = (*seen).max(used);
+        }
+    }
+    if let Err(e) = save_json_file(&usage_path, &last_seen) {
+        warn!("Failed to save app usage: {}", e);
+    }
+
+    let binaries: Vec<String> = apps.iter().map(|a| a.binary.to_string_lossy().into_owned()).collect();
+    let packages = owning_packages(&app, &binaries).await?;
+    let mut unused: Vec<UnusedApp> = apps
+        .into_iter()
+        .filter_map(|desktop_app| {
+            let binary = desktop_app.binary.to_string_lossy().into_owned();
+            let last_used = last_seen.get(&binary).copied();
+            if last_used.is_some_and(|t| t > cutoff) {
+                return None;
+            }
+            Some(UnusedApp {
+                name: desktop_app.name,
+                desktop_file: desktop_app.desktop_file.to_string_lossy().into_owned(),
+                package: packages.get(&binary)?.clone(),
+                binary,
+                last_used,
+            })
+        })
+        .collect();
+    unused.sort_by(|a, b| a.last_used.cmp(&b.last_used).then_with(|| a.name.cmp(&b.name)));
+    let journal_launches = launches.is_some_and(|launches| !launches.is_empty());
+    Ok(UnusedAppsReport { atime_reliable, journal_launches, apps: unused })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_desktop_entry() {
+        let entry = "[Desktop Entry]\nName=GIMP\nExec=env GDK_BACKEND=x11 gimp-2.10 %U\nType=Application\n[Desktop Action new]\nName=New Window\nExec=other\n";
+        assert_eq!(parse_desktop_entry(entry), Some(("GIMP".to_string(), "gimp-2.10".to_string())));
+        assert_eq!(parse_desktop_entry("[Desktop Entry]\nName=Helper\nExec=/usr/libexec/helper\nNoDisplay=true\n"), None);
+
+        let mounts = "/dev/vda3 / btrfs rw,relatime,subvol=/root 0 0\n/dev/vda3 /usr btrfs rw,noatime 0 0\n";
+        assert!(!atime_recorded(mounts, Path::new("/usr/bin")));
+        assert!(atime_recorded(mounts, Path::new("/opt/app")));
+
+        let ids = vec!["org.gnome.TextEditor".to_string(), "gimp".to_string(), "my-app".to_string()];
+        assert_eq!(launched_desktop_id("app-gnome-org.gnome.TextEditor-4242.scope", &ids).as_deref(), Some("org.gnome.TextEditor"));
+        assert_eq!(launched_desktop_id("app-flatpak-gimp@a1b2.service", &ids).as_deref(), Some("gimp"));
+        assert_eq!(launched_desktop_id("app-gnome-my\\x2dapp-77.scope", &ids).as_deref(), Some("my-app"));
+        assert_eq!(launched_desktop_id("session-2.scope", &ids), None);
+        let journal = "{\"USER_UNIT\":\"app-gnome-gimp-10.scope\",\"__REALTIME_TIMESTAMP\":\"1714550400000000\"}\n\
+                       {\"USER_UNIT\":\"app-gnome-gimp-11.scope\",\"__REALTIME_TIMESTAMP\":\"1714636800000000\"}\n";
+        assert_eq!(parse_launches(journal, &ids)["gimp"].timestamp(), 1714636800);
+    }
+}
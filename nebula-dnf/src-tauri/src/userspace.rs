@@ -0,0 +1,197 @@
+use serde::Serialize;
+use std::path::PathBuf;
+use tauri::Manager;
+
+use crate::error::NebulaError;
+use crate::plan::{strings, CommandPlan};
+use crate::process::{run_command, run_plan};
+use crate::services::OperationManager;
+use crate::PackageOperationResult;
+
+// Optional: package managers that install into the user's space next to the system RPMs, so
+// tools from brew or a Nix profile show up in the inventory and get updated with everything else.
+// Both run as the user; neither needs or accepts pkexec.
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum UserspaceManager {
+    Homebrew,
+    Nix,
+}
+
+const MANAGERS: [UserspaceManager; 2] = [UserspaceManager::Homebrew, UserspaceManager::Nix];
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct UserspacePackage {
+    pub manager: UserspaceManager,
+    pub name: String,
+    pub version: Option<String>,
+}
+
+impl UserspaceManager {
+    // Neither is usually in the system bin dirs: Homebrew on Linux lives under linuxbrew, Nix in
+    // the user's or the default profile.
+    fn locate(self) -> Option<PathBuf> {
+        let home = std::env::var("HOME").unwrap_or_default();
+        let candidates = match self {
+            UserspaceManager::Homebrew => vec!["/home/linuxbrew/.linuxbrew/bin/brew".to_string(), format!("{}/.linuxbrew/bin/brew", home)],
+            UserspaceManager::Nix => vec![format!("{}/.nix-profile/bin/nix", home), "/nix/var/nix/profiles/default/bin/nix".to_string()],
+        };
+        let binary = match self {
+            UserspaceManager::Homebrew => "brew",
+            UserspaceManager::Nix => "nix",
+        };
+        candidates.into_iter().map(PathBuf::from).find(|path| path.exists()).or_else(|| crate::usage::resolve_binary(binary))
+    }
+}
+
+// The managers installed on this machine, with where their binary is.
+pub fn installed_managers() -> Vec<(UserspaceManager, PathBuf)> {
+    MANAGERS.iter().filter_map(|manager| manager.locate().map(|path| (*manager, path))).collect()
+}
+
+// `brew list --versions` lines: "htop 3.3.0" or, with older versions kept, "node 20.1.0 21.7.3".
+fn parse_brew_list(output: &str) -> Vec<UserspacePackage> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            let name = words.next()?;
+            Some(UserspacePackage { manager: UserspaceManager::Homebrew, name: name.to_string(), version: words.last().map(String::from) })
+        })
+        .collect()
+}
+
+// "/nix/store/<hash>-hello-2.12.1" -> "2.12.1": the version starts at the first dash followed by a digit.
+fn store_path_version(store_path: &str) -> Option<String> {
+    let name = store_path.rsplit('/').next()?.split_once('-')?.1;
+    let start = name.char_indices().find(|(i, c)| *c == '-' && name[i + 1..].starts_with(|c: char| c.is_ascii_digit()))?.0;
+    Some(name[start + 1..].to_string())
+}
+
+// `nix profile list --json`: "elements" is an object keyed by name since Nix 2.20 and an array
+// before, where the name is the last part of the attribute path.
+fn parse_nix_profile(output: &str) -> Result<Vec<UserspacePackage>, String> {
+    let profile: serde_json::Value = serde_json::from_str(output).map_err(|e| format!("Failed to parse nix profile list: {}", e))?;
+    let entries: Vec<(String, &serde_json::Value)> = match &profile["elements"] {
+        serde_json::Value::Object(elements) => elements.iter().map(|(name, element)| (name.clone(), element)).collect(),
+        serde_json::Value::Array(elements) => elements
+            .iter()
+            .filter_map(|element| Some((element["attrPath"].as_str()?.rsplit('.').next()?.to_string(), element)))
+            .collect(),
+        _ => Vec::new(),
+    };
+    Ok(entries
+        .into_iter()
+        .map(|(name, element)| UserspacePackage {
+            manager: UserspaceManager::Nix,
+            version: element["storePaths"].get(0).and_then(|path| path.as_str()).and_then(store_path_version),
+            name,
+        })
+        .collect())
+}
+
+async fn list(app: &tauri::AppHandle, manager: UserspaceManager, path: &str) -> Result<Vec<UserspacePackage>, String> {
+    let args: &[&str] = match manager {
+        UserspaceManager::Homebrew => &["list", "--formula", "--versions"],
+        UserspaceManager::Nix => &["profile", "list", "--json"],
+    };
+    let out = run_command(app, path, args).await?;
+    if !out.success {
+        return Err(format!("{:?}: {}", manager, out.error_text().trim()));
+    }
+    match manager {
+        UserspaceManager::Homebrew => Ok(parse_brew_list(&out.stdout)),
+        UserspaceManager::Nix => parse_nix_profile(&out.stdout),
+    }
+}
+
+// Everything the installed managers have, plus the ones that could not be listed, with why.
+pub async fn installed(app: &tauri::AppHandle) -> (Vec<UserspacePackage>, Vec<String>) {
+    let mut packages = Vec::new();
+    let mut unavailable = Vec::new();
+    for (manager, path) in installed_managers() {
+        match list(app, manager, &path.to_string_lossy()).await {
+            Ok(listed) => packages.extend(listed),
+            Err(e) => unavailable.push(e),
+        }
+    }
+    (packages, unavailable)
+}
+
+// None for a Nix dry run, which profile upgrades do not have.
+pub fn upgrade_plan(manager: UserspaceManager, path: &str, dry_run: bool) -> Option<CommandPlan> {
+    match (manager, dry_run) {
+        (UserspaceManager::Homebrew, false) => Some(CommandPlan::new(path, strings(&["upgrade"]))),
+        (UserspaceManager::Homebrew, true) => Some(CommandPlan::new(path, strings(&["upgrade", "--dry-run"]))),
+        // '.*' matches every element; older Nix has no --all
+        (UserspaceManager::Nix, false) => Some(CommandPlan::new(path, strings(&["profile", "upgrade", ".*"]))),
+        (UserspaceManager::Nix, true) => None,
+    }
+}
+
+#[tauri::command]
+pub async fn list_userspace_packages(app: tauri::AppHandle) -> Result<Vec<UserspacePackage>, NebulaError> {
+    let (packages, unavailable) = installed(&app).await;
+    if packages.is_empty() && !unavailable.is_empty() {
+        return Err(unavailable.join("\n").into());
+    }
+    Ok(packages)
+}
+
+// Upgrades everything brew and the Nix profile installed, one result per manager. Nix has no dry
+// run for profile upgrades, so a dry run only covers Homebrew.
+#[tauri::command]
+pub async fn update_userspace_packages(app: tauri::AppHandle, dry_run: bool) -> Result<Vec<PackageOperationResult>, NebulaError> {
+    let managers = installed_managers();
+    if managers.is_empty() {
+        return Err("Neither Homebrew nor Nix is installed.".into());
+    }
+    let _busy = (!dry_run).then(|| app.state::<OperationManager>().begin("Updating Homebrew and Nix packages".to_string()));
+    let mut results = Vec::new();
+    for (manager, path) in managers {
+        let Some(plan) = upgrade_plan(manager, &path.to_string_lossy(), dry_run) else {
+            results.push(PackageOperationResult {
+                success: true,
+                message: format!("{:?} has no dry run; skipped.", manager),
+                details: None,
+                confirmation_required: false,
+                simulated: true,
+                reboot_required: false,
+            });
+            continue;
+        };
+        let out = run_plan(&app, &plan).await?;
+        results.push(PackageOperationResult {
+            success: out.success,
+            message: match (out.success, dry_run || out.simulated) {
+                (true, true) => format!("{:?} dry run finished.", manager),
+                (true, false) => format!("{:?} packages updated.", manager),
+                (false, _) => format!("{:?} update failed. Exit code: {}.", manager, out.code),
+            },
+            details: Some(format!("STDOUT:\n{}\nSTDERR:\n{}", out.stdout, out.stderr)),
+            confirmation_required: false,
+            simulated: out.simulated || dry_run,
+            reboot_required: false,
+        });
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_userspace_parsing() {
+        let brew = parse_brew_list("htop 3.3.0\nnode 20.1.0 21.7.3\n");
+        assert_eq!(brew[1].version.as_deref(), Some("21.7.3"));
+
+        assert_eq!(store_path_version("/nix/store/8a9x-hello-2.12.1").as_deref(), Some("2.12.1"));
+        assert_eq!(store_path_version("/nix/store/8a9x-python3.11-requests-2.31.0").as_deref(), Some("2.31.0"));
+        let current = r#"{"version":3,"elements":{"hello":{"active":true,"attrPath":"legacyPackages.x86_64-linux.hello","storePaths":["/nix/store/8a9x-hello-2.12.1"]}}}"#;
+        let old = r#"{"version":2,"elements":[{"active":true,"attrPath":"legacyPackages.x86_64-linux.ripgrep","storePaths":["/nix/store/1b2c-ripgrep-14.1.0"]}]}"#;
+        assert_eq!(parse_nix_profile(current).unwrap()[0].name, "hello");
+        assert_eq!(parse_nix_profile(old).unwrap()[0].version.as_deref(), Some("14.1.0"));
+        assert!(upgrade_plan(UserspaceManager::Nix, "nix", true).is_none());
+    }
+}
@@ -0,0 +1,134 @@
+use serde::Serialize;
+
+use crate::error::NebulaError;
+use crate::plan::{strings, CommandPlan};
+use crate::process::{run_command, run_plan};
+use crate::{validate_package_name, PackageOperationResult};
+
+// dnf5 ships versionlock as a built-in command; dnf4 needs this plugin.
+const DNF4_PLUGIN_PACKAGE: &str = "python3-dnf-plugin-versionlock";
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct VersionLock {
+    name: String,
+    version: String, // [epoch:]version-release the package is held at
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct VersionLockStatus {
+    plugin_installed: bool,
+    plugin_package: Option<String>, // what to install when the plugin is missing
+    locks: Vec<VersionLock>,
+}
+
+// dnf4 prints one pattern per line ("bash-0:5.2.26-3.fc40.*", "!pkg-..." for excludes);
+// dnf5 prints "Package name: bash" followed by "evr = 5.2.26-3.fc40".
+fn parse_versionlock_list(output: &str) -> Vec<VersionLock> {
+    let mut locks = Vec::new();
+    let mut dnf5_name: Option<String> = None;
+    for line in output.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix("Package name:") {
+            dnf5_name = Some(name.trim().to_string());
+        } else if let Some(evr) = line.strip_prefix("evr =") {
+            if let Some(name) = dnf5_name.take() {
+                locks.push(VersionLock { name, version: evr.trim().to_string() });
+            }
+        } else if !line.contains(' ') {
+            let pattern = line.trim_end_matches(".*");
+            let mut parts = pattern.rsplitn(3, '-');
+            let (Some(release), Some(version), Some(name)) = (parts.next(), parts.next(), parts.next()) else { continue };
+            let version = version.strip_prefix("0:").unwrap_or(version);
+            locks.push(VersionLock { name: name.to_string(), version: format!("{}-{}", version, release) });
+        }
+    }
+    locks
+}
+
+fn is_missing_command(stderr: &str) -> bool {
+    stderr.contains("No such command") || stderr.contains("Unknown argument")
+}
+
+#[tauri::command]
+pub async fn get_versionlock_status(app: tauri::AppHandle) -> Result<VersionLockStatus, NebulaError> {
+    let out = run_command(&app, "dnf", &["versionlock", "list", "--quiet"]).await?;
+    if !out.success && is_missing_command(&out.stderr) {
+        return Ok(VersionLockStatus {
+            plugin_installed: false,
+            plugin_package: Some(DNF4_PLUGIN_PACKAGE.to_string()),
+            locks: Vec::new(),
+        });
+    }
+    if !out.success {
+        return Err(NebulaError::command_failed(out.code, &out.stderr, format!("dnf versionlock list failed: {}", out.error_text().trim())));
+    }
+    Ok(VersionLockStatus {
+        plugin_installed: true,
+        plugin_package: None,
+        locks: parse_versionlock_list(&out.stdout),
+    })
+}
+
+async fn run_versionlock_plan(app: &tauri::AppHandle, plan: CommandPlan, done: String) -> Result<PackageOperationResult, String> {
+    let out = run_plan(app, &plan).await?;
+    Ok(PackageOperationResult {
+        success: out.success,
+        message: if out.success { done } else { format!("{} failed: {}", plan.command_line(), out.error_text().trim()) },
+        details: Some(format!("STDOUT:\n{}\nSTDERR:\n{}", out.stdout, out.stderr)),
+        confirmation_required: false,
+        simulated: out.simulated,
+        reboot_required: false,
+    })
+}
+
+fn versionlock_plan(action: &str, packages: &[String]) -> Result<CommandPlan, String> {
+    if packages.is_empty() {
+        return Err("No packages given.".to_string());
+    }
+    for package in packages {
+        validate_package_name(package)?;
+    }
+    let mut args = strings(&["versionlock", action]);
+    args.extend(packages.iter().cloned());
+    Ok(CommandPlan::privileged("dnf", args))
+}
+
+// Locks each package at its currently installed version.
+#[tauri::command]
+pub async fn add_versionlock(app: tauri::AppHandle, packages: Vec<String>) -> Result<PackageOperationResult, NebulaError> {
+    let plan = versionlock_plan("add", &packages)?;
+    Ok(run_versionlock_plan(&app, plan, format!("Locked {}.", packages.join(", "))).await?)
+}
+
+#[tauri::command]
+pub async fn remove_versionlock(app: tauri::AppHandle, packages: Vec<String>) -> Result<PackageOperationResult, NebulaError> {
+    let plan = versionlock_plan("delete", &packages)?;
+    Ok(run_versionlock_plan(&app, plan, format!("Unlocked {}.", packages.join(", "))).await?)
+}
+
+#[tauri::command]
+pub async fn install_versionlock_plugin(app: tauri::AppHandle) -> Result<PackageOperationResult, NebulaError> {
+    let plan = CommandPlan::privileged("dnf", strings(&["install", "--assumeyes", DNF4_PLUGIN_PACKAGE]));
+    Ok(run_versionlock_plan(&app, plan, "Installed the versionlock plugin.".to_string()).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_versionlock_list() {
+        let dnf4 = "# Added lock on Wed May  1 10:00:00 2024\nbash-0:5.2.26-3.fc40.*\nkernel-core-6.8.9-300.fc40.*\n!mesa-libGL-0:24.0.6-1.fc40.*\n";
+        assert_eq!(
+            parse_versionlock_list(dnf4),
+            vec![
+                VersionLock { name: "bash".to_string(), version: "5.2.26-3.fc40".to_string() },
+                VersionLock { name: "kernel-core".to_string(), version: "6.8.9-300.fc40".to_string() },
+            ]
+        );
+        let dnf5 = "# Added by 'versionlock add' command on 2024-05-01 10:00:00\nPackage name: bash\nevr = 5.2.26-3.fc40\n";
+        assert_eq!(parse_versionlock_list(dnf5), vec![VersionLock { name: "bash".to_string(), version: "5.2.26-3.fc40".to_string() }]);
+    }
+}
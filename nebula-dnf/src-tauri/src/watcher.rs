@@ -0,0 +1,88 @@
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+use tracing::{info, warn};
+
+use crate::services::OperationManager;
+
+// The rpmdb moved to /usr/lib/sysimage/rpm in Fedora 36; /var/lib/rpm is a symlink to it there
+// and the real directory on older releases.
+const RPMDB_DIRS: &[&str] = &["/usr/lib/sysimage/rpm", "/var/lib/rpm"];
+// rpm writes the database many times per transaction; act once it has been quiet this long.
+const SETTLE_TIME: Duration = Duration::from_secs(3);
+
+// Readers of a WAL-mode rpmdb touch the shared-memory file, which includes our own refresh.
+fn is_write(event: &Event) -> bool {
+    matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_))
+        && event.paths.iter().any(|path| !path.to_string_lossy().ends_with("-shm"))
+}
+
+fn rpmdb_dirs() -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    RPMDB_DIRS
+        .iter()
+        .filter_map(|dir| Path::new(dir).canonicalize().ok())
+        .filter(|dir| dir.is_dir() && seen.insert(dir.clone()))
+        .collect()
+}
+
+// Watches the rpmdb for packages installed or removed outside the app (dnf in a terminal,
+// GNOME Software, ...): emits `packages-changed-externally` and refreshes the package cache,
+// which then emits `cache-refreshed`. Changes made while the app runs an operation itself are
+// left to that operation. Runs for the life of the app; without inotify it only logs.
+pub async fn watch_rpmdb(app: tauri::AppHandle) {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        if event.is_ok_and(|event| is_write(&event)) {
+            let _ = tx.send(());
+        }
+    });
+    let mut watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            warn!("Cannot watch the rpm database: {}", e);
+            return;
+        }
+    };
+    for dir in rpmdb_dirs() {
+        if let Err(e) = watcher.watch(&dir, RecursiveMode::NonRecursive) {
+            warn!("Cannot watch {:?}: {}", dir, e);
+        }
+    }
+
+    let operations = app.state::<OperationManager>();
+    while rx.recv().await.is_some() {
+        let mut own = operations.any_running();
+        loop {
+            match tokio::time::timeout(SETTLE_TIME, rx.recv()).await {
+                Ok(Some(())) => own |= operations.any_running(),
+                Ok(None) => return,
+                Err(_) => break,
+            }
+        }
+        if own {
+            continue;
+        }
+        info!("The rpm database changed outside the app, refreshing the package cache.");
+        if let Err(e) = app.emit("packages-changed-externally", ()) {
+            warn!("Failed to emit packages-changed-externally event: {}", e);
+        }
+        crate::refresh_user_packages_in_background(&app);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{AccessKind, ModifyKind};
+
+    #[test]
+    fn test_is_write() {
+        let event = |kind, path: &str| Event::new(kind).add_path(PathBuf::from(path));
+        assert!(is_write(&event(EventKind::Modify(ModifyKind::Any), "/usr/lib/sysimage/rpm/rpmdb.sqlite-wal")));
+        assert!(!is_write(&event(EventKind::Modify(ModifyKind::Any), "/usr/lib/sysimage/rpm/rpmdb.sqlite-shm")));
+        assert!(!is_write(&event(EventKind::Access(AccessKind::Any), "/usr/lib/sysimage/rpm/rpmdb.sqlite")));
+    }
+}
@@ -0,0 +1,153 @@
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::backends::{self, PackageBackend};
+use crate::error::NebulaError;
+use crate::packagekit::PackageAction;
+use crate::plan::{strings, CommandPlan};
+use crate::process::run_command;
+use crate::PackageOperationResult;
+
+// Experimental: the core operations on openSUSE, through zypper for transactions and rpm for
+// what is installed. The dnf-specific features (history, modules, versionlock, ...) stay dnf-only.
+
+// Names zypper installed only as dependencies, one per line; everything else was asked for.
+const AUTO_INSTALLED_PATH: &str = "/var/lib/zypp/AutoInstalled";
+
+// A row of zypper's package tables (`search --details`, `packages`).
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ZypperPackage {
+    name: String,
+    version: String,
+    arch: String,
+    repository: String,
+    installed: bool,
+}
+
+// zypper tables are '|'-separated with a header row and a "---+---" rule under it; columns are
+// found by header name since `search` and `packages` order them differently.
+fn parse_table(output: &str) -> Vec<ZypperPackage> {
+    let mut lines = output.lines().map(|line| line.split('|').map(str::trim).collect::<Vec<_>>());
+    let Some(header) = lines.find(|columns| columns.contains(&"Name")) else { return Vec::new() };
+    let column = |name: &str| header.iter().position(|heading| *heading == name);
+    let (status, name, version, arch, repository) = (column("S"), column("Name"), column("Version"), column("Arch"), column("Repository"));
+    let Some(name) = name else { return Vec::new() };
+    let field = |columns: &[&str], index: Option<usize>| index.and_then(|i| columns.get(i)).copied().unwrap_or_default().to_string();
+    lines
+        .filter(|columns| columns.len() == header.len())
+        .map(|columns| ZypperPackage {
+            name: field(&columns, Some(name)),
+            version: field(&columns, version),
+            arch: field(&columns, arch),
+            repository: field(&columns, repository),
+            // "i" installed, "i+" installed by request, "v" another version installed
+            installed: field(&columns, status).starts_with('i'),
+        })
+        .collect()
+}
+
+fn auto_installed(contents: &str) -> HashSet<&str> {
+    contents.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')).collect()
+}
+
+// The installed packages zypper did not pull in as dependencies, i.e. what dnf calls userinstalled.
+pub fn user_installed_names(installed: &HashSet<String>) -> Result<Vec<String>, String> {
+    let contents = match std::fs::read_to_string(AUTO_INSTALLED_PATH) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("Failed to read {}: {}", AUTO_INSTALLED_PATH, e)),
+    };
+    let auto = auto_installed(&contents);
+    let mut names: Vec<String> = installed.iter().filter(|name| !auto.contains(name.as_str())).cloned().collect();
+    names.sort();
+    Ok(names)
+}
+
+pub fn action_plan(action: PackageAction, packages: &[String], dry_run: bool) -> CommandPlan {
+    let mut args = strings(&["--non-interactive"]);
+    args.push(format!("{:?}", action).to_lowercase());
+    if dry_run {
+        args.push("--dry-run".to_string());
+    }
+    if action == PackageAction::Remove {
+        args.push("--clean-deps".to_string()); // like dnf's clean_requirements_on_remove
+    }
+    args.extend(packages.iter().cloned());
+    CommandPlan::privileged("zypper", args)
+}
+
+async fn query_table(app: &tauri::AppHandle, args: &[&str]) -> Result<Vec<ZypperPackage>, String> {
+    let out = run_command(app, "zypper", args).await?;
+    // 104: ZYPPER_EXIT_INF_CAP_NOT_FOUND, i.e. no matches
+    if !out.success && out.code != 104 {
+        return Err(format!("zypper {} failed: {}", args.join(" "), out.error_text().trim()));
+    }
+    Ok(parse_table(&out.stdout))
+}
+
+// Installs, removes or updates packages with zypper; the package list is the rpmdb one, with
+// AutoInstalled standing in for dnf's userinstalled.
+pub struct Zypper;
+
+impl PackageBackend for Zypper {
+    async fn run(&self, app: &tauri::AppHandle, action: PackageAction, packages: &[String], dry_run: bool) -> Result<PackageOperationResult, String> {
+        backends::run_cli(app, "zypper", action_plan(action, packages, dry_run), action, packages, dry_run).await
+    }
+}
+
+#[tauri::command]
+pub async fn zypper_package_action(
+    app: tauri::AppHandle,
+    action: PackageAction,
+    packages: Vec<String>,
+    dry_run: bool,
+    confirmed: Option<bool>,
+) -> Result<PackageOperationResult, NebulaError> {
+    Ok(backends::action_command(&Zypper, &app, action, &packages, dry_run, confirmed).await?)
+}
+
+// Packages in the enabled repositories whose name matches `query`, installed or not.
+#[tauri::command]
+pub async fn zypper_search(app: tauri::AppHandle, query: String) -> Result<Vec<ZypperPackage>, NebulaError> {
+    crate::validate_package_name(&query)?;
+    Ok(query_table(&app, &["--quiet", "--no-refresh", "search", "--details", "--type", "package", &query]).await?)
+}
+
+// Installed packages nothing needs anymore, the zypper side of dnf's autoremove candidates.
+#[tauri::command]
+pub async fn zypper_list_orphans(app: tauri::AppHandle) -> Result<Vec<ZypperPackage>, NebulaError> {
+    Ok(query_table(&app, &["--quiet", "--no-refresh", "packages", "--unneeded"]).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zypper_parsing() {
+        let table = "S  | Repository | Name    | Version   | Arch\n\
+                     ---+------------+---------+-----------+-------\n\
+                     i  | @System    | libfoo1 | 1.2-3.1   | x86_64\n\
+                     i+ | repo-oss   | htop    | 3.3.0-1.2 | x86_64\n";
+        let packages = parse_table(table);
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[1], ZypperPackage {
+            name: "htop".to_string(),
+            version: "3.3.0-1.2".to_string(),
+            arch: "x86_64".to_string(),
+            repository: "repo-oss".to_string(),
+            installed: true,
+        });
+        assert!(parse_table("No matching items found.\n").is_empty());
+
+        assert_eq!(auto_installed("# comment\nlibfoo1\n\nglibc\n"), HashSet::from(["libfoo1", "glibc"]));
+        assert_eq!(
+            action_plan(PackageAction::Update, &[], false).command_line(),
+            "pkexec zypper --non-interactive update"
+        );
+        assert_eq!(
+            action_plan(PackageAction::Remove, &strings(&["htop"]), true).command_line(),
+            "pkexec zypper --non-interactive remove --dry-run --clean-deps htop"
+        );
+    }
+}